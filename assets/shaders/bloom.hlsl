@@ -0,0 +1,91 @@
+cbuffer BloomOptions : register(b0) {
+    float threshold;
+    float intensity;
+    float2 _pad;
+};
+
+struct VSOutput {
+    float4 position : SV_POSITION;
+    float2 uv : TEXCOORD0;
+};
+
+static float2 screenPos[4] = {
+    float2(-1.0, 1.0), // top left
+    float2(-1.0, -1.0), // bottom left
+    float2(1.0, 1.0), // top right
+    float2(1.0, -1.0), // bottom right
+};
+
+static float2 texcoords[4] = {
+    float2(0.0, 0.0),
+    float2(0.0, 1.0),
+    float2(1.0, 0.0),
+    float2(1.0, 1.0),
+};
+
+// 9-tap gaussian, using linear sampling to get 5 fetches
+static const float offsets[3] = { 0.0, 1.3846153846, 3.2307692308 };
+static const float weights[3] = { 0.2270270270, 0.3162162162, 0.0702702703 };
+
+VSOutput VShader(uint vertexID : SV_VertexID) {
+    VSOutput output;
+
+    float4 position = float4(screenPos[vertexID], 0.0, 1.0);
+    output.position = position;
+    output.uv = texcoords[vertexID];
+
+    return output;
+}
+
+Texture2D Source : register(t0);
+
+SamplerState SampleType : register(s0);
+
+float2 QueryTexelSize(Texture2D t) {
+	uint width, height;
+	t.GetDimensions(width, height);
+	return 1.0 / float2(width, height);
+}
+
+float Luminance(float3 c) {
+    return dot(c, float3(0.2126, 0.7152, 0.0722));
+}
+
+// Downsamples the source and keeps only the pixels brighter than the threshold
+float4 PSExtract(VSOutput input) : SV_Target {
+    float2 texelSize = QueryTexelSize(Source);
+    float3 c = Source.SampleLevel(SampleType, input.uv + texelSize * float2(-0.5, -0.5), 0).rgb;
+    c += Source.SampleLevel(SampleType, input.uv + texelSize * float2(0.5, -0.5), 0).rgb;
+    c += Source.SampleLevel(SampleType, input.uv + texelSize * float2(-0.5, 0.5), 0).rgb;
+    c += Source.SampleLevel(SampleType, input.uv + texelSize * float2(0.5, 0.5), 0).rgb;
+    c *= 0.25;
+
+    // Soft knee, avoids a hard edge around the threshold
+    float luma = Luminance(c);
+    float contribution = max(0, luma - threshold) / max(luma, 0.0001);
+
+    return float4(c * contribution, 1.0);
+}
+
+float4 Blur(float2 uv, float2 direction) {
+    float3 c = Source.SampleLevel(SampleType, uv, 0).rgb * weights[0];
+    [unroll] for (int i = 1; i < 3; i++) {
+        c += Source.SampleLevel(SampleType, uv + direction * offsets[i], 0).rgb * weights[i];
+        c += Source.SampleLevel(SampleType, uv - direction * offsets[i], 0).rgb * weights[i];
+    }
+
+    return float4(c, 1.0);
+}
+
+float4 PSBlurHorizontal(VSOutput input) : SV_Target {
+    return Blur(input.uv, float2(QueryTexelSize(Source).x, 0));
+}
+
+float4 PSBlurVertical(VSOutput input) : SV_Target {
+    return Blur(input.uv, float2(0, QueryTexelSize(Source).y));
+}
+
+// Output is added onto the scene
+float4 PSComposite(VSOutput input) : SV_Target {
+    return float4(Source.SampleLevel(SampleType, input.uv, 0).rgb * intensity, 1.0);
+}
@@ -1,16 +1,7 @@
-#define CAMERA_CASCADE_CLIP_NEAR 0.1
-#define CAMERA_CASCADE_CLIP_FAR 4000.0
-#define CAMERA_CASCADE_FALLOFF_START 3500.0
-#define CAMERA_CASCADE_FALLOFF_LENGTH (CAMERA_CASCADE_CLIP_FAR-CAMERA_CASCADE_FALLOFF_START)
-
+// Maximum number of cascades, matches Renderer::CAMERA_CASCADE_LEVEL_COUNT
 #define CAMERA_CASCADE_LEVEL_COUNT 4
-
-static float cascadePlaneDistances[CAMERA_CASCADE_LEVEL_COUNT] = {
-    CAMERA_CASCADE_CLIP_FAR / 50.0,
-    CAMERA_CASCADE_CLIP_FAR / 25.0,
-    CAMERA_CASCADE_CLIP_FAR / 10.0,
-    CAMERA_CASCADE_CLIP_FAR / 1.0,
-};
+// Shadows fade out over the last part of the shadow distance
+#define CAMERA_CASCADE_FALLOFF_START 0.875
 
 cbuffer CompositeOptions : register(b0) {
     row_major float4x4 viewportProjViewMatrixInv;
@@ -27,7 +18,30 @@ cbuffer CompositeOptions : register(b0) {
     float time;
     uint tex_i;
     uint drawLights;
-    bool fxaaEnabled;
+    uint fxaaQuality;
+    uint tonemap;
+    float exposure;
+    // Mode shown right of the split divider
+    uint tex_i_b;
+    // Horizontal position of the split divider (0.0-1.0)
+    float splitPosition;
+    // Matches Background in render_settings.rs
+    uint backgroundMode;
+    float4 backgroundColor;
+    float4 backgroundColorTop;
+    // Depth of pixels without any geometry
+    float clearDepth;
+    bool fogEnabled;
+    // Distance from the camera at which the fog starts
+    float fogStart;
+    float fogDensity;
+    float4 fogColor;
+    // Background of pixels without geometry in debug views, alpha is 0 when the mode shows the raw buffer instead
+    float4 clearColor;
+    float4 clearColorB;
+    // Set when blending entities with an opacity override over the previous shading pass
+    bool opacityPass;
+    float opacity;
 };
 
 cbuffer Lights : register(b1) {
@@ -36,6 +50,10 @@ cbuffer Lights : register(b1) {
 
 cbuffer Cascades : register(b3) {
     float4x4 cascadeMatrices[CAMERA_CASCADE_LEVEL_COUNT];
+    // Far distance of each cascade
+    float4 cascadeSplits;
+    uint cascadeCount;
+    bool cascadeDebug;
 }
 
 struct VSOutput {
@@ -89,6 +107,8 @@ Texture2DArray CascadeShadowMaps : register(t10);
 
 Texture2D LightRenderTarget0 : register(t12);
 Texture2D LightRenderTarget1 : register(t13);
+// Depth before the entities of the current opacity pass were drawn
+Texture2D DepthBeforeOpacityPass : register(t14);
 
 SamplerState SampleType : register(s0);
 
@@ -182,7 +202,7 @@ uint CascadeLevel(float depth) {
     int layer = -1;
     [unroll] for (int i = 0; i < CAMERA_CASCADE_LEVEL_COUNT; ++i)
     {
-        if (depth < cascadePlaneDistances[i])
+        if (i < cascadeCount && depth < cascadeSplits[i])
         {
             layer = i;
             break;
@@ -190,12 +210,23 @@ uint CascadeLevel(float depth) {
     }
     if (layer == -1)
     {
-        layer = CAMERA_CASCADE_LEVEL_COUNT-1;
+        layer = cascadeCount-1;
     }
 
     return layer;
 }
 
+float ShadowDistance() {
+    return cascadeSplits[cascadeCount-1];
+}
+
+static float3 cascadeDebugColors[CAMERA_CASCADE_LEVEL_COUNT] = {
+    float3(1.0, 0.2, 0.2),
+    float3(0.2, 1.0, 0.2),
+    float3(0.2, 0.2, 1.0),
+    float3(1.0, 1.0, 0.2),
+};
+
 float random( float4 p )
 {
     float dot_product = dot( p, float4( 12.9898, 78.233, 45.164, 94.673 ) );
@@ -206,7 +237,8 @@ float CalculateShadow(float3 worldPos, float3 normal, float3 lightDir) {
     float fragmentDistance = distance(worldPos, cameraPos.xyz);
     uint cascade = CascadeLevel(fragmentDistance);
 
-    if(fragmentDistance > cascadePlaneDistances[CAMERA_CASCADE_LEVEL_COUNT-1]) {
+    float shadowDistance = ShadowDistance();
+    if(fragmentDistance > shadowDistance) {
         return 1;
     }
 
@@ -226,10 +258,11 @@ float CalculateShadow(float3 worldPos, float3 normal, float3 lightDir) {
     float pcfDepth = CascadeShadowMaps.Sample(SampleType, float3(texCoords.xy, cascade)).r;
     float shadow = pcfDepth < (currentDepth - 0.0001) ? 0.0 : 1.0;        
             
-    if(fragmentDistance < CAMERA_CASCADE_FALLOFF_START)
+    float falloffStart = shadowDistance * CAMERA_CASCADE_FALLOFF_START;
+    if(fragmentDistance < falloffStart)
         return shadow;
     else {
-        float falloffMul = (fragmentDistance - CAMERA_CASCADE_FALLOFF_START) / CAMERA_CASCADE_FALLOFF_LENGTH;
+        float falloffMul = (fragmentDistance - falloffStart) / (shadowDistance - falloffStart);
         return lerp(shadow, 1.0, falloffMul);
     }
 }
@@ -337,22 +370,25 @@ float4 PeanutButterRasputin(float4 rt0, float4 rt1, float4 rt2, float depth, flo
     // float3 ambient = kD * diffuse;
     float3 ambient = diffuseIBL + specularIBL;
 
+    // Left linear, the final pass applies exposure and tonemapping
     float3 color = ambient + directLighting;
 
-    color = color / (color + float3(1.0, 1.0, 1.0));
+    float fragmentDistance = distance(worldPos, cameraPos.xyz);
+    if(cascadeDebug && fragmentDistance < ShadowDistance()) {
+        color = lerp(color, cascadeDebugColors[CascadeLevel(fragmentDistance)], 0.35);
+    }
 
     return float4(color, 1.0);
 }
 
-// Pixel Shader
-float4 PShader(VSOutput input) : SV_Target {
+float4 Composite(uint mode, VSOutput input) {
     float4 albedo = RenderTarget0.Sample(SampleType, input.uv);
     float4 rt1 = RenderTarget1.Sample(SampleType, input.uv);
     float4 rt2 = RenderTarget2.Sample(SampleType, input.uv);
     float4 rt3 = RenderTarget3.Sample(SampleType, input.uv);
     float depth = DepthTarget.Sample(SampleType, input.uv).r;
 
-    [branch] switch(tex_i) {
+    [branch] switch(mode) {
         case 1: // RT0 (gamma-corrected)
             return float4(albedo.xyz, 1.0);
         case 2: // RT1
@@ -421,6 +457,12 @@ float4 PShader(VSOutput input) : SV_Target {
             return LightRenderTarget1.Sample(SampleType, input.uv);
         }
         default: { // Combined
+            if(backgroundMode != 0 && depth == clearDepth) {
+                if(backgroundMode == 2)
+                    return lerp(backgroundColorTop, backgroundColor, input.uv.y);
+                return backgroundColor;
+            }
+
             float3 emission_ao = rt2.y * 2.0 - 1.0;
             if(drawLights == 0) {
                 float3 normal = DecodeNormal(rt1.xyz);
@@ -449,4 +491,44 @@ float4 PShader(VSOutput input) : SV_Target {
             }
         }
     }
-}
\ No newline at end of file
+}
+
+// Exponential fog based on the distance to the camera.
+// Pixels without geometry are fully fogged to hide the far clip plane, unless a custom background is used
+float4 ApplyFog(float4 c, VSOutput input) {
+    float depth = DepthTarget.Sample(SampleType, input.uv).r;
+    float fog = 1.0;
+    if(depth == clearDepth) {
+        if(backgroundMode != 0)
+            return c;
+    } else {
+        float3 worldPos = WorldPosFromDepth(depth, input.position.xy);
+        float distance = max(length(worldPos - cameraPos.xyz) - fogStart, 0.0);
+        fog = 1.0 - exp(-distance * fogDensity);
+    }
+
+    return float4(lerp(c.rgb, fogColor.rgb, saturate(fog)), c.a);
+}
+
+// Pixel Shader
+float4 PShader(VSOutput input) : SV_Target {
+    uint mode = input.uv.x < splitPosition ? tex_i : tex_i_b;
+    float4 c = Composite(mode, input);
+
+    float4 modeClearColor = input.uv.x < splitPosition ? clearColor : clearColorB;
+    if(mode != 0 && modeClearColor.a != 0.0 && DepthTarget.Sample(SampleType, input.uv).r == clearDepth)
+        c = float4(modeClearColor.rgb, 1.0);
+
+    // Only the combined output is fogged, the debug views show the raw buffers
+    if(fogEnabled && mode == 0)
+        c = ApplyFog(c, input);
+
+    // Only the pixels drawn by the overridden entities are blended, everything else keeps the output of the previous pass
+    if(opacityPass) {
+        int3 pixel = int3(input.position.xy, 0);
+        bool drawn = DepthTarget.Load(pixel).r != DepthBeforeOpacityPass.Load(pixel).r;
+        c.a = drawn ? opacity : 0.0;
+    }
+
+    return c;
+}
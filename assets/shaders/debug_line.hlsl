@@ -13,7 +13,7 @@ cbuffer CompositeOptions : register(b0) {
     float time;
     uint tex_i;
     uint drawLights;
-    bool fxaaEnabled;
+    uint fxaaQuality;
 };
 
 cbuffer DebugShapeOptions : register(b10) {
@@ -0,0 +1,97 @@
+#define DOF_SAMPLES 48
+#define GOLDEN_ANGLE 2.39996323
+
+cbuffer DofOptions : register(b0) {
+    float focusDistance;
+    float strength;
+    // Maximum blur radius, in pixels
+    float maxRadius;
+    // Depth mapping terms of the projection matrix, depth = -a - b / z
+    float depthA;
+    float depthB;
+};
+
+struct VSOutput {
+    float4 position : SV_POSITION;
+    float2 uv : TEXCOORD0;
+};
+
+static float2 screenPos[4] = {
+    float2(-1.0, 1.0), // top left
+    float2(-1.0, -1.0), // bottom left
+    float2(1.0, 1.0), // top right
+    float2(1.0, -1.0), // bottom right
+};
+
+static float2 texcoords[4] = {
+    float2(0.0, 0.0),
+    float2(0.0, 1.0),
+    float2(1.0, 0.0),
+    float2(1.0, 1.0),
+};
+
+VSOutput VShader(uint vertexID : SV_VertexID) {
+    VSOutput output;
+
+    float4 position = float4(screenPos[vertexID], 0.0, 1.0);
+    output.position = position;
+    output.uv = texcoords[vertexID];
+
+    return output;
+}
+
+Texture2D RenderTargetStaging : register(t0);
+Texture2D RenderTargetDepth : register(t1);
+
+SamplerState SampleType : register(s0);
+
+float2 QueryTexelSize(Texture2D t) {
+	uint width, height;
+	t.GetDimensions(width, height);
+	return 1.0 / float2(width, height);
+}
+
+// Works for both reverse-Z and standard projections
+float LinearDepth(float depth) {
+    float d = depth + depthA;
+    // Infinitely far away (eg. the sky)
+    if (abs(d) < 1e-9)
+        return 1e9;
+
+    return depthB / d;
+}
+
+// Circle of confusion radius in pixels
+float CircleOfConfusion(float2 uv) {
+    float z = LinearDepth(RenderTargetDepth.SampleLevel(SampleType, uv, 0).r);
+    float coc = abs(z - focusDistance) / max(z, 0.001) * strength;
+    return saturate(coc) * maxRadius;
+}
+
+// Pixel Shader
+float4 PShader(VSOutput input) : SV_Target {
+    float4 center = RenderTargetStaging.SampleLevel(SampleType, input.uv, 0);
+    float centerCoc = CircleOfConfusion(input.uv);
+    if (centerCoc < 0.5)
+        return center;
+
+    float2 texelSize = QueryTexelSize(RenderTargetStaging);
+    float3 color = center.rgb;
+    float totalWeight = 1.0;
+
+    // Sample a disc using a golden angle spiral
+    [loop] for (int i = 1; i < DOF_SAMPLES; i++) {
+        float radius = sqrt((float)i / DOF_SAMPLES) * centerCoc;
+        float theta = i * GOLDEN_ANGLE;
+        float2 uv = input.uv + float2(cos(theta), sin(theta)) * radius * texelSize;
+
+        // Samples that are more in focus than the disc radius shouldn't bleed into us
+        float sampleCoc = CircleOfConfusion(uv);
+        float weight = saturate(sampleCoc - radius + 1.0);
+
+        color += RenderTargetStaging.SampleLevel(SampleType, uv, 0).rgb * weight;
+        totalWeight += weight;
+    }
+
+    return float4(color / totalWeight, center.a);
+}
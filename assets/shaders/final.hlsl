@@ -13,7 +13,14 @@ cbuffer CompositeOptions : register(b0) {
     float time;
     uint tex_i;
     uint drawLights;
-    bool fxaaEnabled;
+    // 0 disables FXAA, matches AntiAliasing::fxaa_quality in render_settings.rs
+    uint fxaaQuality;
+    uint tonemap;
+    float exposure;
+    // Mode shown right of the split divider
+    uint tex_i_b;
+    // Horizontal position of the split divider (0.0-1.0)
+    float splitPosition;
 };
 
 cbuffer cb12 : register(b12)
@@ -63,6 +70,7 @@ SamplerState SampleType : register(s0);
 
 float4 SampleAlbedoFxaa(VSOutput input);
 float4 FinalCombineFilmCurve(float4 v);
+float3 Tonemap(float3 c);
 
 float3 GammaCorrect(float3 c) {
     return pow(abs(c), (1.0/2.2).xxx);
@@ -71,15 +79,17 @@ float3 GammaCorrect(float3 c) {
 // Pixel Shader
 float4 PShader(VSOutput input) : SV_Target {
     float4 albedo = float4(0, 0, 0, 1);
-    if(fxaaEnabled)
+    if(fxaaQuality != 0)
         albedo = SampleAlbedoFxaa(input);
     else
         albedo = RenderTargetStaging.Sample(SampleType, input.uv);
 
+    uint mode = input.uv.x < splitPosition ? tex_i : tex_i_b;
+
     float4 finalColor = float4(0, 0, 0, 1);
-    if(tex_i == 0 || tex_i == 1)
-        finalColor = FinalCombineFilmCurve(albedo);
-    else if(tex_i == 14) {
+    if(mode == 0 || mode == 1)
+        finalColor = float4(Tonemap(albedo.rgb * exposure), 1.0);
+    else if(mode == 14) {
         float4 u0 = 0;
         u0.xy = target.xy * input.uv.xy;
         u0.xy = (int2)u0.xy;
@@ -107,12 +117,45 @@ float4 FinalCombineFilmCurve(float4 v) {
     return o0;
 }
 
+float3 TonemapReinhard(float3 c) {
+    return c / (1.0 + c);
+}
+
+// Krzysztof Narkowicz's fit of the ACES filmic curve
+float3 TonemapAces(float3 c) {
+    const float a = 2.51;
+    const float b = 0.03;
+    const float d = 2.43;
+    const float e = 0.59;
+    const float f = 0.14;
+    return saturate((c * (a * c + b)) / (c * (d * c + e) + f));
+}
+
+// Matches Tonemap in render/settings.rs
+float3 Tonemap(float3 c) {
+    switch(tonemap) {
+        case 1:
+            return TonemapReinhard(c);
+        case 2:
+            return TonemapAces(c);
+        case 3:
+            // The film curve clips anything above ~4, so the highlights are rolled off first
+            return FinalCombineFilmCurve(float4(TonemapReinhard(c), 1.0)).rgb;
+        default:
+            return saturate(c);
+    }
+}
+
 // FXAA implementation below this line
 
 
-#define FXAA_EDGE_THRESHOLD      (1.0/8.0)
-#define FXAA_EDGE_THRESHOLD_MIN  (1.0/24.0)
-#define FXAA_SEARCH_STEPS        32
+// Edge threshold, minimum edge threshold and search steps for each quality preset (low, medium, high)
+static const float3 FXAA_PRESETS[3] = {
+    float3(1.0/4.0, 1.0/12.0, 4),
+    float3(1.0/6.0, 1.0/16.0, 12),
+    float3(1.0/8.0, 1.0/24.0, 32),
+};
+
 #define FXAA_SEARCH_ACCELERATION 1
 #define FXAA_SEARCH_THRESHOLD    (1.0/4.0)
 #define FXAA_SUBPIX              1
@@ -160,6 +203,11 @@ float4 SampleAlbedoFxaa(VSOutput input)
         1.0 / target.y
     );
 
+    float3 preset = FXAA_PRESETS[clamp(fxaaQuality, 1, 3) - 1];
+    float edgeThreshold = preset.x;
+    float edgeThresholdMin = preset.y;
+    int searchSteps = (int)preset.z;
+
     //SEARCH MAP
     float3 rgbN = FxaaTexOff(RenderTargetStaging, input.uv.xy, int2(0, -1)).xyz;
     float3 rgbW = FxaaTexOff(RenderTargetStaging, input.uv.xy, int2(-1, 0)).xyz;
@@ -174,7 +222,7 @@ float4 SampleAlbedoFxaa(VSOutput input)
     float rangeMin = min(lumaM, min(min(lumaN, lumaW), min(lumaS, lumaE)));
     float rangeMax = max(lumaM, max(max(lumaN, lumaW), max(lumaS, lumaE)));
     float range = rangeMax - rangeMin;
-    if (range < max(FXAA_EDGE_THRESHOLD_MIN, rangeMax * FXAA_EDGE_THRESHOLD))
+    if (range < max(edgeThresholdMin, rangeMax * edgeThreshold))
     {
         return float4(FxaaFilterReturn(rgbM), 1.0f);
     }
@@ -253,7 +301,8 @@ float4 SampleAlbedoFxaa(VSOutput input)
         posN += offNP * float2(-1.0, -1.0);
         posP += offNP * float2(1.0, 1.0);
     #endif
-    for (int i = 0; i < FXAA_SEARCH_STEPS; i++)
+    [loop]
+    for (int i = 0; i < searchSteps; i++)
     {
     #if FXAA_SEARCH_ACCELERATION == 1
         if (!doneN)
@@ -0,0 +1,77 @@
+cbuffer CompositeOptions : register(b0) {
+    row_major float4x4 viewportProjViewMatrixInv;
+    row_major float4x4 projViewMatrixInv;
+    row_major float4x4 projViewMatrix;
+    float4x4 projMatrix;
+    float4x4 viewMatrix;
+    float4 cameraPos;
+};
+
+cbuffer GridOptions : register(b1) {
+    // Center of the grid quad, w is the half-size of the quad
+    float4 gridOrigin;
+    // Distance between minor lines
+    float spacing;
+    // Number of minor cells per major cell
+    float majorEvery;
+    // Distance from the camera at which the grid has fully faded out
+    float fadeDistance;
+};
+
+struct VSOutput {
+    float4 position : SV_POSITION;
+    float3 worldPos : TEXCOORD0;
+};
+
+static float2 corners[4] = {
+    float2(-1.0, 1.0),
+    float2(-1.0, -1.0),
+    float2(1.0, 1.0),
+    float2(1.0, -1.0),
+};
+
+VSOutput VShader(uint vertexID : SV_VertexID) {
+    VSOutput output;
+
+    float3 worldPos = gridOrigin.xyz + float3(corners[vertexID] * gridOrigin.w, 0.0);
+    output.worldPos = worldPos;
+    output.position = mul(float4(worldPos, 1.0), projViewMatrix);
+
+    return output;
+}
+
+// Coverage (0-1) of the lines of a grid with the given cell size, antialiased over one pixel
+float GridLines(float2 pos, float cellSize) {
+    float2 coord = pos / cellSize;
+    float2 derivative = fwidth(coord);
+    float2 lines = abs(frac(coord - 0.5) - 0.5) / max(derivative, 1e-6);
+    return 1.0 - saturate(min(lines.x, lines.y));
+}
+
+// Coverage of the line along an axis, at the given distance from it
+float AxisLine(float distance) {
+    return 1.0 - saturate(abs(distance) / max(fwidth(distance), 1e-6));
+}
+
+float4 PShader(VSOutput input) : SV_Target {
+    float2 pos = input.worldPos.xy;
+
+    float minor = GridLines(pos, spacing) * 0.35;
+    float major = GridLines(pos, spacing * majorEvery) * 0.7;
+
+    float4 color = float4(0.6, 0.6, 0.6, max(minor, major));
+
+    // X axis in red, Y axis in green
+    float xAxis = AxisLine(pos.y);
+    float yAxis = AxisLine(pos.x);
+    color = lerp(color, float4(0.9, 0.25, 0.25, 1.0), xAxis);
+    color = lerp(color, float4(0.25, 0.8, 0.25, 1.0), yAxis);
+
+    float distance = length(input.worldPos - cameraPos.xyz);
+    color.a *= 1.0 - smoothstep(fadeDistance * 0.25, fadeDistance, distance);
+
+    if(color.a < 0.005)
+        discard;
+
+    return color;
+}
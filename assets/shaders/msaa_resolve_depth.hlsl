@@ -0,0 +1,46 @@
+struct VSOutput {
+    float4 position : SV_POSITION;
+};
+
+static float2 screenPos[4] = {
+    float2(-1.0, 1.0), // top left
+    float2(-1.0, -1.0), // bottom left
+    float2(1.0, 1.0), // top right
+    float2(1.0, -1.0), // bottom right
+};
+
+VSOutput VShader(uint vertexID : SV_VertexID) {
+    VSOutput output;
+    output.position = float4(screenPos[vertexID], 0.0, 1.0);
+
+    return output;
+}
+
+Texture2DMS<float> DepthMS : register(t0);
+
+// Pixel Shader
+float PShader(VSOutput input) : SV_Depth {
+    uint width, height, samples;
+    DepthMS.GetDimensions(width, height, samples);
+
+    // Depth is reversed, so the closest sample is the largest one
+    float depth = 0.0;
+    for (uint i = 0; i < samples; i++) {
+        depth = max(depth, DepthMS.Load(int2(input.position.xy), i));
+    }
+
+    return depth;
+}
+
+// Standard depth, the closest sample is the smallest one
+float PShaderStandard(VSOutput input) : SV_Depth {
+    uint width, height, samples;
+    DepthMS.GetDimensions(width, height, samples);
+
+    float depth = 1.0;
+    for (uint i = 0; i < samples; i++) {
+        depth = min(depth, DepthMS.Load(int2(input.position.xy), i));
+    }
+
+    return depth;
+}
@@ -1,4 +1,3 @@
-#define OUTLINE_COLOR float3(1.0, 0.6, 0.2)
 #define OUTLINE_WIDTH 3
 
 struct VSOutput {
@@ -30,6 +29,10 @@ VSOutput VShader(uint vertexID : SV_VertexID) {
     return output;
 }
 
+cbuffer OutlineOptions : register(b0) {
+    float4 OutlineColor;
+};
+
 Texture2D DepthTargetOutline : register(t0);
 Texture2D DepthTargetScene : register(t1);
 
@@ -66,9 +69,9 @@ float4 PShader(VSOutput input) : SV_Target {
                 {
                     float depthScene = DepthTargetScene.Sample(SampleType, input.uv).r;
                     if(depthScene > depth) // Behind scene
-                        return float4(OUTLINE_COLOR, 0.65);
+                        return float4(OutlineColor.rgb, 0.65);
                     else // In front of scene
-                        return float4(OUTLINE_COLOR, 1);
+                        return float4(OutlineColor.rgb, 1);
                 }
             }
         }
@@ -0,0 +1,26 @@
+cbuffer alk_scope_reference_mesh : register(b7) {
+    float4x4 projViewMatrix;
+    float4x4 viewMatrix;
+    float4x4 modelMatrix;
+    float4 color;
+};
+
+struct VSOutput {
+    float4 position : SV_POSITION;
+    float3 normalViewSpace : NORMAL0;
+};
+
+VSOutput VShader(float3 in_position : POSITION, float3 in_normal : NORMAL) {
+    VSOutput output;
+
+    output.position = mul(projViewMatrix, mul(modelMatrix, float4(in_position, 1.0)));
+    output.normalViewSpace = mul((float3x3)viewMatrix, mul((float3x3)modelMatrix, in_normal));
+
+    return output;
+}
+
+// Unlit, with a bit of facing-ratio shading so the shape stays readable
+float4 PShader(VSOutput input) : SV_Target0 {
+    float facing = abs(normalize(input.normalViewSpace).z);
+    return float4(color.rgb * (0.45 + 0.55 * facing), 1.0);
+}
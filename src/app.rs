@@ -0,0 +1,771 @@
+use std::{cell::RefCell, f32::consts::PI};
+
+use egui::epaint::Hsva;
+use glam::{Mat4, Quat, Vec3};
+use hecs::Entity;
+use windows::Win32::Graphics::Direct3D11::{ID3D11RasterizerState, D3D11_VIEWPORT};
+
+use crate::{
+    camera::FpsCamera,
+    ecs::{
+        components::{
+            ActivityGroup, Beacon, CubemapVolume, EntityModel, ErrorMarker, ResourcePoint, Route,
+            Ruler, Sphere, StaticInstances, Terrain, Visible, Water,
+        },
+        resolve_aabb,
+        resources::SelectedEntity,
+        transform::Transform,
+    },
+    input::InputState,
+    keybinds::KeyBindings,
+    map::{MapDataList, SShadowingLight},
+    map_resources::MapResource,
+    overlays::{
+        camera_settings::CurrentCubemap,
+        render_settings::{ActivityGroupFilter, RenderSettingsOverlay},
+    },
+    render::{
+        debug::{DebugDrawFlags, DebugShapes},
+        drawcall::GeometryType,
+        renderer::RendererShared,
+        settings::RenderSettings,
+        time::TimeControl,
+        DeviceContextSwapchain, EntityRenderers,
+    },
+    resources::Resources,
+    structure::ExtendedHash,
+    types::AABB,
+    util::text::{invert_color, keep_color_bright, prettify_distance},
+};
+
+/// The state needed to advance and draw a single frame, borrowed from the event loop.
+/// Rendering doesn't depend on the event loop or the window, so frames can be stepped directly with a fixed delta time.
+/// Everything animated follows the game time in `TimeControl`, which can be set beforehand for reproducible frames
+pub struct App<'a> {
+    pub dcs: &'a DeviceContextSwapchain,
+    pub renderer: &'a RendererShared,
+    pub resources: &'a mut Resources,
+    /// Holds the render layer toggles
+    pub render_settings_overlay: &'a RefCell<RenderSettingsOverlay>,
+    pub rasterizer_state: &'a ID3D11RasterizerState,
+}
+
+impl App<'_> {
+    /// Advances a frame like [`Self::advance_frame`] and reads back the staging target, which holds the linear color of the
+    /// frame before exposure and tonemapping
+    #[allow(unused)]
+    pub fn render_frame(&mut self, dt: f32) -> anyhow::Result<(Vec<u16>, (u32, u32))> {
+        self.advance_frame(dt);
+        self.renderer.read().capture_staging_hdr()
+    }
+
+    /// Advances the camera and game time by `dt` seconds, then draws the current map and submits the frame.
+    /// The frame isn't presented, the GUI is drawn on top of it afterwards
+    pub fn advance_frame(&mut self, dt: f32) {
+        self.update(dt);
+        unsafe {
+            self.draw();
+        }
+    }
+
+    fn update(&mut self, dt: f32) {
+        {
+            let resources = &*self.resources;
+            let mut camera = resources.get_mut::<FpsCamera>().unwrap();
+            let input_state = resources.get::<InputState>().unwrap();
+            let bindings = resources.get::<KeyBindings>().unwrap();
+            let render_settings = resources.get::<RenderSettings>().unwrap();
+            camera.clip_plane = render_settings.clip_plane();
+            camera.target_aspect = render_settings.letterbox_aspect;
+
+            // Camera tweens follow the game time, so they pause and change speed along with the rest of the scene
+            let mut time = resources.get_mut::<TimeControl>().unwrap();
+            time.advance(dt);
+            if let Some(tween) = &mut camera.tween {
+                tween.advance(time.delta);
+            }
+
+            camera.update(
+                &input_state,
+                &bindings,
+                self.renderer.read().gbuffer.size,
+                dt,
+            );
+        }
+
+        let finished_tween = self
+            .resources
+            .get_mut::<FpsCamera>()
+            .unwrap()
+            .finished_tween
+            .take();
+        if let Some(on_finish) = finished_tween.and_then(|t| t.on_finish) {
+            on_finish(self.resources);
+        }
+    }
+
+    unsafe fn draw(&mut self) {
+        let (dcs, renderer, resources) = (self.dcs, self.renderer, &*self.resources);
+        let (render_settings_overlay, rasterizer_state) =
+            (self.render_settings_overlay, self.rasterizer_state);
+        let viewport_size = renderer.read().gbuffer.size;
+        let time = resources.get::<TimeControl>().unwrap().time;
+
+        renderer
+            .read()
+            .clear_render_targets(resources.get::<FpsCamera>().unwrap().depth_clear_value());
+
+        dcs.context().RSSetViewports(Some(&[D3D11_VIEWPORT {
+            TopLeftX: 0.0,
+            TopLeftY: 0.0,
+            Width: viewport_size.0 as f32,
+            Height: viewport_size.1 as f32,
+            MinDepth: 0.0,
+            MaxDepth: 1.0,
+        }]));
+
+        dcs.context().RSSetState(rasterizer_state);
+
+        let max_texture_uploads = resources
+            .get::<RenderSettings>()
+            .unwrap()
+            .max_texture_uploads;
+        renderer
+            .read()
+            .render_data
+            .upload_textures((max_texture_uploads != 0).then_some(max_texture_uploads));
+
+        renderer.read().begin_frame();
+        renderer.read().update_directional_cascades(&resources);
+
+        let mut maps = resources.get_mut::<MapDataList>().unwrap();
+
+        if let Some((_, _, map)) = maps.current_map() {
+            {
+                let gb = render_settings_overlay.borrow();
+
+                let camera = resources.get::<FpsCamera>().unwrap();
+                let render_settings = resources.get::<RenderSettings>().unwrap();
+                let group_filter = resources.get::<ActivityGroupFilter>().unwrap();
+                let entity_renderers = resources.get::<EntityRenderers>().unwrap();
+                // Shadow cascades are drawn from the same draw queue, so geometry that only casts a shadow into view has to be kept
+                let is_visible = |bb: &AABB| {
+                    camera.is_aabb_visible(bb) || renderer.read().is_shadow_caster_visible(bb)
+                };
+                for (e, (StaticInstances(instances, _), visible)) in map
+                    .scene
+                    .query::<(&StaticInstances, Option<&Visible>)>()
+                    .iter()
+                {
+                    if !visible.map_or(true, |v| v.0) {
+                        continue;
+                    }
+
+                    instances
+                        .draw(
+                            &renderer.read(),
+                            &camera,
+                            gb.renderlayer_statics,
+                            gb.renderlayer_statics_transparent,
+                            gb.renderlayer_statics_decals,
+                            render_settings.instance_limit,
+                            e,
+                        )
+                        .unwrap();
+                }
+
+                if gb.renderlayer_terrain {
+                    for (e, (terrain, visible)) in
+                        map.scene.query::<(&Terrain, Option<&Visible>)>().iter()
+                    {
+                        if !visible.map_or(true, |v| v.0) {
+                            continue;
+                        }
+
+                        terrain.0.draw(&renderer.read(), e).unwrap();
+                    }
+                }
+
+                // Entities that failed to load are drawn by the error marker pass below
+                for (e, (transform, rp, group, water, visible)) in map
+                    .scene
+                    .query::<hecs::Without<
+                        (
+                            &Transform,
+                            &ResourcePoint,
+                            Option<&ActivityGroup>,
+                            Option<&Water>,
+                            Option<&Visible>,
+                        ),
+                        &ErrorMarker,
+                    >>()
+                    .iter()
+                {
+                    if !visible.map_or(true, |v| v.0) {
+                        continue;
+                    }
+
+                    if !group_filter.is_visible(group) {
+                        continue;
+                    }
+
+                    // Water only follows its own layer toggle
+                    let layer_enabled = match rp.resource {
+                        _ if water.is_some() => gb.renderlayer_water,
+                        MapResource::Unk80806aa3 { .. } => gb.renderlayer_background,
+                        _ => gb.renderlayer_entities,
+                    };
+                    if !layer_enabled {
+                        continue;
+                    }
+
+                    if let Some(ent) = entity_renderers.0.get(&rp.entity_key()) {
+                        let mm = transform.to_mat4();
+
+                        if !is_visible(&ent.bounds().transform(&mm)) {
+                            renderer.read().count_culling(0, 1);
+                            continue;
+                        }
+                        renderer.read().count_culling(1, 0);
+
+                        let mesh_to_world = Mat4::from_cols(
+                            mm.x_axis.truncate().extend(mm.w_axis.x),
+                            mm.y_axis.truncate().extend(mm.w_axis.y),
+                            mm.z_axis.truncate().extend(mm.w_axis.z),
+                            mm.w_axis,
+                        );
+
+                        rp.entity_cbuffer.data().mesh_to_world = mesh_to_world;
+
+                        let lod = render_settings
+                            .select_lod(camera.position.distance(transform.translation));
+
+                        if ent
+                            .draw(
+                                &renderer.read(),
+                                rp.entity_cbuffer.buffer().clone(),
+                                lod,
+                                if water.is_some() {
+                                    GeometryType::Water
+                                } else {
+                                    GeometryType::Entity
+                                },
+                                e,
+                            )
+                            .is_err()
+                        {
+                            renderer.write().push_fiddlesticks(*transform, Some(e));
+                        }
+                    } else if rp.resource.is_entity() {
+                        // cohae: This will occur when there's no entitymodel for the given entity. Keeping it in just as a reminder of unimplemented entity rendering stuffs
+                        renderer.write().push_fiddlesticks(*transform, Some(e));
+                    }
+                }
+
+                if gb.renderlayer_entities {
+                    for (e, (transform, group, visible)) in map
+                        .scene
+                        .query::<hecs::With<
+                            (&Transform, Option<&ActivityGroup>, Option<&Visible>),
+                            &ErrorMarker,
+                        >>()
+                        .iter()
+                    {
+                        if !visible.map_or(true, |v| v.0) || !group_filter.is_visible(group) {
+                            continue;
+                        }
+
+                        renderer.write().push_fiddlesticks(*transform, Some(e));
+                    }
+                }
+
+                for (e, (transform, em, visible)) in map
+                    .scene
+                    .query::<(&Transform, &EntityModel, Option<&Visible>)>()
+                    .iter()
+                {
+                    if !gb.renderlayer_entities || !visible.map_or(true, |v| v.0) {
+                        continue;
+                    }
+
+                    let mm = transform.to_mat4();
+
+                    if !is_visible(&em.0.bounds().transform(&mm)) {
+                        renderer.read().count_culling(0, 1);
+                        continue;
+                    }
+                    renderer.read().count_culling(1, 0);
+
+                    let mesh_to_world = Mat4::from_cols(
+                        mm.x_axis.truncate().extend(mm.w_axis.x),
+                        mm.y_axis.truncate().extend(mm.w_axis.y),
+                        mm.z_axis.truncate().extend(mm.w_axis.z),
+                        mm.w_axis,
+                    );
+
+                    em.1.data().mesh_to_world = mesh_to_world;
+
+                    let lod =
+                        render_settings.select_lod(camera.position.distance(transform.translation));
+
+                    if em
+                        .0
+                        .draw(
+                            &renderer.read(),
+                            em.1.buffer().clone(),
+                            lod,
+                            GeometryType::Entity,
+                            e,
+                        )
+                        .is_err()
+                    {
+                        renderer.write().push_fiddlesticks(*transform, Some(e));
+                    }
+                }
+            }
+
+            // Find the smallest cubemap volume that the camera is in and set it as the current cubemap
+            let camera = resources.get::<FpsCamera>().unwrap();
+            let mut smallest_volume = f32::MAX;
+            let mut smallest_volume_entity = hecs::Entity::DANGLING;
+            for (e, (transform, volume)) in map.scene.query::<(&Transform, &CubemapVolume)>().iter()
+            {
+                if volume.1.volume() < smallest_volume
+                    && volume
+                        .1
+                        .contains_point_oriented(camera.position, transform.rotation)
+                {
+                    smallest_volume = volume.1.volume();
+                    smallest_volume_entity = e;
+                }
+            }
+
+            if let Ok(cubemap) = map.scene.get::<&CubemapVolume>(smallest_volume_entity) {
+                if let Some(mut cr) = resources.get_mut::<CurrentCubemap>() {
+                    cr.0 = Some(cubemap.2.clone());
+                    cr.1 = Some(ExtendedHash::Hash32(cubemap.0));
+                }
+            } else if let Some(mut cr) = resources.get_mut::<CurrentCubemap>() {
+                cr.0 = None;
+            }
+
+            let mut debugshapes = resources.get_mut::<DebugShapes>().unwrap();
+            for (e, (ruler, visible)) in map.scene.query::<(&Ruler, Option<&Visible>)>().iter() {
+                if !visible.map_or(true, |v| v.0) {
+                    continue;
+                }
+                draw_ruler(&mut debugshapes, ruler, time, Some(e));
+            }
+            for (e, (transform, sphere, visible)) in map
+                .scene
+                .query::<(&Transform, &Sphere, Option<&Visible>)>()
+                .iter()
+            {
+                if !visible.map_or(true, |v| v.0) {
+                    continue;
+                }
+                draw_sphere(&mut debugshapes, transform, sphere, time, Some(e));
+            }
+            for (e, (transform, beacon, visible)) in map
+                .scene
+                .query::<(&Transform, &Beacon, Option<&Visible>)>()
+                .iter()
+            {
+                if !visible.map_or(true, |v| v.0) {
+                    continue;
+                }
+                draw_beacon(&mut debugshapes, transform, beacon, time, Some(e));
+            }
+            for (e, (route, visible)) in map.scene.query::<(&Route, Option<&Visible>)>().iter() {
+                if !visible.map_or(true, |v| v.0) {
+                    continue;
+                }
+                draw_route(&mut debugshapes, route, time, Some(e));
+            }
+
+            let selection = resources.get::<SelectedEntity>().unwrap();
+            if let (Some(selected), Some(instance)) = (selection.0, selection.instance()) {
+                if let Some(bounds) = map
+                    .scene
+                    .get::<&StaticInstances>(selected)
+                    .ok()
+                    .and_then(|si| si.0.occlusion_bounds.get(instance).copied())
+                {
+                    debugshapes.cube_aabb(
+                        bounds,
+                        Quat::IDENTITY,
+                        [255, 255, 0],
+                        false,
+                        DebugDrawFlags::DRAW_NORMAL,
+                        None,
+                    );
+                }
+            }
+
+            for selected in selection.iter() {
+                if let Some(aabb) = map.scene.entity(selected).ok().and_then(resolve_aabb) {
+                    debugshapes.cube_aabb(
+                        aabb,
+                        Quat::IDENTITY,
+                        [255, 255, 255],
+                        false,
+                        DebugDrawFlags::DRAW_NORMAL,
+                        None,
+                    );
+                }
+
+                // Direction handle for spot/directional lights
+                if let Ok(mut q) = map
+                    .scene
+                    .query_one::<(&Transform, &SShadowingLight)>(selected)
+                {
+                    if let Some((transform, _)) = q.get() {
+                        debugshapes.line_orientation(
+                            transform.translation,
+                            transform.rotation,
+                            10.0,
+                            [255, 255, 0],
+                        );
+                        debugshapes.cross(
+                            transform.translation + transform.rotation * Vec3::X * 10.0,
+                            0.5,
+                            [255, 255, 0],
+                        );
+                    }
+                }
+            }
+        }
+
+        if let Some(map) = maps.current_map_mut() {
+            map.command_buffer.run_on(&mut map.scene);
+        }
+
+        drop(maps);
+
+        renderer.read().submit_frame(&resources);
+    }
+}
+
+fn get_rainbow_color(time: f32) -> [u8; 3] {
+    Hsva {
+        h: (time * 0.30) % 1.0,
+        s: 1.0,
+        v: 1.0,
+        a: 1.0,
+    }
+    .to_srgb()
+}
+
+fn draw_route(debugshapes: &mut DebugShapes, route: &Route, time: f32, entity: Option<Entity>) {
+    let color = if route.rainbow {
+        get_rainbow_color(time)
+    } else {
+        route.color
+    };
+
+    for (i, node) in route.nodes.iter().enumerate() {
+        debugshapes.cross(node.pos, route.scale, color);
+
+        let text = match (&node.label, route.show_indices) {
+            (Some(label), true) => Some(format!("{i}: {label}")),
+            (Some(label), false) => Some(label.clone()),
+            (None, true) => Some(i.to_string()),
+            (None, false) => None,
+        };
+        if let Some(text) = text {
+            debugshapes.text(text, node.pos, egui::Align2::CENTER_BOTTOM, [255, 255, 255]);
+        }
+    }
+
+    for w in route.nodes.windows(2) {
+        debugshapes.line(w[0].pos, w[1].pos, color);
+
+        let length = w[0].pos.distance(w[1].pos);
+        if length > f32::EPSILON {
+            debugshapes.cube_extents(
+                (w[0].pos + w[1].pos) / 2.0,
+                Vec3::new(length / 2.0, route.scale / 2.0, route.scale / 2.0),
+                Quat::from_rotation_arc(Vec3::X, (w[1].pos - w[0].pos) / length),
+                color,
+                true,
+                DebugDrawFlags::DRAW_PICK,
+                entity,
+            );
+        }
+    }
+
+    for node in &route.nodes {
+        debugshapes.cube_extents(
+            node.pos,
+            Vec3::splat(route.scale / 2.0),
+            Quat::IDENTITY,
+            color,
+            true,
+            DebugDrawFlags::DRAW_PICK,
+            entity,
+        );
+    }
+}
+
+fn draw_ruler(debugshapes: &mut DebugShapes, ruler: &Ruler, time: f32, entity: Option<Entity>) {
+    let color = if ruler.rainbow {
+        get_rainbow_color(time)
+    } else {
+        ruler.color
+    };
+
+    if let Some(vertex) = ruler.vertex {
+        draw_ruler_angle(debugshapes, ruler, vertex, color, entity);
+        return;
+    }
+
+    debugshapes.cross(ruler.start, ruler.scale, color);
+    debugshapes.cross(ruler.end, ruler.scale, color);
+    debugshapes.line_dotted(ruler.start, ruler.end, color, ruler.scale);
+
+    let ruler_center = (ruler.start + ruler.end) / 2.0;
+    debugshapes.text(
+        ruler.unit.format(ruler.length()),
+        ruler_center,
+        egui::Align2::CENTER_BOTTOM,
+        [255, 255, 255],
+    );
+
+    if ruler.show_individual_axis {
+        let end_x = Vec3::new(ruler.end.x, ruler.start.y, ruler.start.z);
+        let end_y = Vec3::new(ruler.start.x, ruler.end.y, ruler.start.z);
+        let end_z = Vec3::new(ruler.start.x, ruler.start.y, ruler.end.z);
+
+        debugshapes.line(ruler.start, end_x, color);
+        debugshapes.line(ruler.start, end_y, color);
+        debugshapes.line(ruler.start, end_z, color);
+
+        let length_x = (ruler.start - end_x).length();
+        let length_y = (ruler.start - end_y).length();
+        let length_z = (ruler.start - end_z).length();
+
+        let center_x = (ruler.start + end_x) / 2.0;
+        let center_y = (ruler.start + end_y) / 2.0;
+        let center_z = (ruler.start + end_z) / 2.0;
+
+        debugshapes.text(
+            format!("X: {}", ruler.unit.format(length_x)),
+            center_x,
+            egui::Align2::LEFT_CENTER,
+            [255, 255, 255],
+        );
+
+        debugshapes.text(
+            format!("Y: {}", ruler.unit.format(length_y)),
+            center_y,
+            egui::Align2::RIGHT_CENTER,
+            [255, 255, 255],
+        );
+
+        debugshapes.text(
+            format!("Z: {}", ruler.unit.format(length_z)),
+            center_z,
+            egui::Align2::RIGHT_CENTER,
+            [255, 255, 255],
+        );
+    }
+
+    if ruler.marker_interval > 0.0 {
+        let sphere_color = keep_color_bright(invert_color(color));
+        let sphere_color = [sphere_color[0], sphere_color[1], sphere_color[2], 192];
+
+        let mut current = 0.0;
+        while current < ruler.length() {
+            if current > 0.0 {
+                let pos = ruler.start + ruler.direction() * current;
+
+                debugshapes.sphere(
+                    pos,
+                    ruler.scale * 0.20,
+                    sphere_color,
+                    DebugDrawFlags::DRAW_NORMAL,
+                    None,
+                );
+            }
+
+            current += ruler.marker_interval;
+        }
+    }
+    debugshapes.cube_extents(
+        (ruler.start + ruler.end) / 2.0,
+        Vec3::new(ruler.length() / 2.0, ruler.scale / 2.0, ruler.scale / 2.0),
+        Quat::from_rotation_arc(Vec3::X, (ruler.end - ruler.start).normalize()),
+        color,
+        true,
+        DebugDrawFlags::DRAW_PICK,
+        entity,
+    )
+}
+
+/// Draws both legs of an angle ruler, with an arc and the angle at the vertex
+fn draw_ruler_angle(
+    debugshapes: &mut DebugShapes,
+    ruler: &Ruler,
+    vertex: Vec3,
+    color: [u8; 3],
+    entity: Option<Entity>,
+) {
+    const ARC_SEGMENTS: usize = 24;
+
+    debugshapes.cross(ruler.start, ruler.scale, color);
+    debugshapes.cross(ruler.end, ruler.scale, color);
+    debugshapes.cross(vertex, ruler.scale, color);
+
+    for point in [ruler.start, ruler.end] {
+        let length = (point - vertex).length();
+        let center = (vertex + point) / 2.0;
+
+        debugshapes.line_dotted(vertex, point, color, ruler.scale);
+        debugshapes.text(
+            ruler.unit.format(length),
+            center,
+            egui::Align2::CENTER_BOTTOM,
+            [255, 255, 255],
+        );
+        debugshapes.cube_extents(
+            center,
+            Vec3::new(length / 2.0, ruler.scale / 2.0, ruler.scale / 2.0),
+            Quat::from_rotation_arc(Vec3::X, (point - vertex).normalize()),
+            color,
+            true,
+            DebugDrawFlags::DRAW_PICK,
+            entity,
+        );
+    }
+
+    let to_start = ruler.start - vertex;
+    let to_end = ruler.end - vertex;
+    let angle = to_start.angle_between(to_end);
+    let radius = to_start.length().min(to_end.length()) * 0.25;
+
+    // Straight (or zero) angles don't have a plane to draw the arc in
+    let axis = to_start.cross(to_end).normalize_or_zero();
+    let arc_point = |t: f32| {
+        vertex + Quat::from_axis_angle(axis, angle * t) * to_start.normalize_or_zero() * radius
+    };
+
+    let mut text_pos = vertex;
+    if axis != Vec3::ZERO && radius > 0.0 {
+        for i in 0..ARC_SEGMENTS {
+            debugshapes.line(
+                arc_point(i as f32 / ARC_SEGMENTS as f32),
+                arc_point((i + 1) as f32 / ARC_SEGMENTS as f32),
+                color,
+            );
+        }
+        text_pos = arc_point(0.5);
+    }
+
+    debugshapes.text(
+        format!("{:.1}°", angle.to_degrees()),
+        text_pos,
+        egui::Align2::CENTER_CENTER,
+        [255, 255, 255],
+    );
+}
+
+fn draw_sphere(
+    debugshapes: &mut DebugShapes,
+    transform: &Transform,
+    sphere: &Sphere,
+    time: f32,
+    entity: Option<Entity>,
+) {
+    let color = if sphere.rainbow {
+        let c = get_rainbow_color(time);
+        [c[0], c[1], c[2], sphere.color[3]]
+    } else {
+        sphere.color
+    };
+
+    let color_opaque = [color[0], color[1], color[2]];
+    let cross_color = keep_color_bright(invert_color(color_opaque));
+    debugshapes.cross(
+        transform.translation,
+        0.25 * transform.radius(),
+        cross_color,
+    );
+
+    for t in 0..sphere.detail {
+        debugshapes.circle(
+            transform.translation,
+            Vec3::new(
+                transform.radius() * (t as f32 * PI / sphere.detail as f32).sin(),
+                transform.radius() * (t as f32 * PI / sphere.detail as f32).cos(),
+                0.0,
+            ),
+            4 * sphere.detail,
+            color,
+        );
+    }
+    debugshapes.circle(
+        transform.translation,
+        Vec3::new(0.0, 0.0, transform.radius()),
+        4 * sphere.detail,
+        color,
+    );
+
+    // Faint line from the center to the surface, labeled with the radius
+    let surface_point = transform.translation + Vec3::X * transform.radius();
+    debugshapes.line(
+        transform.translation,
+        surface_point,
+        [color[0], color[1], color[2], 96],
+    );
+    debugshapes.text(
+        prettify_distance(transform.radius()),
+        (transform.translation + surface_point) / 2.0,
+        egui::Align2::CENTER_BOTTOM,
+        [255, 255, 255],
+    );
+    debugshapes.sphere(
+        transform.translation,
+        transform.radius(),
+        color,
+        DebugDrawFlags::DRAW_NORMAL | DebugDrawFlags::DRAW_PICK,
+        entity,
+    );
+}
+
+fn draw_beacon(
+    debugshapes: &mut DebugShapes,
+    transform: &Transform,
+    beacon: &Beacon,
+    time: f32,
+    entity: Option<Entity>,
+) {
+    const BEAM_HEIGHT: f32 = 5000.0;
+    const BASE_RADIUS: f32 = 0.1;
+    let color: [u8; 4] = [
+        beacon.color[0],
+        beacon.color[1],
+        beacon.color[2],
+        (150.0 + (time * 2.0 * PI * beacon.freq).sin() * 50.0) as u8,
+    ];
+    debugshapes.sphere(
+        transform.translation,
+        BASE_RADIUS,
+        color,
+        DebugDrawFlags::DRAW_NORMAL,
+        None,
+    );
+    debugshapes.line(
+        transform.translation + Vec3::Z * BASE_RADIUS,
+        transform.translation + Vec3::Z * BEAM_HEIGHT,
+        color,
+    );
+    debugshapes.cube_extents(
+        transform.translation + Vec3::Z * BEAM_HEIGHT / 2.0,
+        Vec3::new(BASE_RADIUS, BASE_RADIUS, BEAM_HEIGHT / 2.0),
+        Quat::IDENTITY,
+        color,
+        true,
+        DebugDrawFlags::DRAW_PICK,
+        entity,
+    );
+}
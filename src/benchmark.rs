@@ -0,0 +1,232 @@
+use std::{
+    fmt::Write as _,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use glam::Vec3;
+use strum::IntoEnumIterator;
+
+use crate::{
+    camera::FpsCamera,
+    ecs::persistence,
+    render::{
+        gpu_timer::GpuTimer, stats::RenderStats, tfx::TfxRenderStage, DeviceContextSwapchain,
+    },
+};
+
+/// Amount of frames without any resources loading before the benchmark starts
+const SETTLE_FRAMES: usize = 30;
+/// Camera speed along the route in meters per second, unless specified otherwise
+pub const DEFAULT_SPEED: f32 = 10.0;
+/// How far ahead along the route the camera looks, in meters
+const LOOK_AHEAD: f32 = 5.0;
+
+/// Benchmark started from the menu, picked up by the main loop
+#[derive(Default)]
+pub struct BenchmarkRequest(pub Option<BenchmarkOptions>);
+
+#[derive(Clone)]
+pub struct BenchmarkOptions {
+    /// Waypoint file (json or csv) exported from a route
+    pub route: PathBuf,
+    pub output: PathBuf,
+    /// Camera speed in meters per second
+    pub speed: f32,
+}
+
+pub enum BenchmarkState {
+    Running,
+    Finished,
+}
+
+struct FrameSample {
+    time: f32,
+    frame_time: f32,
+    cpu_time: f32,
+    gpu_time: Option<f32>,
+    stats: RenderStats,
+}
+
+/// Flies the camera along a route at a constant speed, recording the timings and render stats of every frame to a CSV
+pub struct Benchmark {
+    options: BenchmarkOptions,
+    nodes: Vec<Vec3>,
+    length: f32,
+
+    /// Distance travelled along the route
+    distance: f32,
+    time: f32,
+    settled_frames: usize,
+    started: bool,
+
+    gpu_timer: GpuTimer,
+    /// GPU timer frame index of the frame being recorded
+    gpu_frame: Option<usize>,
+    samples: Vec<FrameSample>,
+    /// GPU timer frame index of the first sample
+    first_gpu_frame: usize,
+}
+
+impl Benchmark {
+    pub fn new(
+        options: BenchmarkOptions,
+        dcs: Arc<DeviceContextSwapchain>,
+    ) -> anyhow::Result<Self> {
+        let route = persistence::import_route(&options.route)?;
+        anyhow::ensure!(
+            route.nodes.len() >= 2,
+            "Benchmark routes need at least 2 waypoints"
+        );
+        anyhow::ensure!(options.speed > 0.0, "Benchmark speed must be positive");
+
+        let length = route.length();
+        Ok(Self {
+            options,
+            nodes: route.nodes.iter().map(|n| n.pos).collect(),
+            length,
+            distance: 0.0,
+            time: 0.0,
+            settled_frames: 0,
+            started: false,
+            gpu_timer: GpuTimer::create(dcs)?,
+            gpu_frame: None,
+            samples: vec![],
+            first_gpu_frame: 0,
+        })
+    }
+
+    /// Moves the camera along the route. Should be called before the camera is updated
+    pub fn update_camera(&mut self, camera: &mut FpsCamera, delta: f32) {
+        if self.started {
+            self.time += delta;
+            self.distance = (self.distance + self.options.speed * delta).min(self.length);
+        }
+
+        // The look angle is relative to the new position
+        camera.teleport(self.point_at(self.distance), None);
+        let orientation = camera.get_look_angle(self.point_at(self.distance + LOOK_AHEAD));
+        camera.teleport(camera.position, Some(orientation));
+    }
+
+    /// Should be called right before the frame starts rendering
+    pub fn begin_frame(&mut self) {
+        if self.started {
+            self.gpu_frame = Some(self.gpu_timer.begin());
+        }
+    }
+
+    /// Should be called after the frame has been submitted. `loading` indicates whether any resources are still being loaded
+    pub fn end_frame(
+        &mut self,
+        frame_time: f32,
+        cpu_time: f32,
+        stats: RenderStats,
+        loading: bool,
+    ) -> anyhow::Result<BenchmarkState> {
+        if !self.started {
+            if loading {
+                self.settled_frames = 0;
+            } else {
+                self.settled_frames += 1;
+            }
+
+            if self.settled_frames >= SETTLE_FRAMES {
+                info!(
+                    "Starting benchmark ({:.1}m route at {}m/s)",
+                    self.length, self.options.speed
+                );
+                self.started = true;
+            }
+
+            return Ok(BenchmarkState::Running);
+        }
+
+        if let Some(frame) = self.gpu_frame.take() {
+            if self.samples.is_empty() {
+                self.first_gpu_frame = frame;
+            }
+            self.gpu_timer.end();
+            self.samples.push(FrameSample {
+                time: self.time,
+                frame_time,
+                cpu_time,
+                gpu_time: None,
+                stats,
+            });
+        }
+        self.collect_gpu_times();
+
+        if self.distance < self.length {
+            return Ok(BenchmarkState::Running);
+        }
+
+        self.gpu_timer.flush();
+        self.collect_gpu_times();
+        self.write_csv()?;
+
+        Ok(BenchmarkState::Finished)
+    }
+
+    pub fn output(&self) -> &Path {
+        &self.options.output
+    }
+
+    fn collect_gpu_times(&mut self) {
+        for (frame, time) in self.gpu_timer.take_results() {
+            if let Some(sample) = frame
+                .checked_sub(self.first_gpu_frame)
+                .and_then(|i| self.samples.get_mut(i))
+            {
+                sample.gpu_time = Some(time).filter(|t| t.is_finite());
+            }
+        }
+    }
+
+    /// Position on the route at the given distance from the start
+    fn point_at(&self, mut distance: f32) -> Vec3 {
+        for w in self.nodes.windows(2) {
+            let segment = w[0].distance(w[1]);
+            if distance <= segment {
+                return w[0].lerp(w[1], distance / segment.max(f32::EPSILON));
+            }
+            distance -= segment;
+        }
+
+        *self.nodes.last().unwrap()
+    }
+
+    fn write_csv(&self) -> anyhow::Result<()> {
+        let mut csv =
+            String::from("frame,time,frame_ms,cpu_ms,gpu_ms,draw_calls,triangles,visible_entities");
+        for stage in TfxRenderStage::iter() {
+            write!(csv, ",{stage:?}")?;
+        }
+        csv.push('\n');
+
+        for (i, s) in self.samples.iter().enumerate() {
+            write!(
+                csv,
+                "{i},{:.4},{:.3},{:.3},{},{},{},{}",
+                s.time,
+                s.frame_time * 1000.0,
+                s.cpu_time * 1000.0,
+                s.gpu_time.map(|t| format!("{t:.3}")).unwrap_or_default(),
+                s.stats.total_draw_calls(),
+                s.stats.triangles,
+                s.stats.visible_entities
+            )?;
+            for stage in TfxRenderStage::iter() {
+                write!(csv, ",{}", s.stats.draw_calls(stage))?;
+            }
+            csv.push('\n');
+        }
+
+        if let Some(parent) = self.options.output.parent() {
+            fs_err::create_dir_all(parent)?;
+        }
+        fs_err::write(&self.options.output, csv)?;
+
+        Ok(())
+    }
+}
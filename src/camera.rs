@@ -1,12 +1,60 @@
-use glam::{Mat4, Quat, Vec2, Vec3};
-use winit::event::VirtualKeyCode;
+use glam::{Mat4, Quat, Vec2, Vec3, Vec4};
+use serde::{Deserialize, Serialize};
 
 use crate::{
     input::InputState,
+    keybinds::{Action, KeyBindings},
     render::tween::{self, Tween},
     types::AABB,
 };
 
+/// Default near plane of the projection matrix
+pub const Z_NEAR: f32 = 0.0001;
+/// Anything below this loses too much depth precision to be usable
+pub const Z_NEAR_MIN: f32 = 0.00001;
+pub const SPEED_MUL_RANGE: std::ops::Range<f32> = 0.01..50.0;
+/// Anything outside of this range results in a (nearly) degenerate projection
+pub const FOV_RANGE: std::ops::Range<f32> = 30.0..120.0;
+pub const ORBIT_DISTANCE_RANGE: std::ops::Range<f32> = 0.5..500.0;
+pub const MOUSE_SENSITIVITY_RANGE: std::ops::Range<f32> = 0.1..5.0;
+/// Field of view that mouse look speed is calibrated for when compensating for the field of view, in degrees
+const MOUSE_REFERENCE_FOV: f32 = 90.0;
+/// Speed multiplier applied per scroll wheel step
+const SCROLL_SPEED_FACTOR: f32 = 1.15;
+/// Field of view change per scroll wheel step, in degrees
+const SCROLL_FOV_STEP: f32 = 5.0;
+/// Distance moved per scroll wheel step, scaled by the speed multiplier
+const SCROLL_DOLLY_STEP: f32 = 2.0;
+/// Look speed at full right stick deflection, in degrees per second
+const GAMEPAD_LOOK_SPEED: f32 = 120.0;
+/// Distance in front of the camera that the orbit pivot is placed at when switching to orbit mode
+const DEFAULT_ORBIT_DISTANCE: f32 = 20.0;
+
+/// What the scroll wheel does in fps mode. In orbit mode it always changes the orbit distance
+#[derive(
+    strum::Display, strum::EnumIter, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize,
+)]
+pub enum ScrollAction {
+    /// Changes the movement speed multiplier
+    #[default]
+    #[strum(serialize = "Movement speed")]
+    Speed,
+    /// Zooms by changing the field of view
+    #[strum(serialize = "Field of view")]
+    Fov,
+    /// Moves the camera forwards and backwards
+    Dolly,
+}
+
+#[derive(strum::Display, strum::EnumIter, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CameraMode {
+    /// Free flying camera, looking around rotates the camera in place
+    #[default]
+    Fps,
+    /// Looking around rotates the camera around a pivot, movement moves the pivot
+    Orbit,
+}
+
 #[derive(Clone)]
 pub struct FpsCamera {
     pub orientation: Vec2,
@@ -19,12 +67,50 @@ pub struct FpsCamera {
     pub speed_mul: f32,
     pub fov: f32,
 
+    /// Distance to the near clipping plane
+    pub z_near: f32,
+    /// Distance to the far clipping plane, or `None` for an infinite projection
+    pub z_far: Option<f32>,
+    /// Map the near plane to a depth of 1 and the far plane to 0.
+    /// Standard depth is easier to read when debugging, but has far less precision at a distance
+    pub reverse_z: bool,
+    /// World-space plane (normal, distance) that geometry on the negative side of is clipped against
+    pub clip_plane: Option<Vec4>,
+    /// Aspect ratio of the letterboxed area of the viewport. The field of view applies to this area instead of the whole viewport
+    pub target_aspect: Option<f32>,
+    /// Vertical field of view of the projection matrix, in degrees. Differs from `fov` when letterboxing
+    projection_fov: f32,
+
+    /// Ease into and out of movement instead of starting and stopping instantly
+    pub smooth_movement: bool,
+    /// Time constant of the movement smoothing, in seconds
+    pub smoothing: f32,
+    velocity: Vec3,
+
+    /// Base mouse look sensitivity multiplier
+    pub mouse_sensitivity: f32,
+    /// Scale mouse look with the field of view, so the scene moves by the same amount on screen when zoomed in
+    pub fov_compensation: bool,
+
     pub view_matrix: Mat4,
     pub projection_matrix: Mat4,
     pub projection_view_matrix: Mat4,
     pub projection_view_matrix_inv: Mat4,
+    /// Left, right, bottom, top, near and far planes.
+    /// The far plane is degenerate (always passes) for infinite projections
+    frustum_planes: [Vec4; 6],
 
     pub tween: Option<Tween>,
+    /// The tween that reached its end during the last update. Cancelled tweens don't end up here
+    pub finished_tween: Option<Tween>,
+
+    pub scroll_action: ScrollAction,
+
+    /// Use [`Self::set_mode`] to switch modes
+    mode: CameraMode,
+    pub orbit_pivot: Vec3,
+    /// Distance between the camera and the orbit pivot
+    pub orbit_distance: f32,
 }
 
 impl Default for FpsCamera {
@@ -38,15 +124,77 @@ impl Default for FpsCamera {
             orientation: Vec2::ZERO,
             speed_mul: 1.0,
             fov: 90.0,
+            z_near: Z_NEAR,
+            z_far: None,
+            reverse_z: true,
+            clip_plane: None,
+            target_aspect: None,
+            projection_fov: 90.0,
+            smooth_movement: false,
+            smoothing: 0.1,
+            velocity: Vec3::ZERO,
+            mouse_sensitivity: 1.0,
+            fov_compensation: true,
             view_matrix: Mat4::IDENTITY,
             projection_matrix: Mat4::IDENTITY,
             projection_view_matrix: Mat4::IDENTITY,
             projection_view_matrix_inv: Mat4::IDENTITY,
+            frustum_planes: [Vec4::ZERO; 6],
             tween: None,
+            finished_tween: None,
+            scroll_action: ScrollAction::Speed,
+            mode: CameraMode::Fps,
+            orbit_pivot: Vec3::ZERO,
+            orbit_distance: DEFAULT_ORBIT_DISTANCE,
         }
     }
 }
 
+/// Extracts the (normalized) clipping planes from a projection-view matrix.
+/// Works for both depth conventions, as both clip against `0 <= z <= w`
+fn extract_frustum_planes(m: &Mat4) -> [Vec4; 6] {
+    let (r0, r1, r2, r3) = (m.row(0), m.row(1), m.row(2), m.row(3));
+
+    [r3 + r0, r3 - r0, r3 + r1, r3 - r1, r2, r3 - r2].map(|p| {
+        let length = p.truncate().length();
+        if length > 0.0 {
+            p / length
+        } else {
+            p
+        }
+    })
+}
+
+/// Largest rectangle with the target aspect ratio (width / height) that fits in the viewport, centered.
+/// Returns the top left corner and size of the rectangle
+pub fn letterbox_rect(viewport_size: Vec2, target_aspect: f32) -> (Vec2, Vec2) {
+    let size = if viewport_size.x / viewport_size.y > target_aspect {
+        // Pillarbox
+        Vec2::new(viewport_size.y * target_aspect, viewport_size.y)
+    } else {
+        // Letterbox
+        Vec2::new(viewport_size.x, viewport_size.x / target_aspect)
+    };
+
+    ((viewport_size - size) / 2.0, size)
+}
+
+/// Returns false if the box is fully on the negative side of any of the (normalized) planes
+pub fn is_aabb_inside_planes(planes: &[Vec4], bb: &AABB) -> bool {
+    // Unbounded boxes (eg. `AABB::INFINITE`) would produce NaNs below
+    if !bb.min.is_finite() || !bb.max.is_finite() {
+        return true;
+    }
+
+    planes.iter().all(|plane| {
+        let normal = plane.truncate();
+        // Corner of the box that is furthest along the plane normal
+        let corner = Vec3::select(normal.cmpge(Vec3::ZERO), bb.max, bb.min);
+
+        normal.dot(corner) + plane.w >= 0.0
+    })
+}
+
 fn flatten_xy(mut dir: Vec3, default: Vec3) -> Vec3 {
     dir[2] = 0.0;
     dir.try_normalize().unwrap_or(default)
@@ -68,8 +216,90 @@ impl FpsCamera {
             .1;
     }
 
+    /// Moves the camera to the given position and orientation (pitch, yaw in degrees), cancelling any movement in progress
+    pub fn teleport(&mut self, position: Vec3, orientation: Option<Vec2>) {
+        self.tween = None;
+        self.velocity = Vec3::ZERO;
+        self.position = position;
+        if let Some(orientation) = orientation {
+            self.orientation = orientation;
+        }
+        self.update_vectors();
+
+        // The orbit camera derives its position from the pivot
+        if self.mode == CameraMode::Orbit {
+            self.orbit_pivot = self.position + self.front * self.orbit_distance;
+        }
+    }
+
+    pub fn mode(&self) -> CameraMode {
+        self.mode
+    }
+
+    /// Switches the camera mode, without changing the current view.
+    /// The orbit pivot is placed `orbit_distance` in front of the camera, and the fps camera
+    /// simply takes over the eye position and rotation of the orbit camera
+    pub fn set_mode(&mut self, mode: CameraMode) {
+        if mode == self.mode {
+            return;
+        }
+
+        if mode == CameraMode::Orbit {
+            self.orbit_pivot = self.position + self.front * self.orbit_distance;
+        }
+
+        self.mode = mode;
+        self.velocity = Vec3::ZERO;
+    }
+
+    /// Places the camera `orbit_distance` away from the pivot, looking at it
+    fn update_orbit_position(&mut self) {
+        self.position = self.orbit_pivot - self.front * self.orbit_distance;
+    }
+
+    /// Applies the given amount of scroll wheel steps, according to the camera mode and `scroll_action`
+    pub fn scroll(&mut self, steps: f32) {
+        if self.mode == CameraMode::Orbit {
+            self.orbit_distance = (self.orbit_distance * SCROLL_SPEED_FACTOR.powf(-steps))
+                .clamp(ORBIT_DISTANCE_RANGE.start, ORBIT_DISTANCE_RANGE.end);
+            self.update_orbit_position();
+            return;
+        }
+
+        match self.scroll_action {
+            ScrollAction::Speed => self.scroll_speed(steps),
+            ScrollAction::Fov => {
+                self.fov =
+                    (self.fov - steps * SCROLL_FOV_STEP).clamp(FOV_RANGE.start, FOV_RANGE.end)
+            }
+            ScrollAction::Dolly => {
+                self.tween = None;
+                self.position += self.front * steps * SCROLL_DOLLY_STEP * self.speed_mul;
+            }
+        }
+    }
+
+    /// Scales the base movement speed by the given amount of scroll wheel steps
+    pub fn scroll_speed(&mut self, steps: f32) {
+        self.speed_mul = (self.speed_mul * SCROLL_SPEED_FACTOR.powf(steps))
+            .clamp(SPEED_MUL_RANGE.start, SPEED_MUL_RANGE.end);
+    }
+
+    /// Multiplier applied to mouse look, combining the base sensitivity and field of view compensation
+    pub fn mouse_look_scale(&self) -> f32 {
+        let mut scale = self.mouse_sensitivity;
+        if self.fov_compensation {
+            // The ratio of the view plane sizes keeps the on-screen movement consistent, unlike the ratio of the angles
+            scale *= (self.projection_fov.to_radians() / 2.0).tan()
+                / (MOUSE_REFERENCE_FOV.to_radians() / 2.0).tan();
+        }
+
+        scale
+    }
+
     pub fn update_mouse(&mut self, mouse_delta: Vec2) {
-        self.orientation += Vec2::new(mouse_delta.y * 0.8, mouse_delta.x) * 0.15;
+        self.orientation +=
+            Vec2::new(mouse_delta.y * 0.8, mouse_delta.x) * 0.15 * self.mouse_look_scale();
         // Cancel angle tween if the user rotates the camera
         if self
             .tween
@@ -79,72 +309,106 @@ impl FpsCamera {
             self.tween = None;
         }
         self.update_vectors();
+
+        if self.mode == CameraMode::Orbit {
+            self.update_orbit_position();
+        }
     }
 
-    pub fn update(&mut self, input: &InputState, window_size: (u32, u32), delta: f32) {
-        let mut speed = delta * 35.0;
+    pub fn update(
+        &mut self,
+        input: &InputState,
+        bindings: &KeyBindings,
+        window_size: (u32, u32),
+        delta: f32,
+    ) {
+        let mut speed = 35.0;
         let mut absolute = false;
         if input.shift() {
-            speed *= 3.0;
+            speed *= 5.0;
         }
         if input.ctrl() {
-            speed *= 0.10;
+            speed *= 0.2;
+        }
+        let pad = input.gamepad;
+        if pad.boost {
+            speed *= 5.0;
+        }
+        if pad.slow {
+            speed *= 0.2;
         }
         // We're gonna have to go right to... LUDICROUS SPEED
-        if input.is_key_down(VirtualKeyCode::Space) {
+        if input.is_action_down(bindings, Action::CameraBoost) {
             speed *= 10.0;
         }
 
-        if input.is_key_down(VirtualKeyCode::LAlt) {
+        if input.is_action_down(bindings, Action::CameraAbsolute) {
             absolute = true;
         }
 
         let mut direction = Vec3::ZERO;
 
         if absolute {
-            if input.is_key_down(VirtualKeyCode::W) {
+            if input.is_action_down(bindings, Action::CameraForward) {
                 direction += flatten_xy(self.front, Vec3::X);
             }
-            if input.is_key_down(VirtualKeyCode::S) {
+            if input.is_action_down(bindings, Action::CameraBackward) {
                 direction -= flatten_xy(self.front, Vec3::X);
             }
 
-            if input.is_key_down(VirtualKeyCode::A) {
+            if input.is_action_down(bindings, Action::CameraLeft) {
                 direction -= flatten_xy(self.right, Vec3::Y);
             }
-            if input.is_key_down(VirtualKeyCode::D) {
+            if input.is_action_down(bindings, Action::CameraRight) {
                 direction += flatten_xy(self.right, Vec3::Y);
             }
 
-            if input.is_key_down(VirtualKeyCode::Q) {
+            if input.is_action_down(bindings, Action::CameraDown) {
                 direction -= Vec3::Z;
             }
-            if input.is_key_down(VirtualKeyCode::E) {
+            if input.is_action_down(bindings, Action::CameraUp) {
                 direction += Vec3::Z;
             }
         } else {
-            if input.is_key_down(VirtualKeyCode::W) {
+            if input.is_action_down(bindings, Action::CameraForward) {
                 direction += self.front;
             }
-            if input.is_key_down(VirtualKeyCode::S) {
+            if input.is_action_down(bindings, Action::CameraBackward) {
                 direction -= self.front;
             }
 
-            if input.is_key_down(VirtualKeyCode::A) {
+            if input.is_action_down(bindings, Action::CameraLeft) {
                 direction -= self.right;
             }
-            if input.is_key_down(VirtualKeyCode::D) {
+            if input.is_action_down(bindings, Action::CameraRight) {
                 direction += self.right;
             }
 
-            if input.is_key_down(VirtualKeyCode::Q) {
+            if input.is_action_down(bindings, Action::CameraDown) {
                 direction -= self.up;
             }
-            if input.is_key_down(VirtualKeyCode::E) {
+            if input.is_action_down(bindings, Action::CameraUp) {
                 direction += self.up;
             }
         }
 
+        // Analog input is added on top of the keys, so stick deflection scales the speed
+        direction += self.front * pad.left_stick.y
+            + self.right * pad.left_stick.x
+            + self.up * (pad.right_trigger - pad.left_trigger);
+
+        if pad.right_stick != Vec2::ZERO {
+            self.orientation +=
+                Vec2::new(-pad.right_stick.y, pad.right_stick.x) * GAMEPAD_LOOK_SPEED * delta;
+            if self
+                .tween
+                .as_ref()
+                .map_or(false, |t| t.angle_movement.is_some())
+            {
+                self.tween = None;
+            }
+        }
+
         speed *= self.speed_mul;
 
         // Cancel tween if the user moves the camera
@@ -152,36 +416,156 @@ impl FpsCamera {
             self.tween = None;
         }
 
+        let target_velocity = direction * speed;
+        if self.smooth_movement && self.smoothing > 0.0 {
+            // Exponential decay towards the target, independent of framerate
+            let t = 1.0 - (-delta / self.smoothing).exp();
+            self.velocity = self.velocity.lerp(target_velocity, t);
+        } else {
+            self.velocity = target_velocity;
+        }
+
+        let tweening = self.tween.is_some();
         if let Some(tween) = &mut self.tween {
+            self.velocity = Vec3::ZERO;
             self.position = tween.update_pos().unwrap_or(self.position);
             self.orientation = tween.update_angle().unwrap_or(self.orientation);
+        } else if self.mode == CameraMode::Orbit {
+            self.orbit_pivot += self.velocity * delta;
         } else {
-            self.position += direction * speed;
+            self.position += self.velocity * delta;
         }
 
-        if self.tween.as_ref().is_some_and(Tween::is_finished) {
-            self.tween = None;
-        }
+        self.finished_tween = if self.tween.as_ref().is_some_and(Tween::is_finished) {
+            self.tween.take()
+        } else {
+            None
+        };
 
         self.orientation.x = self.orientation.x.clamp(-89.9, 89.9);
         self.orientation.y %= 360.0;
 
         self.update_vectors();
 
+        if self.mode == CameraMode::Orbit {
+            // Tweens move the camera itself, so the pivot has to follow along
+            if tweening {
+                self.orbit_pivot = self.position + self.front * self.orbit_distance;
+            } else {
+                self.update_orbit_position();
+            }
+        }
+
+        self.fov = self.fov.clamp(FOV_RANGE.start, FOV_RANGE.end);
         self.view_matrix = self.calculate_matrix();
-        self.projection_matrix = Mat4::perspective_infinite_reverse_rh(
-            self.fov.to_radians(),
-            window_size.0 as f32 / window_size.1 as f32,
-            0.0001,
-        );
+        self.projection_matrix =
+            self.calculate_projection(Vec2::new(window_size.0 as f32, window_size.1 as f32));
+        if let Some(plane) = self.clip_plane {
+            self.projection_matrix = self.clip_projection(plane);
+        }
         self.projection_view_matrix = self.projection_matrix * self.view_matrix;
         self.projection_view_matrix_inv = self.projection_view_matrix.inverse();
+        self.frustum_planes = extract_frustum_planes(&self.projection_view_matrix);
     }
 
     fn calculate_matrix(&self) -> Mat4 {
         Mat4::look_at_rh(self.position, self.position + self.front, Vec3::Z)
     }
 
+    fn calculate_projection(&mut self, viewport_size: Vec2) -> Mat4 {
+        let aspect_ratio = viewport_size.x / viewport_size.y;
+        let mut fov = self.fov.to_radians();
+        if let Some(target_aspect) = self.target_aspect {
+            // Widen the field of view so the letterboxed area sees exactly `fov`
+            let (_, inner) = letterbox_rect(viewport_size, target_aspect);
+            fov = ((fov / 2.0).tan() * viewport_size.y / inner.y).atan() * 2.0;
+        }
+        self.projection_fov = fov.to_degrees();
+
+        self.z_near = self.z_near.max(Z_NEAR_MIN);
+        let z_near = self.z_near;
+
+        match (self.z_far, self.reverse_z) {
+            (None, true) => Mat4::perspective_infinite_reverse_rh(fov, aspect_ratio, z_near),
+            (None, false) => Mat4::perspective_infinite_rh(fov, aspect_ratio, z_near),
+            (Some(z_far), reverse_z) => {
+                let z_far = z_far.max(z_near * 2.0);
+                self.z_far = Some(z_far);
+
+                // Swapping the planes flips the depth range
+                if reverse_z {
+                    Mat4::perspective_rh(fov, aspect_ratio, z_far, z_near)
+                } else {
+                    Mat4::perspective_rh(fov, aspect_ratio, z_near, z_far)
+                }
+            }
+        }
+    }
+
+    /// Replaces the near or far plane of the projection with the given world-space plane, so
+    /// everything on its negative side is clipped without needing any changes to the shaders.
+    ///
+    /// When the camera is on the clipped side, the plane becomes the (oblique) near plane,
+    /// otherwise it becomes the far plane.
+    /// See Lengyel, "Oblique View Frustum Depth Projection and Clipping"
+    fn clip_projection(&self, plane: Vec4) -> Mat4 {
+        let projection = self.projection_matrix;
+        let plane_view = self.view_matrix.inverse().transpose() * plane;
+        let camera_clipped = plane_view.w < 0.0;
+
+        // Rows are easier to work with in the transposed matrix
+        let mut rows = projection.transpose();
+        let row3 = rows.w_axis;
+
+        // Both conventions clip against 0 <= z <= w, reverse-Z just swaps which side is the near plane
+        let new_row2 = if camera_clipped {
+            // Scale the plane so the new far plane goes through the far corner of the frustum
+            // furthest along the plane normal, which keeps the whole visible volume in 0..1
+            let corner = Vec4::new(
+                plane_view.x.signum() / projection.x_axis.x,
+                plane_view.y.signum() / projection.y_axis.y,
+                -1.0,
+                0.0,
+            );
+            let corner = match self.z_far {
+                Some(z_far) => (corner * z_far).truncate().extend(1.0),
+                None => corner,
+            };
+
+            let d = plane_view.dot(corner);
+            if d <= f32::EPSILON {
+                return projection;
+            }
+
+            let scaled = plane_view * (row3.dot(corner) / d);
+            if self.reverse_z {
+                row3 - scaled
+            } else {
+                scaled
+            }
+        } else {
+            // Place the new near plane roughly at the regular near plane distance
+            let scaled = plane_view * (self.z_near / plane_view.w.max(self.z_near));
+            if self.reverse_z {
+                scaled
+            } else {
+                row3 - scaled
+            }
+        };
+
+        rows.z_axis = new_row2;
+        rows.transpose()
+    }
+
+    /// Depth value the depth buffer should be cleared to (the far plane)
+    pub fn depth_clear_value(&self) -> f32 {
+        if self.reverse_z {
+            0.0
+        } else {
+            1.0
+        }
+    }
+
     // pub fn rotation(&self) -> Quat {
     //     Quat::from_rotation_y(self.orientation.y.to_radians())
     //         * Quat::from_rotation_x(self.orientation.x.to_radians())
@@ -225,7 +609,7 @@ impl FpsCamera {
         aspect_ratio: f32,
     ) -> Mat4 {
         let proj = Mat4::perspective_rh(
-            self.fov.to_radians(),
+            self.projection_fov.to_radians(),
             aspect_ratio,
             cascade_z_start,
             cascade_z_end,
@@ -276,31 +660,23 @@ impl FpsCamera {
     }
 
     pub fn is_point_visible(&self, point: Vec3) -> bool {
-        let point_transformed = self.projection_view_matrix.project_point3(point);
+        let clip = self.projection_view_matrix * point.extend(1.0);
 
-        point_transformed.z >= 0.0
+        clip.w > 0.0 && clip.z >= 0.0 && clip.z <= clip.w
     }
 
+    /// Returns false if the box is fully outside of the view frustum
     pub fn is_aabb_visible(&self, bb: &AABB) -> bool {
-        let mut corners = [Vec3::ZERO; 8];
-
-        let mut i = 0;
-        for x in [bb.min.x, bb.max.x].iter() {
-            for y in [bb.min.y, bb.max.y].iter() {
-                for z in [bb.min.z, bb.max.z].iter() {
-                    corners[i] = Vec3::new(*x, *y, *z);
-                    i += 1;
-                }
-            }
-        }
-
-        for corner in corners.iter() {
-            if self.is_point_visible(*corner) {
-                return true;
-            }
-        }
+        is_aabb_inside_planes(&self.frustum_planes, bb)
+    }
 
-        false
+    /// Direction of the ray from the camera through the given point in normalized device coordinates
+    pub fn screen_ray(&self, ndc: Vec2) -> Vec3 {
+        // Depth 0.5 is in front of the camera for both regular and reverse Z, and can't end up at infinity
+        let point = self
+            .projection_view_matrix_inv
+            .project_point3(ndc.extend(0.5));
+        (point - self.position).normalize()
     }
 
     pub fn focus(&mut self, pos: Vec3, distance: f32) {
@@ -319,6 +695,15 @@ impl FpsCamera {
         self.focus(center, radius);
     }
 
+    /// Places the camera so the entire bounding box is in view from the given angle, without tweening
+    pub fn frame_aabb(&mut self, bb: &AABB, orientation: Vec2) {
+        self.orientation = orientation;
+        self.update_vectors();
+
+        let distance = bb.radius() / (self.fov.to_radians() / 2.0).tan();
+        self.teleport(bb.center() - self.front * distance, None);
+    }
+
     // Calculate angle to point camera at pos.
     // The angle has a minimal diff to current camera angle.
     pub fn get_look_angle(&self, pos: Vec3) -> Vec2 {
@@ -0,0 +1,180 @@
+use std::rc::Rc;
+
+use crate::{
+    camera::FpsCamera,
+    ecs::{
+        persistence::{self, AutosaveState},
+        resources::SelectedEntity,
+    },
+    hotkeys::{
+        self, SHORTCUT_GROUP, SHORTCUT_HIDE_UNSELECTED, SHORTCUT_REDO, SHORTCUT_UNDO,
+        SHORTCUT_UNGROUP, SHORTCUT_UNHIDE_ALL,
+    },
+    map::MapDataList,
+    overlays::{
+        gpu_info::GpuInfoWindow, gui::ViewerWindows, load_errors::LoadErrorsWindow,
+        scene_stats::SceneStatsWindow,
+    },
+    render::settings::{RenderSettings, COMPOSITOR_MODES},
+    render_globals::ReloadShadersRequest,
+    resources::Resources,
+    screenshot::ScreenshotRequest,
+};
+
+pub type CommandFn = Rc<dyn Fn(&mut Resources)>;
+
+pub struct Command {
+    pub name: String,
+    pub shortcut: Option<egui::KeyboardShortcut>,
+    pub run: CommandFn,
+}
+
+/// Named actions, listed in the command palette. Commands with a shortcut are also run by [`hotkeys::process_hotkeys`]
+#[derive(Default)]
+pub struct Commands(pub Vec<Command>);
+
+impl Commands {
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        shortcut: Option<egui::KeyboardShortcut>,
+        run: impl Fn(&mut Resources) + 'static,
+    ) {
+        self.0.push(Command {
+            name: name.into(),
+            shortcut,
+            run: Rc::new(run),
+        });
+    }
+
+    /// Consumes the shortcuts pressed this frame, returning the commands they belong to.
+    /// Commands are checked in registration order, so shortcuts with more modifiers need to be registered first
+    pub fn pressed(&self, ctx: &egui::Context) -> Vec<CommandFn> {
+        self.0
+            .iter()
+            .filter(|c| {
+                c.shortcut
+                    .map_or(false, |s| ctx.input_mut(|i| i.consume_shortcut(&s)))
+            })
+            .map(|c| c.run.clone())
+            .collect()
+    }
+
+    pub fn with_defaults() -> Self {
+        let mut commands = Self::default();
+
+        commands.register("Undo", Some(SHORTCUT_UNDO), hotkeys::undo);
+        commands.register("Redo", Some(SHORTCUT_REDO), hotkeys::redo);
+        commands.register("Unhide all", Some(SHORTCUT_UNHIDE_ALL), hotkeys::unhide_all);
+        commands.register(
+            "Solo selected",
+            Some(SHORTCUT_HIDE_UNSELECTED),
+            hotkeys::hide_unselected,
+        );
+        commands.register("Ungroup", Some(SHORTCUT_UNGROUP), hotkeys::ungroup_selected);
+        commands.register("Group", Some(SHORTCUT_GROUP), hotkeys::group_selected);
+
+        commands.register("Frame selected", None, |r| hotkeys::frame_selected(r));
+        commands.register("Frame entire map", None, |r| hotkeys::frame_map(r));
+        commands.register("Take screenshot", None, |r| {
+            r.get_mut::<ScreenshotRequest>().unwrap().0 = true;
+        });
+        commands.register("Frame selected and take screenshot", None, |r| {
+            hotkeys::frame_selected(r);
+
+            // Wait for the camera to arrive before capturing
+            let mut camera = r.get_mut::<FpsCamera>().unwrap();
+            if let Some(tween) = camera.tween.take() {
+                camera.tween = Some(tween.with_on_finish(|r| {
+                    r.get_mut::<ScreenshotRequest>().unwrap().0 = true;
+                }));
+            } else {
+                r.get_mut::<ScreenshotRequest>().unwrap().0 = true;
+            }
+        });
+
+        commands.register("Reload shaders", None, |r| {
+            r.get_mut::<ReloadShadersRequest>().unwrap().0 = true;
+        });
+
+        commands.register("Previous map", None, |r| step_map(r, -1));
+        commands.register("Next map", None, |r| step_map(r, 1));
+        commands.register("Save map", None, |r| {
+            let maps = r.get::<MapDataList>().unwrap();
+            if let Some((_, _, map)) = maps.current_map() {
+                persistence::autosave_map(map, &mut r.get_mut::<AutosaveState>().unwrap());
+            }
+        });
+
+        commands.register("Toggle lights", None, |r| {
+            let mut settings = r.get_mut::<RenderSettings>().unwrap();
+            settings.draw_lights = !settings.draw_lights;
+        });
+        commands.register("Toggle shadows", None, |r| {
+            let mut settings = r.get_mut::<RenderSettings>().unwrap();
+            settings.render_shadows = !settings.render_shadows;
+        });
+        commands.register("Toggle fog", None, |r| {
+            let mut settings = r.get_mut::<RenderSettings>().unwrap();
+            settings.fog_enabled = !settings.fog_enabled;
+        });
+        commands.register("Toggle bloom", None, |r| {
+            let mut settings = r.get_mut::<RenderSettings>().unwrap();
+            settings.bloom_enabled = !settings.bloom_enabled;
+        });
+        commands.register("Toggle depth of field", None, |r| {
+            let mut settings = r.get_mut::<RenderSettings>().unwrap();
+            settings.dof_enabled = !settings.dof_enabled;
+        });
+        commands.register("Toggle grid", None, |r| {
+            let mut settings = r.get_mut::<RenderSettings>().unwrap();
+            settings.grid_enabled = !settings.grid_enabled;
+        });
+        commands.register("Toggle material info", None, |r| {
+            let mut settings = r.get_mut::<RenderSettings>().unwrap();
+            settings.show_material_info = !settings.show_material_info;
+        });
+
+        for (i, mode) in COMPOSITOR_MODES.iter().enumerate() {
+            commands.register(format!("Render pass: {mode}"), None, move |r| {
+                r.get_mut::<RenderSettings>().unwrap().compositor_mode = i;
+            });
+        }
+
+        commands.register("Scene statistics", None, |r| {
+            if let Some(mut viewers) = r.get_mut::<ViewerWindows>() {
+                viewers
+                    .0
+                    .entry("scene_stats".to_string())
+                    .or_insert_with(|| Box::<SceneStatsWindow>::default());
+            }
+        });
+        commands.register("GPU info", None, |r| {
+            if let Some(mut viewers) = r.get_mut::<ViewerWindows>() {
+                viewers
+                    .0
+                    .entry("gpu_info".to_string())
+                    .or_insert_with(|| Box::<GpuInfoWindow>::default());
+            }
+        });
+        commands.register("Load errors", None, |r| {
+            if let Some(mut viewers) = r.get_mut::<ViewerWindows>() {
+                viewers
+                    .0
+                    .entry("load_errors".to_string())
+                    .or_insert_with(|| Box::<LoadErrorsWindow>::default());
+            }
+        });
+
+        commands
+    }
+}
+
+fn step_map(resources: &mut Resources, offset: isize) {
+    let mut maps = resources.get_mut::<MapDataList>().unwrap();
+    maps.step_catalog(
+        offset,
+        &mut resources.get_mut::<SelectedEntity>().unwrap(),
+        &mut resources.get_mut::<FpsCamera>().unwrap(),
+    );
+}
@@ -1,4 +1,11 @@
+use crate::camera::{ScrollAction, Z_NEAR};
+use crate::ecs::tags::TagPalette;
+use crate::keybinds::KeyBindings;
+use crate::map::MapSortMode;
+use crate::overlays::axis_gizmo::ScreenCorner;
+use crate::render::settings::RenderPreset;
 use crate::util::{exe_relative_path, RwLock};
+use destiny_pkg::TagHash;
 use egui::epaint::ahash::HashMap;
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
@@ -44,6 +51,19 @@ macro_rules! config {
 pub struct Config {
     pub window: WindowConfig,
     pub resources: ResourceConfig,
+    pub camera: CameraConfig,
+    pub keybinds: KeyBindings,
+    pub gamepad: GamepadConfig,
+    /// User-defined render setting presets
+    pub render_presets: Vec<RenderPreset>,
+    pub axis_gizmo: AxisGizmoConfig,
+    pub tag_palette: TagPalette,
+    pub map_sort: MapSortMode,
+    pub recent_maps: RecentMaps,
+    pub autosave: AutosaveConfig,
+    pub gpu: GpuConfig,
+    pub prefetch: PrefetchConfig,
+    pub map_unload: MapUnloadConfig,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -87,3 +107,173 @@ impl Default for WindowConfig {
         }
     }
 }
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraConfig {
+    /// Base movement speed multiplier, adjustable with the scroll wheel
+    pub speed_mul: f32,
+    pub scroll_action: ScrollAction,
+    pub smooth_movement: bool,
+    /// Time constant of the movement smoothing, in seconds
+    pub smoothing: f32,
+    pub z_near: f32,
+    /// `None` for an infinite far plane
+    pub z_far: Option<f32>,
+    pub reverse_z: bool,
+    pub mouse_sensitivity: f32,
+    /// Scale mouse look with the field of view
+    pub fov_compensation: bool,
+}
+
+impl Default for CameraConfig {
+    fn default() -> Self {
+        Self {
+            speed_mul: 1.0,
+            scroll_action: ScrollAction::Speed,
+            smooth_movement: false,
+            smoothing: 0.1,
+            z_near: Z_NEAR,
+            z_far: None,
+            reverse_z: true,
+            mouse_sensitivity: 1.0,
+            fov_compensation: true,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct PrefetchConfig {
+    /// Only keep the current map and the maps next to it in the map list loaded, instead of loading every map at once
+    pub enabled: bool,
+    /// Amount of maps before and after the current map that are loaded in the background
+    pub window: usize,
+}
+
+impl Default for PrefetchConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            window: 1,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct MapUnloadConfig {
+    /// Unload the least recently used maps when more than `max_loaded_maps` maps are loaded
+    pub auto_unload: bool,
+    pub max_loaded_maps: usize,
+}
+
+impl Default for MapUnloadConfig {
+    fn default() -> Self {
+        Self {
+            auto_unload: false,
+            max_loaded_maps: 8,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct GpuConfig {
+    /// Name of the adapter to create the device on, `None` uses the system default.
+    /// Only applied on startup
+    pub adapter: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct GamepadConfig {
+    pub enabled: bool,
+    /// Stick and trigger values below this are ignored to prevent drift
+    pub deadzone: f32,
+}
+
+impl Default for GamepadConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            deadzone: 0.15,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct AxisGizmoConfig {
+    pub enabled: bool,
+    /// Width and height of the gizmo, in points
+    pub size: f32,
+    pub corner: ScreenCorner,
+}
+
+impl Default for AxisGizmoConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            size: 96.0,
+            corner: ScreenCorner::TopRight,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct AutosaveConfig {
+    /// Periodically saves the user entities of all loaded maps. They are always saved on exit
+    pub enabled: bool,
+    /// Time between saves, in seconds
+    pub interval: f32,
+}
+
+impl Default for AutosaveConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval: 120.0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RecentMap {
+    /// Raw value of the map's TagHash
+    pub hash: u32,
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct RecentMaps {
+    pub max_entries: usize,
+    /// Most recently opened first
+    pub maps: Vec<RecentMap>,
+}
+
+impl RecentMaps {
+    /// Moves the map to the top of the list, adding it if it wasn't in there yet
+    pub fn push(&mut self, hash: TagHash, name: &str) {
+        self.maps.retain(|m| m.hash != hash.0);
+        self.maps.insert(
+            0,
+            RecentMap {
+                hash: hash.0,
+                name: name.to_string(),
+            },
+        );
+        self.maps.truncate(self.max_entries);
+    }
+}
+
+impl Default for RecentMaps {
+    fn default() -> Self {
+        Self {
+            max_entries: 10,
+            maps: vec![],
+        }
+    }
+}
@@ -1,20 +1,30 @@
+use std::f32::consts::PI;
+
+use destiny_pkg::TagHash;
 use egui::{Color32, FontId, RichText, Widget};
 use glam::{Quat, Vec3};
 use hecs::{Entity, EntityRef};
+use strum::IntoEnumIterator;
 
 use crate::{
     camera::FpsCamera,
     ecs::transform::TransformFlags,
     hotkeys::{SHORTCUT_DELETE, SHORTCUT_HIDE},
     icons::{
-        ICON_ALERT, ICON_ALPHA_A_BOX, ICON_ALPHA_B_BOX, ICON_AXIS_ARROW, ICON_CAMERA,
-        ICON_CAMERA_CONTROL, ICON_CUBE_OUTLINE, ICON_DELETE, ICON_EYE, ICON_EYE_OFF, ICON_HELP,
-        ICON_IDENTIFIER, ICON_MAP_MARKER, ICON_RADIUS_OUTLINE, ICON_RESIZE, ICON_ROTATE_ORBIT,
-        ICON_RULER_SQUARE, ICON_SIGN_POLE, ICON_SPHERE, ICON_TAG,
+        ICON_ALERT, ICON_ALPHA_A_BOX, ICON_ALPHA_B_BOX, ICON_ANGLE_ACUTE, ICON_ARROW_DOWN,
+        ICON_ARROW_UP, ICON_AXIS_ARROW, ICON_CAMERA, ICON_CAMERA_CONTROL, ICON_CLIPBOARD,
+        ICON_CUBE_OUTLINE, ICON_DELETE, ICON_EYE, ICON_EYE_OFF, ICON_HELP, ICON_IDENTIFIER,
+        ICON_IMAGE_MULTIPLE, ICON_LIGHTBULB_ON, ICON_MAP, ICON_MAP_MARKER, ICON_MAP_MARKER_PATH,
+        ICON_MAP_MARKER_PLUS, ICON_MAP_MARKER_REMOVE, ICON_NOTE_TEXT, ICON_OPACITY,
+        ICON_RADIUS_OUTLINE, ICON_RESIZE, ICON_RESTORE, ICON_ROTATE_ORBIT, ICON_RULER_SQUARE,
+        ICON_SIGN_POLE, ICON_SPHERE, ICON_TAG, ICON_VECTOR_POLYGON,
     },
-    render::tween::Tween,
+    render::{renderer::RendererShared, tween::Tween},
     resources::Resources,
     util::{
+        error::ErrorAlert,
+        exe_relative_path,
+        export::export_material_textures,
         text::{prettify_distance, split_pascal_case},
         BoolExts as _,
     },
@@ -22,10 +32,13 @@ use crate::{
 
 use super::{
     components::{
-        Beacon, EntityModel, EntityWorldId, Global, Label, Mutable, ResourcePoint, Ruler, Sphere,
-        StaticInstances, Visible,
+        Beacon, EditedLabel, EntityModel, EntityWorldId, ErrorMarker, Global, Label, LightColor,
+        Mutable, Note, OpacityOverride, ReferenceMesh, ResourcePoint, Route, RouteNode, Ruler,
+        RulerUnit, SourceMap, Sphere, StaticInstances, Visible,
     },
+    history::{despawn_mutable, EditHistory},
     resolve_entity_icon, resolve_entity_name,
+    resources::SelectedEntity,
     tags::{insert_tag, remove_tag, EntityTag, Tags},
     transform::{OriginalTransform, Transform},
     Scene,
@@ -35,6 +48,7 @@ pub fn show_inspector_panel(
     ui: &mut egui::Ui,
     scene: &mut Scene,
     cmd: &mut hecs::CommandBuffer,
+    history: &mut EditHistory,
     ent: Entity,
     resources: &Resources,
 ) {
@@ -55,7 +69,7 @@ pub fn show_inspector_panel(
                 .clicked()
                 || ui.input_mut(|i| i.consume_shortcut(&SHORTCUT_DELETE)))
         {
-            cmd.despawn(ent);
+            despawn_mutable(e, cmd, history);
         }
 
         if ui
@@ -74,29 +88,65 @@ pub fn show_inspector_panel(
             }
         }
 
+        // Applies to the whole selection, so overlapping entities can be made see-through at once
+        let has_opacity = e.has::<OpacityOverride>();
+        if ui
+            .button(RichText::new(ICON_OPACITY).size(24.0).strong())
+            .on_hover_text(if has_opacity {
+                "Remove opacity override"
+            } else {
+                "Make selected entities see-through"
+            })
+            .clicked()
+        {
+            let selection = resources.get::<SelectedEntity>().unwrap();
+            for selected in selection.iter() {
+                if has_opacity {
+                    cmd.remove_one::<OpacityOverride>(selected);
+                } else {
+                    cmd.insert_one(selected, OpacityOverride(0.5));
+                }
+            }
+        }
+
         let title = format!(
             "{} {}",
             resolve_entity_icon(e).unwrap_or(ICON_HELP),
             resolve_entity_name(e, true)
         );
 
-        if e.has::<Mutable>() {
-            if let Some(mut label) = e.get::<&mut Label>() {
-                egui::TextEdit::singleline(&mut label.0)
-                    .font(FontId::proportional(22.0))
-                    .ui(ui);
-            } else {
-                ui.label(RichText::new(title).size(24.0).strong());
-                if ui
-                    .button(RichText::new(ICON_TAG.to_string()).size(24.0).strong())
-                    .on_hover_text("Add label")
-                    .clicked()
-                {
-                    cmd.insert_one(ent, Label(resolve_entity_name(e, false)));
-                }
+        // Labels on map entities are saved separately, so keep track of which ones were changed
+        let mark_edited = |cmd: &mut hecs::CommandBuffer| {
+            if !e.has::<Mutable>() && e.has::<EntityWorldId>() {
+                cmd.insert_one(ent, EditedLabel);
+            }
+        };
+
+        if let Some(mut label) = e.get::<&mut Label>() {
+            let response = egui::TextEdit::singleline(&mut label.0)
+                .font(FontId::proportional(22.0))
+                .hint_text("Label")
+                .ui(ui);
+
+            if response.changed() {
+                mark_edited(cmd);
+            }
+
+            // An empty label falls back to the default name
+            if label.0.is_empty() && !response.has_focus() {
+                cmd.remove_one::<Label>(ent);
+                mark_edited(cmd);
             }
         } else {
             ui.label(RichText::new(title).size(24.0).strong());
+            if ui
+                .button(RichText::new(ICON_TAG.to_string()).size(24.0).strong())
+                .on_hover_text("Add label")
+                .clicked()
+            {
+                cmd.insert_one(ent, Label(resolve_entity_name(e, false)));
+                mark_edited(cmd);
+            }
         }
     });
     ui.separator();
@@ -122,7 +172,7 @@ pub fn show_inspector_panel(
         };
         ui.separator();
     }
-    show_inspector_components(ui, e, resources);
+    show_inspector_components(ui, e, history, resources);
 
     if global_changed {
         if global {
@@ -133,8 +183,14 @@ pub fn show_inspector_panel(
     }
 }
 
-fn show_inspector_components(ui: &mut egui::Ui, e: EntityRef<'_>, resources: &Resources) {
+fn show_inspector_components(
+    ui: &mut egui::Ui,
+    e: EntityRef<'_>,
+    history: &mut EditHistory,
+    resources: &Resources,
+) {
     if let Some(mut t) = e.get::<&mut Transform>() {
+        let transform_before = *t;
         inspector_component_frame(ui, "Transform", ICON_AXIS_ARROW, |ui| {
             t.show_inspector_ui(e, ui, resources);
             if let Some(ot) = e.get::<&OriginalTransform>() {
@@ -150,6 +206,14 @@ fn show_inspector_components(ui: &mut egui::Ui, e: EntityRef<'_>, resources: &Re
 				});
             }
         });
+
+        if !ui.input(|i| i.pointer.any_down()) {
+            history.finish_transform_edit();
+        }
+
+        if e.has::<Mutable>() && *t != transform_before {
+            history.record_transform(e.entity(), transform_before, *t);
+        }
     }
 
     macro_rules! component_views {
@@ -170,9 +234,16 @@ fn show_inspector_components(ui: &mut egui::Ui, e: EntityRef<'_>, resources: &Re
         StaticInstances,
         // HavokShape,
         EntityWorldId,
+        SourceMap,
+        OpacityOverride,
+        ErrorMarker,
+        LightColor,
         Ruler,
+        Route,
         Sphere,
-        Beacon
+        Beacon,
+        Note,
+        ReferenceMesh
     );
 }
 
@@ -253,11 +324,19 @@ impl ComponentPanel for Transform {
         true
     }
 
-    fn show_inspector_ui(&mut self, _: EntityRef<'_>, ui: &mut egui::Ui, resources: &Resources) {
-        let mut rotation_euler: Vec3 = self.rotation.to_euler(glam::EulerRot::XYZ).into();
-        rotation_euler.x = rotation_euler.x.to_degrees();
-        rotation_euler.y = rotation_euler.y.to_degrees();
-        rotation_euler.z = rotation_euler.z.to_degrees();
+    fn show_inspector_ui(&mut self, e: EntityRef<'_>, ui: &mut egui::Ui, resources: &Resources) {
+        // Converting back and forth between euler angles and quaternions every frame makes the
+        // angles jump around (eg. near 90 degrees of pitch), so keep the angles that were typed in
+        // for as long as the rotation isn't changed elsewhere
+        let euler_id = egui::Id::new(("transform_rotation_euler", e.entity()));
+        let mut rotation_euler = ui
+            .data(|d| d.get_temp::<(Quat, Vec3)>(euler_id))
+            .filter(|(rotation, _)| *rotation == self.rotation)
+            .map(|(_, euler)| euler)
+            .unwrap_or_else(|| {
+                let (x, y, z) = self.rotation.to_euler(glam::EulerRot::XYZ);
+                Vec3::new(x.to_degrees(), y.to_degrees(), z.to_degrees())
+            });
 
         let mut rotation_changed = false;
         egui::Grid::new("transform_input_grid")
@@ -321,6 +400,23 @@ impl ComponentPanel for Transform {
                 }
             });
 
+        if ui
+            .button(format!("{ICON_RESTORE} Reset to identity"))
+            .on_hover_text("Resets every field that isn't locked for this entity")
+            .clicked()
+        {
+            if !self.flags.contains(TransformFlags::IGNORE_TRANSLATION) {
+                self.translation = Vec3::ZERO;
+            }
+            if !self.flags.contains(TransformFlags::IGNORE_ROTATION) {
+                rotation_euler = Vec3::ZERO;
+                rotation_changed = true;
+            }
+            if !self.flags.contains(TransformFlags::IGNORE_SCALE) {
+                self.scale = Vec3::ONE;
+            }
+        }
+
         if rotation_changed {
             self.rotation = Quat::from_euler(
                 glam::EulerRot::XYZ,
@@ -328,6 +424,7 @@ impl ComponentPanel for Transform {
                 rotation_euler.y.to_radians(),
                 rotation_euler.z.to_radians(),
             );
+            ui.data_mut(|d| d.insert_temp(euler_id, (self.rotation, rotation_euler)));
         }
     }
 }
@@ -350,6 +447,65 @@ impl ComponentPanel for EntityWorldId {
     }
 }
 
+impl ComponentPanel for SourceMap {
+    fn inspector_name() -> &'static str {
+        "Source Map"
+    }
+
+    fn inspector_icon() -> char {
+        ICON_MAP
+    }
+
+    fn has_inspector_ui() -> bool {
+        true
+    }
+
+    fn show_inspector_ui(&mut self, _: EntityRef<'_>, ui: &mut egui::Ui, _: &Resources) {
+        ui.label(format!("Map hash: {}", self.0));
+    }
+}
+
+impl ComponentPanel for OpacityOverride {
+    fn inspector_name() -> &'static str {
+        "Opacity Override"
+    }
+
+    fn inspector_icon() -> char {
+        ICON_OPACITY
+    }
+
+    fn has_inspector_ui() -> bool {
+        true
+    }
+
+    fn show_inspector_ui(&mut self, _: EntityRef<'_>, ui: &mut egui::Ui, _: &Resources) {
+        ui.add(egui::Slider::new(&mut self.0, 0.0..=1.0).text("Opacity"));
+        ui.label(
+            RichText::new("Decals aren't projected onto the entity while it's see-through")
+                .weak()
+                .italics(),
+        );
+    }
+}
+
+impl ComponentPanel for ErrorMarker {
+    fn inspector_name() -> &'static str {
+        "Load Error"
+    }
+
+    fn inspector_icon() -> char {
+        ICON_ALERT
+    }
+
+    fn has_inspector_ui() -> bool {
+        true
+    }
+
+    fn show_inspector_ui(&mut self, _: EntityRef<'_>, ui: &mut egui::Ui, _: &Resources) {
+        ui.label(RichText::new(&self.0).color(Color32::from_rgb(255, 100, 100)));
+    }
+}
+
 impl ComponentPanel for ResourcePoint {
     fn inspector_name() -> &'static str {
         "Map Resource"
@@ -408,11 +564,12 @@ impl ComponentPanel for EntityModel {
         true
     }
 
-    fn show_inspector_ui(&mut self, _: EntityRef<'_>, ui: &mut egui::Ui, _: &Resources) {
+    fn show_inspector_ui(&mut self, _: EntityRef<'_>, ui: &mut egui::Ui, resources: &Resources) {
         ui.horizontal(|ui| {
             ui.strong("Tag:");
             ui.label(format!("{}", self.2));
         });
+        texture_export_button(ui, self.2, &self.0.materials(), resources);
     }
 }
 
@@ -429,7 +586,7 @@ impl ComponentPanel for StaticInstances {
         true
     }
 
-    fn show_inspector_ui(&mut self, _: EntityRef<'_>, ui: &mut egui::Ui, _: &Resources) {
+    fn show_inspector_ui(&mut self, _: EntityRef<'_>, ui: &mut egui::Ui, resources: &Resources) {
         ui.horizontal(|ui| {
             ui.strong("Mesh tag:");
             ui.label(self.1.to_string());
@@ -438,6 +595,106 @@ impl ComponentPanel for StaticInstances {
             ui.strong("Instance count:");
             ui.label(format!("{}", self.0.instance_count));
         });
+        texture_export_button(ui, self.1, &self.0.materials(), resources);
+
+        let selection = resources.get::<SelectedEntity>().unwrap().clone();
+        let Some((index, matrix)) = selection
+            .instance()
+            .and_then(|i| Some((i, self.0.instance_transform(i)?)))
+        else {
+            ui.label(RichText::new("Right click an instance in the viewport to inspect it").weak());
+            return;
+        };
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.strong(format!("Instance {index}"));
+            if ui
+                .button(ICON_MAP_MARKER.to_string())
+                .on_hover_text("Focus instance")
+                .clicked()
+            {
+                if let Some(bounds) = self.0.occlusion_bounds.get(index) {
+                    resources.get_mut::<FpsCamera>().unwrap().focus_aabb(bounds);
+                }
+            }
+            if ui
+                .button(ICON_CLIPBOARD.to_string())
+                .on_hover_text("Copy world matrix")
+                .clicked()
+            {
+                let rows = (0..4)
+                    .map(|r| {
+                        let row = matrix.row(r);
+                        format!("{} {} {} {}", row.x, row.y, row.z, row.w)
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                ui.output_mut(|o| o.copied_text = rows);
+            }
+        });
+
+        let (scale, rotation, translation) = matrix.to_scale_rotation_translation();
+        let (x, y, z) = rotation.to_euler(glam::EulerRot::XYZ);
+        egui::Grid::new("static_instance_transform")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.strong("Translation");
+                ui.label(format!(
+                    "{:.3}, {:.3}, {:.3}",
+                    translation.x, translation.y, translation.z
+                ));
+                ui.end_row();
+
+                ui.strong("Rotation");
+                ui.label(format!(
+                    "{:.1}°, {:.1}°, {:.1}°",
+                    x.to_degrees(),
+                    y.to_degrees(),
+                    z.to_degrees()
+                ));
+                ui.end_row();
+
+                ui.strong("Scale");
+                ui.label(format!("{:.3}", scale.x));
+                ui.end_row();
+            });
+
+        ui.collapsing("World matrix", |ui| {
+            for r in 0..4 {
+                let row = matrix.row(r);
+                ui.monospace(format!(
+                    "{:>10.3} {:>10.3} {:>10.3} {:>10.3}",
+                    row.x, row.y, row.z, row.w
+                ));
+            }
+        });
+    }
+}
+
+/// Exports the textures of the given materials to `textures/<tag>/` next to the executable
+fn texture_export_button(
+    ui: &mut egui::Ui,
+    tag: TagHash,
+    materials: &[TagHash],
+    resources: &Resources,
+) {
+    if ui
+        .button(format!("{ICON_IMAGE_MULTIPLE} Export textures"))
+        .on_hover_text(format!(
+            "Exports the textures used by this model's {} material(s) to textures/{tag}/ as DDS and PNG",
+            materials.len()
+        ))
+        .clicked()
+    {
+        let renderer = resources.get::<RendererShared>().unwrap();
+        let dir = exe_relative_path(&format!("textures/{tag}"));
+        if let Ok(count) =
+            export_material_textures(&renderer.read().render_data.data(), materials, &dir)
+                .err_alert()
+        {
+            info!("Exported {count} textures to {}", dir.display());
+        }
     }
 }
 
@@ -491,6 +748,18 @@ impl ComponentPanel for Ruler {
                 self.start = camera.position;
             }
         });
+        if let Some(vertex) = &mut self.vertex {
+            ui.horizontal(|ui| {
+                input_float3!(ui, format!("{ICON_ANGLE_ACUTE} Vertex"), vertex);
+                if ui
+                    .button(ICON_CAMERA_CONTROL.to_string())
+                    .on_hover_text("Set position to camera")
+                    .clicked()
+                {
+                    *vertex = camera.position;
+                }
+            });
+        }
         ui.horizontal(|ui| {
             input_float3!(ui, format!("{ICON_ALPHA_B_BOX} End"), &mut self.end);
             if ui
@@ -502,6 +771,15 @@ impl ComponentPanel for Ruler {
             }
         });
 
+        let mut measure_angle = self.vertex.is_some();
+        if ui
+            .checkbox(&mut measure_angle, "Measure angle")
+            .on_hover_text("Adds a vertex between the start and end, and measures the angle at it")
+            .changed()
+        {
+            self.vertex = measure_angle.then_some((self.start + self.end) / 2.0);
+        }
+
         ui.horizontal(|ui| {
             ui.strong("Scale");
             ui.add(
@@ -526,15 +804,33 @@ impl ComponentPanel for Ruler {
         });
         ui.checkbox(&mut self.show_individual_axis, "Show individual axis");
 
+        ui.horizontal(|ui| {
+            ui.strong("Unit");
+            egui::ComboBox::from_id_source("ruler_unit")
+                .selected_text(self.unit.to_string())
+                .show_ui(ui, |ui| {
+                    for unit in RulerUnit::iter() {
+                        ui.selectable_value(&mut self.unit, unit, unit.to_string());
+                    }
+                });
+        });
+
         ui.horizontal(|ui| {
             ui.strong("Length:");
-            ui.label(prettify_distance(self.length()));
+            ui.label(self.unit.format(self.length()));
         });
 
+        if let Some(angle) = self.angle() {
+            ui.horizontal(|ui| {
+                ui.strong("Angle:");
+                ui.label(format!("{angle:.2}°"));
+            });
+        }
+
         if self.marker_interval > 0.0 {
             ui.horizontal(|ui| {
                 ui.strong("Length remainder at end:");
-                ui.label(prettify_distance(self.length() % self.marker_interval));
+                ui.label(self.unit.format(self.length() % self.marker_interval));
             });
         }
 
@@ -551,6 +847,191 @@ impl ComponentPanel for Ruler {
     }
 }
 
+impl ComponentPanel for LightColor {
+    fn inspector_name() -> &'static str {
+        "Light"
+    }
+
+    fn inspector_icon() -> char {
+        ICON_LIGHTBULB_ON
+    }
+
+    fn has_inspector_ui() -> bool {
+        true
+    }
+
+    fn show_inspector_ui(&mut self, _: EntityRef<'_>, ui: &mut egui::Ui, _: &Resources) {
+        ui.horizontal(|ui| {
+            ui.color_edit_button_rgb(&mut self.color);
+            ui.label("Color");
+        });
+
+        ui.horizontal(|ui| {
+            ui.strong("Intensity");
+            ui.add(
+                egui::DragValue::new(&mut self.intensity)
+                    .speed(0.01)
+                    .clamp_range(0f32..=100f32)
+                    .min_decimals(2)
+                    .max_decimals(2),
+            )
+        });
+
+        if ui.button(format!("{ICON_RESTORE} Reset")).clicked() {
+            *self = LightColor::default();
+        }
+
+        ui.label(
+            RichText::new("Range is defined by the light's shading technique and can't be edited")
+                .italics()
+                .weak(),
+        );
+    }
+}
+
+impl ComponentPanel for Route {
+    fn inspector_name() -> &'static str {
+        "Route"
+    }
+
+    fn inspector_icon() -> char {
+        ICON_MAP_MARKER_PATH
+    }
+
+    fn has_inspector_ui() -> bool {
+        true
+    }
+
+    fn show_inspector_ui(&mut self, _: EntityRef<'_>, ui: &mut egui::Ui, resources: &Resources) {
+        enum NodeAction {
+            MoveUp(usize),
+            MoveDown(usize),
+            InsertAfter(usize),
+            Remove(usize),
+        }
+
+        let camera = resources.get::<FpsCamera>().unwrap();
+        let node_count = self.nodes.len();
+        let mut action = None;
+        egui::Grid::new("route_nodes_grid")
+            .num_columns(3)
+            .striped(true)
+            .show(ui, |ui| {
+                for (i, node) in self.nodes.iter_mut().enumerate() {
+                    input_float3!(ui, format!("#{i}"), &mut node.pos);
+                    ui.horizontal(|ui| {
+                        if ui
+                            .button(ICON_CAMERA_CONTROL.to_string())
+                            .on_hover_text("Set position to camera")
+                            .clicked()
+                        {
+                            node.pos = camera.position;
+                        }
+
+                        let mut label = node.label.clone().unwrap_or_default();
+                        if ui
+                            .add(
+                                egui::TextEdit::singleline(&mut label)
+                                    .desired_width(80.0)
+                                    .hint_text("Label"),
+                            )
+                            .changed()
+                        {
+                            node.label = (!label.is_empty()).then_some(label);
+                        }
+
+                        if ui
+                            .add_enabled(i > 0, egui::Button::new(ICON_ARROW_UP.to_string()))
+                            .on_hover_text("Move up")
+                            .clicked()
+                        {
+                            action = Some(NodeAction::MoveUp(i));
+                        }
+                        if ui
+                            .add_enabled(
+                                i + 1 < node_count,
+                                egui::Button::new(ICON_ARROW_DOWN.to_string()),
+                            )
+                            .on_hover_text("Move down")
+                            .clicked()
+                        {
+                            action = Some(NodeAction::MoveDown(i));
+                        }
+                        if ui
+                            .button(ICON_MAP_MARKER_PLUS.to_string())
+                            .on_hover_text("Insert a node at the camera after this one")
+                            .clicked()
+                        {
+                            action = Some(NodeAction::InsertAfter(i));
+                        }
+                        if ui
+                            .button(ICON_MAP_MARKER_REMOVE.to_string())
+                            .on_hover_text("Remove node")
+                            .clicked()
+                        {
+                            action = Some(NodeAction::Remove(i));
+                        }
+                    });
+                    ui.end_row();
+                }
+            });
+
+        match action {
+            Some(NodeAction::MoveUp(i)) => self.nodes.swap(i, i - 1),
+            Some(NodeAction::MoveDown(i)) => self.nodes.swap(i, i + 1),
+            Some(NodeAction::InsertAfter(i)) => self.nodes.insert(
+                i + 1,
+                RouteNode {
+                    pos: camera.position,
+                    label: None,
+                },
+            ),
+            Some(NodeAction::Remove(i)) => {
+                self.nodes.remove(i);
+            }
+            None => {}
+        }
+
+        if ui
+            .button(format!("{ICON_MAP_MARKER_PLUS} Add node at camera"))
+            .clicked()
+        {
+            self.nodes.push(RouteNode {
+                pos: camera.position,
+                label: None,
+            });
+        }
+
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.strong("Scale");
+            ui.add(
+                egui::DragValue::new(&mut self.scale)
+                    .speed(0.1)
+                    .clamp_range(0f32..=100f32)
+                    .min_decimals(2)
+                    .max_decimals(2),
+            )
+        });
+        ui.checkbox(&mut self.show_indices, "Show node numbers");
+
+        ui.horizontal(|ui| {
+            ui.strong("Length:");
+            ui.label(prettify_distance(self.length()));
+        });
+
+        ui.horizontal(|ui| {
+            ui.color_edit_button_srgb(&mut self.color)
+                .context_menu(|ui| {
+                    ui.checkbox(&mut self.rainbow, "Rainbow mode");
+                });
+
+            ui.label("Color");
+        });
+    }
+}
+
 impl ComponentPanel for Sphere {
     fn inspector_name() -> &'static str {
         "Sphere"
@@ -565,7 +1046,37 @@ impl ComponentPanel for Sphere {
     }
 
     fn show_inspector_ui(&mut self, e: EntityRef<'_>, ui: &mut egui::Ui, _resources: &Resources) {
-        if !e.has::<Transform>() {
+        if let Some(mut transform) = e.get::<&mut Transform>() {
+            if transform.flags.contains(TransformFlags::SCALE_IS_RADIUS) {
+                let mut radius = transform.radius();
+                ui.horizontal(|ui| {
+                    ui.strong("Radius");
+                    if ui
+                        .add(
+                            egui::DragValue::new(&mut radius)
+                                .speed(0.1)
+                                .clamp_range(0f32..=f32::INFINITY)
+                                .min_decimals(2)
+                                .max_decimals(4)
+                                .suffix(" m"),
+                        )
+                        .on_hover_text("Click to type an exact radius")
+                        .changed()
+                    {
+                        transform.scale = Vec3::splat(radius);
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    ui.strong("Diameter:");
+                    ui.label(prettify_distance(radius * 2.0));
+                });
+                ui.horizontal(|ui| {
+                    ui.strong("Volume:");
+                    ui.label(format!("{:.2} m³", 4.0 / 3.0 * PI * radius.powi(3)));
+                });
+            }
+        } else {
             ui.label(format!(
                 "{} This entity has no transform component",
                 ICON_ALERT
@@ -592,6 +1103,96 @@ impl ComponentPanel for Sphere {
     }
 }
 
+impl ComponentPanel for ReferenceMesh {
+    fn inspector_name() -> &'static str {
+        "Reference Mesh"
+    }
+
+    fn inspector_icon() -> char {
+        ICON_VECTOR_POLYGON
+    }
+
+    fn has_inspector_ui() -> bool {
+        true
+    }
+
+    fn show_inspector_ui(&mut self, e: EntityRef<'_>, ui: &mut egui::Ui, _: &Resources) {
+        ui.horizontal(|ui| {
+            ui.strong("File:");
+            ui.label(self.path.file_name().map_or_else(
+                || self.path.display().to_string(),
+                |f| f.to_string_lossy().to_string(),
+            ))
+            .on_hover_text(self.path.display().to_string());
+        });
+
+        ui.horizontal(|ui| {
+            ui.strong("Triangles:");
+            ui.label((self.mesh.vertex_count / 3).to_string());
+        });
+
+        let scale = e.get::<&Transform>().map_or(Vec3::ONE, |t| t.scale);
+        let size = self.mesh.bounds.dimensions() * scale;
+        ui.horizontal(|ui| {
+            ui.strong("Size:");
+            ui.label(format!(
+                "{} x {} x {}",
+                prettify_distance(size.x),
+                prettify_distance(size.y),
+                prettify_distance(size.z)
+            ));
+        });
+
+        ui.horizontal(|ui| {
+            ui.color_edit_button_srgb(&mut self.color);
+            ui.label("Color");
+        });
+    }
+}
+
+impl ComponentPanel for Note {
+    fn inspector_name() -> &'static str {
+        "Note"
+    }
+
+    fn inspector_icon() -> char {
+        ICON_NOTE_TEXT
+    }
+
+    fn has_inspector_ui() -> bool {
+        true
+    }
+
+    fn show_inspector_ui(&mut self, e: EntityRef<'_>, ui: &mut egui::Ui, _: &Resources) {
+        if !e.has::<Transform>() {
+            ui.label(format!(
+                "{} This entity has no transform component",
+                ICON_ALERT
+            ));
+        }
+
+        ui.add(
+            egui::TextEdit::multiline(&mut self.text)
+                .desired_rows(3)
+                .desired_width(f32::INFINITY),
+        );
+
+        ui.horizontal(|ui| {
+            ui.strong("Font size");
+            ui.add(
+                egui::DragValue::new(&mut self.font_size)
+                    .speed(0.5)
+                    .clamp_range(8.0..=64.0),
+            );
+        });
+
+        ui.horizontal(|ui| {
+            ui.color_edit_button_srgb(&mut self.color);
+            ui.label("Color");
+        });
+    }
+}
+
 impl ComponentPanel for Beacon {
     fn inspector_name() -> &'static str {
         "Beacon"
@@ -1,16 +1,23 @@
-use std::ops::{Deref, DerefMut};
+use std::{
+    ops::{Deref, DerefMut},
+    path::PathBuf,
+    sync::Arc,
+};
 
 use destiny_pkg::TagHash;
 use glam::{Vec3, Vec4};
+use hecs::Entity;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     map_resources::MapResource,
     render::{
-        cbuffer::ConstantBufferCached, scopes::ScopeRigidModel, EntityRenderer, InstancedRenderer,
-        TerrainRenderer,
+        cbuffer::ConstantBufferCached, reference_mesh::ReferenceMeshData, scopes::ScopeRigidModel,
+        EntityRenderer, InstancedRenderer, TerrainRenderer,
     },
     structure::ExtendedHash,
     types::AABB,
+    util::text::prettify_distance,
 };
 
 #[derive(Copy, Clone)]
@@ -53,14 +60,49 @@ pub struct PointLight {
     pub attenuation: Vec4,
 }
 
+/// Multiplies the output of a light's shading technique, for editing lights without touching their bytecode
+#[derive(Clone, Copy)]
+pub struct LightColor {
+    /// Linear RGB
+    pub color: [f32; 3],
+    pub intensity: f32,
+}
+
+impl Default for LightColor {
+    fn default() -> Self {
+        Self {
+            color: [1.0; 3],
+            intensity: 1.0,
+        }
+    }
+}
+
+impl LightColor {
+    /// Value passed to the light blend state as the blend factor
+    pub fn blend_factor(&self) -> [f32; 4] {
+        let [r, g, b] = self.color;
+        [
+            r * self.intensity,
+            g * self.intensity,
+            b * self.intensity,
+            1.0,
+        ]
+    }
+}
+
 // pub struct HavokShape(pub TagHash, pub Option<CustomDebugShape>);
 
 pub struct CubemapVolume(pub TagHash, pub AABB, pub String);
 
 pub struct ActivityGroup(pub u32);
 
+#[derive(Clone)]
 pub struct Label(pub String);
 
+/// Marker for map entities whose label was changed by the user, these get saved alongside the user entities
+#[derive(Clone, Copy)]
+pub struct EditedLabel;
+
 // TODO(cohae): This is currently only used for user-spawned entities, but it should be used for resource points as well
 pub struct EntityModel(
     pub EntityRenderer,
@@ -74,8 +116,31 @@ pub struct StaticInstances(pub InstancedRenderer, pub TagHash);
 
 pub struct Water;
 
+/// An external mesh imported to compare placement and scale against, drawn with a flat unlit material.
+/// These aren't saved with the user entities
+#[derive(Clone)]
+pub struct ReferenceMesh {
+    pub mesh: Arc<ReferenceMeshData>,
+    /// File the mesh was imported from
+    pub path: PathBuf,
+    pub color: [u8; 3],
+}
+
+/// The map an entity was originally loaded in, for entities in a combined map
+#[derive(Clone, Copy)]
+pub struct SourceMap(pub TagHash);
+
+/// Draws the entity see-through with a constant opacity, for looking at what's behind or inside it
+#[derive(Clone, Copy)]
+pub struct OpacityOverride(pub f32);
+
+/// Marks an entity whose model failed to load, these are drawn as error geometry instead
+#[derive(Clone)]
+pub struct ErrorMarker(pub String);
+
 macro_rules! bool_trait {
     ($name: ident) => {
+        #[derive(Clone, Copy)]
         pub struct $name(pub bool);
 
         impl Deref for $name {
@@ -97,14 +162,19 @@ macro_rules! bool_trait {
 bool_trait!(Visible);
 bool_trait!(Global);
 
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Ruler {
     pub start: Vec3,
     pub end: Vec3,
+    /// Turns the ruler into an angle measurement, measuring the angle at this point between the start and end
+    pub vertex: Option<Vec3>,
     pub color: [u8; 3],
     pub rainbow: bool,
     pub scale: f32,
     pub marker_interval: f32,
     pub show_individual_axis: bool,
+    pub unit: RulerUnit,
 }
 
 impl Default for Ruler {
@@ -112,11 +182,13 @@ impl Default for Ruler {
         Self {
             start: Vec3::ZERO,
             end: Vec3::ZERO,
+            vertex: None,
             color: [255, 255, 255],
             rainbow: false,
             scale: 1.0,
             marker_interval: 0.0,
             show_individual_axis: false,
+            unit: RulerUnit::Auto,
         }
     }
 }
@@ -129,8 +201,49 @@ impl Ruler {
     pub fn direction(&self) -> Vec3 {
         (self.end - self.start).normalize()
     }
+
+    /// Angle at the vertex in degrees, if the ruler has one
+    pub fn angle(&self) -> Option<f32> {
+        let vertex = self.vertex?;
+        Some(
+            (self.start - vertex)
+                .angle_between(self.end - vertex)
+                .to_degrees(),
+        )
+    }
+}
+
+#[derive(
+    strum::Display, strum::EnumIter, Copy, Clone, PartialEq, Default, Serialize, Deserialize,
+)]
+pub enum RulerUnit {
+    /// Picks a metric unit based on the distance
+    #[default]
+    Auto,
+    Millimeters,
+    Centimeters,
+    Meters,
+    Kilometers,
+    Inches,
+    Feet,
 }
 
+impl RulerUnit {
+    pub fn format(&self, meters: f32) -> String {
+        match self {
+            RulerUnit::Auto => prettify_distance(meters),
+            RulerUnit::Millimeters => format!("{:.2} mm", meters * 1000.0),
+            RulerUnit::Centimeters => format!("{:.2} cm", meters * 100.0),
+            RulerUnit::Meters => format!("{:.2} m", meters),
+            RulerUnit::Kilometers => format!("{:.2} km", meters / 1000.0),
+            RulerUnit::Inches => format!("{:.2} in", meters / 0.0254),
+            RulerUnit::Feet => format!("{:.2} ft", meters / 0.3048),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Sphere {
     pub detail: u8,
     pub color: [u8; 4],
@@ -147,6 +260,8 @@ impl Default for Sphere {
     }
 }
 
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Beacon {
     pub color: [u8; 3],
     pub freq: f32,
@@ -164,6 +279,76 @@ impl Default for Beacon {
         }
     }
 }
+
+/// Free-floating text in the world, drawn facing the camera
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Note {
+    pub text: String,
+    pub color: [u8; 3],
+    pub font_size: f32,
+}
+
+impl Default for Note {
+    fn default() -> Self {
+        Self {
+            text: "Note".to_string(),
+            color: [255, 255, 255],
+            font_size: 16.0,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct RouteNode {
+    pub pos: Vec3,
+    /// Optional note shown next to the node
+    pub label: Option<String>,
+}
+
+/// An ordered list of points, eg. for patrol paths
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Route {
+    pub nodes: Vec<RouteNode>,
+    pub color: [u8; 3],
+    pub rainbow: bool,
+    pub scale: f32,
+    pub show_indices: bool,
+}
+
+impl Default for Route {
+    fn default() -> Self {
+        Self {
+            nodes: vec![],
+            color: [255, 255, 255],
+            rainbow: false,
+            scale: 1.0,
+            show_indices: true,
+        }
+    }
+}
+
+impl Route {
+    /// Total length of the path through all nodes, in meters
+    pub fn length(&self) -> f32 {
+        self.nodes
+            .windows(2)
+            .map(|w| w[0].pos.distance(w[1].pos))
+            .sum()
+    }
+}
 /// Marker component to indicate that the entity is allowed to be modified in potentially destructive ways
 /// (e.g. deleting it, changing it's name, etc.)
+#[derive(Clone, Copy)]
 pub struct Mutable;
+
+/// Parent of an entity in the scene hierarchy. Transforms are kept in world space, children just follow along when their parent is moved.
+/// Only `Mutable` entities can be parented
+#[derive(Clone, Copy)]
+pub struct Parent(pub Entity);
+
+/// Children of an entity in the scene hierarchy, kept in sync with their `Parent` components
+#[derive(Clone, Default)]
+pub struct Children(pub Vec<Entity>);
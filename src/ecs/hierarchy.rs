@@ -0,0 +1,174 @@
+use std::{any::TypeId, collections::HashMap};
+
+use hecs::{Entity, EntityRef};
+
+use super::{
+    components::{Children, EditedLabel, Global, Label, Mutable, Parent, Visible},
+    tags::Tags,
+    transform::{OriginalTransform, Transform},
+    Scene,
+};
+
+/// Points `Parent` and `Children` components at the new handles of entities that were respawned under a different id
+pub fn remap_entities(scene: &mut Scene, remap: &HashMap<Entity, Entity>) {
+    if remap.is_empty() {
+        return;
+    }
+
+    for (_, parent) in scene.query_mut::<&mut Parent>() {
+        if let Some(&new) = remap.get(&parent.0) {
+            parent.0 = new;
+        }
+    }
+
+    for (_, children) in scene.query_mut::<&mut Children>() {
+        for child in &mut children.0 {
+            if let Some(&new) = remap.get(child) {
+                *child = new;
+            }
+        }
+    }
+}
+
+/// Cuts the `Parent` and `Children` links between `entities` and the rest of the scene, so they can be moved to another scene on their own
+pub fn detach_entities(scene: &mut Scene, entities: &[Entity]) {
+    let orphans: Vec<Entity> = scene
+        .query_mut::<&Parent>()
+        .into_iter()
+        .filter(|(e, parent)| entities.contains(e) != entities.contains(&parent.0))
+        .map(|(e, _)| e)
+        .collect();
+    for e in orphans {
+        scene.remove_one::<Parent>(e).ok();
+    }
+
+    for (e, children) in scene.query_mut::<&mut Children>() {
+        let inside = entities.contains(&e);
+        children.0.retain(|c| entities.contains(c) == inside);
+    }
+}
+
+/// Returns the parent of an entity, as long as the parent still exists
+pub fn parent_of(scene: &Scene, entity: Entity) -> Option<Entity> {
+    scene
+        .get::<&Parent>(entity)
+        .ok()
+        .map(|p| p.0)
+        .filter(|&p| scene.contains(p))
+}
+
+/// Returns the children of an entity that still exist
+pub fn children_of(scene: &Scene, entity: Entity) -> Vec<Entity> {
+    scene
+        .get::<&Children>(entity)
+        .map(|c| c.0.iter().copied().filter(|&c| scene.contains(c)).collect())
+        .unwrap_or_default()
+}
+
+/// Returns all children, grandchildren, etc. of an entity, depth first
+pub fn descendants(scene: &Scene, entity: Entity) -> Vec<Entity> {
+    let mut result = vec![];
+    let mut stack = children_of(scene, entity);
+    while let Some(e) = stack.pop() {
+        // Parent and Children can only disagree for despawned entities, but don't loop forever if they do
+        if e == entity || result.contains(&e) {
+            continue;
+        }
+
+        result.push(e);
+        stack.extend(children_of(scene, e));
+    }
+
+    result
+}
+
+/// Is `entity` a (grand)child of `ancestor`?
+pub fn is_descendant_of(scene: &Scene, entity: Entity, ancestor: Entity) -> bool {
+    let mut current = parent_of(scene, entity);
+    let mut depth = 0;
+    while let Some(p) = current {
+        if p == ancestor {
+            return true;
+        }
+
+        depth += 1;
+        if depth > 1024 {
+            return false;
+        }
+        current = parent_of(scene, p);
+    }
+
+    false
+}
+
+/// Is this a user entity that only exists to hold other entities, like the ones created by grouping?
+pub fn is_empty_group(e: EntityRef<'_>) -> bool {
+    let allowed = [
+        TypeId::of::<Transform>(),
+        TypeId::of::<OriginalTransform>(),
+        TypeId::of::<Label>(),
+        TypeId::of::<EditedLabel>(),
+        TypeId::of::<Tags>(),
+        TypeId::of::<Visible>(),
+        TypeId::of::<Global>(),
+        TypeId::of::<Mutable>(),
+        TypeId::of::<Parent>(),
+        TypeId::of::<Children>(),
+    ];
+
+    e.has::<Mutable>() && e.component_types().all(|t| allowed.contains(&t))
+}
+
+/// Checks whether `child` can be moved under `parent`, or to the root when `parent` is `None`.
+/// Both have to be `Mutable`, and an entity can't be moved under one of its own descendants
+pub fn can_reparent(scene: &Scene, child: Entity, parent: Option<Entity>) -> bool {
+    if !scene.entity(child).map_or(false, |e| e.has::<Mutable>()) {
+        return false;
+    }
+
+    match parent {
+        Some(p) => {
+            p != child
+                && scene.entity(p).map_or(false, |e| e.has::<Mutable>())
+                && !is_descendant_of(scene, p, child)
+        }
+        None => true,
+    }
+}
+
+/// Moves `child` under `parent`, or to the root when `parent` is `None`, updating the `Parent` and `Children` components on both sides.
+/// Transforms are in world space, so they don't need to be touched
+pub fn set_parent(scene: &mut Scene, child: Entity, parent: Option<Entity>) {
+    if let Some(old) = scene.get::<&Parent>(child).ok().map(|p| p.0) {
+        let now_empty = scene
+            .get::<&mut Children>(old)
+            .map(|mut c| {
+                c.0.retain(|&e| e != child);
+                c.0.is_empty()
+            })
+            .unwrap_or(false);
+
+        if now_empty {
+            scene.remove_one::<Children>(old).ok();
+        }
+    }
+
+    let Some(parent) = parent else {
+        scene.remove_one::<Parent>(child).ok();
+        return;
+    };
+
+    scene.insert_one(child, Parent(parent)).ok();
+    let has_children = scene
+        .get::<&mut Children>(parent)
+        .map(|mut c| {
+            if !c.0.contains(&child) {
+                c.0.push(child);
+            }
+        })
+        .is_ok();
+
+    if !has_children {
+        scene.insert_one(parent, Children(vec![child])).ok();
+    }
+}
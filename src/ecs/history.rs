@@ -0,0 +1,503 @@
+use std::collections::HashMap;
+
+use glam::Vec3;
+use hecs::{BuiltEntityClone, Entity, EntityBuilderClone, EntityRef};
+
+use super::{
+    components::{
+        Beacon, Children, Global, Label, Mutable, Note, Parent, ReferenceMesh, Route, Ruler,
+        Sphere, Visible,
+    },
+    hierarchy,
+    tags::{EntityTag, Tags},
+    transform::{OriginalTransform, Transform, TransformFlags},
+    Scene,
+};
+
+/// Maximum amount of actions that can be undone
+const HISTORY_LIMIT: usize = 256;
+
+pub enum EditAction {
+    Spawn {
+        entity: Entity,
+        /// Filled in when the spawn is undone, so it can be redone
+        snapshot: Option<BuiltEntityClone>,
+    },
+    Despawn {
+        entity: Entity,
+        snapshot: BuiltEntityClone,
+    },
+    Transform {
+        entity: Entity,
+        old: Transform,
+        new: Transform,
+    },
+    /// Transforms of multiple entities edited at once, as `(entity, old, new)`
+    TransformGroup(Vec<(Entity, Transform, Transform)>),
+    Reparent {
+        entity: Entity,
+        old: Option<Entity>,
+        new: Option<Entity>,
+    },
+    /// Multiple actions that are undone and redone as one
+    Batch(Vec<EditAction>),
+}
+
+/// New handles of entities that were respawned by an undo or redo, by their old handle
+type EntityRemap = HashMap<Entity, Entity>;
+
+impl EditAction {
+    /// Points the action at the new handles of respawned entities
+    fn remap(&mut self, remap: &EntityRemap) {
+        let map = |e: &mut Entity| {
+            if let Some(&new) = remap.get(e) {
+                *e = new;
+            }
+        };
+
+        match self {
+            EditAction::Spawn { entity, .. }
+            | EditAction::Despawn { entity, .. }
+            | EditAction::Transform { entity, .. } => map(entity),
+            EditAction::TransformGroup(edits) => {
+                for (entity, ..) in edits {
+                    map(entity);
+                }
+            }
+            EditAction::Reparent { entity, old, new } => {
+                map(entity);
+                old.iter_mut().for_each(map);
+                new.iter_mut().for_each(map);
+            }
+            EditAction::Batch(actions) => {
+                for action in actions {
+                    action.remap(remap);
+                }
+            }
+        }
+    }
+
+    /// Restored entities are spawned with a fresh handle, as hecs may have reused the slot of the old one.
+    /// The old handle is added to `remap` so the rest of the history follows along
+    fn respawn(
+        scene: &mut Scene,
+        entity: &mut Entity,
+        snapshot: &BuiltEntityClone,
+        remap: &mut EntityRemap,
+    ) {
+        let new = scene.spawn(snapshot);
+        remap.insert(*entity, new);
+        *entity = new;
+        hierarchy::remap_entities(scene, remap);
+    }
+
+    fn undo(&mut self, scene: &mut Scene, remap: &mut EntityRemap) {
+        self.remap(remap);
+        match self {
+            EditAction::Spawn { entity, snapshot } => {
+                if let Ok(e) = scene.entity(*entity) {
+                    *snapshot = Some(snapshot_entity(e));
+                    scene.despawn(*entity).ok();
+                }
+            }
+            EditAction::Despawn { entity, snapshot } => {
+                if !scene.contains(*entity) {
+                    Self::respawn(scene, entity, snapshot, remap);
+                }
+            }
+            EditAction::Transform { entity, old, .. } => {
+                if let Ok(mut t) = scene.get::<&mut Transform>(*entity) {
+                    *t = *old;
+                }
+            }
+            EditAction::TransformGroup(edits) => {
+                for (entity, old, _) in edits {
+                    if let Ok(mut t) = scene.get::<&mut Transform>(*entity) {
+                        *t = *old;
+                    }
+                }
+            }
+            EditAction::Reparent { entity, old, .. } => {
+                if scene.contains(*entity) {
+                    hierarchy::set_parent(scene, *entity, *old);
+                }
+            }
+            EditAction::Batch(actions) => {
+                for action in actions.iter_mut().rev() {
+                    action.undo(scene, remap);
+                }
+            }
+        }
+    }
+
+    fn redo(&mut self, scene: &mut Scene, remap: &mut EntityRemap) {
+        self.remap(remap);
+        match self {
+            EditAction::Spawn { entity, snapshot } => {
+                if let Some(snapshot) = snapshot.take() {
+                    if !scene.contains(*entity) {
+                        Self::respawn(scene, entity, &snapshot, remap);
+                    }
+                }
+            }
+            EditAction::Despawn { entity, .. } => {
+                scene.despawn(*entity).ok();
+            }
+            EditAction::Transform { entity, new, .. } => {
+                if let Ok(mut t) = scene.get::<&mut Transform>(*entity) {
+                    *t = *new;
+                }
+            }
+            EditAction::TransformGroup(edits) => {
+                for (entity, _, new) in edits {
+                    if let Ok(mut t) = scene.get::<&mut Transform>(*entity) {
+                        *t = *new;
+                    }
+                }
+            }
+            EditAction::Reparent { entity, new, .. } => {
+                if scene.contains(*entity) {
+                    hierarchy::set_parent(scene, *entity, *new);
+                }
+            }
+            EditAction::Batch(actions) => {
+                for action in actions.iter_mut() {
+                    action.redo(scene, remap);
+                }
+            }
+        }
+    }
+}
+
+/// Undo/redo stack for user edits to `Mutable` entities
+#[derive(Default)]
+pub struct EditHistory {
+    undo_stack: Vec<EditAction>,
+    redo_stack: Vec<EditAction>,
+
+    /// Entity whose transform is currently being dragged. Consecutive edits are merged into a single action
+    open_transform_edit: Option<Entity>,
+
+    /// Set when an edit has been recorded, undone or redone since the last `take_changed`
+    changed: bool,
+}
+
+impl EditHistory {
+    pub fn push(&mut self, action: EditAction) {
+        self.redo_stack.clear();
+        self.open_transform_edit = None;
+        self.changed = true;
+
+        self.undo_stack.push(action);
+        if self.undo_stack.len() > HISTORY_LIMIT {
+            self.undo_stack.remove(0);
+        }
+    }
+
+    pub fn record_spawn(&mut self, entity: Entity) {
+        self.push(EditAction::Spawn {
+            entity,
+            snapshot: None,
+        });
+    }
+
+    pub fn record_transform(&mut self, entity: Entity, old: Transform, new: Transform) {
+        if self.open_transform_edit == Some(entity) {
+            if let Some(EditAction::Transform {
+                entity: last_entity,
+                new: last_new,
+                ..
+            }) = self.undo_stack.last_mut()
+            {
+                if *last_entity == entity {
+                    *last_new = new;
+                    self.changed = true;
+                    return;
+                }
+            }
+        }
+
+        self.push(EditAction::Transform { entity, old, new });
+        self.open_transform_edit = Some(entity);
+    }
+
+    /// Same as [`Self::record_transform`], for an edit applied to multiple entities at once.
+    /// The first entity is used to identify the edit when merging
+    pub fn record_group_transform(&mut self, edits: Vec<(Entity, Transform, Transform)>) {
+        let Some(&(first, ..)) = edits.first() else {
+            return;
+        };
+
+        if self.open_transform_edit == Some(first) {
+            if let Some(EditAction::TransformGroup(last)) = self.undo_stack.last_mut() {
+                if last.len() == edits.len() && last.iter().zip(&edits).all(|(a, b)| a.0 == b.0) {
+                    for (last, (_, _, new)) in last.iter_mut().zip(edits) {
+                        last.2 = new;
+                    }
+                    self.changed = true;
+                    return;
+                }
+            }
+        }
+
+        self.push(EditAction::TransformGroup(edits));
+        self.open_transform_edit = Some(first);
+    }
+
+    /// Ends the current transform edit, the next edit will be recorded as a new action
+    pub fn finish_transform_edit(&mut self) {
+        self.open_transform_edit = None;
+    }
+
+    pub fn undo(&mut self, scene: &mut Scene) -> bool {
+        self.open_transform_edit = None;
+        if let Some(mut action) = self.undo_stack.pop() {
+            let mut remap = EntityRemap::new();
+            action.undo(scene, &mut remap);
+            self.redo_stack.push(action);
+            self.remap(&remap);
+            self.changed = true;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn redo(&mut self, scene: &mut Scene) -> bool {
+        self.open_transform_edit = None;
+        if let Some(mut action) = self.redo_stack.pop() {
+            let mut remap = EntityRemap::new();
+            action.redo(scene, &mut remap);
+            self.undo_stack.push(action);
+            self.remap(&remap);
+            self.changed = true;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn remap(&mut self, remap: &EntityRemap) {
+        if remap.is_empty() {
+            return;
+        }
+
+        for action in self.undo_stack.iter_mut().chain(self.redo_stack.iter_mut()) {
+            action.remap(remap);
+        }
+    }
+
+    /// Returns whether the scene was edited since the last call
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.changed)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+}
+
+/// Records and applies moving a `Mutable` entity under `parent`, or to the root when `parent` is `None`.
+/// Returns false if the entity can't be moved there
+pub fn reparent_mutable(
+    scene: &mut Scene,
+    history: &mut EditHistory,
+    entity: Entity,
+    parent: Option<Entity>,
+) -> bool {
+    let old = hierarchy::parent_of(scene, entity);
+    if old == parent || !hierarchy::can_reparent(scene, entity, parent) {
+        return false;
+    }
+
+    hierarchy::set_parent(scene, entity, parent);
+    history.push(EditAction::Reparent {
+        entity,
+        old,
+        new: parent,
+    });
+
+    true
+}
+
+/// Groups `Mutable` entities under a new empty entity placed at their centroid, as a single undoable action.
+/// Returns the new group entity, or `None` if none of the entities can be grouped
+pub fn group_mutable(
+    scene: &mut Scene,
+    history: &mut EditHistory,
+    entities: &[Entity],
+) -> Option<Entity> {
+    // Entities that are already inside another grouped entity move along with it
+    let members: Vec<Entity> = entities
+        .iter()
+        .copied()
+        .filter(|&e| {
+            scene
+                .entity(e)
+                .map_or(false, |e| e.has::<Mutable>() && e.has::<Transform>())
+                && !entities
+                    .iter()
+                    .any(|&other| hierarchy::is_descendant_of(scene, e, other))
+        })
+        .collect();
+
+    if members.is_empty() {
+        return None;
+    }
+
+    let centroid = members
+        .iter()
+        .filter_map(|&e| scene.get::<&Transform>(e).ok().map(|t| t.translation))
+        .sum::<Vec3>()
+        / members.len() as f32;
+
+    // Keep the group where the entities were if they all shared a parent
+    let parents: Vec<Option<Entity>> = members
+        .iter()
+        .map(|&e| hierarchy::parent_of(scene, e))
+        .collect();
+    let parent = if parents.iter().all(|p| *p == parents[0]) {
+        parents[0]
+    } else {
+        None
+    };
+
+    let group = scene.spawn((
+        Transform {
+            translation: centroid,
+            flags: TransformFlags::IGNORE_SCALE,
+            ..Default::default()
+        },
+        Label("Group".to_string()),
+        Tags::from_iter([EntityTag::Utility]),
+        Mutable,
+    ));
+
+    let mut actions = vec![EditAction::Spawn {
+        entity: group,
+        snapshot: None,
+    }];
+
+    if parent.is_some() {
+        hierarchy::set_parent(scene, group, parent);
+        actions.push(EditAction::Reparent {
+            entity: group,
+            old: None,
+            new: parent,
+        });
+    }
+
+    for (e, old) in members.into_iter().zip(parents) {
+        hierarchy::set_parent(scene, e, Some(group));
+        actions.push(EditAction::Reparent {
+            entity: e,
+            old,
+            new: Some(group),
+        });
+    }
+
+    history.push(EditAction::Batch(actions));
+
+    Some(group)
+}
+
+/// Moves the children of `group` to its own parent (or the root) as a single undoable action.
+/// The group itself is despawned when it doesn't hold anything but its children. Returns the former children
+pub fn ungroup_mutable(scene: &mut Scene, history: &mut EditHistory, group: Entity) -> Vec<Entity> {
+    let Ok(e) = scene.entity(group) else {
+        return vec![];
+    };
+
+    if !e.has::<Mutable>() {
+        return vec![];
+    }
+
+    let despawn = hierarchy::is_empty_group(e);
+    let parent = hierarchy::parent_of(scene, group);
+    let children = hierarchy::children_of(scene, group);
+    if children.is_empty() {
+        return vec![];
+    }
+
+    let mut actions = vec![];
+    for &child in &children {
+        hierarchy::set_parent(scene, child, parent);
+        actions.push(EditAction::Reparent {
+            entity: child,
+            old: Some(group),
+            new: parent,
+        });
+    }
+
+    if despawn {
+        if let Ok(e) = scene.entity(group) {
+            actions.push(EditAction::Despawn {
+                entity: group,
+                snapshot: snapshot_entity(e),
+            });
+        }
+        scene.despawn(group).ok();
+    }
+
+    history.push(EditAction::Batch(actions));
+
+    children
+}
+
+/// Records and despawns a `Mutable` entity. Returns false if the entity isn't mutable
+pub fn despawn_mutable(
+    e: EntityRef<'_>,
+    cmd: &mut hecs::CommandBuffer,
+    history: &mut EditHistory,
+) -> bool {
+    if !e.has::<Mutable>() {
+        return false;
+    }
+
+    history.push(EditAction::Despawn {
+        entity: e.entity(),
+        snapshot: snapshot_entity(e),
+    });
+    cmd.despawn(e.entity());
+
+    true
+}
+
+/// Clones all the components a user-created entity can have
+fn snapshot_entity(e: EntityRef<'_>) -> BuiltEntityClone {
+    let mut builder = EntityBuilderClone::new();
+
+    macro_rules! snapshot_components {
+        ($($component:ty),+) => {
+            $(
+                if let Some(c) = e.get::<&$component>() {
+                    builder.add((*c).clone());
+                }
+            )*
+        };
+    }
+
+    snapshot_components!(
+        Transform,
+        OriginalTransform,
+        Tags,
+        Label,
+        Visible,
+        Global,
+        Ruler,
+        Sphere,
+        Beacon,
+        Route,
+        Note,
+        ReferenceMesh,
+        Parent,
+        Children,
+        Mutable
+    );
+
+    builder.build()
+}
@@ -1,5 +1,8 @@
 pub mod component_panels;
 pub mod components;
+pub mod hierarchy;
+pub mod history;
+pub mod persistence;
 pub mod resources;
 pub mod tags;
 pub mod transform;
@@ -12,9 +15,13 @@ use itertools::Itertools;
 
 use crate::ecs::component_panels::ComponentPanel;
 use crate::ecs::components::*;
+use crate::icons::ICON_FOLDER_OUTLINE;
+use crate::map_resources::MapResource;
+use crate::structure::ExtendedHash;
 use crate::types::AABB;
 use crate::util::text::split_pascal_case;
 
+use self::tags::{EntityTag, Tags};
 use self::transform::Transform;
 
 pub fn resolve_entity_icon(e: EntityRef<'_>) -> Option<char> {
@@ -36,12 +43,19 @@ pub fn resolve_entity_icon(e: EntityRef<'_>) -> Option<char> {
         // TODO(cohae): Custom havok icon
         // HavokShape,
         Beacon,
+        Note,
         Ruler,
+        Route,
         Sphere,
+        ReferenceMesh,
         EntityModel,
         StaticInstances
     );
 
+    if hierarchy::is_empty_group(e) {
+        return Some(ICON_FOLDER_OUTLINE);
+    }
+
     None
 }
 
@@ -67,7 +81,16 @@ pub fn resolve_entity_name(e: EntityRef<'_>, append_ent: bool) -> String {
             };
         }
 
-        name_from_component_panels!(Beacon, Ruler, Sphere, EntityModel, StaticInstances);
+        name_from_component_panels!(
+            Beacon,
+            Note,
+            Ruler,
+            Route,
+            Sphere,
+            ReferenceMesh,
+            EntityModel,
+            StaticInstances
+        );
 
         format!("ent {}", e.entity().id())
     }
@@ -75,7 +98,20 @@ pub fn resolve_entity_name(e: EntityRef<'_>, append_ent: bool) -> String {
 
 pub fn resolve_aabb(e: EntityRef<'_>) -> Option<AABB> {
     if let Some(ruler) = e.get::<&Ruler>() {
-        return Some(AABB::from_points([ruler.start, ruler.end]));
+        return Some(AABB::from_points(
+            [ruler.start, ruler.end]
+                .into_iter()
+                .chain(ruler.vertex)
+                .collect_vec(),
+        ));
+    }
+
+    if let Some(route) = e.get::<&Route>() {
+        if !route.nodes.is_empty() {
+            return Some(AABB::from_points(
+                route.nodes.iter().map(|n| n.pos).collect_vec(),
+            ));
+        }
     }
 
     if let Some(si) = e.get::<&StaticInstances>() {
@@ -87,6 +123,10 @@ pub fn resolve_aabb(e: EntityRef<'_>) -> Option<AABB> {
         return Some(AABB::from_points(points));
     }
 
+    if let (Some(rm), Some(transform)) = (e.get::<&ReferenceMesh>(), e.get::<&Transform>()) {
+        return Some(rm.mesh.bounds.transform(&transform.to_mat4()));
+    }
+
     if let Some(transform) = e.get::<&Transform>() {
         let radius = transform.radius();
         if radius.is_normal() {
@@ -99,3 +139,86 @@ pub fn resolve_aabb(e: EntityRef<'_>) -> Option<AABB> {
 
     None
 }
+
+/// Combined bounds of every entity in the scene, excluding utilities
+pub fn scene_aabb(scene: &Scene) -> Option<AABB> {
+    scene
+        .iter()
+        .filter(|e| {
+            e.get::<&Tags>()
+                .map_or(true, |tags| !tags.0.contains(&EntityTag::Utility))
+        })
+        .filter_map(resolve_aabb)
+        .filter(|bb| bb.min.is_finite() && bb.max.is_finite())
+        .reduce(|a, b| a.union(&b))
+}
+
+/// Caches the result of `scene_aabb`, recalculating it when entities are spawned or despawned, or after `invalidate`
+#[derive(Default)]
+pub struct SceneBounds {
+    /// Entity count of the scene the bounds were calculated for
+    cache: Option<(u32, Option<AABB>)>,
+    /// Set when entities have moved since the bounds were calculated
+    dirty: bool,
+}
+
+impl SceneBounds {
+    pub fn get(&mut self, scene: &Scene) -> Option<AABB> {
+        match self.cache {
+            Some((len, bounds)) if len == scene.len() && !self.dirty => bounds,
+            _ => {
+                self.dirty = false;
+                let bounds = scene_aabb(scene);
+                self.cache = Some((scene.len(), bounds));
+                bounds
+            }
+        }
+    }
+
+    pub fn invalidate(&mut self) {
+        self.dirty = true;
+    }
+}
+
+/// Tags the entity was created from, labeled by what they refer to
+pub fn entity_source_tags(e: EntityRef<'_>) -> Vec<(&'static str, ExtendedHash)> {
+    let mut tags = vec![];
+
+    if let Some(rp) = e.get::<&ResourcePoint>() {
+        tags.push(("tag", rp.entity));
+        match rp.resource {
+            MapResource::Unk80806aa3(_, t, _) => tags.push(("model", ExtendedHash::Hash32(t))),
+            MapResource::Unk808068d4(t) => tags.push(("resource", ExtendedHash::Hash32(t))),
+            _ => {}
+        }
+    } else if let Some(model) = e.get::<&EntityModel>() {
+        tags.push(("tag", ExtendedHash::Hash32(model.2)));
+    } else if let Some(si) = e.get::<&StaticInstances>() {
+        tags.push(("tag", ExtendedHash::Hash32(si.1)));
+    }
+
+    tags
+}
+
+/// Compact description of the entity's source tag(s) and position for pasting into quicktag or issue reports
+/// eg. `tag=80A1B2C3 model=80D4E5F6 pos=(1.00,2.00,3.00)`
+pub fn entity_reference_string(e: EntityRef<'_>) -> String {
+    let mut parts = entity_source_tags(e)
+        .into_iter()
+        .map(|(label, tag)| format!("{label}={tag}"))
+        .collect_vec();
+
+    let position = e
+        .get::<&Transform>()
+        .map(|t| t.translation)
+        .or_else(|| resolve_aabb(e).map(|bb| bb.center()));
+    if let Some(p) = position {
+        parts.push(format!("pos=({:.2},{:.2},{:.2})", p.x, p.y, p.z));
+    }
+
+    if parts.is_empty() {
+        format!("ent={}", e.entity().id())
+    } else {
+        parts.join(" ")
+    }
+}
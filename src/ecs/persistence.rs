@@ -0,0 +1,690 @@
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap},
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use anyhow::Context;
+use destiny_pkg::TagHash;
+use glam::{Quat, Vec3};
+use hecs::{Entity, EntityRef};
+use nohash_hasher::IntMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::AutosaveConfig,
+    map::{MapData, MapDataList, SShadowingLight},
+    util::exe_relative_path,
+};
+
+use super::{
+    components::{
+        ActivityGroup, Beacon, Children, CubemapVolume, EditedLabel, EntityModel, EntityWorldId,
+        ErrorMarker, Global, Label, LightColor, Mutable, Note, OpacityOverride, Parent, PointLight,
+        ReferenceMesh, ResourcePoint, Route, RouteNode, Ruler, SourceMap, Sphere, StaticInstances,
+        Terrain, Visible, Water,
+    },
+    entity_source_tags, hierarchy,
+    history::EditHistory,
+    resolve_aabb, resolve_entity_icon, resolve_entity_name,
+    tags::{EntityTag, Tags},
+    transform::Transform,
+};
+
+/// A user-placed (`Mutable`) entity as it is stored on disk
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct SerializedEntity {
+    /// Set when this is a label annotation for an entity from the map itself, rather than a user entity.
+    /// An empty label means the user removed the label
+    #[serde(skip_serializing_if = "Option::is_none")]
+    world_id: Option<u64>,
+    transform: Option<Transform>,
+    tags: Vec<EntityTag>,
+    label: Option<String>,
+    visible: Option<bool>,
+    global: Option<bool>,
+
+    ruler: Option<Ruler>,
+    sphere: Option<Sphere>,
+    beacon: Option<Beacon>,
+    route: Option<Route>,
+    note: Option<Note>,
+
+    /// Index of the parent entity in the saved entity list
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parent: Option<usize>,
+}
+
+/// Path of the file user entities for the given map are automatically saved to and loaded from
+pub fn map_sidecar_path(map_hash: TagHash) -> PathBuf {
+    exe_relative_path(&format!("user_entities/{map_hash}.ron"))
+}
+
+/// Writes all `Mutable` entities and edited map entity labels to `path`, returns the amount of entities written
+pub fn save_user_entities(map: &MapData, path: &Path) -> anyhow::Result<usize> {
+    let (data, count) = serialize_user_entities(map)?;
+
+    if let Some(parent) = path.parent() {
+        fs_err::create_dir_all(parent)?;
+    }
+
+    fs_err::write(path, data)?;
+
+    Ok(count)
+}
+
+/// Serializes all `Mutable` entities and edited map entity labels, returns the data and the amount of entities
+fn serialize_user_entities(map: &MapData) -> anyhow::Result<(String, usize)> {
+    let scene = &map.scene;
+    let saved: Vec<Entity> = scene
+        .query::<()>()
+        .with::<&Mutable>()
+        // Reference meshes point to files outside the game, they only live for the session
+        .without::<&ReferenceMesh>()
+        .iter()
+        .map(|(e, _)| e)
+        .collect();
+
+    let mut entities: Vec<SerializedEntity> = saved
+        .iter()
+        .map(|&ent| {
+            let e = scene.entity(ent).unwrap();
+            SerializedEntity {
+                transform: e.get::<&Transform>().map(|t| *t),
+                tags: e
+                    .get::<&Tags>()
+                    .map(|t| t.0.iter().copied().collect())
+                    .unwrap_or_default(),
+                label: e.get::<&Label>().map(|l| l.0.clone()),
+                visible: e.get::<&Visible>().map(|v| v.0),
+                global: e.get::<&Global>().map(|g| g.0),
+                ruler: e.get::<&Ruler>().map(|r| (*r).clone()),
+                sphere: e.get::<&Sphere>().map(|s| (*s).clone()),
+                beacon: e.get::<&Beacon>().map(|b| (*b).clone()),
+                route: e.get::<&Route>().map(|r| (*r).clone()),
+                note: e.get::<&Note>().map(|n| (*n).clone()),
+                parent: hierarchy::parent_of(scene, ent)
+                    .and_then(|p| saved.iter().position(|&s| s == p)),
+                ..Default::default()
+            }
+        })
+        .collect();
+
+    entities.extend(
+        scene
+            .query::<(&EntityWorldId, Option<&Label>)>()
+            .with::<&EditedLabel>()
+            .without::<&Mutable>()
+            .iter()
+            .map(|(_, (world_id, label))| SerializedEntity {
+                world_id: Some(world_id.0),
+                label: Some(label.map(|l| l.0.clone()).unwrap_or_default()),
+                ..Default::default()
+            }),
+    );
+
+    Ok((
+        ron::ser::to_string_pretty(&entities, ron::ser::PrettyConfig::default())?,
+        entities.len(),
+    ))
+}
+
+/// Replaces all `Mutable` entities (except reference meshes) in the map with the ones stored in `path` and applies the stored map entity labels,
+/// returns the amount of entities loaded
+pub fn load_user_entities(map: &mut MapData, path: &Path) -> anyhow::Result<usize> {
+    let entities: Vec<SerializedEntity> = ron::from_str(&fs_err::read_to_string(path)?)?;
+
+    // Entity handles in the edit history won't survive the reload
+    map.history = EditHistory::default();
+    let scene = &mut map.scene;
+
+    let existing: Vec<Entity> = scene
+        .query::<()>()
+        .with::<&Mutable>()
+        .without::<&ReferenceMesh>()
+        .iter()
+        .map(|(e, _)| e)
+        .collect();
+    for e in existing {
+        scene.despawn(e).ok();
+    }
+
+    let world_ids: Vec<(Entity, u64)> = scene
+        .query::<&EntityWorldId>()
+        .iter()
+        .map(|(e, w)| (e, w.0))
+        .collect();
+
+    // Entities spawned for each entry, for resolving parents afterwards
+    let mut spawned = vec![None; entities.len()];
+    for (se, slot) in entities.iter().zip(spawned.iter_mut()) {
+        if let Some(world_id) = se.world_id {
+            let label = se.label.clone().unwrap_or_default();
+            // Multiple entities can be spawned from the same world entity
+            for &(e, _) in world_ids.iter().filter(|(_, w)| *w == world_id) {
+                if label.is_empty() {
+                    scene.remove_one::<Label>(e).ok();
+                } else {
+                    scene.insert_one(e, Label(label.clone())).ok();
+                }
+                scene.insert_one(e, EditedLabel).ok();
+            }
+
+            continue;
+        }
+
+        let mut builder = hecs::EntityBuilder::new();
+        builder.add(Tags::from_iter(se.tags.iter().copied()));
+        builder.add(Mutable);
+
+        if let Some(transform) = se.transform {
+            builder.add(transform);
+        }
+        if let Some(label) = &se.label {
+            builder.add(Label(label.clone()));
+        }
+        if let Some(visible) = se.visible {
+            builder.add(Visible(visible));
+        }
+        if let Some(global) = se.global {
+            builder.add(Global(global));
+        }
+        if let Some(ruler) = &se.ruler {
+            builder.add(ruler.clone());
+        }
+        if let Some(sphere) = &se.sphere {
+            builder.add(sphere.clone());
+        }
+        if let Some(beacon) = &se.beacon {
+            builder.add(beacon.clone());
+        }
+        if let Some(route) = &se.route {
+            builder.add(route.clone());
+        }
+        if let Some(note) = &se.note {
+            builder.add(note.clone());
+        }
+
+        *slot = Some(scene.spawn(builder.build()));
+    }
+
+    for (se, &e) in entities.iter().zip(&spawned) {
+        if let (Some(e), Some(parent)) =
+            (e, se.parent.and_then(|i| spawned.get(i).copied().flatten()))
+        {
+            if hierarchy::can_reparent(scene, e, Some(parent)) {
+                hierarchy::set_parent(scene, e, Some(parent));
+            }
+        }
+    }
+
+    Ok(entities.len())
+}
+
+/// Keeps track of what was last written to the sidecar file of each map, so maps without changes aren't written again
+#[derive(Default)]
+pub struct AutosaveState {
+    /// Hash of the serialized user entities as they were last saved or loaded, per map
+    saved: IntMap<TagHash, u64>,
+    /// Last time any map was actually written to disk
+    pub last_save: Option<Instant>,
+    last_interval_save: Option<Instant>,
+    /// Map that was current last frame
+    current_map: Option<TagHash>,
+}
+
+impl AutosaveState {
+    /// Saves all maps when the autosave interval has passed, and the previous map when switching maps
+    pub fn update(&mut self, maps: &MapDataList, config: &AutosaveConfig) {
+        let current_map = maps.current_map().map(|(hash, _, _)| *hash);
+        if current_map != self.current_map {
+            if let Some(map) = self
+                .current_map
+                .and_then(|hash| maps.maps.iter().find(|(h, _, _)| *h == hash))
+            {
+                autosave_map(&map.2, self);
+            }
+            self.current_map = current_map;
+        }
+
+        if !config.enabled {
+            self.last_interval_save = None;
+            return;
+        }
+
+        let last = *self.last_interval_save.get_or_insert_with(Instant::now);
+        if last.elapsed().as_secs_f32() >= config.interval {
+            for (_, _, map) in &maps.maps {
+                autosave_map(map, self);
+            }
+            self.last_interval_save = Some(Instant::now());
+        }
+    }
+}
+
+fn hash_data(data: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    data.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Saves the user entities of a map to its sidecar file, unless they haven't changed since they were last saved or loaded.
+/// Combined maps are skipped, they keep the hash of their first source map and would overwrite its sidecar
+pub fn autosave_map(map: &MapData, state: &mut AutosaveState) {
+    if !map.sources.is_empty() {
+        return;
+    }
+
+    let path = map_sidecar_path(map.hash);
+    let has_user_entities = map.scene.query::<&Mutable>().iter().next().is_some()
+        || map.scene.query::<&EditedLabel>().iter().next().is_some();
+
+    // Don't litter the directory with empty files, but do clear out entities that have been deleted
+    if !has_user_entities && !path.exists() {
+        return;
+    }
+
+    let (data, count) = match serialize_user_entities(map) {
+        Ok(v) => v,
+        Err(e) => {
+            error!("Failed to save user entities for map {}: {e:?}", map.name);
+            return;
+        }
+    };
+
+    let hash = hash_data(&data);
+    if state.saved.get(&map.hash) == Some(&hash) && path.exists() {
+        return;
+    }
+
+    let result = path
+        .parent()
+        .map_or(Ok(()), fs_err::create_dir_all)
+        .and_then(|_| fs_err::write(&path, data));
+    match result {
+        Ok(()) => {
+            info!("Saved {count} user entities for map {}", map.name);
+            state.saved.insert(map.hash, hash);
+            state.last_save = Some(Instant::now());
+        }
+        Err(e) => error!("Failed to save user entities for map {}: {e:?}", map.name),
+    }
+}
+
+/// Loads the user entities of a map from its sidecar file, if it has one. Combined maps don't have a sidecar of their own
+pub fn autoload_map(map: &mut MapData, state: &mut AutosaveState) {
+    if !map.sources.is_empty() {
+        return;
+    }
+
+    let path = map_sidecar_path(map.hash);
+    if !path.exists() {
+        return;
+    }
+
+    match load_user_entities(map, &path) {
+        Ok(count) => {
+            info!("Loaded {count} user entities for map {}", map.name);
+            if let Ok((data, _)) = serialize_user_entities(map) {
+                state.saved.insert(map.hash, hash_data(&data));
+            }
+        }
+        Err(e) => error!("Failed to load user entities for map {}: {e:?}", map.name),
+    }
+}
+
+/// Console command that spawns a utility entity from a snippet written by [`utility_spawn_command`]
+pub const SPAWN_UTILITY_COMMAND: &str = "spawn_utility";
+
+/// A single utility entity in the form it is shared between users
+#[derive(Serialize, Deserialize)]
+enum UtilitySnippet {
+    Ruler(Ruler),
+    Sphere(Transform, Sphere),
+    Beacon(Transform, Beacon),
+    Note(Transform, Note),
+}
+
+#[derive(Serialize, Deserialize)]
+struct SpawnSnippet {
+    utility: UtilitySnippet,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+}
+
+/// Builds a single-line console command that recreates the given user entity, as long as it's a ruler, sphere, beacon or note
+pub fn utility_spawn_command(e: EntityRef<'_>) -> Option<String> {
+    if !e.has::<Mutable>() {
+        return None;
+    }
+
+    let transform = || e.get::<&Transform>().map(|t| *t).unwrap_or_default();
+    let utility = if let Some(ruler) = e.get::<&Ruler>() {
+        UtilitySnippet::Ruler(ruler.clone())
+    } else if let Some(sphere) = e.get::<&Sphere>() {
+        UtilitySnippet::Sphere(transform(), sphere.clone())
+    } else if let Some(beacon) = e.get::<&Beacon>() {
+        UtilitySnippet::Beacon(transform(), beacon.clone())
+    } else if let Some(note) = e.get::<&Note>() {
+        UtilitySnippet::Note(transform(), note.clone())
+    } else {
+        return None;
+    };
+
+    let snippet = SpawnSnippet {
+        utility,
+        label: e.get::<&Label>().map(|l| l.0.clone()),
+    };
+
+    ron::to_string(&snippet)
+        .ok()
+        .map(|s| format!("{SPAWN_UTILITY_COMMAND} {s}"))
+}
+
+/// Spawns the utility entity described by a snippet from [`utility_spawn_command`], with or without the command name in front of it
+pub fn spawn_utility_from_command(map: &mut MapData, command: &str) -> anyhow::Result<Entity> {
+    let command = command.trim();
+    let snippet = command
+        .strip_prefix(SPAWN_UTILITY_COMMAND)
+        .unwrap_or(command);
+    let snippet: SpawnSnippet = ron::from_str(snippet).context("Invalid spawn snippet")?;
+
+    let mut builder = hecs::EntityBuilder::new();
+    builder.add(Tags::from_iter([EntityTag::Utility]));
+    builder.add(Mutable);
+    match snippet.utility {
+        UtilitySnippet::Ruler(ruler) => {
+            builder.add(ruler);
+        }
+        UtilitySnippet::Sphere(transform, sphere) => {
+            builder.add(transform).add(sphere);
+        }
+        UtilitySnippet::Beacon(transform, beacon) => {
+            builder.add(transform).add(beacon);
+        }
+        UtilitySnippet::Note(transform, note) => {
+            builder.add(transform).add(note);
+        }
+    }
+
+    if let Some(label) = snippet.label {
+        builder.add(Label(label));
+    }
+
+    let e = map.scene.spawn(builder.build());
+    map.history.record_spawn(e);
+
+    Ok(e)
+}
+
+/// A route node as it is exported for use outside of Alkahest
+#[derive(Serialize, Deserialize)]
+struct Waypoint {
+    x: f32,
+    y: f32,
+    z: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct WaypointFile {
+    waypoints: Vec<Waypoint>,
+}
+
+fn is_csv(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"))
+}
+
+/// Quotes a CSV field if it contains separators, quotes or line breaks. Quotes inside the field are doubled
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) || field.trim() != field {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Splits CSV data into rows of fields. Quoted fields can contain separators, line breaks and doubled quotes
+fn parse_csv(data: &str) -> anyhow::Result<Vec<Vec<String>>> {
+    let mut rows = vec![];
+    let mut row = vec![];
+    let mut field = String::new();
+    let mut in_quotes = false;
+
+    let mut chars = data.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                c => field.push(c),
+            }
+
+            continue;
+        }
+
+        match c {
+            '"' => in_quotes = true,
+            ',' => row.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            c => field.push(c),
+        }
+    }
+
+    anyhow::ensure!(!in_quotes, "Unterminated quoted field");
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+
+    Ok(rows)
+}
+
+/// Writes the nodes of a route as world space waypoints. The format (JSON or CSV) is picked from the file extension
+pub fn export_route(route: &Route, path: &Path) -> anyhow::Result<()> {
+    let data = if is_csv(path) {
+        let mut csv = String::from("index,x,y,z,label\n");
+        for (i, node) in route.nodes.iter().enumerate() {
+            csv.push_str(&format!(
+                "{i},{},{},{},{}\n",
+                node.pos.x,
+                node.pos.y,
+                node.pos.z,
+                csv_field(node.label.as_deref().unwrap_or_default())
+            ));
+        }
+        csv
+    } else {
+        serde_json::to_string_pretty(&WaypointFile {
+            waypoints: route
+                .nodes
+                .iter()
+                .map(|n| Waypoint {
+                    x: n.pos.x,
+                    y: n.pos.y,
+                    z: n.pos.z,
+                    label: n.label.clone(),
+                })
+                .collect(),
+        })?
+    };
+
+    fs_err::write(path, data)?;
+
+    Ok(())
+}
+
+/// Reads waypoints written by [`export_route`] into a new route
+pub fn import_route(path: &Path) -> anyhow::Result<Route> {
+    let data = fs_err::read_to_string(path)?;
+
+    let nodes = if is_csv(path) {
+        parse_csv(&data)?
+            .into_iter()
+            .skip(1)
+            .enumerate()
+            .filter(|(_, fields)| !fields.iter().all(|f| f.trim().is_empty()))
+            .map(|(i, fields)| {
+                anyhow::ensure!(fields.len() >= 4, "Row {} has too few fields", i + 2);
+
+                let coord = |f: &str| {
+                    f.trim()
+                        .parse::<f32>()
+                        .with_context(|| format!("Invalid coordinate on row {}", i + 2))
+                };
+
+                Ok(RouteNode {
+                    pos: Vec3::new(coord(&fields[1])?, coord(&fields[2])?, coord(&fields[3])?),
+                    label: fields.get(4).filter(|l| !l.is_empty()).cloned(),
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?
+    } else {
+        let file: WaypointFile = serde_json::from_str(&data)?;
+        file.waypoints
+            .into_iter()
+            .map(|w| RouteNode {
+                pos: Vec3::new(w.x, w.y, w.z),
+                label: w.label,
+            })
+            .collect()
+    };
+
+    Ok(Route {
+        nodes,
+        ..Default::default()
+    })
+}
+
+/// Summary of a single entity in a scene dump. Only meant for reading by external tools, it can't be loaded back
+#[derive(Serialize)]
+struct SceneDumpEntity {
+    id: u32,
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    icon: Option<char>,
+    components: Vec<&'static str>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<EntityTag>,
+    /// Tags the entity was loaded from (entity, model, etc.)
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    source_tags: BTreeMap<&'static str, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    world_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    translation: Option<Vec3>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rotation: Option<Quat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scale: Option<Vec3>,
+    /// World space bounds as `[min, max]`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bounds: Option<[Vec3; 2]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parent: Option<u32>,
+}
+
+#[derive(Serialize)]
+struct SceneDump {
+    map: String,
+    map_name: String,
+    entities: Vec<SceneDumpEntity>,
+}
+
+/// Writes a summary of every entity in the map to `path` as JSON, returns the amount of entities written
+pub fn export_scene_json(map: &MapData, path: &Path) -> anyhow::Result<usize> {
+    let scene = &map.scene;
+    let mut entities: Vec<SceneDumpEntity> = scene
+        .iter()
+        .map(|e| {
+            let mut components = vec![];
+            macro_rules! component_names {
+                ($($component:ty),+) => {
+                    $(
+                        if e.has::<$component>() {
+                            components.push(stringify!($component));
+                        }
+                    )*
+                };
+            }
+
+            component_names!(
+                Transform,
+                ResourcePoint,
+                EntityModel,
+                StaticInstances,
+                Terrain,
+                Water,
+                PointLight,
+                SShadowingLight,
+                LightColor,
+                CubemapVolume,
+                ActivityGroup,
+                Label,
+                Visible,
+                Global,
+                SourceMap,
+                OpacityOverride,
+                ErrorMarker,
+                Ruler,
+                Sphere,
+                Beacon,
+                Route,
+                Note,
+                ReferenceMesh,
+                Parent,
+                Children,
+                Mutable
+            );
+
+            let transform = e.get::<&Transform>().map(|t| *t);
+            SceneDumpEntity {
+                id: e.entity().id(),
+                name: resolve_entity_name(e, false),
+                icon: resolve_entity_icon(e),
+                components,
+                tags: e
+                    .get::<&Tags>()
+                    .map(|t| t.0.iter().copied().collect())
+                    .unwrap_or_default(),
+                source_tags: entity_source_tags(e)
+                    .into_iter()
+                    .map(|(label, tag)| (label, tag.to_string()))
+                    .collect(),
+                world_id: e.get::<&EntityWorldId>().map(|w| format!("{:016X}", w.0)),
+                translation: transform.map(|t| t.translation),
+                rotation: transform.map(|t| t.rotation),
+                scale: transform.map(|t| t.scale),
+                bounds: resolve_aabb(e)
+                    .filter(|bb| bb.min.is_finite() && bb.max.is_finite())
+                    .map(|bb| [bb.min, bb.max]),
+                parent: hierarchy::parent_of(scene, e.entity()).map(|p| p.id()),
+            }
+        })
+        .collect();
+
+    entities.sort_by_key(|e| e.id);
+
+    let count = entities.len();
+    fs_err::write(
+        path,
+        serde_json::to_string_pretty(&SceneDump {
+            map: map.hash.to_string(),
+            map_name: map.name.clone(),
+            entities,
+        })?,
+    )?;
+
+    Ok(count)
+}
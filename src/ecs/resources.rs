@@ -1,7 +1,60 @@
 use hecs::Entity;
 
+#[derive(Default, Clone)]
 pub struct SelectedEntity(
+    /// The primary selection, this is the entity shown in the inspector
     pub Option<Entity>,
     /// has an entity been selected this frame?
     pub bool,
+    /// Other entities added to the selection with Ctrl+click
+    pub Vec<Entity>,
+    /// Instance of a `StaticInstances` entity picked in the viewport, only valid while that entity is the primary selection
+    pub Option<(Entity, usize)>,
 );
+
+impl SelectedEntity {
+    /// Replaces the whole selection with the given entity
+    pub fn select(&mut self, entity: Option<Entity>) {
+        self.0 = entity;
+        self.2.clear();
+        self.3 = None;
+    }
+
+    /// Adds the entity to the selection as the primary selection, or removes it if it was already selected
+    pub fn toggle(&mut self, entity: Entity) {
+        self.3 = None;
+        if self.0 == Some(entity) {
+            self.0 = self.2.pop();
+        } else if let Some(i) = self.2.iter().position(|e| *e == entity) {
+            self.2.remove(i);
+        } else if let Some(previous) = self.0.replace(entity) {
+            self.2.push(previous);
+        }
+    }
+
+    /// Selects a single instance of a `StaticInstances` entity
+    pub fn select_instance(&mut self, entity: Entity, index: usize) {
+        self.select(Some(entity));
+        self.3 = Some((entity, index));
+    }
+
+    /// Index of the selected instance of the primary selection
+    pub fn instance(&self) -> Option<usize> {
+        self.3
+            .filter(|(e, _)| self.0 == Some(*e))
+            .map(|(_, index)| index)
+    }
+
+    pub fn is_selected(&self, entity: Entity) -> bool {
+        self.0 == Some(entity) || self.2.contains(&entity)
+    }
+
+    pub fn is_multiple(&self) -> bool {
+        self.0.is_some() && !self.2.is_empty()
+    }
+
+    /// All selected entities, starting with the primary selection
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.0.into_iter().chain(self.2.iter().copied())
+    }
+}
@@ -2,13 +2,14 @@ use std::fmt::Display;
 
 use egui::Color32;
 use hecs::Entity;
-use nohash_hasher::IntSet;
+use nohash_hasher::{IntMap, IntSet};
+use serde::{Deserialize, Serialize};
 
 use crate::{icons::ICON_WEB, overlays::UiExt, util::text::name_to_color};
 
 use super::Scene;
 
-#[derive(strum::EnumIter, Hash, PartialEq, Eq)]
+#[derive(strum::EnumIter, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub enum EntityTag {
     Activity,
     Ambient,
@@ -42,7 +43,51 @@ impl Display for EntityTag {
     }
 }
 
-#[derive(Default)]
+/// Colors used to tint entities in the viewport by their tags
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct TagPalette {
+    pub enabled: bool,
+    /// Tags without a color are left untinted
+    pub colors: IntMap<EntityTag, Color32>,
+}
+
+impl TagPalette {
+    /// Order in which tags are considered when an entity has multiple tags, most specific first
+    const PRIORITY: [EntityTag; 6] = [
+        EntityTag::Utility,
+        EntityTag::Havok,
+        EntityTag::User,
+        EntityTag::Activity,
+        EntityTag::Ambient,
+        EntityTag::Global,
+    ];
+
+    pub fn color_for(&self, tags: &Tags) -> Option<Color32> {
+        if !self.enabled {
+            return None;
+        }
+
+        Self::PRIORITY
+            .iter()
+            .filter(|t| tags.0.contains(t))
+            .find_map(|t| self.colors.get(t).copied())
+    }
+}
+
+impl Default for TagPalette {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            colors: [EntityTag::Utility, EntityTag::Havok]
+                .into_iter()
+                .map(|t| (t, t.color()))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Default, Clone)]
 pub struct Tags(pub IntSet<EntityTag>);
 
 impl Tags {
@@ -1,7 +1,8 @@
 use bitflags::bitflags;
 use glam::{Mat4, Quat, Vec3};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 #[repr(C, align(16))]
 pub struct Transform {
     pub translation: Vec3,
@@ -53,7 +54,7 @@ impl Default for Transform {
 }
 
 bitflags! {
-    #[derive(Default, Debug, Copy, Clone, PartialEq)]
+    #[derive(Default, Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
     pub struct TransformFlags: u32 {
         const IGNORE_TRANSLATION = (1 << 0);
         const IGNORE_ROTATION = (1 << 1);
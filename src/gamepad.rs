@@ -0,0 +1,120 @@
+use gilrs::{Axis, Button, EventType, Gamepad, GamepadId, Gilrs};
+use glam::Vec2;
+
+use crate::input::InputState;
+
+/// Gamepad state with the deadzone applied
+#[derive(Default, Clone, Copy)]
+pub struct GamepadInput {
+    /// Used for translation
+    pub left_stick: Vec2,
+    /// Used for looking around
+    pub right_stick: Vec2,
+    pub left_trigger: f32,
+    pub right_trigger: f32,
+    /// Right bumper
+    pub boost: bool,
+    /// Left bumper
+    pub slow: bool,
+}
+
+pub struct Gamepads {
+    gilrs: Option<Gilrs>,
+    /// The gamepad that was used last
+    active: Option<GamepadId>,
+
+    pub enabled: bool,
+    pub deadzone: f32,
+}
+
+impl Gamepads {
+    pub fn new(enabled: bool, deadzone: f32) -> Self {
+        let gilrs = match Gilrs::new() {
+            Ok(gilrs) => Some(gilrs),
+            Err(e) => {
+                warn!("Failed to initialize gamepad support: {e}");
+                None
+            }
+        };
+
+        Self {
+            gilrs,
+            active: None,
+            enabled,
+            deadzone,
+        }
+    }
+
+    /// Processes pending gamepad events and writes the state of the active gamepad to `input`
+    pub fn update(&mut self, input: &mut InputState) {
+        input.gamepad = GamepadInput::default();
+
+        let Some(gilrs) = &mut self.gilrs else {
+            return;
+        };
+
+        while let Some(event) = gilrs.next_event() {
+            match event.event {
+                EventType::Connected => {
+                    info!("Gamepad connected: {}", gilrs.gamepad(event.id).name());
+                }
+                EventType::Disconnected => {
+                    if self.active == Some(event.id) {
+                        self.active = None;
+                    }
+                }
+                _ => self.active = Some(event.id),
+            }
+        }
+
+        if !self.enabled {
+            return;
+        }
+
+        if let Some(id) = self.active {
+            input.gamepad = read_gamepad(gilrs.gamepad(id), self.deadzone);
+        }
+    }
+}
+
+fn read_gamepad(gamepad: Gamepad<'_>, deadzone: f32) -> GamepadInput {
+    let trigger = |button: Button| {
+        let value = gamepad.button_data(button).map_or(0.0, |d| d.value());
+        if value > deadzone {
+            value
+        } else {
+            0.0
+        }
+    };
+
+    GamepadInput {
+        left_stick: apply_deadzone(
+            Vec2::new(
+                gamepad.value(Axis::LeftStickX),
+                gamepad.value(Axis::LeftStickY),
+            ),
+            deadzone,
+        ),
+        right_stick: apply_deadzone(
+            Vec2::new(
+                gamepad.value(Axis::RightStickX),
+                gamepad.value(Axis::RightStickY),
+            ),
+            deadzone,
+        ),
+        left_trigger: trigger(Button::LeftTrigger2),
+        right_trigger: trigger(Button::RightTrigger2),
+        boost: gamepad.is_pressed(Button::RightTrigger),
+        slow: gamepad.is_pressed(Button::LeftTrigger),
+    }
+}
+
+/// Radial deadzone, rescaled so the output still ramps up smoothly from zero
+fn apply_deadzone(v: Vec2, deadzone: f32) -> Vec2 {
+    let length = v.length();
+    if length <= deadzone {
+        return Vec2::ZERO;
+    }
+
+    v / length * ((length - deadzone) / (1.0 - deadzone)).min(1.0)
+}
@@ -1,6 +1,18 @@
+use hecs::Entity;
+
 use crate::{
-    ecs::{components::Visible, resources::SelectedEntity},
-    map::MapDataList,
+    camera::FpsCamera,
+    commands::Commands,
+    ecs::{
+        components::{Global, StaticInstances, Visible},
+        history::{group_mutable, ungroup_mutable},
+        persistence::{self, AutosaveState, SPAWN_UTILITY_COMMAND},
+        resolve_aabb,
+        resources::SelectedEntity,
+        transform::Transform,
+    },
+    map::{MapData, MapDataList},
+    render::EntityRenderers,
     resources::Resources,
 };
 
@@ -16,37 +28,125 @@ pub const SHORTCUT_UNHIDE_ALL: egui::KeyboardShortcut =
 pub const SHORTCUT_HIDE_UNSELECTED: egui::KeyboardShortcut =
     egui::KeyboardShortcut::new(egui::Modifiers::SHIFT, egui::Key::H);
 
-pub const SHORTCUT_FOCUS: egui::KeyboardShortcut =
-    egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::F);
+// W/E/R are taken by the camera controls, so the gizmo modes live on the number keys
+pub const SHORTCUT_GIZMO_TRANSLATE: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::Num1);
+
+pub const SHORTCUT_GIZMO_ROTATE: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::Num2);
+
+pub const SHORTCUT_GIZMO_SCALE: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::Num3);
+
+pub const SHORTCUT_UNDO: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::CTRL, egui::Key::Z);
+
+pub const SHORTCUT_REDO: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::CTRL, egui::Key::Y);
+
+pub const SHORTCUT_GROUP: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::CTRL, egui::Key::G);
+
+pub const SHORTCUT_UNGROUP: egui::KeyboardShortcut = egui::KeyboardShortcut::new(
+    egui::Modifiers {
+        shift: true,
+        ..egui::Modifiers::CTRL
+    },
+    egui::Key::G,
+);
+
+pub const SHORTCUT_COMMAND_PALETTE: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::CTRL, egui::Key::P);
 
 pub fn process_hotkeys(ctx: &egui::Context, resources: &mut Resources) {
-    if ctx.input_mut(|i| i.consume_shortcut(&SHORTCUT_UNHIDE_ALL)) {
-        unhide_all(resources);
+    let pressed = resources.get::<Commands>().unwrap().pressed(ctx);
+    for run in pressed {
+        run(resources);
+    }
+
+    // Pasting a spawn command anywhere outside of a text field spawns the utility it describes
+    if !ctx.wants_keyboard_input() {
+        let pasted = ctx.input(|i| {
+            i.events.iter().find_map(|e| match e {
+                egui::Event::Paste(text)
+                    if text.trim_start().starts_with(SPAWN_UTILITY_COMMAND) =>
+                {
+                    Some(text.clone())
+                }
+                _ => None,
+            })
+        });
+
+        if let Some(command) = pasted {
+            spawn_utility(resources, &command);
+        }
+    }
+}
+
+/// Spawns the utility entity described by a spawn command, and selects it
+pub fn spawn_utility(resources: &Resources, command: &str) {
+    if let Some(mut maps) = resources.get_mut::<MapDataList>() {
+        if let Some(map) = maps.current_map_mut() {
+            match persistence::spawn_utility_from_command(map, command) {
+                Ok(e) => {
+                    resources
+                        .get_mut::<SelectedEntity>()
+                        .unwrap()
+                        .select(Some(e));
+                    info!("Spawned utility entity from command");
+                }
+                Err(e) => error!("Failed to spawn utility entity: {e:?}"),
+            }
+        }
     }
+}
 
-    if ctx.input_mut(|i| i.consume_shortcut(&SHORTCUT_HIDE_UNSELECTED)) {
-        hide_unselected(resources);
+/// Saves the user entities of maps that were unloaded, and drops the entity renderers that none of the remaining maps use anymore
+pub fn release_unloaded_maps(resources: &Resources, maps: &MapDataList, unloaded: Vec<MapData>) {
+    if unloaded.is_empty() {
+        return;
     }
+
+    let mut autosave = resources.get_mut::<AutosaveState>().unwrap();
+    for map in &unloaded {
+        persistence::autosave_map(map, &mut autosave);
+        info!("Unloaded map {} ({})", map.name, map.hash);
+    }
+
+    let used = maps.used_entity_renderers();
+    resources
+        .get_mut::<EntityRenderers>()
+        .unwrap()
+        .0
+        .retain(|k, _| used.contains(k));
 }
 
-fn hide_unselected(resources: &mut Resources) {
-    let selected_entity = resources.get::<SelectedEntity>().unwrap().0;
+/// Solo the selected entities by hiding everything else. Entities shown in all maps keep their visibility
+pub fn hide_unselected(resources: &mut Resources) {
+    let selected = resources.get::<SelectedEntity>().unwrap();
     if let Some(mut maps) = resources.get_mut::<MapDataList>() {
         if let Some(map) = maps.current_map_mut() {
-            for (entity, vis) in map.scene.query::<Option<&mut Visible>>().iter() {
-                if Some(entity) != selected_entity {
-                    if let Some(vis) = vis {
-                        vis.0 = false;
-                    } else {
-                        map.command_buffer.insert_one(entity, Visible(false));
-                    }
+            for (entity, (vis, global)) in map
+                .scene
+                .query::<(Option<&mut Visible>, Option<&Global>)>()
+                .iter()
+            {
+                if global.map_or(false, |g| g.0) {
+                    continue;
+                }
+
+                let visible = selected.is_selected(entity);
+                if let Some(vis) = vis {
+                    vis.0 = visible;
+                } else if !visible {
+                    map.command_buffer.insert_one(entity, Visible(false));
                 }
             }
         }
     }
 }
 
-fn unhide_all(resources: &mut Resources) {
+pub fn unhide_all(resources: &mut Resources) {
     if let Some(maps) = resources.get::<MapDataList>() {
         if let Some((_, _, map)) = maps.current_map() {
             for (_, vis) in map.scene.query::<&mut Visible>().iter() {
@@ -55,3 +155,96 @@ fn unhide_all(resources: &mut Resources) {
         }
     }
 }
+
+/// Moves the camera to the selected entity, or the selected instance of a static
+pub fn frame_selected(resources: &Resources) {
+    let mut camera = resources.get_mut::<FpsCamera>().unwrap();
+    let selected_entity = resources.get::<SelectedEntity>().unwrap();
+    let maps = resources.get::<MapDataList>().unwrap();
+
+    if let Some((_, _, map)) = maps.current_map() {
+        if let Ok(e) = map
+            .scene
+            .entity(selected_entity.0.unwrap_or(Entity::DANGLING))
+        {
+            let instance_bounds = selected_entity.instance().and_then(|i| {
+                e.get::<&StaticInstances>()?
+                    .0
+                    .occlusion_bounds
+                    .get(i)
+                    .copied()
+            });
+
+            if let Some(target) = instance_bounds.or_else(|| resolve_aabb(e)) {
+                camera.focus_aabb(&target);
+            } else if let Some(transform) = e.get::<&Transform>() {
+                camera.focus(transform.translation, 10.0);
+            }
+        }
+    }
+}
+
+/// Moves the camera back far enough to see the entire map from the current angle
+pub fn frame_map(resources: &Resources) {
+    if let Some(mut maps) = resources.get_mut::<MapDataList>() {
+        if let Some(map) = maps.current_map_mut() {
+            if map.history.take_changed() {
+                map.bounds.invalidate();
+            }
+
+            if let Some(bounds) = map.bounds.get(&map.scene) {
+                let mut camera = resources.get_mut::<FpsCamera>().unwrap();
+                let orientation = camera.orientation;
+                camera.frame_aabb(&bounds, orientation);
+            }
+        }
+    }
+}
+
+/// Groups the selected user entities under a new empty entity, and selects it
+pub fn group_selected(resources: &mut Resources) {
+    let mut selected = resources.get_mut::<SelectedEntity>().unwrap();
+    if let Some(mut maps) = resources.get_mut::<MapDataList>() {
+        if let Some(map) = maps.current_map_mut() {
+            let entities: Vec<Entity> = selected.iter().collect();
+            if let Some(group) = group_mutable(&mut map.scene, &mut map.history, &entities) {
+                selected.select(Some(group));
+            }
+        }
+    }
+}
+
+/// Moves the children of the selected entities out of them, despawning the ones that were empty groups.
+/// The former children become the new selection
+pub fn ungroup_selected(resources: &mut Resources) {
+    let mut selected = resources.get_mut::<SelectedEntity>().unwrap();
+    if let Some(mut maps) = resources.get_mut::<MapDataList>() {
+        if let Some(map) = maps.current_map_mut() {
+            let mut children = vec![];
+            for group in selected.iter().collect::<Vec<_>>() {
+                children.extend(ungroup_mutable(&mut map.scene, &mut map.history, group));
+            }
+
+            if let Some((&first, rest)) = children.split_first() {
+                selected.select(Some(first));
+                selected.2.extend_from_slice(rest);
+            }
+        }
+    }
+}
+
+pub fn undo(resources: &mut Resources) {
+    if let Some(mut maps) = resources.get_mut::<MapDataList>() {
+        if let Some(map) = maps.current_map_mut() {
+            map.history.undo(&mut map.scene);
+        }
+    }
+}
+
+pub fn redo(resources: &mut Resources) {
+    if let Some(mut maps) = resources.get_mut::<MapDataList>() {
+        if let Some(map) = maps.current_map_mut() {
+            map.history.redo(&mut map.scene);
+        }
+    }
+}
@@ -1,5 +1,10 @@
 use winit::event::{KeyboardInput, VirtualKeyCode, WindowEvent};
 
+use crate::{
+    gamepad::GamepadInput,
+    keybinds::{is_modifier_key, Action, KeyBind, KeyBindings},
+};
+
 pub type Key = VirtualKeyCode;
 const WINIT_KEY_COUNT: usize = Key::Cut as usize + 1;
 
@@ -34,6 +39,11 @@ pub struct InputState {
     alt: bool,
     shift: bool,
 
+    /// The last key that went from up to down, used for capturing keybinds
+    last_pressed: Option<Key>,
+
+    pub gamepad: GamepadInput,
+
     /// Left mouse button
     mouse1: ButtonState,
     /// Right mouse button
@@ -53,6 +63,8 @@ impl Default for InputState {
             ctrl: false,
             alt: false,
             shift: false,
+            last_pressed: None,
+            gamepad: GamepadInput::default(),
             mouse1: ButtonState::Up,
             mouse2: ButtonState::Up,
             mouse3: ButtonState::Up,
@@ -82,7 +94,10 @@ impl InputState {
                     let key = &mut self.keys[*vk as usize];
                     match state {
                         winit::event::ElementState::Pressed => match *key {
-                            ButtonState::Up => *key = ButtonState::Down,
+                            ButtonState::Up => {
+                                *key = ButtonState::Down;
+                                self.last_pressed = Some(*vk);
+                            }
                             ButtonState::Down => *key = ButtonState::Repeated,
                             ButtonState::Repeated => {}
                         },
@@ -152,6 +167,44 @@ impl InputState {
         self.key_state(vk) == ButtonState::Down
     }
 
+    /// Returns true if the bound key is being held along with (at least) the bound modifiers
+    pub fn is_bind_down(&self, bind: KeyBind) -> bool {
+        self.is_key_down(bind.key)
+            && (!bind.ctrl || self.ctrl)
+            && (!bind.alt || self.alt)
+            && (!bind.shift || self.shift)
+    }
+
+    /// Returns true if the bound key was pressed with exactly the bound modifiers
+    pub fn is_bind_pressed(&self, bind: KeyBind) -> bool {
+        if is_modifier_key(bind.key) {
+            return self.is_key_pressed(bind.key);
+        }
+
+        self.is_key_pressed(bind.key)
+            && bind.ctrl == self.ctrl
+            && bind.alt == self.alt
+            && bind.shift == self.shift
+    }
+
+    pub fn is_action_down(&self, bindings: &KeyBindings, action: Action) -> bool {
+        self.is_bind_down(bindings.get(action))
+    }
+
+    pub fn is_action_pressed(&self, bindings: &KeyBindings, action: Action) -> bool {
+        self.is_bind_pressed(bindings.get(action))
+    }
+
+    /// Returns the last pressed key and clears it
+    pub fn take_last_pressed(&mut self) -> Option<Key> {
+        self.last_pressed.take()
+    }
+
+    /// Returns true if any of the mouse buttons are being held
+    pub fn any_mouse_down(&self) -> bool {
+        self.mouse_left() || self.mouse_right() || self.mouse_middle()
+    }
+
     pub fn ctrl(&self) -> bool {
         self.ctrl
     }
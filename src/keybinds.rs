@@ -0,0 +1,154 @@
+use std::{collections::HashMap, fmt::Display};
+
+use serde::{Deserialize, Serialize};
+use strum::{EnumIter, IntoEnumIterator};
+
+use crate::input::Key;
+
+#[derive(
+    Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug, EnumIter, strum::Display,
+)]
+pub enum Action {
+    #[strum(serialize = "Move forward")]
+    CameraForward,
+    #[strum(serialize = "Move backward")]
+    CameraBackward,
+    #[strum(serialize = "Move left")]
+    CameraLeft,
+    #[strum(serialize = "Move right")]
+    CameraRight,
+    #[strum(serialize = "Move down")]
+    CameraDown,
+    #[strum(serialize = "Move up")]
+    CameraUp,
+    #[strum(serialize = "Speed boost")]
+    CameraBoost,
+    /// Moves the camera along the world axes instead of the camera axes
+    #[strum(serialize = "Absolute movement")]
+    CameraAbsolute,
+
+    #[strum(serialize = "Frame selected")]
+    FocusSelected,
+    #[strum(serialize = "Frame entire map")]
+    FrameMap,
+    #[strum(serialize = "Toggle fullscreen")]
+    ToggleFullscreen,
+    #[strum(serialize = "Take screenshot")]
+    Screenshot,
+    #[strum(serialize = "Toggle render statistics")]
+    ToggleRenderStats,
+    #[strum(serialize = "Reload shaders")]
+    ReloadShaders,
+}
+
+impl Action {
+    pub fn default_bind(&self) -> KeyBind {
+        match self {
+            Action::CameraForward => KeyBind::new(Key::W),
+            Action::CameraBackward => KeyBind::new(Key::S),
+            Action::CameraLeft => KeyBind::new(Key::A),
+            Action::CameraRight => KeyBind::new(Key::D),
+            Action::CameraDown => KeyBind::new(Key::Q),
+            Action::CameraUp => KeyBind::new(Key::E),
+            Action::CameraBoost => KeyBind::new(Key::Space),
+            Action::CameraAbsolute => KeyBind::new(Key::LAlt),
+            Action::FocusSelected => KeyBind::new(Key::F),
+            Action::FrameMap => KeyBind::new(Key::Home),
+            Action::ToggleFullscreen => KeyBind::new(Key::Return).with_alt(),
+            Action::Screenshot => KeyBind::new(Key::F12),
+            Action::ToggleRenderStats => KeyBind::new(Key::F3),
+            Action::ReloadShaders => KeyBind::new(Key::F5),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct KeyBind {
+    pub key: Key,
+    #[serde(default)]
+    pub ctrl: bool,
+    #[serde(default)]
+    pub alt: bool,
+    #[serde(default)]
+    pub shift: bool,
+}
+
+impl KeyBind {
+    pub const fn new(key: Key) -> Self {
+        Self {
+            key,
+            ctrl: false,
+            alt: false,
+            shift: false,
+        }
+    }
+
+    pub const fn with_alt(self) -> Self {
+        Self { alt: true, ..self }
+    }
+}
+
+impl Display for KeyBind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.ctrl {
+            f.write_str("Ctrl+")?;
+        }
+        if self.alt {
+            f.write_str("Alt+")?;
+        }
+        if self.shift {
+            f.write_str("Shift+")?;
+        }
+
+        write!(f, "{:?}", self.key)
+    }
+}
+
+/// Returns true if the key is a modifier key (ctrl, alt, shift, win)
+pub fn is_modifier_key(key: Key) -> bool {
+    matches!(
+        key,
+        Key::LControl
+            | Key::RControl
+            | Key::LAlt
+            | Key::RAlt
+            | Key::LShift
+            | Key::RShift
+            | Key::LWin
+            | Key::RWin
+    )
+}
+
+/// Maps actions to key combinations. Actions without an entry use their default binding.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(transparent)]
+pub struct KeyBindings(HashMap<Action, KeyBind>);
+
+impl KeyBindings {
+    pub fn get(&self, action: Action) -> KeyBind {
+        self.0
+            .get(&action)
+            .copied()
+            .unwrap_or_else(|| action.default_bind())
+    }
+
+    pub fn set(&mut self, action: Action, bind: KeyBind) {
+        if bind == action.default_bind() {
+            self.0.remove(&action);
+        } else {
+            self.0.insert(action, bind);
+        }
+    }
+
+    pub fn reset_all(&mut self) {
+        self.0.clear();
+    }
+
+    /// Returns the other actions that are bound to the same key combination as `action`
+    pub fn conflicts(&self, action: Action) -> Vec<Action> {
+        let bind = self.get(action);
+        Action::iter()
+            .filter(|&a| a != action && self.get(a) == bind)
+            .collect()
+    }
+}
@@ -9,56 +9,49 @@ extern crate windows;
 extern crate tracing;
 
 use std::cell::RefCell;
-use std::f32::consts::PI;
 use std::io::{Cursor, Read, Seek, SeekFrom};
-use std::mem::transmute;
+use std::mem::{take, transmute};
 use std::path::PathBuf;
 use std::rc::Rc;
 use std::str::FromStr;
-use std::sync::atomic::Ordering;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use crate::activity::SActivity;
-use crate::ecs::components::{
-    ActivityGroup, Beacon, EntityModel, ResourcePoint, Ruler, Sphere, StaticInstances, Terrain,
-    Visible, Water,
-};
-use crate::ecs::resolve_aabb;
+use crate::app::App;
+use crate::ecs::components::StaticInstances;
+use crate::ecs::persistence::{self, AutosaveState};
 use crate::ecs::resources::SelectedEntity;
-use crate::hotkeys::SHORTCUT_FOCUS;
+use crate::ecs::tags::TagPalette;
+use crate::overlays::axis_gizmo::AxisGizmoOverlay;
+use crate::overlays::bytecode_inspector::BytecodeInspectorOverlay;
+use crate::overlays::clip_plane::ClipPlaneOverlay;
+use crate::overlays::command_palette::CommandPaletteOverlay;
 use crate::overlays::console::ConsoleOverlay;
+use crate::overlays::gizmo::TransformGizmoOverlay;
 use crate::overlays::inspector::InspectorOverlay;
+use crate::overlays::keybinds::KeybindsOverlay;
+use crate::overlays::material_info::MaterialInfoOverlay;
 use crate::overlays::menu::MenuBar;
 use crate::overlays::outliner::OutlinerOverlay;
-use crate::structure::ExtendedHash;
-use crate::texture::{Texture, LOW_RES};
+use crate::texture::LOW_RES;
 use crate::util::consts::print_banner;
 use crate::util::image::Png;
-use crate::util::text::{invert_color, keep_color_bright, prettify_distance};
 use crate::util::{exe_relative_path, FilterDebugLockTarget, RwLock};
 use anyhow::Context;
 use binrw::BinReaderExt;
 use clap::Parser;
 use destiny_pkg::PackageVersion::{self};
 use destiny_pkg::{PackageManager, TagHash};
-use dxbc::{get_input_signature, get_output_signature, DxbcHeader, DxbcInputType};
-use ecs::components::CubemapVolume;
-use ecs::transform::Transform;
 use egui::epaint::ahash::HashMap;
-use egui::epaint::Hsva;
-use glam::{Mat4, Quat, Vec3};
-use hecs::Entity;
+use glam::{Vec2, Vec3};
 use itertools::Itertools;
 use nohash_hasher::{IntMap, IntSet};
 use overlays::camera_settings::CurrentCubemap;
-use packages::get_named_tag;
 use poll_promise::Promise;
-use render::debug::DebugDrawFlags;
-use render::vertex_layout::InputElement;
 
-use render_globals::SRenderGlobals;
-use technique::Technique;
+use render_globals::{ReloadShadersRequest, RenderGlobals};
 use tracing::level_filters::LevelFilter;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::{EnvFilter, Layer};
@@ -67,41 +60,60 @@ use windows::Win32::Foundation::DXGI_STATUS_OCCLUDED;
 use windows::Win32::Graphics::Direct3D11::*;
 use windows::Win32::Graphics::Dxgi::{Common::*, DXGI_PRESENT_TEST, DXGI_SWAP_EFFECT_SEQUENTIAL};
 use winit::dpi::{PhysicalPosition, PhysicalSize};
-use winit::event::VirtualKeyCode;
 use winit::platform::windows::WindowBuilderExtWindows;
 use winit::{
-    event::{Event, WindowEvent},
+    event::{Event, MouseScrollDelta, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
+    window::Fullscreen,
 };
 
 use crate::camera::FpsCamera;
-use crate::config::{WindowConfig, CONFIGURATION};
+use crate::commands::Commands;
+use crate::config::{
+    AutosaveConfig, AxisGizmoConfig, MapUnloadConfig, PrefetchConfig, RecentMaps, WindowConfig,
+    CONFIGURATION,
+};
+use crate::gamepad::Gamepads;
 use crate::input::InputState;
-use crate::map::MapDataList;
+use crate::keybinds::{Action, KeyBindings};
+use crate::map::{parse_map_hash, MapDataList};
 use crate::map_resources::MapResource;
 use crate::mapload_temporary::load_maps;
 use crate::overlays::camera_settings::CameraPositionOverlay;
 
 use crate::overlays::fps_display::FpsDisplayOverlay;
 use crate::overlays::gui::{GuiManager, ViewerWindows};
+use crate::overlays::hover_info::HoverInfoOverlay;
 use crate::overlays::load_indicator::LoadIndicatorOverlay;
+use crate::overlays::notes::NoteOverlay;
 use crate::overlays::render_settings::{
-    ActivityGroupFilter, RenderSettings, RenderSettingsOverlay,
+    ActivityGroupFilter, LightAnimation, RenderSettingsOverlay, SunPath,
 };
+use crate::overlays::render_stats::RenderStatsOverlay;
 use crate::overlays::resource_nametags::ResourceTypeOverlay;
 use crate::overlays::tag_dump::TagDumper;
+use crate::overlays::toasts::{ToastOverlay, Toasts};
 use crate::packages::{package_manager, PACKAGE_MANAGER};
 use crate::render::debug::DebugShapes;
 use crate::render::overrides::{EnabledShaderOverrides, ScopeOverrides};
 use crate::render::renderer::{Renderer, RendererShared, ShadowMapsResource};
 
-use crate::render::{DeviceContextSwapchain, EntityRenderer};
+use crate::render::resource_mt::{PROGRESS_BUFFERS, PROGRESS_TEXTURES};
+use crate::render::settings::RenderSettings;
+use crate::render::time::TimeControl;
+use crate::render::{DeviceContextSwapchain, EntityRenderers};
 use crate::resources::Resources;
+use crate::screenshot::{save_screenshot, CaptureState, HeadlessCapture, ScreenshotRequest};
+
+use crate::benchmark::{Benchmark, BenchmarkOptions, BenchmarkRequest, BenchmarkState};
 
 use crate::text::{decode_text, StringContainer, StringData, StringPart};
 
 mod activity;
+mod app;
+mod benchmark;
 mod camera;
+mod commands;
 mod config;
 #[cfg(feature = "discord_rpc")]
 mod discord;
@@ -109,9 +121,11 @@ mod dxbc;
 mod dxgi;
 mod ecs;
 mod entity;
+mod gamepad;
 mod hotkeys;
 mod icons;
 mod input;
+mod keybinds;
 mod map;
 mod map_resources;
 mod mapload_temporary;
@@ -121,6 +135,7 @@ mod panic_handler;
 mod render;
 mod render_globals;
 mod resources;
+mod screenshot;
 mod statics;
 mod structure;
 mod technique;
@@ -148,6 +163,52 @@ struct Args {
 
     #[arg(long)]
     lowres: bool,
+
+    /// Render a single frame to the given PNG or EXR file and exit, without showing the window
+    #[arg(long)]
+    screenshot: Option<PathBuf>,
+
+    /// Camera position to render the screenshot from (x,y,z)
+    #[arg(
+        long,
+        value_delimiter = ',',
+        num_args = 3,
+        allow_negative_numbers = true
+    )]
+    camera_pos: Option<Vec<f32>>,
+
+    /// Camera rotation to render the screenshot from, in degrees (pitch,yaw)
+    #[arg(
+        long,
+        value_delimiter = ',',
+        num_args = 2,
+        allow_negative_numbers = true
+    )]
+    camera_rot: Option<Vec<f32>>,
+
+    /// Render a thumbnail of every loaded map (eg. all maps in an --activity) into the given directory and exit
+    #[arg(long, conflicts_with = "screenshot")]
+    thumbnails: Option<PathBuf>,
+
+    /// Advance every frame by this many seconds instead of the real time elapsed, for reproducible captures
+    #[arg(long)]
+    fixed_delta: Option<f32>,
+
+    /// Freeze the game time used by animated materials at this value, in seconds
+    #[arg(long)]
+    game_time: Option<f32>,
+
+    /// Fly the camera along a route waypoint file (json or csv), log per-frame timings to a CSV and exit
+    #[arg(long, conflicts_with_all = ["screenshot", "thumbnails"])]
+    benchmark: Option<PathBuf>,
+
+    /// CSV file to write the benchmark results to
+    #[arg(long, default_value = "benchmark.csv")]
+    benchmark_output: PathBuf,
+
+    /// Camera speed along the benchmark route, in meters per second
+    #[arg(long, default_value_t = benchmark::DEFAULT_SPEED)]
+    benchmark_speed: f32,
 }
 
 #[tokio::main]
@@ -295,34 +356,28 @@ pub async fn main() -> anyhow::Result<()> {
             PhysicalPosition::new(c.window.pos_x, c.window.pos_y)
         }))
         .with_maximized(config!().window.maximised)
+        .with_visible(args.screenshot.is_none() && args.thumbnails.is_none())
         .with_window_icon(Some(icon.clone()))
         .with_taskbar_icon(Some(icon))
         .build(&event_loop)?;
     let window = Arc::new(window);
 
-    let dcs = Arc::new(DeviceContextSwapchain::create(&window)?);
+    let dcs = Arc::new(DeviceContextSwapchain::create(
+        &window,
+        config::with(|c| c.gpu.adapter.clone()).as_deref(),
+    )?);
 
     // TODO(cohae): resources should be added to renderdata directly
     let renderer: RendererShared = Arc::new(RwLock::new(Renderer::create(&window, dcs.clone())?));
 
-    load_render_globals(&renderer.read());
+    {
+        let renderer = renderer.read();
+        RenderGlobals::load(&renderer)?.apply(&renderer);
+    }
 
     let mut map_hashes = if let Some(map_hash) = &args.map {
-        let hash = match u32::from_str_radix(map_hash, 16) {
-            Ok(v) => TagHash(u32::from_be(v)),
-            Err(_e) => anyhow::bail!("The given map '{map_hash}' is not a valid hash!"),
-        };
-
-        if package_manager()
-            .get_entry(hash)
-            .context("Could not find given map hash")?
-            .reference
-            != u32::from_be(0x1E898080)
-        {
-            anyhow::bail!("The given hash '{map_hash}' is not a map!")
-        }
-
-        vec![hash]
+        vec![parse_map_hash(map_hash)
+            .with_context(|| format!("The given map '{map_hash}' is not valid"))?]
     } else {
         package
             .get_all_by_reference(u32::from_be(0x1E898080))
@@ -361,15 +416,44 @@ pub async fn main() -> anyhow::Result<()> {
         }
     }
 
-    let mut map_load_task = Some(Promise::spawn_async(load_maps(
-        dcs.clone(),
-        renderer.clone(),
-        map_hashes,
-        stringmap.clone(),
-        activity_hash,
-        !args.no_ambient,
-    )));
-    let mut entity_renderers: IntMap<u64, EntityRenderer> = Default::default();
+    let spawn_map_load = {
+        let dcs = dcs.clone();
+        let renderer = renderer.clone();
+        let stringmap = stringmap.clone();
+        let load_ambient_activity = !args.no_ambient;
+        move |map_hashes: Vec<TagHash>, cancel: Arc<AtomicBool>| {
+            Promise::spawn_async(load_maps(
+                dcs.clone(),
+                renderer.clone(),
+                map_hashes,
+                stringmap.clone(),
+                activity_hash,
+                load_ambient_activity,
+                cancel,
+            ))
+        }
+    };
+    // With prefetching only the first map is loaded up front, the maps next to it follow in the background.
+    // Headless captures need every map
+    let map_catalog = map_hashes.clone();
+    if config::with(|c| c.prefetch.enabled)
+        && args.screenshot.is_none()
+        && args.thumbnails.is_none()
+    {
+        map_hashes.truncate(1);
+    }
+
+    let mut map_load_cancel = Arc::new(AtomicBool::new(false));
+    let mut map_load_hashes = map_hashes;
+    let mut map_load_task = Some(spawn_map_load(
+        map_load_hashes.clone(),
+        map_load_cancel.clone(),
+    ));
+    let mut map_load_error: Option<String> = None;
+    // Set when the user cancels a load, prefetching stays paused until they move to another map
+    let mut map_load_cancelled = false;
+    let mut last_current_map: Option<TagHash> = None;
+    resources.insert(EntityRenderers::default());
 
     let rasterizer_state = unsafe {
         dcs.device
@@ -389,23 +473,62 @@ pub async fn main() -> anyhow::Result<()> {
     };
 
     let mut resources: Resources = Resources::default();
-    resources.insert(FpsCamera::default());
+    resources.insert(config::with(|c| FpsCamera {
+        speed_mul: c.camera.speed_mul,
+        scroll_action: c.camera.scroll_action,
+        smooth_movement: c.camera.smooth_movement,
+        smoothing: c.camera.smoothing,
+        z_near: c.camera.z_near,
+        z_far: c.camera.z_far,
+        reverse_z: c.camera.reverse_z,
+        mouse_sensitivity: c.camera.mouse_sensitivity,
+        fov_compensation: c.camera.fov_compensation,
+        ..Default::default()
+    }));
     resources.insert(InputState::default());
+    resources.insert(config::with(|c| {
+        Gamepads::new(c.gamepad.enabled, c.gamepad.deadzone)
+    }));
+    resources.insert(config::with(|c| c.keybinds.clone()));
+    resources.insert(config::with(|c| c.axis_gizmo.clone()));
+    resources.insert(config::with(|c| c.tag_palette.clone()));
+    resources.insert(config::with(|c| c.recent_maps.clone()));
+    resources.insert(config::with(|c| c.autosave.clone()));
+    resources.insert(config::with(|c| c.prefetch.clone()));
+    resources.insert(config::with(|c| c.map_unload.clone()));
+    resources.insert(AutosaveState::default());
     resources.insert(MapDataList {
         current_map: 0,
         maps: vec![],
+        sort_mode: config::with(|c| c.map_sort),
+        load_order: vec![],
+        load_requests: vec![],
+        catalog: map_catalog,
+        prefetched: vec![],
+        pending_switch: None,
+        recently_used: vec![],
     });
     resources.insert(ScopeOverrides::default());
     resources.insert(DebugShapes::default());
     resources.insert(EnabledShaderOverrides::default());
     resources.insert(RenderSettings::default());
     resources.insert(ShadowMapsResource::create(dcs.clone()));
+    resources.insert(Toasts::default());
+    resources.insert(BenchmarkRequest::default());
+    resources.insert(ScreenshotRequest::default());
+    resources.insert(ReloadShadersRequest::default());
+    resources.insert(Commands::with_defaults());
     resources.insert(CurrentCubemap(None, None));
     resources.insert(ActivityGroupFilter::default());
     resources.insert(ViewerWindows::default());
     resources.insert(renderer.clone());
     resources.insert(renderer.read().dcs.clone());
-    resources.insert(SelectedEntity(None, false));
+    resources.insert(SelectedEntity::default());
+    resources.insert(TimeControl {
+        paused: args.game_time.is_some(),
+        time: args.game_time.unwrap_or_default(),
+        ..Default::default()
+    });
 
     let _blend_state = unsafe {
         dcs.device.CreateBlendState(&D3D11_BLEND_DESC {
@@ -427,6 +550,7 @@ pub async fn main() -> anyhow::Result<()> {
     };
 
     let gui_fps = Rc::new(RefCell::new(FpsDisplayOverlay::default()));
+    let gui_stats = Rc::new(RefCell::new(RenderStatsOverlay::default()));
     let gui_rendersettings = Rc::new(RefCell::new(RenderSettingsOverlay {
         renderlayer_statics: true,
         renderlayer_statics_transparent: true,
@@ -437,8 +561,17 @@ pub async fn main() -> anyhow::Result<()> {
         renderlayer_water: true,
         shadow_res_index: 1,
         animate_light: false,
+        light_animation: LightAnimation::Spin,
         light_dir_degrees: Vec3::new(1.0, 0.0, 50.0),
+        sun_path: SunPath::default(),
+        time_of_day: 12.0,
         last_frame: Instant::now(),
+        preset_name: "Default".to_string(),
+        map_hash_input: String::new(),
+        map_hash_error: None,
+        camera_location_input: String::new(),
+        camera_location_error: None,
+        merge_selection: vec![],
     }));
     let gui_debug = Rc::new(RefCell::new(CameraPositionOverlay {
         show_map_resources: config::with(|cfg| cfg.resources.show_resources),
@@ -484,15 +617,52 @@ pub async fn main() -> anyhow::Result<()> {
     gui.add_overlay(gui_dump);
     gui.add_overlay(gui_loading);
     gui.add_overlay(gui_fps);
+    gui.add_overlay(gui_stats.clone());
 
+    gui.add_overlay(Rc::new(RefCell::new(TransformGizmoOverlay::default())));
+    gui.add_overlay(Rc::new(RefCell::new(ClipPlaneOverlay)));
+    gui.add_overlay(Rc::new(RefCell::new(NoteOverlay)));
+    gui.add_overlay(Rc::new(RefCell::new(HoverInfoOverlay::default())));
+    gui.add_overlay(Rc::new(RefCell::new(AxisGizmoOverlay)));
     gui.add_overlay(Rc::new(RefCell::new(InspectorOverlay)));
+    gui.add_overlay(Rc::new(RefCell::new(BytecodeInspectorOverlay)));
+    gui.add_overlay(Rc::new(RefCell::new(MaterialInfoOverlay)));
+    gui.add_overlay(Rc::new(RefCell::new(KeybindsOverlay::default())));
     gui.add_overlay(Rc::new(RefCell::new(OutlinerOverlay::default())));
     gui.add_overlay(Rc::new(RefCell::new(MenuBar)));
+    gui.add_overlay(Rc::new(RefCell::new(CommandPaletteOverlay::default())));
+    gui.add_overlay(Rc::new(RefCell::new(ToastOverlay)));
+
+    let mut headless_capture = if let Some(dir) = &args.thumbnails {
+        Some(HeadlessCapture::thumbnails(dir.clone()))
+    } else {
+        args.screenshot.as_ref().map(|path| {
+            HeadlessCapture::single(
+                path.clone(),
+                args.camera_pos.as_deref().map(Vec3::from_slice),
+                args.camera_rot.as_deref().map(Vec2::from_slice),
+            )
+        })
+    };
+
+    let mut benchmark = match &args.benchmark {
+        Some(route) => Some(Benchmark::new(
+            BenchmarkOptions {
+                route: route.clone(),
+                output: args.benchmark_output.clone(),
+                speed: args.benchmark_speed,
+            },
+            dcs.clone(),
+        )?),
+        None => None,
+    };
+    // Benchmarks started from the command line exit when they're done
+    let exit_after_benchmark = benchmark.is_some();
 
-    let start_time = Instant::now();
     let mut last_frame = Instant::now();
     let mut last_cursor_pos: Option<PhysicalPosition<f64>> = None;
     let mut present_parameters = 0;
+    let fixed_delta = args.fixed_delta;
 
     event_loop.run(move |event, _, control_flow| {
         match &event {
@@ -542,6 +712,17 @@ pub async fn main() -> anyhow::Result<()> {
                     WindowEvent::CloseRequested => {
                         *control_flow = ControlFlow::Exit;
                     }
+                    WindowEvent::MouseWheel { delta, .. } => {
+                        if !gui_event_captured
+                            && !resources.get::<InputState>().unwrap().any_mouse_down()
+                        {
+                            let steps = match delta {
+                                MouseScrollDelta::LineDelta(_, y) => *y,
+                                MouseScrollDelta::PixelDelta(p) => p.y as f32 / 120.0,
+                            };
+                            resources.get_mut::<FpsCamera>().unwrap().scroll(steps);
+                        }
+                    }
                     WindowEvent::CursorMoved { position, .. } => {
                         if let Some(ref mut p) = last_cursor_pos {
                             let delta = (position.x - p.x, position.y - p.y);
@@ -593,27 +774,35 @@ pub async fn main() -> anyhow::Result<()> {
                     // TODO(cohae): Should this even be in here at this point?
                     WindowEvent::KeyboardInput { .. } => {
                         let input = resources.get::<InputState>().unwrap();
+                        let bindings = resources.get::<KeyBindings>().unwrap();
 
-                        if input.is_key_pressed(VirtualKeyCode::Up) {
-                            if let Some(selected_entity) =
-                                resources.get_mut::<SelectedEntity>().unwrap().0.as_mut()
-                            {
-                                *selected_entity = Entity::from_bits(
-                                    selected_entity.to_bits().get().saturating_add(1),
-                                )
-                                .unwrap_or(*selected_entity);
-                            }
+                        if input.is_action_pressed(&bindings, Action::ToggleFullscreen) {
+                            window.set_fullscreen(if window.fullscreen().is_some() {
+                                None
+                            } else {
+                                Some(Fullscreen::Borderless(None))
+                            });
                         }
 
-                        if input.is_key_pressed(VirtualKeyCode::Down) {
-                            if let Some(selected_entity) =
-                                resources.get_mut::<SelectedEntity>().unwrap().0.as_mut()
-                            {
-                                *selected_entity = Entity::from_bits(
-                                    selected_entity.to_bits().get().saturating_sub(1),
-                                )
-                                .unwrap_or(*selected_entity);
-                            }
+                        if input.is_action_pressed(&bindings, Action::Screenshot) {
+                            resources.get_mut::<ScreenshotRequest>().unwrap().0 = true;
+                        }
+
+                        if input.is_action_pressed(&bindings, Action::ReloadShaders) {
+                            resources.get_mut::<ReloadShadersRequest>().unwrap().0 = true;
+                        }
+
+                        if input.is_action_pressed(&bindings, Action::ToggleRenderStats) {
+                            let mut stats = gui_stats.borrow_mut();
+                            stats.visible = !stats.visible;
+                        }
+
+                        if input.is_action_pressed(&bindings, Action::FocusSelected) {
+                            hotkeys::frame_selected(&resources);
+                        }
+
+                        if input.is_action_pressed(&bindings, Action::FrameMap) {
+                            hotkeys::frame_map(&resources);
                         }
                     }
                     _ => (),
@@ -622,273 +811,216 @@ pub async fn main() -> anyhow::Result<()> {
             Event::RedrawRequested(..) => {
                 resources.get_mut::<SelectedEntity>().unwrap().1 = false;
 
-                // if !gui_event_captured
-                {
-                    let mut camera = resources.get_mut::<FpsCamera>().unwrap();
-                    let input_state = resources.get::<InputState>().unwrap();
-                    camera.update(
-                        &input_state,
-                        window.inner_size().into(),
-                        last_frame.elapsed().as_secs_f32(),
-                    );
+                let frame_delta = fixed_delta.unwrap_or_else(|| last_frame.elapsed().as_secs_f32());
+                last_frame = Instant::now();
 
-                    if gui.egui.input_mut(|i| i.consume_shortcut(&SHORTCUT_FOCUS)) {
-                        if let Some(selected_entity) = resources.get::<SelectedEntity>() {
-                            let maps = resources.get::<MapDataList>().unwrap();
+                if std::mem::take(&mut resources.get_mut::<ReloadShadersRequest>().unwrap().0) {
+                    RenderGlobals::reload(&mut renderer.write());
+                }
 
-                            if let Some((_, _, map)) = maps.current_map() {
-                                if let Ok(e) = map
-                                    .scene
-                                    .entity(selected_entity.0.unwrap_or(Entity::DANGLING))
-                                {
-                                    if let Some(target) = resolve_aabb(e) {
-                                        camera.focus_aabb(&target);
-                                    } else if let Some(transform) = e.get::<&Transform>() {
-                                        camera.focus(transform.translation, 10.0);
-                                    }
-                                }
-                            }
+                if benchmark.is_none() {
+                    if let Some(options) = resources.get_mut::<BenchmarkRequest>().unwrap().0.take()
+                    {
+                        match Benchmark::new(options, dcs.clone()) {
+                            Ok(b) => benchmark = Some(b),
+                            Err(e) => error!("Failed to start benchmark: {e:?}"),
                         }
                     }
                 }
-                last_frame = Instant::now();
 
-                let window_dims = window.inner_size();
+                resources
+                    .get_mut::<Gamepads>()
+                    .unwrap()
+                    .update(&mut resources.get_mut::<InputState>().unwrap());
 
                 if map_load_task.as_ref().and_then(|v| v.ready()).is_some() {
                     if let Some(Ok(map_res)) = map_load_task.take().map(|v| v.try_take()) {
-                        let map_res = map_res.expect("Failed to load map(s)");
-                        entity_renderers.extend(map_res.entity_renderers);
-                        let mut maps = resources.get_mut::<MapDataList>().unwrap();
-                        maps.maps = map_res.maps;
-                        map_load_task = None;
-
-                        #[cfg(feature = "discord_rpc")]
-                        if let Some((_, _, map)) = maps.current_map() {
-                            discord::set_status_from_mapdata(map);
-                        }
-                    }
-                }
-
-                unsafe {
-                    renderer.read().clear_render_targets();
-
-                    dcs.context().RSSetViewports(Some(&[D3D11_VIEWPORT {
-                        TopLeftX: 0.0,
-                        TopLeftY: 0.0,
-                        Width: window_dims.width as f32,
-                        Height: window_dims.height as f32,
-                        MinDepth: 0.0,
-                        MaxDepth: 1.0,
-                    }]));
-
-                    dcs.context().RSSetState(&rasterizer_state);
-
-                    renderer.read().begin_frame();
-
-                    let mut maps = resources.get_mut::<MapDataList>().unwrap();
-
-                    if let Some((_, _, map)) = maps.current_map() {
-                        {
-                            let gb = gui_rendersettings.borrow();
-
-                            let camera = resources.get::<FpsCamera>().unwrap();
-                            for (e, (StaticInstances(instances, _), visible)) in map
-                                .scene
-                                .query::<(&StaticInstances, Option<&Visible>)>()
-                                .iter()
-                            {
-                                if !visible.map_or(true, |v| v.0) {
-                                    continue;
-                                }
-
-                                if instances.instance_count == 1
-                                    && !camera.is_aabb_visible(&instances.occlusion_bounds[0])
-                                {
-                                    continue;
-                                }
-
-                                instances
-                                    .draw(
-                                        &renderer.read(),
-                                        gb.renderlayer_statics,
-                                        gb.renderlayer_statics_transparent,
-                                        gb.renderlayer_statics_decals,
-                                        e,
-                                    )
-                                    .unwrap();
+                        match map_res {
+                            Ok(None) => {
+                                info!("Map loading was cancelled");
+                                map_load_cancelled = true;
+                                resources.get_mut::<MapDataList>().unwrap().pending_switch = None;
                             }
-
-                            if gb.renderlayer_terrain {
-                                for (e, (terrain, visible)) in
-                                    map.scene.query::<(&Terrain, Option<&Visible>)>().iter()
-                                {
-                                    if !visible.map_or(true, |v| v.0) {
-                                        continue;
+                            Ok(Some(map_res)) => {
+                                resources
+                                    .get_mut::<EntityRenderers>()
+                                    .unwrap()
+                                    .0
+                                    .extend(map_res.entity_renderers);
+                                let mut maps = resources.get_mut::<MapDataList>().unwrap();
+                                maps.add_maps(map_res.maps);
+                                let mut autosave = resources.get_mut::<AutosaveState>().unwrap();
+                                for (hash, _, map) in maps.maps.iter_mut() {
+                                    if map_load_hashes.contains(hash) {
+                                        persistence::autoload_map(map, &mut autosave);
                                     }
-
-                                    terrain.0.draw(&renderer.read(), e).unwrap();
                                 }
-                            }
 
-                            for (e, (transform, rp, group, water, visible)) in map
-                                .scene
-                                .query::<(
-                                    &Transform,
-                                    &ResourcePoint,
-                                    Option<&ActivityGroup>,
-                                    Option<&Water>,
-                                    Option<&Visible>,
-                                )>()
-                                .iter()
-                            {
-                                if !visible.map_or(true, |v| v.0) {
-                                    continue;
-                                }
-
-                                if !gb.renderlayer_water && water.is_some() {
-                                    continue;
-                                }
-
-                                if let (Some(group), Some(group_filters)) =
-                                    (group, resources.get::<ActivityGroupFilter>())
+                                // Switch to maps loaded from the map hash box, recent maps, etc.
+                                if let Some(index) =
+                                    maps.pending_switch.and_then(|h| maps.index_of(h))
                                 {
-                                    if !group_filters.filters.get(&group.0).unwrap_or(&true) {
-                                        continue;
-                                    }
+                                    maps.pending_switch = None;
+                                    maps.set_current_map(
+                                        index,
+                                        &mut resources.get_mut::<SelectedEntity>().unwrap(),
+                                        &mut resources.get_mut::<FpsCamera>().unwrap(),
+                                    );
                                 }
 
-                                match rp.resource {
-                                    MapResource::Unk80806aa3 { .. } => {
-                                        if !gb.renderlayer_background {
-                                            continue;
-                                        }
-                                    }
-                                    _ => {
-                                        if !gb.renderlayer_entities {
-                                            continue;
-                                        }
-                                    }
+                                #[cfg(feature = "discord_rpc")]
+                                if let Some((_, _, map)) = maps.current_map() {
+                                    discord::set_status_from_mapdata(map);
                                 }
 
-                                if let Some(ent) = entity_renderers.get(&rp.entity_key()) {
-                                    let mm = transform.to_mat4();
-
-                                    let mesh_to_world = Mat4::from_cols(
-                                        mm.x_axis.truncate().extend(mm.w_axis.x),
-                                        mm.y_axis.truncate().extend(mm.w_axis.y),
-                                        mm.z_axis.truncate().extend(mm.w_axis.z),
-                                        mm.w_axis,
-                                    );
+                                if let Some(capture) = headless_capture.as_mut() {
+                                    capture.maps_loaded(&maps);
+                                }
+                            }
+                            Err(e) => {
+                                error!("Failed to load map(s): {e:?}");
+                                map_load_error = Some(e.to_string());
 
-                                    rp.entity_cbuffer.data().mesh_to_world = mesh_to_world;
-
-                                    if ent
-                                        .draw(
-                                            &renderer.read(),
-                                            rp.entity_cbuffer.buffer().clone(),
-                                            e,
-                                        )
-                                        .is_err()
-                                    {
-                                        renderer.write().push_fiddlesticks(*transform, Some(e));
-                                    }
-                                } else if rp.resource.is_entity() {
-                                    // cohae: This will occur when there's no entitymodel for the given entity. Keeping it in just as a reminder of unimplemented entity rendering stuffs
-                                    renderer.write().push_fiddlesticks(*transform, Some(e));
+                                if headless_capture.is_some() {
+                                    *control_flow = ControlFlow::ExitWithCode(1);
                                 }
                             }
+                        }
+                    }
+                }
 
-                            for (e, (transform, em)) in
-                                map.scene.query::<(&Transform, &EntityModel)>().iter()
-                            {
-                                let mm = transform.to_mat4();
+                {
+                    let prefetch = resources.get::<PrefetchConfig>().unwrap();
+                    // Don't keep retrying prefetches that failed to load
+                    if prefetch.enabled
+                        && map_load_error.is_none()
+                        && !map_load_cancelled
+                        && headless_capture.is_none()
+                    {
+                        let loading = if map_load_task.is_some() {
+                            map_load_hashes.as_slice()
+                        } else {
+                            &[]
+                        };
+                        let mut maps = resources.get_mut::<MapDataList>().unwrap();
+                        let unloaded = maps.update_prefetch(prefetch.window, loading);
+                        hotkeys::release_unloaded_maps(&resources, &maps, unloaded);
+                    }
+                }
 
-                                let mesh_to_world = Mat4::from_cols(
-                                    mm.x_axis.truncate().extend(mm.w_axis.x),
-                                    mm.y_axis.truncate().extend(mm.w_axis.y),
-                                    mm.z_axis.truncate().extend(mm.w_axis.z),
-                                    mm.w_axis,
-                                );
+                {
+                    let unload = resources.get::<MapUnloadConfig>().unwrap();
+                    if unload.auto_unload {
+                        let mut maps = resources.get_mut::<MapDataList>().unwrap();
+                        let unloaded = maps.unload_least_recently_used(unload.max_loaded_maps);
+                        hotkeys::release_unloaded_maps(&resources, &maps, unloaded);
+                    }
+                }
 
-                                em.1.data().mesh_to_world = mesh_to_world;
+                if map_load_task.is_none() {
+                    let requests = {
+                        let mut maps = resources.get_mut::<MapDataList>().unwrap();
+                        let requests = take(&mut maps.load_requests);
+                        requests
+                            .into_iter()
+                            .filter(|h| maps.index_of(*h).is_none())
+                            .collect_vec()
+                    };
 
-                                if em
-                                    .0
-                                    .draw(&renderer.read(), em.1.buffer().clone(), e)
-                                    .is_err()
-                                {
-                                    renderer.write().push_fiddlesticks(*transform, Some(e));
-                                }
-                            }
-                        }
+                    if !requests.is_empty() {
+                        map_load_error = None;
+                        map_load_hashes = requests;
+                        map_load_cancel = Arc::new(AtomicBool::new(false));
+                        map_load_task = Some(spawn_map_load(
+                            map_load_hashes.clone(),
+                            map_load_cancel.clone(),
+                        ));
+                    }
+                }
 
-                        // Find the smallest cubemap volume that the camera is in and set it as the current cubemap
-                        let camera = resources.get::<FpsCamera>().unwrap();
-                        let mut smallest_volume = f32::MAX;
-                        let mut smallest_volume_entity = hecs::Entity::DANGLING;
-                        for (e, (transform, volume)) in
-                            map.scene.query::<(&Transform, &CubemapVolume)>().iter()
-                        {
-                            if volume.1.volume() < smallest_volume
-                                && volume
-                                    .1
-                                    .contains_point_oriented(camera.position, transform.rotation)
-                            {
-                                smallest_volume = volume.1.volume();
-                                smallest_volume_entity = e;
-                            }
+                {
+                    let maps = resources.get::<MapDataList>().unwrap();
+                    if let Some((hash, _, map)) = maps.current_map() {
+                        if last_current_map != Some(*hash) {
+                            last_current_map = Some(*hash);
+                            map_load_cancelled = false;
+                            resources
+                                .get_mut::<RecentMaps>()
+                                .unwrap()
+                                .push(*hash, &map.name);
                         }
+                    }
+                }
 
-                        if let Ok(cubemap) = map.scene.get::<&CubemapVolume>(smallest_volume_entity)
+                {
+                    let mut render_settings = resources.get_mut::<RenderSettings>().unwrap();
+                    let sample_count = renderer.read().gbuffer.sample_count;
+                    if render_settings.msaa_samples != sample_count {
+                        if let Err(e) = renderer
+                            .write()
+                            .set_sample_count(render_settings.msaa_samples)
                         {
-                            if let Some(mut cr) = resources.get_mut::<CurrentCubemap>() {
-                                cr.0 = Some(cubemap.2.clone());
-                                cr.1 = Some(ExtendedHash::Hash32(cubemap.0));
-                            }
-                        } else if let Some(mut cr) = resources.get_mut::<CurrentCubemap>() {
-                            cr.0 = None;
+                            error!("Failed to change MSAA sample count: {e:?}");
+                            render_settings.msaa_samples = sample_count;
                         }
+                    }
+                }
 
-                        let mut debugshapes = resources.get_mut::<DebugShapes>().unwrap();
-                        for (e, (ruler, visible)) in
-                            map.scene.query::<(&Ruler, Option<&Visible>)>().iter()
-                        {
-                            if !visible.map_or(true, |v| v.0) {
-                                continue;
-                            }
-                            draw_ruler(&mut debugshapes, ruler, start_time, Some(e));
-                        }
-                        for (e, (transform, sphere, visible)) in map
-                            .scene
-                            .query::<(&Transform, &Sphere, Option<&Visible>)>()
-                            .iter()
-                        {
-                            if !visible.map_or(true, |v| v.0) {
-                                continue;
-                            }
-                            draw_sphere(&mut debugshapes, transform, sphere, start_time, Some(e));
-                        }
-                        for (e, (transform, beacon, visible)) in map
-                            .scene
-                            .query::<(&Transform, &Beacon, Option<&Visible>)>()
-                            .iter()
-                        {
-                            if !visible.map_or(true, |v| v.0) {
-                                continue;
-                            }
-                            draw_beacon(&mut debugshapes, transform, beacon, start_time, Some(e));
+                // Shadow map resizes are deferred to here, as reallocating them mid-frame isn't safe
+                if let Some(warning) = resources
+                    .get_mut::<ShadowMapsResource>()
+                    .unwrap()
+                    .apply_resize()
+                {
+                    resources.get_mut::<Toasts>().unwrap().warning(warning);
+                }
+
+                let render_start = Instant::now();
+                if let Some(benchmark) = benchmark.as_mut() {
+                    benchmark
+                        .update_camera(&mut resources.get_mut::<FpsCamera>().unwrap(), frame_delta);
+                    benchmark.begin_frame();
+                }
+
+                App {
+                    dcs: &dcs,
+                    renderer: &renderer,
+                    resources: &mut resources,
+                    render_settings_overlay: &gui_rendersettings,
+                    rasterizer_state: &rasterizer_state,
+                }
+                .advance_frame(frame_delta);
+
+                unsafe {
+                    if std::mem::take(&mut resources.get_mut::<ScreenshotRequest>().unwrap().0) {
+                        let render_settings = resources.get::<RenderSettings>().unwrap();
+                        let path = exe_relative_path(&format!(
+                            "screenshots/{}.{}",
+                            chrono::Local::now().format("%Y-%m-%d_%H-%M-%S"),
+                            render_settings.screenshot_format.extension()
+                        ));
+                        let letterbox = render_settings.letterbox_aspect;
+                        match save_screenshot(&renderer.read(), &path, letterbox) {
+                            Ok(_) => info!("Saved screenshot to {}", path.display()),
+                            Err(e) => error!("Failed to save screenshot: {e:?}"),
                         }
                     }
 
-                    if let Some(map) = maps.current_map_mut() {
-                        map.command_buffer.run_on(&mut map.scene);
+                    if let Some(capture) = headless_capture.as_mut() {
+                        let loading = map_load_task.is_some()
+                            || PROGRESS_TEXTURES.snapshot().total() > 0
+                            || PROGRESS_BUFFERS.snapshot().total() > 0;
+
+                        match capture.after_frame(&renderer.read(), &resources, loading) {
+                            Ok(CaptureState::Running) => {}
+                            Ok(CaptureState::Finished) => *control_flow = ControlFlow::Exit,
+                            Err(e) => {
+                                error!("Failed to save screenshot: {e:?}");
+                                *control_flow = ControlFlow::ExitWithCode(1);
+                            }
+                        }
                     }
 
-                    drop(maps);
-
-                    renderer.read().submit_frame(&resources);
-
                     gui.draw_frame(window.clone(), &mut resources, |ctx, _resources| {
                         if let Some(task) = map_load_task.as_ref() {
                             if task.ready().is_none() {
@@ -900,10 +1032,39 @@ pub async fn main() -> anyhow::Result<()> {
                                         ui.horizontal(|ui| {
                                             ui.spinner();
                                             ui.heading("Loading maps")
-                                        })
+                                        });
+
+                                        let cancelling = map_load_cancel.load(Ordering::Relaxed);
+                                        if ui
+                                            .add_enabled(!cancelling, egui::Button::new("Cancel"))
+                                            .clicked()
+                                        {
+                                            map_load_cancel.store(true, Ordering::Relaxed);
+                                        }
                                     });
                             }
                         }
+
+                        let mut retry = false;
+                        if let Some(error) = map_load_error.as_ref() {
+                            egui::Window::new("Failed to load maps")
+                                .collapsible(false)
+                                .resizable(false)
+                                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                                .show(ctx, |ui| {
+                                    ui.label(error);
+                                    retry = ui.button("Retry").clicked();
+                                });
+                        }
+
+                        if retry {
+                            map_load_error = None;
+                            map_load_cancel = Arc::new(AtomicBool::new(false));
+                            map_load_task = Some(spawn_map_load(
+                                map_load_hashes.clone(),
+                                map_load_cancel.clone(),
+                            ));
+                        }
                     });
 
                     // TODO(cohae): This triggers when dragging as well, which is super annoying. Don't know if we can fix this without a proper egui response object though.
@@ -911,44 +1072,87 @@ pub async fn main() -> anyhow::Result<()> {
                         && !gui.egui.wants_pointer_input()
                         && !resources.get::<SelectedEntity>().unwrap().1
                     {
-                        if let Some(mouse_pos) = gui.egui.pointer_interact_pos() {
-                            if let Ok(m) = renderer
-                                .read()
-                                .gbuffer
-                                .pick_buffer_staging
-                                .map(D3D11_MAP_READ)
-                            {
-                                let data = m.ptr.add(
-                                    (mouse_pos.y as f64 * window.scale_factor()).round() as usize
-                                        * m.row_pitch as usize
-                                        + (mouse_pos.x as f64 * window.scale_factor()).round()
-                                            as usize
-                                            * 4,
-                                ) as *mut u32;
-
-                                let id = *data;
-                                let maps = resources.get::<MapDataList>().unwrap();
+                        let pick_pos = gui.egui.pointer_interact_pos().map(|mouse_pos| {
+                            (
+                                (mouse_pos.x as f64 * window.scale_factor()).round() as usize,
+                                (mouse_pos.y as f64 * window.scale_factor()).round() as usize,
+                            )
+                        });
+
+                        if let Some((pick_x, pick_y)) = pick_pos {
+                            let id = renderer.read().pick_entity_id(pick_x, pick_y);
+                            let maps = resources.get::<MapDataList>().unwrap();
 
-                                if let Some((_, _, map)) = maps.current_map() {
-                                    if id != u32::MAX {
-                                        *resources.get_mut::<SelectedEntity>().unwrap() =
-                                            SelectedEntity(
-                                                Some(map.scene.find_entity_from_id(id)),
-                                                true,
-                                            );
-                                    } else {
-                                        *resources.get_mut::<SelectedEntity>().unwrap() =
-                                            SelectedEntity(None, true);
+                            if let Some((_, _, map)) = maps.current_map() {
+                                // The pick buffer is a frame behind, so the entity might not exist anymore
+                                let entity = id
+                                    .map(|id| map.scene.find_entity_from_id(id))
+                                    .filter(|e| map.scene.contains(*e));
+
+                                // Static instance groups are drawn in a single instanced drawcall, so the individual instance
+                                // is found by casting a ray through the cursor against the bounds of each instance
+                                let instance = entity.and_then(|e| {
+                                    let si = map.scene.get::<&StaticInstances>(e).ok()?;
+                                    let mouse_pos = gui.egui.pointer_interact_pos()?;
+                                    let screen = gui.egui.screen_rect();
+                                    let camera = resources.get::<FpsCamera>().unwrap();
+                                    let direction = camera.screen_ray(Vec2::new(
+                                        mouse_pos.x / screen.width() * 2.0 - 1.0,
+                                        1.0 - mouse_pos.y / screen.height() * 2.0,
+                                    ));
+
+                                    si.0.pick_instance(camera.position, direction)
+                                });
+
+                                let mut selected = resources.get_mut::<SelectedEntity>().unwrap();
+                                if gui.egui.input(|i| i.modifiers.ctrl) {
+                                    if let Some(entity) = entity {
+                                        selected.toggle(entity);
                                     }
+                                } else if let (Some(entity), Some(instance)) = (entity, instance) {
+                                    selected.select_instance(entity, instance);
+                                } else {
+                                    selected.select(entity);
                                 }
+                                selected.1 = true;
                             }
                         }
                     }
 
                     hotkeys::process_hotkeys(&gui.egui, &mut resources);
 
+                    resources.get_mut::<AutosaveState>().unwrap().update(
+                        &resources.get::<MapDataList>().unwrap(),
+                        &resources.get::<AutosaveConfig>().unwrap(),
+                    );
+
                     dcs.context().OMSetDepthStencilState(None, 0);
 
+                    let cpu_time = render_start.elapsed().as_secs_f32();
+                    if let Some(b) = benchmark.as_mut() {
+                        let loading = map_load_task.is_some()
+                            || PROGRESS_TEXTURES.snapshot().total() > 0
+                            || PROGRESS_BUFFERS.snapshot().total() > 0;
+
+                        match b.end_frame(frame_delta, cpu_time, renderer.read().stats(), loading) {
+                            Ok(BenchmarkState::Running) => {}
+                            Ok(BenchmarkState::Finished) => {
+                                info!("Wrote benchmark results to {}", b.output().display());
+                                benchmark = None;
+                                if exit_after_benchmark {
+                                    *control_flow = ControlFlow::Exit;
+                                }
+                            }
+                            Err(e) => {
+                                error!("Failed to write benchmark results: {e:?}");
+                                benchmark = None;
+                                if exit_after_benchmark {
+                                    *control_flow = ControlFlow::ExitWithCode(1);
+                                }
+                            }
+                        }
+                    }
+
                     if dcs
                         .swap_chain
                         .Present(DXGI_SWAP_EFFECT_SEQUENTIAL.0 as _, present_parameters)
@@ -992,365 +1196,41 @@ pub async fn main() -> anyhow::Result<()> {
                     c.resources.map_resource_label_background = gdb.map_resource_label_background;
                     c.resources.resource_distance_limit = gdb.map_resource_distance_limit_enabled;
                     c.resources.filters = resource_filters;
+
+                    let camera = resources.get::<FpsCamera>().unwrap();
+                    c.camera.speed_mul = camera.speed_mul;
+                    c.camera.scroll_action = camera.scroll_action;
+                    c.camera.smooth_movement = camera.smooth_movement;
+                    c.camera.smoothing = camera.smoothing;
+                    c.camera.z_near = camera.z_near;
+                    c.camera.z_far = camera.z_far;
+                    c.camera.reverse_z = camera.reverse_z;
+                    c.camera.mouse_sensitivity = camera.mouse_sensitivity;
+                    c.camera.fov_compensation = camera.fov_compensation;
+
+                    c.keybinds = resources.get::<KeyBindings>().unwrap().clone();
+                    c.axis_gizmo = resources.get::<AxisGizmoConfig>().unwrap().clone();
+                    c.tag_palette = resources.get::<TagPalette>().unwrap().clone();
+                    c.recent_maps = resources.get::<RecentMaps>().unwrap().clone();
+                    c.autosave = resources.get::<AutosaveConfig>().unwrap().clone();
+                    c.prefetch = resources.get::<PrefetchConfig>().unwrap().clone();
+                    c.map_unload = resources.get::<MapUnloadConfig>().unwrap().clone();
+                    c.map_sort = resources.get::<MapDataList>().unwrap().sort_mode;
+
+                    let gamepads = resources.get::<Gamepads>().unwrap();
+                    c.gamepad.enabled = gamepads.enabled;
+                    c.gamepad.deadzone = gamepads.deadzone;
                 });
                 config::persist();
+
+                if let Some(maps) = resources.get::<MapDataList>() {
+                    let mut autosave = resources.get_mut::<AutosaveState>().unwrap();
+                    for (_, _, map) in &maps.maps {
+                        persistence::autosave_map(map, &mut autosave);
+                    }
+                }
             }
             _ => (),
         }
     });
 }
-
-fn get_rainbow_color(start_time: Instant) -> [u8; 3] {
-    Hsva {
-        h: (start_time.elapsed().as_secs_f32() * 0.30) % 1.0,
-        s: 1.0,
-        v: 1.0,
-        a: 1.0,
-    }
-    .to_srgb()
-}
-
-fn draw_ruler(
-    debugshapes: &mut DebugShapes,
-    ruler: &Ruler,
-    start_time: Instant,
-    entity: Option<Entity>,
-) {
-    let color = if ruler.rainbow {
-        get_rainbow_color(start_time)
-    } else {
-        ruler.color
-    };
-
-    debugshapes.cross(ruler.start, ruler.scale, color);
-    debugshapes.cross(ruler.end, ruler.scale, color);
-    debugshapes.line_dotted(ruler.start, ruler.end, color, ruler.scale);
-
-    let ruler_center = (ruler.start + ruler.end) / 2.0;
-    debugshapes.text(
-        prettify_distance(ruler.length()),
-        ruler_center,
-        egui::Align2::CENTER_BOTTOM,
-        [255, 255, 255],
-    );
-
-    if ruler.show_individual_axis {
-        let end_x = Vec3::new(ruler.end.x, ruler.start.y, ruler.start.z);
-        let end_y = Vec3::new(ruler.start.x, ruler.end.y, ruler.start.z);
-        let end_z = Vec3::new(ruler.start.x, ruler.start.y, ruler.end.z);
-
-        debugshapes.line(ruler.start, end_x, color);
-        debugshapes.line(ruler.start, end_y, color);
-        debugshapes.line(ruler.start, end_z, color);
-
-        let length_x = (ruler.start - end_x).length();
-        let length_y = (ruler.start - end_y).length();
-        let length_z = (ruler.start - end_z).length();
-
-        let center_x = (ruler.start + end_x) / 2.0;
-        let center_y = (ruler.start + end_y) / 2.0;
-        let center_z = (ruler.start + end_z) / 2.0;
-
-        debugshapes.text(
-            format!("X: {}", prettify_distance(length_x)),
-            center_x,
-            egui::Align2::LEFT_CENTER,
-            [255, 255, 255],
-        );
-
-        debugshapes.text(
-            format!("Y: {}", prettify_distance(length_y)),
-            center_y,
-            egui::Align2::RIGHT_CENTER,
-            [255, 255, 255],
-        );
-
-        debugshapes.text(
-            format!("Z: {}", prettify_distance(length_z)),
-            center_z,
-            egui::Align2::RIGHT_CENTER,
-            [255, 255, 255],
-        );
-    }
-
-    if ruler.marker_interval > 0.0 {
-        let sphere_color = keep_color_bright(invert_color(color));
-        let sphere_color = [sphere_color[0], sphere_color[1], sphere_color[2], 192];
-
-        let mut current = 0.0;
-        while current < ruler.length() {
-            if current > 0.0 {
-                let pos = ruler.start + ruler.direction() * current;
-
-                debugshapes.sphere(
-                    pos,
-                    ruler.scale * 0.20,
-                    sphere_color,
-                    DebugDrawFlags::DRAW_NORMAL,
-                    None,
-                );
-            }
-
-            current += ruler.marker_interval;
-        }
-    }
-    debugshapes.cube_extents(
-        (ruler.start + ruler.end) / 2.0,
-        Vec3::new(ruler.length() / 2.0, ruler.scale / 2.0, ruler.scale / 2.0),
-        Quat::from_rotation_arc(Vec3::X, (ruler.end - ruler.start).normalize()),
-        color,
-        true,
-        DebugDrawFlags::DRAW_PICK,
-        entity,
-    )
-}
-
-fn draw_sphere(
-    debugshapes: &mut DebugShapes,
-    transform: &Transform,
-    sphere: &Sphere,
-    start_time: Instant,
-    entity: Option<Entity>,
-) {
-    let color = if sphere.rainbow {
-        let c = get_rainbow_color(start_time);
-        [c[0], c[1], c[2], sphere.color[3]]
-    } else {
-        sphere.color
-    };
-
-    let color_opaque = [color[0], color[1], color[2]];
-    let cross_color = keep_color_bright(invert_color(color_opaque));
-    debugshapes.cross(
-        transform.translation,
-        0.25 * transform.radius(),
-        cross_color,
-    );
-
-    for t in 0..sphere.detail {
-        debugshapes.circle(
-            transform.translation,
-            Vec3::new(
-                transform.radius() * (t as f32 * PI / sphere.detail as f32).sin(),
-                transform.radius() * (t as f32 * PI / sphere.detail as f32).cos(),
-                0.0,
-            ),
-            4 * sphere.detail,
-            color,
-        );
-    }
-    debugshapes.circle(
-        transform.translation,
-        Vec3::new(0.0, 0.0, transform.radius()),
-        4 * sphere.detail,
-        color,
-    );
-
-    debugshapes.text(
-        prettify_distance(transform.radius()),
-        transform.translation,
-        egui::Align2::CENTER_BOTTOM,
-        [255, 255, 255],
-    );
-    debugshapes.sphere(
-        transform.translation,
-        transform.radius(),
-        color,
-        DebugDrawFlags::DRAW_NORMAL | DebugDrawFlags::DRAW_PICK,
-        entity,
-    );
-}
-
-fn draw_beacon(
-    debugshapes: &mut DebugShapes,
-    transform: &Transform,
-    beacon: &Beacon,
-    start_time: Instant,
-    entity: Option<Entity>,
-) {
-    const BEAM_HEIGHT: f32 = 5000.0;
-    const BASE_RADIUS: f32 = 0.1;
-    let color: [u8; 4] = [
-        beacon.color[0],
-        beacon.color[1],
-        beacon.color[2],
-        (150.0 + (start_time.elapsed().as_secs_f32() * 2.0 * PI * beacon.freq).sin() * 50.0) as u8,
-    ];
-    debugshapes.sphere(
-        transform.translation,
-        BASE_RADIUS,
-        color,
-        DebugDrawFlags::DRAW_NORMAL,
-        None,
-    );
-    debugshapes.line(
-        transform.translation + Vec3::Z * BASE_RADIUS,
-        transform.translation + Vec3::Z * BEAM_HEIGHT,
-        color,
-    );
-    debugshapes.cube_extents(
-        transform.translation + Vec3::Z * BEAM_HEIGHT / 2.0,
-        Vec3::new(BASE_RADIUS, BASE_RADIUS, BEAM_HEIGHT / 2.0),
-        Quat::IDENTITY,
-        color,
-        true,
-        DebugDrawFlags::DRAW_PICK,
-        entity,
-    );
-}
-
-fn load_render_globals(renderer: &Renderer) {
-    let tag =
-        get_named_tag::<0x8080978C>("render_globals").expect("Could not find render globals!");
-    let globals: SRenderGlobals = package_manager()
-        .read_tag_struct(tag)
-        .expect("Failed to read render globals");
-
-    // println!("{globals:#?}");
-    // for (i, s) in globals.unk8[0].unk8.scopes.iter().enumerate() {
-    //     println!("scope #{i}: {}", *s.name);
-    //     if s.scope.stage_vertex.constant_buffer.is_some() {
-    //         println!(
-    //             "\tVS cb{} ({} bytes)",
-    //             s.scope.stage_vertex.constant_buffer_slot,
-    //             buffer_size(s.scope.stage_vertex.constant_buffer)
-    //         );
-    //     }
-    //     if s.scope.stage_pixel.constant_buffer.is_some() {
-    //         println!(
-    //             "\tPS cb{} ({} bytes)",
-    //             s.scope.stage_pixel.constant_buffer_slot,
-    //             buffer_size(s.scope.stage_pixel.constant_buffer)
-    //         );
-    //     }
-    //     if s.scope.stage_geometry.constant_buffer.is_some() {
-    //         println!(
-    //             "\tGS cb{} ({} bytes)",
-    //             s.scope.stage_geometry.constant_buffer_slot,
-    //             buffer_size(s.scope.stage_geometry.constant_buffer)
-    //         );
-    //     }
-    //     if s.scope.stage_hull.constant_buffer.is_some() {
-    //         println!(
-    //             "\tHS cb{} ({} bytes)",
-    //             s.scope.stage_hull.constant_buffer_slot,
-    //             buffer_size(s.scope.stage_hull.constant_buffer)
-    //         );
-    //     }
-    //     if s.scope.stage_compute.constant_buffer.is_some() {
-    //         println!(
-    //             "\tCS cb{} ({} bytes)",
-    //             s.scope.stage_compute.constant_buffer_slot,
-    //             buffer_size(s.scope.stage_compute.constant_buffer)
-    //         );
-    //     }
-    //     if s.scope.stage_domain.constant_buffer.is_some() {
-    //         println!(
-    //             "\tDS cb{} ({} bytes)",
-    //             s.scope.stage_domain.constant_buffer_slot,
-    //             buffer_size(s.scope.stage_domain.constant_buffer)
-    //         );
-    //     }
-    // }
-
-    let mut techniques: HashMap<String, TagHash> = HashMap::default();
-    for t in &globals.unk8[0].unk8.unk20 {
-        // println!("technique #{i}: {}, {}", *t.name, t.technique);
-        techniques.insert(t.name.to_string(), t.technique);
-    }
-
-    let technique_tag = techniques["deferred_shading_no_atm"];
-    let technique = Technique::load(
-        renderer,
-        package_manager().read_tag_struct(technique_tag).unwrap(),
-        technique_tag,
-        true,
-    );
-
-    load_shaders(renderer, &technique);
-
-    renderer
-        .render_data
-        .data_mut()
-        .technique_deferred_shading_no_atm = Some(technique);
-
-    renderer.render_data.data_mut().iridescence_lookup = {
-        let texture_tag = globals.unk8[0].unk8.unk30.iridescence_lookup_texture;
-        Texture::load(&renderer.dcs, ExtendedHash::Hash32(texture_tag)).ok()
-    };
-
-    info!("Loaded deferred_shading_no_atm");
-}
-
-// fn buffer_size(tag: TagHash) -> usize {
-//     let eeee = package_manager().get_entry(tag).unwrap().reference;
-//     package_manager().read_tag(TagHash(eeee)).unwrap().len()
-// }
-
-fn load_shaders(renderer: &Renderer, m: &Technique) {
-    let mut render_data = renderer.render_data.data_mut();
-
-    if let Some(v) = package_manager().get_entry(m.stage_vertex.shader.shader) {
-        let _span = debug_span!("load vshader", shader = ?m.stage_vertex.shader).entered();
-
-        let vs_data = package_manager().read_tag(v.reference).unwrap();
-
-        let mut vs_cur = Cursor::new(&vs_data);
-        let dxbc_header: DxbcHeader = vs_cur.read_le().unwrap();
-        let input_sig = get_input_signature(&mut vs_cur, &dxbc_header).unwrap();
-
-        let layout_converted = input_sig
-            .elements
-            .iter()
-            .map(|e| InputElement::from_dxbc(e, e.component_type == DxbcInputType::Float, false))
-            .collect_vec();
-
-        let shader = unsafe {
-            let v = renderer
-                .dcs
-                .device
-                .CreateVertexShader(&vs_data, None)
-                .context("Failed to load vertex shader")
-                .unwrap();
-
-            (v, layout_converted, vs_data)
-        };
-
-        render_data
-            .vshaders
-            .insert(m.stage_vertex.shader.shader, shader);
-    }
-
-    // return Ok(());
-
-    if let Some(v) = package_manager().get_entry(m.stage_pixel.shader.shader) {
-        let _span = debug_span!("load pshader", shader = ?m.stage_pixel.shader.shader).entered();
-
-        let ps_data = package_manager().read_tag(v.reference).unwrap();
-
-        let mut ps_cur = Cursor::new(&ps_data);
-        let dxbc_header: DxbcHeader = ps_cur.read_le().unwrap();
-        let output_sig = get_output_signature(&mut ps_cur, &dxbc_header).unwrap();
-
-        let layout_converted = output_sig
-            .elements
-            .iter()
-            .map(|e| InputElement::from_dxbc(e, e.component_type == DxbcInputType::Float, false))
-            .collect_vec();
-
-        let shader = unsafe {
-            let v = renderer
-                .dcs
-                .device
-                .CreatePixelShader(&ps_data, None)
-                .context("Failed to load pixel shader")
-                .unwrap();
-
-            (v, layout_converted)
-        };
-
-        render_data
-            .pshaders
-            .insert(m.stage_pixel.shader.shader, shader);
-    }
-}
@@ -1,20 +1,95 @@
-use crate::ecs::Scene;
+use crate::camera::FpsCamera;
+use crate::ecs::components::{Global, LightColor, ResourcePoint, SourceMap, Visible};
+use crate::ecs::hierarchy;
+use crate::ecs::history::EditHistory;
+use crate::ecs::resources::SelectedEntity;
+use crate::ecs::transform::Transform;
+use crate::ecs::{Scene, SceneBounds};
+use crate::packages::package_manager;
 
 use crate::statics::SStaticMeshInstances;
 use crate::structure::{ExtendedHash, ExtendedTag, RelPointer, ResourcePointer, TablePointer, Tag};
 use crate::types::{Matrix4, ResourceHash, Vector4, AABB};
 use binrw::{BinRead, NullString};
 use destiny_pkg::{TagHash, TagHash64};
-use glam::Vec4;
+use glam::{Vec3, Vec4};
+use hecs::Entity;
+use itertools::Itertools;
+use nohash_hasher::IntSet;
+use serde::{Deserialize, Serialize};
 
+use std::collections::HashMap;
 use std::fmt::Debug;
 use std::io::SeekFrom;
+use std::mem::{swap, take};
 
 pub struct MapData {
     pub hash: TagHash,
     pub name: String,
     pub scene: Scene,
     pub command_buffer: hecs::CommandBuffer,
+    pub history: EditHistory,
+    /// Camera field of view last used in this map
+    pub fov: Option<f32>,
+    pub bounds: SceneBounds,
+    /// Maps that were merged into this one, empty for regular maps
+    pub sources: Vec<MergedMap>,
+}
+
+impl MapData {
+    /// Direction and color of the map's sun, taken from its first visible shadowing light.
+    /// The direction points towards the light, which faces along its +X axis like other entities.
+    /// Maps don't carry an ambient term we can read, so only the sun is captured
+    pub fn sun_light(&self) -> Option<(Vec3, Vec4)> {
+        let mut query = self.scene.query::<(
+            &Transform,
+            &SShadowingLight,
+            Option<&LightColor>,
+            Option<&Visible>,
+        )>();
+
+        let (transform, color) = query
+            .iter()
+            .filter(|(_, (_, _, _, vis))| vis.map_or(true, |v| v.0))
+            .map(|(_, (transform, _, color, _))| (*transform, color.copied()))
+            .next()?;
+
+        let color = color.unwrap_or_default().blend_factor();
+        Some((
+            -(transform.rotation * Vec3::X).normalize(),
+            Vec4::from_array(color),
+        ))
+    }
+
+    /// Shows or hides all entities that came from one of the merged maps
+    pub fn set_source_visible(&mut self, hash: TagHash, visible: bool) {
+        let Some(source) = self.sources.iter_mut().find(|s| s.hash == hash) else {
+            return;
+        };
+        source.visible = visible;
+
+        for (entity, (source, vis)) in self
+            .scene
+            .query::<(&SourceMap, Option<&mut Visible>)>()
+            .iter()
+        {
+            if source.0 != hash {
+                continue;
+            }
+
+            if let Some(vis) = vis {
+                vis.0 = visible;
+            } else if !visible {
+                self.command_buffer.insert_one(entity, Visible(false));
+            }
+        }
+    }
+}
+
+pub struct MergedMap {
+    pub hash: TagHash,
+    pub name: String,
+    pub visible: bool,
 }
 
 #[derive(Clone)]
@@ -23,12 +98,76 @@ pub struct SimpleLight {
     pub attenuation: Vec4,
 }
 
+#[derive(
+    strum::Display, strum::EnumIter, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default,
+)]
+pub enum MapSortMode {
+    #[default]
+    Name,
+    Hash,
+    /// The order the maps were loaded in
+    #[strum(serialize = "Load order")]
+    LoadOrder,
+}
+
 pub struct MapDataList {
     pub current_map: usize, // TODO(cohae): Shouldn't be here
     pub maps: Vec<(TagHash, Option<TagHash64>, MapData)>,
+    pub sort_mode: MapSortMode,
+    /// Map hashes in the order they were loaded in
+    pub load_order: Vec<TagHash>,
+    /// Maps that should be loaded once the current load task has finished
+    pub load_requests: Vec<TagHash>,
+    /// Every map of the package or activity, in the order they were listed in. Prefetching loads the maps next to the current one in this list
+    pub catalog: Vec<TagHash>,
+    /// Maps that were loaded in the background and haven't been visited yet.
+    /// They are unloaded again once they fall outside of the prefetch window
+    pub prefetched: Vec<TagHash>,
+    /// Map to switch to once it has finished loading
+    pub pending_switch: Option<TagHash>,
+    /// Maps in the order they were last switched to, the most recent one last
+    pub recently_used: Vec<TagHash>,
 }
 
 impl MapDataList {
+    /// Adds maps to the list, and sorts them using the current sort mode. Maps that are already in the list are skipped
+    pub fn add_maps(&mut self, maps: Vec<(TagHash, Option<TagHash64>, MapData)>) {
+        for map in maps {
+            if self.index_of(map.0).is_some() {
+                continue;
+            }
+
+            self.load_order.push(map.0);
+            self.maps.push(map);
+        }
+        self.sort(self.sort_mode);
+    }
+
+    /// Index of the map with the given hash, or of the combined map it was merged into
+    pub fn index_of(&self, hash: TagHash) -> Option<usize> {
+        self.maps
+            .iter()
+            .position(|(h, _, map)| *h == hash || map.sources.iter().any(|s| s.hash == hash))
+    }
+
+    /// Sorts the list of maps. The current map stays the same, even if its index changes
+    pub fn sort(&mut self, mode: MapSortMode) {
+        self.sort_mode = mode;
+        let current_hash = self.current_map().map(|(hash, _, _)| *hash);
+
+        match mode {
+            MapSortMode::Name => self.maps.sort_by(|a, b| a.2.name.cmp(&b.2.name)),
+            MapSortMode::Hash => self.maps.sort_by_key(|(hash, _, _)| hash.0),
+            MapSortMode::LoadOrder => self
+                .maps
+                .sort_by_key(|(hash, _, _)| self.load_order.iter().position(|h| h == hash)),
+        }
+
+        if let Some(index) = current_hash.and_then(|h| self.index_of(h)) {
+            self.current_map = index;
+        }
+    }
+
     pub fn current_map(&self) -> Option<&(TagHash, Option<TagHash64>, MapData)> {
         if self.maps.is_empty() {
             None
@@ -49,6 +188,377 @@ impl MapDataList {
     pub fn map_mut(&mut self, i: usize) -> Option<&mut MapData> {
         self.maps.get_mut(i).map(|v| &mut v.2)
     }
+
+    /// Removes a map from the list and returns it. The current map can't be removed
+    fn remove_map(&mut self, index: usize) -> Option<MapData> {
+        if index == self.current_map || index >= self.maps.len() {
+            return None;
+        }
+
+        let (hash, _, map) = self.maps.remove(index);
+        self.load_order.retain(|&h| h != hash);
+        self.recently_used.retain(|&h| h != hash);
+        self.prefetched.retain(|&h| h != hash);
+        if index < self.current_map {
+            self.current_map -= 1;
+        }
+
+        Some(map)
+    }
+
+    /// Loaded maps other than the current one, from least to most recently used. Maps that were never switched to come first
+    fn maps_by_recency(&self) -> Vec<usize> {
+        (0..self.maps.len())
+            .filter(|&i| i != self.current_map)
+            .sorted_by_key(|&i| {
+                self.recently_used
+                    .iter()
+                    .position(|&h| h == self.maps[i].0)
+                    .map_or(0, |p| p + 1)
+            })
+            .collect()
+    }
+
+    /// Unloads a map, returning it so its user entities can be saved. Dropping it frees its scene and the buffers owned by it.
+    /// Unloading the current map switches to the most recently used other map first, which takes the global entities along.
+    /// The last loaded map can't be unloaded
+    pub fn unload_map(
+        &mut self,
+        index: usize,
+        selected: &mut SelectedEntity,
+        camera: &mut FpsCamera,
+    ) -> Option<MapData> {
+        if index >= self.maps.len() || self.maps.len() < 2 {
+            return None;
+        }
+
+        if index == self.current_map {
+            let next = *self.maps_by_recency().last()?;
+            self.set_current_map(next, selected, camera);
+        }
+
+        self.remove_map(index)
+    }
+
+    /// Unloads the least recently used maps until at most `max_loaded` maps are loaded, returning the unloaded maps.
+    /// The current map, prefetched maps and the map that's about to be switched to are kept
+    pub fn unload_least_recently_used(&mut self, max_loaded: usize) -> Vec<MapData> {
+        let mut unloaded = vec![];
+        let candidates = self
+            .maps_by_recency()
+            .into_iter()
+            .map(|i| self.maps[i].0)
+            .filter(|h| !self.prefetched.contains(h) && self.pending_switch != Some(*h))
+            .collect_vec();
+
+        for hash in candidates {
+            if self.maps.len() <= max_loaded.max(1) {
+                break;
+            }
+
+            if let Some(map) = self
+                .maps
+                .iter()
+                .position(|(h, _, _)| *h == hash)
+                .and_then(|i| self.remove_map(i))
+            {
+                unloaded.push(map);
+            }
+        }
+
+        unloaded
+    }
+
+    /// Keys of the entity renderers used by the resources of all loaded maps
+    pub fn used_entity_renderers(&self) -> IntSet<u64> {
+        self.maps
+            .iter()
+            .flat_map(|(_, _, map)| {
+                map.scene
+                    .query::<&ResourcePoint>()
+                    .iter()
+                    .map(|(_, rp)| rp.entity_key())
+                    .collect_vec()
+            })
+            .collect()
+    }
+
+    /// Position of the current map in the catalog. Combined maps use the first of their sources that's in the catalog
+    pub fn catalog_position(&self) -> Option<usize> {
+        let (hash, _, map) = self.current_map()?;
+        std::iter::once(*hash)
+            .chain(map.sources.iter().map(|s| s.hash))
+            .find_map(|h| self.catalog.iter().position(|&c| c == h))
+    }
+
+    /// Switches to a map, or requests it to be loaded and switches to it once it's done loading
+    pub fn switch_or_load(
+        &mut self,
+        hash: TagHash,
+        selected: &mut SelectedEntity,
+        camera: &mut FpsCamera,
+    ) {
+        if let Some(index) = self.index_of(hash) {
+            self.set_current_map(index, selected, camera);
+        } else {
+            if !self.load_requests.contains(&hash) {
+                self.load_requests.push(hash);
+            }
+            self.pending_switch = Some(hash);
+        }
+    }
+
+    /// Switches to the map `offset` entries away from the current one in the catalog
+    pub fn step_catalog(
+        &mut self,
+        offset: isize,
+        selected: &mut SelectedEntity,
+        camera: &mut FpsCamera,
+    ) {
+        let Some(hash) = self
+            .catalog_position()
+            .and_then(|p| p.checked_add_signed(offset))
+            .and_then(|i| self.catalog.get(i).copied())
+        else {
+            return;
+        };
+
+        self.switch_or_load(hash, selected, camera);
+    }
+
+    /// Requests the maps within `window` catalog entries of the current map to be loaded, and unloads prefetched maps that fell outside of it.
+    /// `loading` are the maps that are currently being loaded. Returns the unloaded maps
+    pub fn update_prefetch(&mut self, window: usize, loading: &[TagHash]) -> Vec<MapData> {
+        let Some(position) = self.catalog_position() else {
+            return vec![];
+        };
+
+        // Maps that have been visited aren't unloaded automatically anymore
+        if let Some(&(current_hash, _, _)) = self.current_map() {
+            self.prefetched.retain(|&h| h != current_hash);
+        }
+
+        let wanted = self.catalog
+            [position.saturating_sub(window)..(position + window + 1).min(self.catalog.len())]
+            .to_vec();
+
+        for &hash in &wanted {
+            if self.index_of(hash).is_none()
+                && !self.load_requests.contains(&hash)
+                && !loading.contains(&hash)
+            {
+                self.load_requests.push(hash);
+                self.prefetched.push(hash);
+            }
+        }
+
+        // Maps that are still loading are unloaded once they're done
+        let unwanted = self
+            .prefetched
+            .iter()
+            .copied()
+            .filter(|h| !wanted.contains(h) && !loading.contains(h))
+            .collect_vec();
+        let mut unloaded = vec![];
+        for hash in unwanted {
+            self.prefetched.retain(|&h| h != hash);
+            self.load_requests.retain(|&h| h != hash);
+            // Maps that were merged into another one are part of the combined map now
+            if let Some(map) = self
+                .maps
+                .iter()
+                .position(|(h, _, map)| *h == hash && map.sources.is_empty())
+                .and_then(|i| self.remove_map(i))
+            {
+                unloaded.push(map);
+            }
+        }
+
+        unloaded
+    }
+
+    /// Switches to another map, moving the global entities over and restoring the FOV the map was last viewed with
+    pub fn set_current_map(
+        &mut self,
+        index: usize,
+        selected: &mut SelectedEntity,
+        camera: &mut FpsCamera,
+    ) {
+        let old_map_index = self.current_map;
+        if index == old_map_index || index >= self.maps.len() {
+            return;
+        }
+        self.current_map = index;
+        let (old_hash, new_hash) = (self.maps[old_map_index].0, self.maps[index].0);
+        if !self.recently_used.contains(&old_hash) {
+            self.recently_used.push(old_hash);
+        }
+        self.recently_used.retain(|&h| h != new_hash);
+        self.recently_used.push(new_hash);
+
+        // We have learned the power to Take worlds
+        let mut old_scene = take(&mut self.map_mut(old_map_index).unwrap().scene);
+
+        let mut ent_list = vec![];
+
+        for (entity, global) in old_scene.query::<&Global>().iter() {
+            if global.0 {
+                ent_list.push(entity);
+            }
+        }
+
+        if let Some(map) = self.current_map_mut() {
+            // Parents and children that don't move along are left behind
+            hierarchy::detach_entities(&mut old_scene, &ent_list);
+
+            // Reserve the new handles first, so the hierarchy can point at them before the entities are moved
+            let moved: HashMap<Entity, Entity> = ent_list
+                .iter()
+                .map(|&entity| (entity, map.scene.reserve_entity()))
+                .collect();
+            hierarchy::remap_entities(&mut old_scene, &moved);
+            for (&entity, &new_ent) in &moved {
+                map.scene
+                    .spawn_at(new_ent, old_scene.take(entity).ok().unwrap());
+            }
+
+            // Only global entities can stay selected, as they're the only ones that move along.
+            // Anything else would point at an unrelated entity in the new scene
+            let remap = |e: Entity| moved.get(&e).copied();
+            selected.0 = selected.0.and_then(remap);
+            selected.2 = selected.2.iter().filter_map(|&e| remap(e)).collect();
+            selected.3 = selected.3.and_then(|(e, i)| Some((remap(e)?, i)));
+            if selected.0.is_none() {
+                selected.0 = selected.2.pop();
+            }
+        }
+        swap(
+            &mut old_scene,
+            &mut self.map_mut(old_map_index).unwrap().scene,
+        );
+
+        // Each map remembers the FOV it was last viewed with
+        self.map_mut(old_map_index).unwrap().fov = Some(camera.fov);
+        if let Some(fov) = self.current_map_mut().and_then(|m| m.fov) {
+            camera.fov = fov;
+        }
+
+        // The status needs the package manager and a tokio runtime
+        #[cfg(all(feature = "discord_rpc", not(test)))]
+        crate::discord::set_status_from_mapdata(&self.maps[self.current_map].2);
+    }
+
+    /// Moves the entities of several maps into one combined map, so adjacent maps can be viewed together (they share world coordinates).
+    /// Every entity is tagged with the `SourceMap` it came from, except for global entities, which only ever live in the current map.
+    /// The combined map takes the place of the first of the given maps in the list, the others are removed
+    pub fn merge_maps(
+        &mut self,
+        hashes: &[TagHash],
+        selected: &mut SelectedEntity,
+    ) -> anyhow::Result<()> {
+        let mut indices: Vec<usize> = hashes.iter().filter_map(|&h| self.index_of(h)).collect();
+        indices.sort_unstable();
+        indices.dedup();
+        anyhow::ensure!(
+            indices.len() >= 2,
+            "At least 2 loaded maps are needed to merge"
+        );
+
+        let current_hash = self.current_map().map(|(hash, _, _)| *hash);
+        let current_merged = indices.contains(&self.current_map);
+
+        let mut combined = Scene::new();
+        let mut sources = vec![];
+        let mut names = vec![];
+        for &i in &indices {
+            let map = &mut self.maps[i].2;
+            let mut scene = take(&mut map.scene);
+            let hash = map.hash;
+
+            let entities: Vec<Entity> = scene.iter().map(|e| e.entity()).collect();
+            // Reserve the new handles first, so the hierarchy can point at them before the entities are moved
+            let remap: HashMap<Entity, Entity> = entities
+                .iter()
+                .map(|&entity| (entity, combined.reserve_entity()))
+                .collect();
+            hierarchy::remap_entities(&mut scene, &remap);
+
+            for entity in entities {
+                let global = scene.get::<&Global>(entity).map_or(false, |g| g.0);
+                // Entities from a map that was merged before already know where they came from
+                let has_source = scene.get::<&SourceMap>(entity).is_ok();
+
+                let new_entity = remap[&entity];
+                combined.spawn_at(new_entity, scene.take(entity).ok().unwrap());
+                if !global && !has_source {
+                    combined.insert_one(new_entity, SourceMap(hash)).ok();
+                }
+            }
+
+            if map.sources.is_empty() {
+                sources.push(MergedMap {
+                    hash,
+                    name: map.name.clone(),
+                    visible: true,
+                });
+            } else {
+                sources.append(&mut map.sources);
+            }
+            names.push(map.name.clone());
+        }
+
+        let first = indices[0];
+        let map = &mut self.maps[first].2;
+        map.scene = combined;
+        map.name = names.join(" + ");
+        map.sources = sources;
+        map.bounds = SceneBounds::default();
+        // Entity handles in the edit history won't survive the move
+        map.history = EditHistory::default();
+        let combined_hash = map.hash;
+
+        for &i in indices[1..].iter().rev() {
+            self.maps.remove(i);
+        }
+
+        // Entity handles changed, so the selection can't be kept
+        if current_merged {
+            *selected = SelectedEntity::default();
+        }
+
+        let current_hash = if current_merged {
+            Some(combined_hash)
+        } else {
+            current_hash
+        };
+        self.current_map = current_hash.and_then(|h| self.index_of(h)).unwrap_or(0);
+        self.sort(self.sort_mode);
+
+        Ok(())
+    }
+}
+
+/// Parses a map hash as written in the package manager (eg. `8098A2C2` or `0x8098A2C2`), and checks that it is actually a map
+pub fn parse_map_hash(s: &str) -> anyhow::Result<TagHash> {
+    let s = s.trim();
+    let s = s
+        .strip_prefix("0x")
+        .or_else(|| s.strip_prefix("0X"))
+        .unwrap_or(s);
+    let hash = match u32::from_str_radix(s, 16) {
+        Ok(v) => TagHash(u32::from_be(v)),
+        Err(_e) => anyhow::bail!("'{s}' is not a valid hash"),
+    };
+
+    let Some(entry) = package_manager().get_entry(hash) else {
+        anyhow::bail!("Could not find a tag with hash {hash}");
+    };
+
+    if entry.reference != u32::from_be(0x1E898080) {
+        anyhow::bail!("{hash} is not a map");
+    }
+
+    Ok(hash)
 }
 
 #[derive(BinRead, Debug)]
@@ -663,3 +1173,169 @@ pub struct Unk80806ed8 {
     pub unkd0: [u32; 4],
     pub unke0: [u32; 4],
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::components::{Children, Parent};
+
+    fn test_map(hash: TagHash) -> (TagHash, Option<TagHash64>, MapData) {
+        (
+            hash,
+            None,
+            MapData {
+                hash,
+                name: hash.to_string(),
+                scene: Scene::new(),
+                command_buffer: hecs::CommandBuffer::new(),
+                history: EditHistory::default(),
+                fov: None,
+                bounds: SceneBounds::default(),
+                sources: vec![],
+            },
+        )
+    }
+
+    fn test_list(count: u32) -> MapDataList {
+        MapDataList {
+            current_map: 0,
+            maps: (0..count)
+                .map(|i| test_map(TagHash(0x80800000 + i)))
+                .collect(),
+            sort_mode: MapSortMode::Name,
+            load_order: vec![],
+            load_requests: vec![],
+            catalog: vec![],
+            prefetched: vec![],
+            pending_switch: None,
+            recently_used: vec![],
+        }
+    }
+
+    #[test]
+    fn set_current_map_moves_globals_and_selection() {
+        let mut maps = test_list(2);
+        let scene = &mut maps.map_mut(0).unwrap().scene;
+        let local = scene.spawn((1u32,));
+        let global_a = scene.spawn((Global(true), 2u32));
+        let global_b = scene.spawn((Global(true), 3u32));
+        // Take up the ids of the old scene, so the moved entities get different handles
+        for _ in 0..3 {
+            maps.map_mut(1).unwrap().scene.spawn((4u32,));
+        }
+
+        let mut selected = SelectedEntity(Some(global_a), false, vec![local, global_b], None);
+        let mut camera = FpsCamera::default();
+        camera.fov = 70.0;
+        maps.set_current_map(1, &mut selected, &mut camera);
+
+        assert_eq!(maps.current_map, 1);
+        assert_eq!(maps.recently_used, vec![maps.maps[0].0, maps.maps[1].0]);
+        assert_eq!(maps.maps[0].2.fov, Some(70.0));
+
+        let old_scene = &maps.maps[0].2.scene;
+        assert!(old_scene.contains(local));
+        assert_eq!(old_scene.len(), 1);
+
+        let new_scene = &maps.maps[1].2.scene;
+        assert_eq!(new_scene.len(), 5);
+        assert!(selected.0 != Some(global_a));
+
+        // The selection follows the global entities to their new handles, the local entity is dropped
+        let primary = selected.0.unwrap();
+        assert_eq!(*new_scene.get::<&u32>(primary).unwrap(), 2);
+        assert_eq!(selected.2.len(), 1);
+        assert_eq!(*new_scene.get::<&u32>(selected.2[0]).unwrap(), 3);
+    }
+
+    #[test]
+    fn set_current_map_promotes_selection_and_ignores_invalid_indices() {
+        let mut maps = test_list(2);
+        let scene = &mut maps.map_mut(0).unwrap().scene;
+        let local = scene.spawn((1u32,));
+        let global = scene.spawn((Global(true), 2u32));
+
+        let mut camera = FpsCamera::default();
+        let mut selected = SelectedEntity(Some(local), false, vec![global], Some((local, 0)));
+        maps.set_current_map(0, &mut selected, &mut camera);
+        maps.set_current_map(2, &mut selected, &mut camera);
+        assert_eq!(maps.current_map, 0);
+        assert_eq!(selected.0, Some(local));
+        assert!(maps.recently_used.is_empty());
+
+        maps.map_mut(1).unwrap().fov = Some(90.0);
+        maps.set_current_map(1, &mut selected, &mut camera);
+
+        // The primary selection stayed behind, so the remaining global entity takes its place
+        let primary = selected.0.unwrap();
+        assert_eq!(*maps.maps[1].2.scene.get::<&u32>(primary).unwrap(), 2);
+        assert!(selected.2.is_empty());
+        assert_eq!(selected.3, None);
+        assert_eq!(camera.fov, 90.0);
+    }
+
+    fn find_u32(scene: &Scene, value: u32) -> Entity {
+        scene
+            .query::<&u32>()
+            .iter()
+            .find(|(_, v)| **v == value)
+            .map(|(e, _)| e)
+            .unwrap()
+    }
+
+    #[test]
+    fn set_current_map_keeps_hierarchy_of_globals() {
+        let mut maps = test_list(2);
+        let scene = &mut maps.map_mut(0).unwrap().scene;
+        let local = scene.spawn((1u32,));
+        let global = scene.spawn((Global(true), 2u32, Parent(local)));
+        let global_child = scene.spawn((Global(true), 3u32, Parent(global)));
+        scene.insert_one(local, Children(vec![global])).unwrap();
+        scene
+            .insert_one(global, Children(vec![global_child]))
+            .unwrap();
+        for _ in 0..3 {
+            maps.map_mut(1).unwrap().scene.spawn((4u32,));
+        }
+
+        let mut camera = FpsCamera::default();
+        maps.set_current_map(1, &mut SelectedEntity::default(), &mut camera);
+
+        // The local parent stayed behind, so the global entity becomes a root
+        let old_scene = &maps.maps[0].2.scene;
+        assert!(old_scene.get::<&Children>(local).unwrap().0.is_empty());
+
+        let new_scene = &maps.maps[1].2.scene;
+        let (global, global_child) = (find_u32(new_scene, 2), find_u32(new_scene, 3));
+        assert!(new_scene.get::<&Parent>(global).is_err());
+        assert_eq!(
+            new_scene.get::<&Children>(global).unwrap().0,
+            vec![global_child]
+        );
+        assert_eq!(new_scene.get::<&Parent>(global_child).unwrap().0, global);
+    }
+
+    #[test]
+    fn merge_maps_keeps_hierarchy() {
+        let mut maps = test_list(2);
+        // Take up the ids of the second map, so its entities get different handles in the combined scene
+        for _ in 0..3 {
+            maps.map_mut(0).unwrap().scene.spawn((1u32,));
+        }
+        let scene = &mut maps.map_mut(1).unwrap().scene;
+        let parent = scene.spawn((2u32,));
+        let child = scene.spawn((3u32, Parent(parent)));
+        scene.insert_one(parent, Children(vec![child])).unwrap();
+
+        let hashes = [maps.maps[0].0, maps.maps[1].0];
+        maps.merge_maps(&hashes, &mut SelectedEntity::default())
+            .unwrap();
+
+        assert_eq!(maps.maps.len(), 1);
+        let scene = &maps.maps[0].2.scene;
+        assert_eq!(scene.len(), 5);
+        let (parent, child) = (find_u32(scene, 2), find_u32(scene, 3));
+        assert_eq!(scene.get::<&Parent>(child).unwrap().0, parent);
+        assert_eq!(scene.get::<&Children>(parent).unwrap().0, vec![child]);
+    }
+}
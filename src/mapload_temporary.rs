@@ -3,19 +3,23 @@
 use std::{
     collections::HashSet,
     io::{Cursor, Read, Seek, SeekFrom},
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
 };
 
 use crate::{
     activity::{SActivity, SEntityResource, Unk80808cef, Unk80808e89, Unk808092d8},
     ecs::{
         components::{
-            ActivityGroup, CubemapVolume, EntityWorldId, Label, PointLight, ResourceOriginType,
-            ResourcePoint, StaticInstances, Terrain, Water,
+            ActivityGroup, CubemapVolume, EntityWorldId, ErrorMarker, Label, LightColor,
+            PointLight, ResourceOriginType, ResourcePoint, StaticInstances, Terrain, Water,
         },
+        history::EditHistory,
         tags::{insert_tag, EntityTag},
         transform::{OriginalTransform, Transform},
-        Scene,
+        Scene, SceneBounds,
     },
     entity::{Unk8080906b, Unk80809905},
     map::SMapDataTable,
@@ -24,9 +28,14 @@ use crate::{
         Unk808068d4, Unk80806ac2, Unk80806c98, Unk80806d19, Unk80808246, Unk808085c2, Unk80808604,
         Unk80808cb7, Unk80809178, Unk8080917b, Unk80809802,
     },
-    render::{cbuffer::ConstantBufferCached, debug::CustomDebugShape, renderer::RendererShared},
+    render::{
+        asset_errors::{AssetKind, ASSET_ERRORS},
+        cbuffer::ConstantBufferCached,
+        debug::CustomDebugShape,
+        renderer::RendererShared,
+    },
     types::{FnvHash, ResourceHash},
-    util::fnv1,
+    util::{fnv1, scan::scan_u32},
 };
 use anyhow::Context;
 use binrw::{BinReaderExt, VecArgs};
@@ -36,7 +45,7 @@ use itertools::{multizip, Itertools};
 use nohash_hasher::{IntMap, IntSet};
 use windows::Win32::Graphics::{
     Direct3D::WKPDID_D3DDebugObjectName,
-    Direct3D11::{ID3D11PixelShader, ID3D11SamplerState, ID3D11VertexShader},
+    Direct3D11::{ID3D11PixelShader, ID3D11VertexShader},
 };
 
 use crate::structure::ExtendedHash;
@@ -59,6 +68,7 @@ use crate::{
     types::AABB,
 };
 
+/// Loads the given maps and the entities they reference. Returns `None` when the load was cancelled through `cancel`
 pub async fn load_maps(
     dcs: Arc<DeviceContextSwapchain>,
     renderer: RendererShared,
@@ -66,12 +76,12 @@ pub async fn load_maps(
     stringmap: Arc<IntMap<u32, String>>,
     activity_hash: Option<TagHash>,
     load_ambient_activity: bool,
-) -> anyhow::Result<LoadMapsData> {
+    cancel: Arc<AtomicBool>,
+) -> anyhow::Result<Option<LoadMapsData>> {
     let mut vshader_map: IntMap<TagHash, (ID3D11VertexShader, Vec<InputElement>, Vec<u8>)> =
         Default::default();
     let mut pshader_map: IntMap<TagHash, (ID3D11PixelShader, Vec<InputElement>)> =
         Default::default();
-    let mut sampler_map: IntMap<u64, ID3D11SamplerState> = Default::default();
 
     let mut maps: Vec<(TagHash, Option<TagHash64>, MapData)> = vec![];
     let mut material_map: IntMap<TagHash, Technique> = Default::default();
@@ -140,6 +150,10 @@ pub async fn load_maps(
     }
 
     for hash in map_hashes {
+        if cancel.load(Ordering::Relaxed) {
+            return Ok(None);
+        }
+
         let _span = debug_span!("Load map", %hash).entered();
         let Ok(think) = package_manager().read_tag_struct::<SBubbleParent>(hash) else {
             error!("Failed to load map {hash}");
@@ -219,23 +233,20 @@ pub async fn load_maps(
                             }
                         }
 
-                        let mut data_tables2 = IntSet::default();
                         // TODO(cohae): This is a very dirty hack to find every other data table in the entityresource. We need to fully flesh out the EntityResource format first.
                         // TODO(cohae): PS: gets assigned as Activity2 to keep them separate from known tables
-                        for b in data.chunks_exact(4) {
-                            let v: [u8; 4] = b.try_into().unwrap();
-                            let hash = TagHash(u32::from_le_bytes(v));
-
-                            if hash.is_pkg_file()
+                        let data_tables2: IntSet<TagHash> = scan_u32(&data, |v| {
+                            let hash = TagHash(v);
+                            hash.is_pkg_file()
                                 && package_manager()
                                     .get_entry(hash)
                                     .map(|v| v.reference == 0x80809883)
                                     .unwrap_or_default()
                                 && !data_tables.contains(&hash)
-                            {
-                                data_tables2.insert(hash);
-                            }
-                        }
+                        })
+                        .into_iter()
+                        .map(|(_, v)| TagHash(v))
+                        .collect();
 
                         if !data_tables2.is_empty() {
                             let tstr = data_tables2.iter().map(|v| v.to_string()).join(", ");
@@ -342,6 +353,10 @@ pub async fn load_maps(
                 name: map_name,
                 scene,
                 command_buffer: hecs::CommandBuffer::new(),
+                history: EditHistory::default(),
+                fov: None,
+                bounds: SceneBounds::default(),
+                sources: vec![],
             },
         ));
     }
@@ -359,12 +374,20 @@ pub async fn load_maps(
         .collect();
 
     let mut entity_renderers: IntMap<u64, EntityRenderer> = Default::default();
+    // Entity keys that failed to load, with the reason
+    let mut failed_entities: IntMap<u64, String> = Default::default();
     for te in &to_load_entities {
         let renderer = renderer.read();
         if let Some(nh) = te.hash32() {
             let _span = debug_span!("Load entity", hash = %nh).entered();
             let Ok(header) = package_manager().read_tag_struct::<Unk80809c0f>(nh) else {
                 error!("Could not load entity {nh} ({te:?})");
+                failed_entities.insert(te.key(), format!("Could not read entity {nh}"));
+                ASSET_ERRORS.push(
+                    AssetKind::Entity,
+                    *te,
+                    format!("Could not read entity {nh}"),
+                );
                 continue;
             };
             debug!("Loading entity {nh}");
@@ -419,6 +442,8 @@ pub async fn load_maps(
                             }
                             Err(e) => {
                                 error!("Failed to load entity {te:?}: {e}");
+                                failed_entities.insert(te.key(), e.to_string());
+                                ASSET_ERRORS.push(AssetKind::Entity, *te, &e);
                             }
                         }
 
@@ -437,6 +462,10 @@ pub async fn load_maps(
         }
     }
 
+    if cancel.load(Ordering::Relaxed) {
+        return Ok(None);
+    }
+
     info!("Loading {} background entities", to_load_entitymodels.len());
 
     for t in to_load_entitymodels {
@@ -467,6 +496,8 @@ pub async fn load_maps(
             }
             Err(e) => {
                 error!("Failed to load entity {t}: {e}");
+                failed_entities.insert(t.0 as u64, e.to_string());
+                ASSET_ERRORS.push(AssetKind::Entity, ExtendedHash::Hash32(t), &e);
             }
         }
     }
@@ -480,7 +511,12 @@ pub async fn load_maps(
     // TODO(cohae): Maybe not the best idea?
     info!("Updating resource constant buffers");
     for (_, _, m) in &mut maps {
-        for (_, (transform, rp)) in m.scene.query_mut::<(&Transform, &mut ResourcePoint)>() {
+        let mut errored = vec![];
+        for (e, (transform, rp)) in m.scene.query_mut::<(&Transform, &mut ResourcePoint)>() {
+            if let Some(reason) = failed_entities.get(&rp.entity_key()) {
+                errored.push((e, ErrorMarker(reason.clone())));
+            }
+
             if let Some(ent) = entity_renderers.get(&rp.entity_key()) {
                 let mm = transform.to_mat4();
 
@@ -512,6 +548,10 @@ pub async fn load_maps(
                 .unwrap();
             }
         }
+
+        for (e, marker) in errored {
+            m.scene.insert_one(e, marker).ok();
+        }
     }
 
     let mut to_load_samplers: HashSet<ExtendedHash> = Default::default();
@@ -659,21 +699,18 @@ pub async fn load_maps(
         }
     }
 
-    for s in to_load_samplers {
-        let sampler_header_ref = package_manager()
-            .get_entry(s.hash32().unwrap())
-            .unwrap()
-            .reference;
-        let sampler_data = package_manager().read_tag(sampler_header_ref).unwrap();
-
-        let sampler = unsafe { dcs.device.CreateSamplerState(sampler_data.as_ptr() as _) };
-
-        if let Ok(sampler) = sampler {
-            sampler_map.insert(s.key(), sampler);
+    {
+        let renderer = renderer.read();
+        let mut sampler_count = 0;
+        for s in to_load_samplers {
+            match renderer.render_data.load_sampler(s) {
+                Ok(()) => sampler_count += 1,
+                Err(e) => error!("Failed to load sampler {s:?}: {e}"),
+            }
         }
-    }
 
-    info!("Loaded {} samplers", sampler_map.len());
+        info!("Loaded {} samplers", sampler_count);
+    }
 
     {
         let renderer = renderer.read();
@@ -681,16 +718,12 @@ pub async fn load_maps(
         data.techniques.extend(material_map);
         data.vshaders.extend(vshader_map);
         data.pshaders.extend(pshader_map);
-        data.samplers.extend(sampler_map);
     };
 
-    #[cfg(not(feature = "keep_map_order"))]
-    maps.sort_by_key(|m| m.2.name.clone());
-
-    Ok(LoadMapsData {
+    Ok(Some(LoadMapsData {
         maps,
         entity_renderers,
-    })
+    }))
 }
 
 pub struct LoadMapsData {
@@ -825,6 +858,11 @@ fn load_datatable_into_scene<R: Read + Seek>(
                                         Ok(o) => o,
                                         Err(e) => {
                                             error!("Failed to create InstancedRenderer: {e}");
+                                            ASSET_ERRORS.push(
+                                                AssetKind::StaticModel,
+                                                ExtendedHash::Hash32(mesh_tag),
+                                                &e,
+                                            );
                                             continue 'next_instance;
                                         }
                                     };
@@ -836,6 +874,11 @@ fn load_datatable_into_scene<R: Read + Seek>(
                                 }
                                 Err(e) => {
                                     error!(model = ?mesh_tag, "Failed to load model: {e}");
+                                    ASSET_ERRORS.push(
+                                        AssetKind::StaticModel,
+                                        ExtendedHash::Hash32(mesh_tag),
+                                        &e,
+                                    );
                                 }
                             }
                         }
@@ -878,6 +921,11 @@ fn load_datatable_into_scene<R: Read + Seek>(
                         }
                         Err(e) => {
                             error!("Failed to load terrain: {e}");
+                            ASSET_ERRORS.push(
+                                AssetKind::Terrain,
+                                ExtendedHash::Hash32(terrain_resource.terrain),
+                                &e,
+                            );
                         }
                     }
                 }
@@ -1103,6 +1151,7 @@ fn load_datatable_into_scene<R: Read + Seek>(
                                 attenuation: Vec4::ONE,
                             },
                             light.clone(),
+                            LightColor::default(),
                             bounds.bb,
                         )));
                     }
@@ -1265,6 +1314,7 @@ fn load_datatable_into_scene<R: Read + Seek>(
                         },
                         EntityWorldId(data.world_id),
                         light,
+                        LightColor::default(),
                     )));
                 }
                 0x80809178 => {
@@ -1819,25 +1869,18 @@ fn get_entity_labels(entity: TagHash) -> Option<IntMap<u64, String>> {
         return None;
     }
 
-    for (i, b) in data.chunks_exact(4).enumerate() {
-        let v: [u8; 4] = b.try_into().unwrap();
-        let hash = u32::from_le_bytes(v);
-        let offset = i as u64 * 4;
-
-        if hash == 0x80809905 {
-            cur.seek(SeekFrom::Start(offset - 8)).ok()?;
-            let count: u64 = cur.read_le().ok()?;
-            cur.seek(SeekFrom::Start(offset + 8)).ok()?;
-            world_id_list = cur
-                .read_le_args(VecArgs {
-                    count: count as usize,
-                    inner: (),
-                })
-                .ok()?;
-            // let list: TablePointer<Unk80809905> = cur.read_le().ok()?;
-            // world_id_list = list.take_data();
-            break;
-        }
+    if let Some(&(offset, _)) = scan_u32(&data, |v| v == 0x80809905).first() {
+        cur.seek(SeekFrom::Start(offset - 8)).ok()?;
+        let count: u64 = cur.read_le().ok()?;
+        cur.seek(SeekFrom::Start(offset + 8)).ok()?;
+        world_id_list = cur
+            .read_le_args(VecArgs {
+                count: count as usize,
+                inner: (),
+            })
+            .ok()?;
+        // let list: TablePointer<Unk80809905> = cur.read_le().ok()?;
+        // world_id_list = list.take_data();
     }
 
     // if matches!(e.unk18.resource_type, 0x80808cf8 | 0x808098fa) {
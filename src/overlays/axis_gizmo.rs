@@ -0,0 +1,169 @@
+use egui::{Color32, Sense, Stroke};
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+use winit::window::Window;
+
+use crate::{
+    camera::FpsCamera,
+    config::AxisGizmoConfig,
+    render::tween::{self, Tween},
+    resources::Resources,
+};
+
+use super::gui::Overlay;
+
+/// Distance from the edges of the screen
+const MARGIN: f32 = 16.0;
+/// Extra space at the top of the screen for the menu bar
+const MENU_BAR_HEIGHT: f32 = 24.0;
+const HANDLE_RADIUS: f32 = 8.0;
+const SNAP_DURATION: f32 = 0.4;
+
+const AXES: [(Vec3, Color32, &str); 3] = [
+    (Vec3::X, Color32::from_rgb(230, 60, 60), "X"),
+    (Vec3::Y, Color32::from_rgb(60, 200, 60), "Y"),
+    (Vec3::Z, Color32::from_rgb(60, 110, 240), "Z"),
+];
+
+#[derive(strum::Display, strum::EnumIter, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScreenCorner {
+    #[strum(serialize = "Top left")]
+    TopLeft,
+    #[strum(serialize = "Top right")]
+    TopRight,
+    #[strum(serialize = "Bottom left")]
+    BottomLeft,
+    #[strum(serialize = "Bottom right")]
+    BottomRight,
+}
+
+impl ScreenCorner {
+    fn anchor(&self) -> (egui::Align2, egui::Vec2) {
+        match self {
+            ScreenCorner::TopLeft => (
+                egui::Align2::LEFT_TOP,
+                egui::vec2(MARGIN, MARGIN + MENU_BAR_HEIGHT),
+            ),
+            ScreenCorner::TopRight => (
+                egui::Align2::RIGHT_TOP,
+                egui::vec2(-MARGIN, MARGIN + MENU_BAR_HEIGHT),
+            ),
+            ScreenCorner::BottomLeft => (egui::Align2::LEFT_BOTTOM, egui::vec2(MARGIN, -MARGIN)),
+            ScreenCorner::BottomRight => (egui::Align2::RIGHT_BOTTOM, egui::vec2(-MARGIN, -MARGIN)),
+        }
+    }
+}
+
+/// Shows the orientation of the camera in a corner of the screen.
+/// Clicking an axis makes the camera look down that axis
+pub struct AxisGizmoOverlay;
+
+impl Overlay for AxisGizmoOverlay {
+    fn draw(
+        &mut self,
+        ctx: &egui::Context,
+        _window: &Window,
+        resources: &mut Resources,
+        _gui: &mut super::gui::GuiContext<'_>,
+    ) -> bool {
+        let settings = resources.get::<AxisGizmoConfig>().unwrap().clone();
+        if !settings.enabled {
+            return true;
+        }
+
+        let mut camera = resources.get_mut::<FpsCamera>().unwrap();
+        let (align, offset) = settings.corner.anchor();
+        egui::Area::new("axis_gizmo")
+            .anchor(align, offset)
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui| {
+                let (rect, response) =
+                    ui.allocate_exact_size(egui::Vec2::splat(settings.size), Sense::click());
+                let center = rect.center();
+                let length = settings.size / 2.0 - HANDLE_RADIUS;
+
+                // Positive and negative axes, with their position on screen and depth towards the viewer
+                let mut handles = AXES
+                    .iter()
+                    .flat_map(|&(axis, color, label)| {
+                        [(axis, color, Some(label)), (-axis, color, None)]
+                    })
+                    .map(|(axis, color, label)| {
+                        let view = camera.rotation * axis;
+                        let pos = center + egui::vec2(view.x, -view.y) * length;
+                        (axis, color, label, pos, view.z)
+                    })
+                    .collect::<Vec<_>>();
+                handles.sort_by(|a, b| a.4.total_cmp(&b.4));
+
+                let hovered = response
+                    .hover_pos()
+                    .and_then(|pointer| {
+                        // Frontmost handle under the cursor
+                        handles
+                            .iter()
+                            .rev()
+                            .find(|h| h.3.distance(pointer) <= HANDLE_RADIUS)
+                    })
+                    .map(|h| h.0);
+
+                let painter = ui.painter();
+                if response.hovered() {
+                    painter.circle_filled(
+                        center,
+                        settings.size / 2.0,
+                        Color32::from_white_alpha(16),
+                    );
+                }
+
+                for &(axis, color, label, pos, _) in &handles {
+                    let color = if hovered == Some(axis) {
+                        Color32::WHITE
+                    } else {
+                        color
+                    };
+
+                    if let Some(label) = label {
+                        painter.line_segment([center, pos], Stroke::new(2.0, color));
+                        painter.circle_filled(pos, HANDLE_RADIUS, color);
+                        painter.text(
+                            pos,
+                            egui::Align2::CENTER_CENTER,
+                            label,
+                            egui::FontId::proportional(HANDLE_RADIUS * 1.5),
+                            Color32::BLACK,
+                        );
+                    } else {
+                        painter.circle(
+                            pos,
+                            HANDLE_RADIUS * 0.8,
+                            color.linear_multiply(0.35),
+                            Stroke::new(1.5, color),
+                        );
+                    }
+                }
+
+                if let Some(axis) = hovered.filter(|_| response.clicked()) {
+                    snap_to_axis(&mut camera, axis);
+                }
+            });
+
+        true
+    }
+}
+
+/// Tweens the camera to look down the given axis, towards the origin
+fn snap_to_axis(camera: &mut FpsCamera, axis: Vec3) {
+    let mut target = camera.get_look_angle(camera.position - axis);
+    // Looking straight up or down, keep the current heading
+    if axis.z != 0.0 {
+        target.y = camera.orientation.y;
+    }
+
+    camera.tween = Some(Tween::new(
+        tween::ease_out_exponential,
+        None,
+        Some((camera.orientation, target)),
+        SNAP_DURATION,
+    ));
+}
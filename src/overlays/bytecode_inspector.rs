@@ -0,0 +1,103 @@
+use destiny_pkg::TagHash;
+use egui::{Color32, RichText};
+use winit::window::Window;
+
+use crate::{
+    ecs::resources::SelectedEntity,
+    render::{renderer::RendererShared, settings::RenderSettings},
+    resources::Resources,
+    technique::TechniqueStage,
+};
+
+use super::gui::Overlay;
+
+/// Shows the TFX bytecode of the materials used by the selected entity, and the values it writes to their constant buffers
+pub struct BytecodeInspectorOverlay;
+
+impl Overlay for BytecodeInspectorOverlay {
+    fn draw(
+        &mut self,
+        ctx: &egui::Context,
+        _window: &Window,
+        resources: &mut Resources,
+        _gui: &mut super::gui::GuiContext<'_>,
+    ) -> bool {
+        if !resources.get::<RenderSettings>().unwrap().evaluate_bytecode {
+            return true;
+        }
+
+        let Some(selected) = resources.get::<SelectedEntity>().unwrap().0 else {
+            return true;
+        };
+
+        let renderer = resources.get::<RendererShared>().unwrap();
+        let renderer = renderer.read();
+        let materials = renderer.entity_materials(selected);
+        let render_data = renderer.render_data.data();
+
+        egui::Window::new("TFX Bytecode").show(ctx, |ui| {
+            if materials.is_empty() {
+                ui.label("The selected entity doesn't have any visible materials");
+                return;
+            }
+
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for material in materials {
+                    let Some(technique) = render_data.techniques.get(&material) else {
+                        continue;
+                    };
+
+                    egui::CollapsingHeader::new(format!("Material {material}"))
+                        .default_open(true)
+                        .show(ui, |ui| {
+                            for (name, stage) in [
+                                ("Vertex", &technique.stage_vertex),
+                                ("Pixel", &technique.stage_pixel),
+                            ] {
+                                stage_ui(ui, material, name, stage);
+                            }
+                        });
+                }
+            });
+        });
+
+        true
+    }
+}
+
+fn stage_ui(ui: &mut egui::Ui, material: TagHash, name: &str, stage: &TechniqueStage) {
+    let Some((disassembly, outputs)) = stage.bytecode_disassembly() else {
+        ui.label(format!("{name}: no bytecode"));
+        return;
+    };
+
+    egui::CollapsingHeader::new(format!("{name} ({} ops)", disassembly.len()))
+        .id_source((material, name))
+        .show(ui, |ui| {
+            ui.label(RichText::new("Disassembly").strong());
+            for (ip, line) in disassembly.iter().enumerate() {
+                ui.label(RichText::new(format!("{ip:>3}: {line}")).monospace());
+            }
+
+            ui.separator();
+            ui.label(
+                RichText::new(format!("Outputs (cb{})", stage.shader.constant_buffer_slot))
+                    .strong(),
+            );
+            let data = stage.cbuffer_data().unwrap_or_default();
+            if outputs.is_empty() {
+                ui.label("The bytecode doesn't write to the constant buffer");
+            }
+
+            for element in outputs {
+                let text = match data.get(element) {
+                    Some(v) => format!(
+                        "[{element:>3}] {:>10.4} {:>10.4} {:>10.4} {:>10.4}",
+                        v.x, v.y, v.z, v.w
+                    ),
+                    None => format!("[{element:>3}] out of range"),
+                };
+                ui.label(RichText::new(text).monospace().color(Color32::WHITE));
+            }
+        });
+}
@@ -1,9 +1,18 @@
 use egui::{Color32, RichText};
 
+use strum::IntoEnumIterator;
 use winit::window::Window;
 
+use crate::camera::{
+    CameraMode, ScrollAction, FOV_RANGE, MOUSE_SENSITIVITY_RANGE, ORBIT_DISTANCE_RANGE,
+    SPEED_MUL_RANGE, Z_NEAR, Z_NEAR_MIN,
+};
+use crate::config::AxisGizmoConfig;
+use crate::ecs::tags::{EntityTag, TagPalette};
+use crate::gamepad::Gamepads;
 use crate::icons::{ICON_BUG, ICON_CLIPBOARD};
 use crate::map_resources::MapResource;
+use crate::overlays::axis_gizmo::ScreenCorner;
 use crate::resources::Resources;
 use crate::structure::ExtendedHash;
 use crate::FpsCamera;
@@ -76,8 +85,145 @@ impl Overlay for CameraPositionOverlay {
                 }
             ));
             ui.separator();
-            ui.add(egui::Slider::new(&mut camera.speed_mul, 0.01..=10.0).text("Speed Multiplier"));
-            ui.add(egui::Slider::new(&mut camera.fov, 10.0..=120.0).text("Field of View"));
+            ui.add(
+                egui::Slider::new(
+                    &mut camera.speed_mul,
+                    SPEED_MUL_RANGE.start..=SPEED_MUL_RANGE.end,
+                )
+                .logarithmic(true)
+                .text("Speed Multiplier"),
+            );
+            let mut mode = camera.mode();
+            egui::ComboBox::from_label("Camera mode")
+                .selected_text(mode.to_string())
+                .show_ui(ui, |ui| {
+                    for m in CameraMode::iter() {
+                        ui.selectable_value(&mut mode, m, m.to_string());
+                    }
+                });
+            camera.set_mode(mode);
+            if mode == CameraMode::Orbit {
+                ui.add(
+                    egui::Slider::new(
+                        &mut camera.orbit_distance,
+                        ORBIT_DISTANCE_RANGE.start..=ORBIT_DISTANCE_RANGE.end,
+                    )
+                        .logarithmic(true)
+                        .suffix("m")
+                        .text("Orbit distance"),
+                );
+            }
+
+            ui.add_enabled_ui(mode == CameraMode::Fps, |ui| {
+                egui::ComboBox::from_label("Scroll wheel")
+                    .selected_text(camera.scroll_action.to_string())
+                    .show_ui(ui, |ui| {
+                        for action in ScrollAction::iter() {
+                            ui.selectable_value(
+                                &mut camera.scroll_action,
+                                action,
+                                action.to_string(),
+                            );
+                        }
+                    });
+            });
+
+            ui.checkbox(&mut camera.smooth_movement, "Smooth movement");
+            ui.add_enabled(
+                camera.smooth_movement,
+                egui::Slider::new(&mut camera.smoothing, 0.01..=1.0)
+                    .suffix("s")
+                    .text("Smoothing"),
+            );
+            ui.add(
+                egui::Slider::new(&mut camera.fov, FOV_RANGE.start..=FOV_RANGE.end)
+                    .suffix("°")
+                    .text("Field of View"),
+            );
+            ui.add(
+                egui::Slider::new(
+                    &mut camera.mouse_sensitivity,
+                    MOUSE_SENSITIVITY_RANGE.start..=MOUSE_SENSITIVITY_RANGE.end,
+                )
+                .logarithmic(true)
+                .text("Mouse sensitivity"),
+            );
+            ui.checkbox(
+                &mut camera.fov_compensation,
+                "Scale mouse sensitivity with field of view",
+            )
+            .on_hover_text("Keeps the speed the scene moves on screen the same when zooming in or out");
+            ui.collapsing("Advanced", |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Near plane");
+                    ui.add(
+                        egui::DragValue::new(&mut camera.z_near)
+                            .speed(0.0001)
+                            .clamp_range(Z_NEAR_MIN..=10.0)
+                            .max_decimals(5)
+                            .suffix("m"),
+                    );
+                });
+
+                ui.horizontal(|ui| {
+                    let mut infinite = camera.z_far.is_none();
+                    if ui.checkbox(&mut infinite, "Infinite far plane").changed() {
+                        camera.z_far = (!infinite).then_some(10000.0);
+                    }
+
+                    if let Some(z_far) = camera.z_far.as_mut() {
+                        ui.add(
+                            egui::DragValue::new(z_far)
+                                .speed(10.0)
+                                .clamp_range(1.0..=1000000.0)
+                                .suffix("m"),
+                        );
+                    }
+                });
+
+                ui.checkbox(&mut camera.reverse_z, "Reversed depth")
+                    .on_hover_text(
+                        "Maps the near plane to a depth of 1 and the far plane to 0.\n\
+                        Standard depth is easier to inspect, but loses a lot of precision in the distance",
+                    );
+
+                if ui.button("Reset").clicked() {
+                    camera.z_near = Z_NEAR;
+                    camera.z_far = None;
+                    camera.reverse_z = true;
+                }
+            });
+
+            let mut gamepads = resources.get_mut::<Gamepads>().unwrap();
+            ui.checkbox(&mut gamepads.enabled, "Gamepad controls");
+            ui.add_enabled(
+                gamepads.enabled,
+                egui::Slider::new(&mut gamepads.deadzone, 0.0..=0.5).text("Gamepad deadzone"),
+            );
+
+            let mut axis_gizmo = resources.get_mut::<AxisGizmoConfig>().unwrap();
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut axis_gizmo.enabled, "Axis indicator");
+                ui.add_enabled_ui(axis_gizmo.enabled, |ui| {
+                    egui::ComboBox::from_id_source("axis_gizmo_corner")
+                        .selected_text(axis_gizmo.corner.to_string())
+                        .show_ui(ui, |ui| {
+                            for corner in ScreenCorner::iter() {
+                                ui.selectable_value(
+                                    &mut axis_gizmo.corner,
+                                    corner,
+                                    corner.to_string(),
+                                );
+                            }
+                        });
+                    ui.add(
+                        egui::DragValue::new(&mut axis_gizmo.size)
+                            .clamp_range(48.0..=256.0)
+                            .suffix("pt"),
+                    );
+                });
+            });
+
             ui.separator();
             ui.checkbox(&mut self.show_map_resources, "Show map resources");
             if self.show_map_resources {
@@ -122,6 +268,31 @@ impl Overlay for CameraPositionOverlay {
                     "Only show named entities",
                 );
             }
+
+            ui.separator();
+            let mut palette = resources.get_mut::<TagPalette>().unwrap();
+            ui.checkbox(&mut palette.enabled, "Color entities by tag")
+                .on_hover_text("Tints selection outlines and map resources by the tags of the entity");
+            if palette.enabled {
+                ui.indent("tag_palette_indent", |ui| {
+                    for tag in EntityTag::iter() {
+                        ui.horizontal(|ui| {
+                            let mut tinted = palette.colors.contains_key(&tag);
+                            if ui.checkbox(&mut tinted, tag.to_string()).changed() {
+                                if tinted {
+                                    palette.colors.insert(tag, tag.color());
+                                } else {
+                                    palette.colors.remove(&tag);
+                                }
+                            }
+
+                            if let Some(color) = palette.colors.get_mut(&tag) {
+                                ui.color_edit_button_srgba(color);
+                            }
+                        });
+                    }
+                });
+            }
         });
 
         true
@@ -0,0 +1,96 @@
+use egui::{Color32, Pos2, Sense, Stroke};
+use glam::Vec3;
+use winit::window::Window;
+
+use crate::{camera::FpsCamera, render::settings::RenderSettings, resources::Resources};
+
+use super::gui::Overlay;
+
+/// Size of the plane outline relative to the distance from the camera
+const PLANE_SCALE: f32 = 0.3;
+const HANDLE_SIZE: f32 = 14.0;
+const PLANE_COLOR: Color32 = Color32::from_rgb(60, 200, 230);
+const HIGHLIGHT_COLOR: Color32 = Color32::from_rgb(255, 220, 60);
+
+/// Draws the clipping plane, with a handle to move it along its normal
+pub struct ClipPlaneOverlay;
+
+impl Overlay for ClipPlaneOverlay {
+    fn draw(
+        &mut self,
+        ctx: &egui::Context,
+        _window: &Window,
+        resources: &mut Resources,
+        _gui: &mut super::gui::GuiContext<'_>,
+    ) -> bool {
+        let mut render_settings = resources.get_mut::<RenderSettings>().unwrap();
+        let Some(plane) = render_settings.clip_plane() else {
+            return true;
+        };
+
+        let camera = resources.get::<FpsCamera>().unwrap();
+        let screen_size = ctx.screen_rect().size();
+        // Points on the plane sit right on the clipping boundary, so only reject points behind the camera
+        let project = |point: Vec3| {
+            let clip = camera.projection_view_matrix * point.extend(1.0);
+            (clip.w > 0.0).then(|| {
+                let ndc = clip.truncate() / clip.w;
+                Pos2::new(
+                    ((ndc.x + 1.0) * 0.5) * screen_size.x,
+                    ((1.0 - ndc.y) * 0.5) * screen_size.y,
+                )
+            })
+        };
+
+        let origin = render_settings.clip_plane_point;
+        let normal = plane.truncate();
+        let length = camera.position.distance(origin) * PLANE_SCALE;
+        let (u, v) = normal.any_orthonormal_pair();
+
+        let painter = ctx.layer_painter(egui::LayerId::background());
+        let outline: Option<Vec<Pos2>> = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+            .into_iter()
+            .map(|(a, b)| project(origin + (u * a + v * b) * length))
+            .collect();
+        if let Some(outline) = outline {
+            painter.add(egui::Shape::closed_line(
+                outline,
+                Stroke::new(1.5, PLANE_COLOR),
+            ));
+        }
+
+        let (Some(origin_screen), Some(handle_screen)) =
+            (project(origin), project(origin + normal * length))
+        else {
+            return true;
+        };
+
+        let response = egui::Area::new("clip_plane_handle")
+            .fixed_pos(handle_screen - egui::Vec2::splat(HANDLE_SIZE / 2.0))
+            .order(egui::Order::Background)
+            .show(ctx, |ui| {
+                ui.allocate_exact_size(egui::Vec2::splat(HANDLE_SIZE), Sense::drag())
+                    .1
+            })
+            .inner;
+
+        let color = if response.hovered() || response.dragged() {
+            HIGHLIGHT_COLOR
+        } else {
+            PLANE_COLOR
+        };
+        painter.line_segment([origin_screen, handle_screen], Stroke::new(2.5, color));
+        painter.circle_filled(handle_screen, HANDLE_SIZE / 2.0, color);
+
+        if response.dragged() {
+            // Project the mouse movement onto the normal on screen, and scale it back to world units
+            let axis_screen = handle_screen - origin_screen;
+            if axis_screen.length_sq() > 1.0 {
+                let delta = response.drag_delta().dot(axis_screen) / axis_screen.length_sq();
+                render_settings.clip_plane_point += normal * delta * length;
+            }
+        }
+
+        true
+    }
+}
@@ -0,0 +1,177 @@
+use std::rc::Rc;
+
+use destiny_pkg::TagHash;
+use winit::window::Window;
+
+use crate::{
+    camera::FpsCamera,
+    commands::{CommandFn, Commands},
+    config::RecentMaps,
+    ecs::resources::SelectedEntity,
+    hotkeys::SHORTCUT_COMMAND_PALETTE,
+    map::MapDataList,
+    resources::Resources,
+};
+
+use super::gui::Overlay;
+
+/// Searchable list of all registered commands and recently opened maps, opened with Ctrl+P
+#[derive(Default)]
+pub struct CommandPaletteOverlay {
+    open: bool,
+    query: String,
+    selected: usize,
+}
+
+struct PaletteEntry {
+    name: String,
+    shortcut: Option<String>,
+    run: CommandFn,
+}
+
+impl Overlay for CommandPaletteOverlay {
+    fn draw(
+        &mut self,
+        ctx: &egui::Context,
+        _window: &Window,
+        resources: &mut Resources,
+        _gui: &mut super::gui::GuiContext<'_>,
+    ) -> bool {
+        if ctx.input_mut(|i| i.consume_shortcut(&SHORTCUT_COMMAND_PALETTE)) {
+            self.open = !self.open;
+            self.query.clear();
+            self.selected = 0;
+        }
+
+        if !self.open {
+            return true;
+        }
+
+        let entries = Self::entries(ctx, resources);
+        let query = self.query.to_lowercase();
+        let matches: Vec<&PaletteEntry> = entries
+            .iter()
+            .filter(|e| {
+                let name = e.name.to_lowercase();
+                query.split_whitespace().all(|word| name.contains(word))
+            })
+            .collect();
+
+        let (up, down, enter, escape) = ctx.input_mut(|i| {
+            (
+                i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::Enter),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::Escape),
+            )
+        });
+
+        if escape {
+            self.open = false;
+            return true;
+        }
+
+        if up {
+            self.selected = self.selected.saturating_sub(1);
+        }
+        if down {
+            self.selected += 1;
+        }
+        self.selected = self.selected.min(matches.len().saturating_sub(1));
+
+        let mut run = enter
+            .then(|| matches.get(self.selected))
+            .flatten()
+            .map(|e| e.run.clone());
+        egui::Window::new("Command Palette")
+            .title_bar(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_TOP, [0.0, 48.0])
+            .fixed_size([420.0, 0.0])
+            .show(ctx, |ui| {
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut self.query)
+                        .hint_text("Search commands")
+                        .desired_width(f32::INFINITY),
+                );
+                response.request_focus();
+                if response.changed() {
+                    self.selected = 0;
+                }
+
+                ui.separator();
+
+                if matches.is_empty() {
+                    ui.label(egui::RichText::new("No matching commands").weak());
+                }
+
+                egui::ScrollArea::vertical()
+                    .max_height(320.0)
+                    .show(ui, |ui| {
+                        for (i, entry) in matches.iter().enumerate() {
+                            let response = ui
+                                .horizontal(|ui| {
+                                    let response =
+                                        ui.selectable_label(i == self.selected, &entry.name);
+                                    if let Some(shortcut) = &entry.shortcut {
+                                        ui.with_layout(
+                                            egui::Layout::right_to_left(egui::Align::Center),
+                                            |ui| ui.weak(shortcut),
+                                        );
+                                    }
+                                    response
+                                })
+                                .inner;
+
+                            if i == self.selected && (up || down) {
+                                response.scroll_to_me(None);
+                            }
+
+                            if response.clicked() {
+                                run = Some(entry.run.clone());
+                            }
+                        }
+                    });
+            });
+
+        if let Some(run) = run {
+            self.open = false;
+            run(resources);
+        }
+
+        true
+    }
+}
+
+impl CommandPaletteOverlay {
+    fn entries(ctx: &egui::Context, resources: &Resources) -> Vec<PaletteEntry> {
+        let mut entries: Vec<PaletteEntry> = resources
+            .get::<Commands>()
+            .unwrap()
+            .0
+            .iter()
+            .map(|c| PaletteEntry {
+                name: c.name.clone(),
+                shortcut: c.shortcut.map(|s| ctx.format_shortcut(&s)),
+                run: c.run.clone(),
+            })
+            .collect();
+
+        for map in &resources.get::<RecentMaps>().unwrap().maps {
+            let hash = TagHash(map.hash);
+            entries.push(PaletteEntry {
+                name: format!("Open map: {} ({hash})", map.name),
+                shortcut: None,
+                run: Rc::new(move |r| {
+                    r.get_mut::<MapDataList>().unwrap().switch_or_load(
+                        hash,
+                        &mut r.get_mut::<SelectedEntity>().unwrap(),
+                        &mut r.get_mut::<FpsCamera>().unwrap(),
+                    );
+                }),
+            });
+        }
+
+        entries
+    }
+}
@@ -1,8 +1,11 @@
 use crate::camera::FpsCamera;
 use crate::ecs::components::{EntityModel, Visible};
+use crate::ecs::resources::SelectedEntity;
 use crate::ecs::tags::{insert_tag, EntityTag};
 use crate::ecs::transform::{OriginalTransform, Transform};
+use crate::ecs::{entity_source_tags, persistence, resolve_aabb};
 use crate::entity::{SEntityModel, Unk808072c5, Unk80809c0f};
+use crate::hotkeys;
 use crate::map::MapDataList;
 use crate::overlays::gui::Overlay;
 use crate::packages::package_manager;
@@ -16,6 +19,7 @@ use crate::render::renderer::{Renderer, RendererShared};
 use crate::render::scopes::ScopeRigidModel;
 use crate::resources::Resources;
 use crate::structure::{ExtendedHash, TablePointer};
+use crate::util::exe_relative_path;
 
 use anyhow::Context;
 use binrw::BinReaderExt;
@@ -27,6 +31,7 @@ use lazy_static::lazy_static;
 use ringbuffer::{AllocRingBuffer, RingBuffer};
 use std::fmt::Debug;
 use std::io::{Cursor, Seek, SeekFrom};
+use std::path::PathBuf;
 use std::sync::Arc;
 use tracing::field::{Field, Visit};
 use tracing::{Event, Level, Subscriber};
@@ -221,17 +226,15 @@ fn execute_command(
                 Ok(Vec3::new(x, y, z))
             })();
 
-            match parsed_pos {
-                Ok(new_pos) => {
-                    let mut camera = resources.get_mut::<FpsCamera>().unwrap();
-                    camera.position = new_pos;
-                    info!("Teleported to {} {} {}", new_pos.x, new_pos.y, new_pos.z);
-                }
+            let new_pos = match parsed_pos {
+                Ok(new_pos) => new_pos,
                 Err(e) => {
                     error!("Invalid coordinates: {e}");
+                    return;
                 }
-            }
+            };
 
+            let mut new_ang = None;
             if args.len() >= 5 {
                 let parsed_ang: anyhow::Result<Vec2> = (|| {
                     let x = str::parse(args[3])?;
@@ -241,15 +244,16 @@ fn execute_command(
                 })();
 
                 match parsed_ang {
-                    Ok(new_ang) => {
-                        let mut camera = resources.get_mut::<FpsCamera>().unwrap();
-                        camera.orientation = new_ang;
-                    }
+                    Ok(ang) => new_ang = Some(ang),
                     Err(e) => {
                         error!("Invalid angles: {e}");
                     }
                 }
             }
+
+            let mut camera = resources.get_mut::<FpsCamera>().unwrap();
+            camera.teleport(new_pos, new_ang);
+            info!("Teleported to {} {} {}", new_pos.x, new_pos.y, new_pos.z);
         }
         "goto.raw" => {
             if args.len() != 1 || (args[0].len() != 24 && args[0].len() != 32) {
@@ -273,7 +277,7 @@ fn execute_command(
             );
 
             let mut camera = resources.get_mut::<FpsCamera>().unwrap();
-            camera.position = new_pos;
+            camera.teleport(new_pos, None);
             info!("Teleported to {} {} {}", new_pos.x, new_pos.y, new_pos.z);
         }
         "open.tex" | "open.texture" => {
@@ -338,6 +342,68 @@ fn execute_command(
                 }
             }
         }
+        "goto.tag" | "jump_tag" => {
+            if args.len() != 1 {
+                error!("Missing tag argument, expected 32/64-bit tag");
+                return;
+            }
+
+            let tag = match parse_extended_hash(args[0]) {
+                Ok(o) => o,
+                Err(e) => {
+                    error!("Failed to parse tag: {e}");
+                    return;
+                }
+            };
+
+            let Some(tag32) = tag.hash32() else {
+                error!("Couldn't translate hash64 {tag}");
+                return;
+            };
+
+            let mut maps = resources.get_mut::<MapDataList>().unwrap();
+            // Prefer the current map, then any other loaded map
+            let map_order = std::iter::once(maps.current_map)
+                .chain((0..maps.maps.len()).filter(|&i| i != maps.current_map))
+                .filter(|&i| i < maps.maps.len())
+                .collect_vec();
+
+            let found = map_order.into_iter().find_map(|i| {
+                let (map_hash, _, map) = &maps.maps[i];
+                let entity = map.scene.iter().find_map(|e| {
+                    entity_source_tags(e)
+                        .into_iter()
+                        .any(|(_, t)| t.hash32() == Some(tag32))
+                        .then_some(e.entity())
+                });
+
+                (entity.is_some() || *map_hash == tag32).then_some((i, entity))
+            });
+
+            let Some((map_index, entity)) = found else {
+                error!("Tag {tag} was not found in any of the loaded maps");
+                return;
+            };
+
+            let mut selected = resources.get_mut::<SelectedEntity>().unwrap();
+            let mut camera = resources.get_mut::<FpsCamera>().unwrap();
+            maps.set_current_map(map_index, &mut selected, &mut camera);
+            info!("Jumped to {tag} in map '{}'", maps.maps[map_index].2.name);
+
+            if let Some(ent) = entity {
+                selected.select(Some(ent));
+                if let Ok(e) = maps.maps[map_index].2.scene.entity(ent) {
+                    if let Some(target) = resolve_aabb(e) {
+                        camera.focus_aabb(&target);
+                    } else if let Some(transform) = e.get::<&Transform>() {
+                        camera.focus(transform.translation, 10.0);
+                    }
+                }
+            }
+        }
+        "frame_map" => {
+            hotkeys::frame_map(resources);
+        }
         "clear_map" => {
             if let Some(mut maps) = resources.get_mut::<MapDataList>() {
                 let current_map = maps.current_map;
@@ -577,6 +643,27 @@ fn execute_command(
                 }
             }
         }
+        "export_scene_json" | "dump_scene" => {
+            if let Some(maps) = resources.get::<MapDataList>() {
+                if let Some((_, _, map)) = maps.current_map() {
+                    // Spaces split arguments, so paths containing them have to be joined back together
+                    let path = if args.is_empty() {
+                        exe_relative_path(&format!("scene_{}.json", map.hash))
+                    } else {
+                        PathBuf::from(args.join(" "))
+                    };
+
+                    match persistence::export_scene_json(map, &path) {
+                        Ok(count) => info!("Exported {count} entities to {}", path.display()),
+                        Err(e) => error!("Failed to export scene: {e:?}"),
+                    }
+                }
+            }
+        }
+        persistence::SPAWN_UTILITY_COMMAND => {
+            // Spaces split arguments, so the snippet has to be joined back together
+            hotkeys::spawn_utility(resources, &args.join(" "));
+        }
         "unhide_all" | "show_all" => {
             if let Some(maps) = resources.get::<MapDataList>() {
                 if let Some((_, _, map)) = maps.current_map() {
@@ -618,25 +705,8 @@ fn load_entity_model(
 
         for stage in technique.all_stages() {
             for s in stage.shader.samplers.iter() {
-                let sampler_header_ref = package_manager()
-                    .get_entry(s.hash32().unwrap())
-                    .unwrap()
-                    .reference;
-                let sampler_data = package_manager().read_tag(sampler_header_ref).unwrap();
-
-                let sampler = unsafe {
-                    renderer
-                        .dcs
-                        .device
-                        .CreateSamplerState(sampler_data.as_ptr() as _)
-                };
-
-                if let Ok(sampler) = sampler {
-                    renderer
-                        .render_data
-                        .data_mut()
-                        .samplers
-                        .insert(s.key(), sampler);
+                if let Err(e) = renderer.render_data.load_sampler(*s) {
+                    error!("Failed to load sampler {s:?}: {e}");
                 }
             }
 
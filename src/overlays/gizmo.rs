@@ -0,0 +1,373 @@
+use egui::{Color32, Id, Pos2, Sense, Stroke};
+use glam::{Quat, Vec3};
+use hecs::Entity;
+
+use crate::{
+    camera::FpsCamera,
+    ecs::{
+        components::Mutable,
+        hierarchy,
+        resources::SelectedEntity,
+        transform::{Transform, TransformFlags},
+    },
+    hotkeys::{SHORTCUT_GIZMO_ROTATE, SHORTCUT_GIZMO_SCALE, SHORTCUT_GIZMO_TRANSLATE},
+    icons::{ICON_AXIS_ARROW, ICON_RESIZE, ICON_ROTATE_ORBIT},
+    map::MapDataList,
+    resources::Resources,
+};
+
+use super::gui::Overlay;
+
+/// Length of the gizmo axes relative to the distance from the camera, keeps the gizmo roughly the same size on screen
+const GIZMO_SCALE: f32 = 0.15;
+const HANDLE_SIZE: f32 = 14.0;
+const RING_SEGMENTS: usize = 48;
+
+const GIZMO_AXES: [(Vec3, Color32); 3] = [
+    (Vec3::X, Color32::from_rgb(230, 60, 60)),
+    (Vec3::Y, Color32::from_rgb(60, 200, 60)),
+    (Vec3::Z, Color32::from_rgb(60, 110, 240)),
+];
+const HIGHLIGHT_COLOR: Color32 = Color32::from_rgb(255, 220, 60);
+
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum GizmoMode {
+    Translate,
+    Rotate,
+    Scale,
+}
+
+impl GizmoMode {
+    fn is_enabled(&self, flags: TransformFlags) -> bool {
+        match self {
+            GizmoMode::Translate => !flags.contains(TransformFlags::IGNORE_TRANSLATION),
+            GizmoMode::Rotate => !flags.contains(TransformFlags::IGNORE_ROTATION),
+            GizmoMode::Scale => !flags.contains(TransformFlags::IGNORE_SCALE),
+        }
+    }
+}
+
+pub struct TransformGizmoOverlay {
+    pub mode: GizmoMode,
+}
+
+impl Default for TransformGizmoOverlay {
+    fn default() -> Self {
+        Self {
+            mode: GizmoMode::Translate,
+        }
+    }
+}
+
+impl Overlay for TransformGizmoOverlay {
+    fn draw(
+        &mut self,
+        ctx: &egui::Context,
+        _window: &winit::window::Window,
+        resources: &mut Resources,
+        _gui: &mut super::gui::GuiContext<'_>,
+    ) -> bool {
+        let selection = resources.get::<SelectedEntity>().unwrap().clone();
+        let Some(selected) = selection.0 else {
+            return true;
+        };
+
+        let camera = resources.get::<FpsCamera>().unwrap();
+        let mut maps = resources.get_mut::<MapDataList>().unwrap();
+        let Some(map) = maps.current_map_mut() else {
+            return true;
+        };
+
+        if !ctx.input(|i| i.pointer.any_down()) {
+            map.history.finish_transform_edit();
+        }
+
+        // With multiple entities selected, the gizmo moves all of the mutable ones around their centroid
+        let group: Vec<Entity> = if selection.is_multiple() {
+            selection
+                .iter()
+                .filter(|&e| {
+                    map.scene
+                        .entity(e)
+                        .map_or(false, |e| e.has::<Mutable>() && e.has::<Transform>())
+                })
+                .collect()
+        } else {
+            vec![]
+        };
+
+        let transform_before = if selection.is_multiple() {
+            if group.is_empty() {
+                return true;
+            }
+
+            let centroid = group
+                .iter()
+                .filter_map(|&e| map.scene.get::<&Transform>(e).ok().map(|t| t.translation))
+                .sum::<Vec3>()
+                / group.len() as f32;
+
+            // The pivot is oriented along the world axes, scaling a group isn't supported
+            Transform {
+                translation: centroid,
+                flags: TransformFlags::IGNORE_SCALE,
+                ..Default::default()
+            }
+        } else {
+            // Only user entities can be edited, their changes are recorded in the undo history
+            if !map
+                .scene
+                .entity(selected)
+                .map_or(false, |e| e.has::<Mutable>())
+            {
+                return true;
+            }
+
+            let Ok(transform) = map.scene.get::<&Transform>(selected) else {
+                return true;
+            };
+            *transform
+        };
+        let mut transform = transform_before;
+
+        // Don't switch modes while typing numbers into a text field
+        if !ctx.wants_keyboard_input() {
+            if ctx.input_mut(|i| i.consume_shortcut(&SHORTCUT_GIZMO_TRANSLATE)) {
+                self.mode = GizmoMode::Translate;
+            }
+            if ctx.input_mut(|i| i.consume_shortcut(&SHORTCUT_GIZMO_ROTATE)) {
+                self.mode = GizmoMode::Rotate;
+            }
+            if ctx.input_mut(|i| i.consume_shortcut(&SHORTCUT_GIZMO_SCALE)) {
+                self.mode = GizmoMode::Scale;
+            }
+        }
+
+        egui::Area::new("transform_gizmo_modes")
+            .anchor(egui::Align2::CENTER_TOP, [0.0, 32.0])
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        for (mode, icon, name, shortcut) in [
+                            (
+                                GizmoMode::Translate,
+                                ICON_AXIS_ARROW,
+                                "Translate",
+                                SHORTCUT_GIZMO_TRANSLATE,
+                            ),
+                            (
+                                GizmoMode::Rotate,
+                                ICON_ROTATE_ORBIT,
+                                "Rotate",
+                                SHORTCUT_GIZMO_ROTATE,
+                            ),
+                            (GizmoMode::Scale, ICON_RESIZE, "Scale", SHORTCUT_GIZMO_SCALE),
+                        ] {
+                            ui.add_enabled_ui(mode.is_enabled(transform.flags), |ui| {
+                                ui.selectable_value(&mut self.mode, mode, icon.to_string())
+                                    .on_hover_text(format!(
+                                        "{name} ({})",
+                                        ctx.format_shortcut(&shortcut)
+                                    ));
+                            });
+                        }
+                    });
+                });
+            });
+
+        if !self.mode.is_enabled(transform.flags) || !camera.is_point_visible(transform.translation)
+        {
+            return true;
+        }
+
+        let screen_size = ctx.screen_rect().size();
+        let project = |point: Vec3| {
+            let projected_point = camera.projection_view_matrix.project_point3(point);
+            Pos2::new(
+                ((projected_point.x + 1.0) * 0.5) * screen_size.x,
+                ((1.0 - projected_point.y) * 0.5) * screen_size.y,
+            )
+        };
+
+        let origin = transform.translation;
+        let length = camera.position.distance(origin) * GIZMO_SCALE;
+        let orientation = if transform.flags.contains(TransformFlags::IGNORE_ROTATION) {
+            Quat::IDENTITY
+        } else {
+            transform.rotation
+        };
+
+        let painter = ctx.layer_painter(egui::LayerId::background());
+        let origin_screen = project(origin);
+        for (i, (axis_local, color)) in GIZMO_AXES.into_iter().enumerate() {
+            let axis = orientation * axis_local;
+
+            let handle = match self.mode {
+                GizmoMode::Translate | GizmoMode::Scale => origin + axis * length,
+                GizmoMode::Rotate => {
+                    // Put the handle on the part of the ring closest to the camera
+                    let to_camera = camera.position - origin;
+                    let on_plane = to_camera - axis * to_camera.dot(axis);
+                    let direction = if on_plane.length_squared() > 0.0001 {
+                        on_plane.normalize()
+                    } else {
+                        axis.any_orthonormal_vector()
+                    };
+
+                    origin + direction * length
+                }
+            };
+
+            if !camera.is_point_visible(handle) {
+                continue;
+            }
+
+            let handle_screen = project(handle);
+            let response = egui::Area::new(Id::new("transform_gizmo_handle").with(i))
+                .fixed_pos(handle_screen - egui::Vec2::splat(HANDLE_SIZE / 2.0))
+                .order(egui::Order::Background)
+                .show(ctx, |ui| {
+                    ui.allocate_exact_size(egui::Vec2::splat(HANDLE_SIZE), Sense::drag())
+                        .1
+                })
+                .inner;
+
+            let color = if response.hovered() || response.dragged() {
+                HIGHLIGHT_COLOR
+            } else {
+                color
+            };
+
+            match self.mode {
+                GizmoMode::Translate => {
+                    painter.line_segment([origin_screen, handle_screen], Stroke::new(2.5, color));
+                    painter.circle_filled(handle_screen, HANDLE_SIZE / 2.0, color);
+                }
+                GizmoMode::Rotate => {
+                    let (u, v) = axis.any_orthonormal_pair();
+                    let ring = (0..=RING_SEGMENTS)
+                        .map(|s| {
+                            let t = s as f32 / RING_SEGMENTS as f32 * std::f32::consts::TAU;
+                            origin + (u * t.cos() + v * t.sin()) * length
+                        })
+                        .filter(|p| camera.is_point_visible(*p))
+                        .map(project)
+                        .collect();
+                    painter.add(egui::Shape::line(ring, Stroke::new(2.0, color)));
+                    painter.circle_filled(handle_screen, HANDLE_SIZE / 2.0, color);
+                }
+                GizmoMode::Scale => {
+                    painter.line_segment([origin_screen, handle_screen], Stroke::new(2.5, color));
+                    painter.rect_filled(
+                        egui::Rect::from_center_size(handle_screen, egui::Vec2::splat(HANDLE_SIZE)),
+                        0.0,
+                        color,
+                    );
+                }
+            }
+
+            if !response.dragged() {
+                continue;
+            }
+
+            let drag = response.drag_delta();
+            match self.mode {
+                GizmoMode::Translate => {
+                    // Project the mouse movement onto the axis on screen, and scale it back to world units
+                    let axis_screen = handle_screen - origin_screen;
+                    if axis_screen.length_sq() > 1.0 {
+                        let delta = drag.dot(axis_screen) / axis_screen.length_sq();
+                        transform.translation += axis * delta * length;
+                    }
+                }
+                GizmoMode::Rotate => {
+                    if let Some(pointer) = response.interact_pointer_pos() {
+                        let current = pointer - origin_screen;
+                        let previous = current - drag;
+                        // Screen space Y points down, so the winding is flipped when the axis faces the camera
+                        let cross = previous.x * current.y - previous.y * current.x;
+                        let screen_angle = cross.atan2(previous.dot(current));
+                        let facing = axis.dot(camera.position - origin).signum();
+
+                        transform.rotation = (Quat::from_axis_angle(axis, -screen_angle * facing)
+                            * transform.rotation)
+                            .normalize();
+                    }
+                }
+                GizmoMode::Scale => {
+                    let axis_screen = handle_screen - origin_screen;
+                    if axis_screen.length_sq() > 1.0 {
+                        let factor = 1.0 + drag.dot(axis_screen) / axis_screen.length_sq();
+                        if transform.flags.contains(TransformFlags::SCALE_IS_RADIUS) {
+                            // Radius can only be scaled uniformly
+                            transform.scale = Vec3::splat((transform.scale.x * factor).max(0.01));
+                        } else {
+                            let scale = transform.scale[i];
+                            transform.scale[i] = (scale * factor).max(0.01);
+                        }
+                    }
+                }
+            }
+        }
+
+        if transform == transform_before {
+            return true;
+        }
+        map.bounds.invalidate();
+
+        // Children follow their parent around, unless they're selected themselves
+        let moved = if group.is_empty() {
+            vec![selected]
+        } else {
+            group.clone()
+        };
+        let mut followers: Vec<Entity> = vec![];
+        for &e in &moved {
+            for child in hierarchy::descendants(&map.scene, e) {
+                let mutable = map
+                    .scene
+                    .entity(child)
+                    .map_or(false, |e| e.has::<Mutable>());
+                if mutable && !moved.contains(&child) && !followers.contains(&child) {
+                    followers.push(child);
+                }
+            }
+        }
+
+        let mut edits = vec![];
+        if group.is_empty() {
+            if let Ok(mut t) = map.scene.get::<&mut Transform>(selected) {
+                *t = transform;
+            }
+
+            if followers.is_empty() {
+                map.history
+                    .record_transform(selected, transform_before, transform);
+                return true;
+            }
+
+            edits.push((selected, transform_before, transform));
+        }
+
+        let rotation = transform.rotation * transform_before.rotation.inverse();
+        for &e in group.iter().chain(&followers) {
+            let Ok(mut t) = map.scene.get::<&mut Transform>(e) else {
+                continue;
+            };
+
+            let old = *t;
+            if !t.flags.contains(TransformFlags::IGNORE_TRANSLATION) {
+                t.translation = transform.translation
+                    + rotation * (t.translation - transform_before.translation);
+            }
+            if !t.flags.contains(TransformFlags::IGNORE_ROTATION) {
+                t.rotation = (rotation * t.rotation).normalize();
+            }
+            edits.push((e, old, *t));
+        }
+
+        map.history.record_group_transform(edits);
+
+        true
+    }
+}
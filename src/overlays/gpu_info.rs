@@ -0,0 +1,123 @@
+use egui::RichText;
+use winit::window::Window;
+
+use crate::{
+    config,
+    render::dcs::{feature_level_name, AdapterInfo, DcsShared},
+    resources::Resources,
+    util::text::prettify_bytes,
+};
+
+use super::gui::Overlay;
+
+/// Shows the GPU the device was created on, and lets the user pick the adapter to use on the next start
+#[derive(Default)]
+pub struct GpuInfoWindow;
+
+impl Overlay for GpuInfoWindow {
+    fn draw(
+        &mut self,
+        ctx: &egui::Context,
+        _window: &Window,
+        resources: &mut Resources,
+        _gui: &mut super::gui::GuiContext<'_>,
+    ) -> bool {
+        let dcs = resources.get::<DcsShared>().unwrap().clone();
+
+        let mut open = true;
+        egui::Window::new("GPU Info")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("gpu_info_grid")
+                    .striped(true)
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        adapter_rows(ui, &dcs.adapter);
+
+                        ui.label("Feature level");
+                        ui.label(feature_level_name(dcs.feature_level));
+                        ui.end_row();
+                    });
+
+                ui.separator();
+
+                let mut preferred = config::with(|c| c.gpu.adapter.clone());
+                let previous = preferred.clone();
+                egui::ComboBox::from_label("Adapter")
+                    .selected_text(preferred.as_deref().unwrap_or("System default"))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut preferred, None, "System default");
+                        for adapter in &dcs.adapters {
+                            ui.selectable_value(
+                                &mut preferred,
+                                Some(adapter.name.clone()),
+                                format!(
+                                    "{} ({})",
+                                    adapter.name,
+                                    prettify_bytes(adapter.dedicated_video_memory)
+                                ),
+                            )
+                            .on_hover_ui(|ui| {
+                                egui::Grid::new("gpu_info_adapter_grid")
+                                    .num_columns(2)
+                                    .show(ui, |ui| adapter_rows(ui, adapter));
+                            });
+                        }
+                    });
+
+                if preferred != previous {
+                    config::with_mut(|c| c.gpu.adapter = preferred.clone());
+                }
+
+                // The system default is always the first adapter
+                let active = dcs.adapters.iter().position(|a| a.name == dcs.adapter.name);
+                let wanted = match &preferred {
+                    Some(name) => dcs.adapters.iter().position(|a| &a.name == name),
+                    None => Some(0),
+                };
+                if wanted.is_some() && wanted != active {
+                    ui.label(
+                        RichText::new("Restart Alkahest to switch to the selected adapter")
+                            .color(egui::Color32::YELLOW),
+                    );
+                } else if wanted.is_none() {
+                    ui.label(
+                        RichText::new("The selected adapter is not available, the default adapter is used instead")
+                            .color(egui::Color32::YELLOW),
+                    );
+                }
+            });
+
+        open
+    }
+}
+
+fn adapter_rows(ui: &mut egui::Ui, adapter: &AdapterInfo) {
+    ui.label("Adapter");
+    ui.label(&adapter.name);
+    ui.end_row();
+
+    ui.label("Vendor");
+    ui.label(format!(
+        "{} ({:04x}:{:04x})",
+        adapter.vendor_name(),
+        adapter.vendor_id,
+        adapter.device_id
+    ));
+    ui.end_row();
+
+    ui.label("Dedicated video memory");
+    ui.label(prettify_bytes(adapter.dedicated_video_memory));
+    ui.end_row();
+
+    ui.label("Shared system memory");
+    ui.label(prettify_bytes(adapter.shared_system_memory));
+    ui.end_row();
+
+    if adapter.software {
+        ui.label("Type");
+        ui.label("Software");
+        ui.end_row();
+    }
+}
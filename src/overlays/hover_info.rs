@@ -0,0 +1,98 @@
+use std::time::{Duration, Instant};
+
+use egui::Pos2;
+use hecs::Entity;
+use winit::window::Window;
+
+use crate::{
+    ecs::{entity_source_tags, resolve_entity_name},
+    map::MapDataList,
+    render::renderer::RendererShared,
+    resources::Resources,
+};
+
+use super::gui::Overlay;
+
+/// How long the cursor has to rest before the entity under it is looked up
+const IDLE_DELAY: Duration = Duration::from_millis(400);
+/// While the cursor keeps resting, the lookup is repeated at this interval in case the camera moved
+const REFRESH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Shows the name of the entity under the cursor when the mouse rests over the viewport
+#[derive(Default)]
+pub struct HoverInfoOverlay {
+    last_pointer: Option<Pos2>,
+    last_moved: Option<Instant>,
+    /// Pointer position and time the pick buffer was last read at, and the entity found there
+    picked: Option<(Pos2, Instant, Option<Entity>)>,
+}
+
+impl Overlay for HoverInfoOverlay {
+    fn draw(
+        &mut self,
+        ctx: &egui::Context,
+        window: &Window,
+        resources: &mut Resources,
+        _gui: &mut super::gui::GuiContext<'_>,
+    ) -> bool {
+        let (pointer, any_down) = ctx.input(|i| (i.pointer.hover_pos(), i.pointer.any_down()));
+        if pointer != self.last_pointer {
+            self.last_pointer = pointer;
+            self.last_moved = Some(Instant::now());
+        }
+
+        let Some(pointer) = pointer else {
+            return true;
+        };
+
+        let idle = self.last_moved.map_or(false, |t| t.elapsed() > IDLE_DELAY);
+        if !idle || any_down || ctx.wants_pointer_input() || ctx.is_pointer_over_area() {
+            return true;
+        }
+
+        let maps = resources.get::<MapDataList>().unwrap();
+        let Some((_, _, map)) = maps.current_map() else {
+            return true;
+        };
+
+        // Mapping the pick buffer stalls the CPU, so it's only read occasionally
+        if self.picked.map_or(true, |(pos, t, _)| {
+            pos != pointer || t.elapsed() > REFRESH_INTERVAL
+        }) {
+            let scale = window.scale_factor();
+            let id = resources
+                .get::<RendererShared>()
+                .unwrap()
+                .read()
+                .pick_entity_id(
+                    (pointer.x as f64 * scale).round() as usize,
+                    (pointer.y as f64 * scale).round() as usize,
+                );
+
+            self.picked = Some((
+                pointer,
+                Instant::now(),
+                id.map(|id| map.scene.find_entity_from_id(id)),
+            ));
+        }
+
+        // The pick buffer is a frame behind, so the entity might not exist anymore
+        let Some(Ok(e)) = self
+            .picked
+            .and_then(|(_, _, e)| e)
+            .map(|e| map.scene.entity(e))
+        else {
+            return true;
+        };
+
+        egui::show_tooltip_at_pointer(ctx, egui::Id::new("hover_info_tooltip"), |ui| {
+            ui.label(resolve_entity_name(e, true));
+            for (label, tag) in entity_source_tags(e) {
+                ui.monospace(format!("{label}: {tag}"));
+            }
+            ui.label(egui::RichText::new("Right click to select").weak());
+        });
+
+        true
+    }
+}
@@ -21,11 +21,21 @@ impl Overlay for InspectorOverlay {
 
         if let Some(map) = maps.current_map_mut() {
             egui::Window::new("Inspector").show(ctx, |ui| {
-                if let Some(ent) = resources.get::<SelectedEntity>().unwrap().0 {
+                let selection = resources.get::<SelectedEntity>().unwrap().clone();
+                if let Some(ent) = selection.0 {
+                    if selection.is_multiple() {
+                        ui.label(format!(
+                            "{} entities selected, showing the last selected one",
+                            selection.2.len() + 1
+                        ));
+                        ui.separator();
+                    }
+
                     show_inspector_panel(
                         ui,
                         &mut map.scene,
                         &mut map.command_buffer,
+                        &mut map.history,
                         ent,
                         resources,
                     );
@@ -0,0 +1,125 @@
+use egui::{Color32, RichText};
+use strum::IntoEnumIterator;
+
+use crate::{
+    icons::{ICON_ALERT, ICON_KEYBOARD},
+    input::{InputState, Key},
+    keybinds::{is_modifier_key, Action, KeyBind, KeyBindings},
+    resources::Resources,
+};
+
+use super::gui::Overlay;
+
+#[derive(Default)]
+pub struct KeybindsOverlay {
+    /// Action that is waiting for a new key combination
+    capturing: Option<Action>,
+    /// Modifier key that was pressed while capturing. Bound on its own if it's released without pressing another key
+    pending_modifier: Option<Key>,
+}
+
+impl KeybindsOverlay {
+    fn capture(&mut self, bindings: &mut KeyBindings, input: &mut InputState) {
+        let Some(action) = self.capturing else {
+            return;
+        };
+
+        match input.take_last_pressed() {
+            Some(Key::Escape) => self.stop_capture(),
+            Some(key) if is_modifier_key(key) => self.pending_modifier = Some(key),
+            Some(key) => {
+                bindings.set(
+                    action,
+                    KeyBind {
+                        key,
+                        ctrl: input.ctrl(),
+                        alt: input.alt(),
+                        shift: input.shift(),
+                    },
+                );
+                self.stop_capture();
+            }
+            None => {
+                if let Some(modifier) = self.pending_modifier {
+                    if !input.ctrl() && !input.alt() && !input.shift() {
+                        bindings.set(action, KeyBind::new(modifier));
+                        self.stop_capture();
+                    }
+                }
+            }
+        }
+    }
+
+    fn stop_capture(&mut self) {
+        self.capturing = None;
+        self.pending_modifier = None;
+    }
+}
+
+impl Overlay for KeybindsOverlay {
+    fn draw(
+        &mut self,
+        ctx: &egui::Context,
+        _window: &winit::window::Window,
+        resources: &mut Resources,
+        _gui: &mut super::gui::GuiContext<'_>,
+    ) -> bool {
+        let mut bindings = resources.get_mut::<KeyBindings>().unwrap();
+        let mut input = resources.get_mut::<InputState>().unwrap();
+        self.capture(&mut bindings, &mut input);
+
+        egui::Window::new(format!("{} Key Bindings", ICON_KEYBOARD))
+            .default_open(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("keybinds_grid")
+                    .num_columns(3)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for action in Action::iter() {
+                            ui.label(action.to_string());
+
+                            let text = if self.capturing == Some(action) {
+                                RichText::new("Press a key...").italics()
+                            } else {
+                                RichText::new(bindings.get(action).to_string())
+                            };
+
+                            if ui
+                                .button(text)
+                                .on_hover_text("Click to rebind, Esc to cancel")
+                                .clicked()
+                            {
+                                // Clear any key press that happened before the capture started
+                                input.take_last_pressed();
+                                self.capturing = Some(action);
+                                self.pending_modifier = None;
+                            }
+
+                            let conflicts = bindings.conflicts(action);
+                            if conflicts.is_empty() {
+                                ui.label("");
+                            } else {
+                                ui.label(RichText::new(ICON_ALERT).color(Color32::YELLOW))
+                                    .on_hover_text(format!(
+                                        "Also bound to: {}",
+                                        conflicts
+                                            .iter()
+                                            .map(|a| a.to_string())
+                                            .collect::<Vec<_>>()
+                                            .join(", ")
+                                    ));
+                            }
+                            ui.end_row();
+                        }
+                    });
+
+                ui.separator();
+                if ui.button("Reset to defaults").clicked() {
+                    bindings.reset_all();
+                    self.stop_capture();
+                }
+            });
+
+        true
+    }
+}
@@ -0,0 +1,140 @@
+use egui::RichText;
+use hecs::Entity;
+use winit::window::Window;
+
+use crate::{
+    ecs::{
+        components::{ErrorMarker, ResourcePoint},
+        resources::SelectedEntity,
+    },
+    hotkeys,
+    map::MapDataList,
+    render::asset_errors::{AssetError, AssetKind, ASSET_ERRORS},
+    resources::Resources,
+    util::text::prettify_duration,
+};
+
+use super::gui::Overlay;
+
+/// Lists the assets that failed to load, and locates the entities affected by them
+#[derive(Default)]
+pub struct LoadErrorsWindow {
+    filter: String,
+}
+
+impl Overlay for LoadErrorsWindow {
+    fn draw(
+        &mut self,
+        ctx: &egui::Context,
+        _window: &Window,
+        resources: &mut Resources,
+        _gui: &mut super::gui::GuiContext<'_>,
+    ) -> bool {
+        let errors = ASSET_ERRORS.snapshot();
+        let filter = self.filter.to_lowercase();
+
+        let mut locate = None;
+        let mut open = true;
+        egui::Window::new("Load Errors")
+            .open(&mut open)
+            .default_width(560.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.filter)
+                            .hint_text("Filter by kind, tag or reason"),
+                    );
+                    if ui.button("Clear").clicked() {
+                        ASSET_ERRORS.clear();
+                    }
+                    ui.label(format!("{} errors", errors.len()));
+                });
+
+                ui.separator();
+
+                if errors.is_empty() {
+                    ui.label(RichText::new("Everything loaded successfully").weak());
+                    return;
+                }
+
+                let maps = resources.get::<MapDataList>().unwrap();
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    egui::Grid::new("load_errors_grid")
+                        .striped(true)
+                        .num_columns(4)
+                        .show(ui, |ui| {
+                            ui.strong("Kind");
+                            ui.strong("Tag");
+                            ui.strong("Reason");
+                            ui.strong("");
+                            ui.end_row();
+
+                            // Newest first
+                            for error in errors.iter().rev().filter(|e| matches(e, &filter)) {
+                                ui.label(error.kind.to_string()).on_hover_text(format!(
+                                    "{} ago",
+                                    prettify_duration(error.time.elapsed())
+                                ));
+                                if ui
+                                    .add(
+                                        egui::Label::new(
+                                            RichText::new(error.hash.to_string()).monospace(),
+                                        )
+                                        .sense(egui::Sense::click()),
+                                    )
+                                    .on_hover_text("Click to copy")
+                                    .clicked()
+                                {
+                                    ui.output_mut(|o| o.copied_text = error.hash.to_string());
+                                }
+                                ui.label(&error.reason);
+
+                                let entity = maps
+                                    .current_map()
+                                    .and_then(|(_, _, map)| affected_entity(&map.scene, error));
+                                if ui
+                                    .add_enabled(entity.is_some(), egui::Button::new("Locate"))
+                                    .on_disabled_hover_text(
+                                        "No entity in the current map uses this asset",
+                                    )
+                                    .clicked()
+                                {
+                                    locate = entity;
+                                }
+                                ui.end_row();
+                            }
+                        });
+                });
+            });
+
+        if let Some(e) = locate {
+            resources
+                .get_mut::<SelectedEntity>()
+                .unwrap()
+                .select(Some(e));
+            hotkeys::frame_selected(resources);
+        }
+
+        open
+    }
+}
+
+fn matches(error: &AssetError, filter: &str) -> bool {
+    filter.is_empty()
+        || error.kind.to_string().to_lowercase().contains(filter)
+        || error.hash.to_string().to_lowercase().contains(filter)
+        || error.reason.to_lowercase().contains(filter)
+}
+
+/// Failed entities are kept in the scene as error geometry, other assets aren't tied to a single entity
+fn affected_entity(scene: &crate::ecs::Scene, error: &AssetError) -> Option<Entity> {
+    if error.kind != AssetKind::Entity {
+        return None;
+    }
+
+    scene
+        .query::<(&ResourcePoint, &ErrorMarker)>()
+        .iter()
+        .find(|(_, (rp, _))| rp.entity_key() == error.hash.key())
+        .map(|(e, _)| e)
+}
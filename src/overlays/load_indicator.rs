@@ -2,7 +2,7 @@ use egui::{Color32, RichText};
 use winit::window::Window;
 
 use crate::{
-    render::resource_mt::{self, LoadingThreadState},
+    render::resource_mt::{self, LoadingProgress, LoadingThreadState},
     resources::Resources,
 };
 
@@ -35,6 +35,42 @@ const SPINNER_FRAMES: &[&str] = &[
     "\u{F1456}", // ICON_CLOCK_TIME_TWELVE_OUTLINE
 ];
 const SPINNER_INTERVAL: usize = 50;
+
+impl LoadIndicatorOverlay {
+    fn draw_status(
+        ui: &mut egui::Ui,
+        kind: &str,
+        status: &LoadingThreadState,
+        progress: LoadingProgress,
+    ) {
+        if let LoadingThreadState::Loading {
+            start_time,
+            remaining,
+        } = *status
+        {
+            let time_millis = start_time.elapsed().as_millis() as usize;
+            ui.label(
+                RichText::new(format!(
+                    "{} Loading {} {kind} ({:.1}s)",
+                    SPINNER_FRAMES[(time_millis / SPINNER_INTERVAL) % SPINNER_FRAMES.len()],
+                    remaining,
+                    start_time.elapsed().as_secs_f32()
+                ))
+                .size(18.0)
+                .color(Color32::WHITE),
+            );
+
+            let bar_text = if let Some(current) = progress.current {
+                format!("{}/{} ({current})", progress.completed, progress.total())
+            } else {
+                format!("{}/{}", progress.completed, progress.total())
+            };
+            let bar = egui::ProgressBar::new(progress.fraction()).text(bar_text);
+            ui.add(bar);
+        }
+    }
+}
+
 impl Overlay for LoadIndicatorOverlay {
     fn draw(
         &mut self,
@@ -52,43 +88,18 @@ impl Overlay for LoadIndicatorOverlay {
                 .anchor(egui::Align2::RIGHT_TOP, [-12.0, 12.0])
                 .title_bar(false)
                 .show(ctx, |ui| {
-                    if let LoadingThreadState::Loading {
-                        start_time,
-                        remaining,
-                    } = *resource_mt::STATUS_TEXTURES.read()
-                    {
-                        let time_millis = start_time.elapsed().as_millis() as usize;
-                        ui.label(
-                            RichText::new(format!(
-                                "{} Loading {} textures ({:.1}s)",
-                                SPINNER_FRAMES
-                                    [(time_millis / SPINNER_INTERVAL) % SPINNER_FRAMES.len()],
-                                remaining,
-                                start_time.elapsed().as_secs_f32()
-                            ))
-                            .size(18.0)
-                            .color(Color32::WHITE),
-                        );
-                    }
-
-                    if let LoadingThreadState::Loading {
-                        start_time,
-                        remaining,
-                    } = *resource_mt::STATUS_BUFFERS.read()
-                    {
-                        let time_millis = start_time.elapsed().as_millis() as usize;
-                        ui.label(
-                            RichText::new(format!(
-                                "{} Loading {} buffers ({:.1}s)",
-                                SPINNER_FRAMES
-                                    [(time_millis / SPINNER_INTERVAL) % SPINNER_FRAMES.len()],
-                                remaining,
-                                start_time.elapsed().as_secs_f32()
-                            ))
-                            .size(18.0)
-                            .color(Color32::WHITE),
-                        );
-                    }
+                    Self::draw_status(
+                        ui,
+                        "textures",
+                        &resource_mt::STATUS_TEXTURES.read(),
+                        resource_mt::PROGRESS_TEXTURES.snapshot(),
+                    );
+                    Self::draw_status(
+                        ui,
+                        "buffers",
+                        &resource_mt::STATUS_BUFFERS.read(),
+                        resource_mt::PROGRESS_BUFFERS.snapshot(),
+                    );
 
                     self.window_rect = ctx.used_rect();
                 });
@@ -0,0 +1,227 @@
+use destiny_pkg::TagHash;
+use egui::{Color32, RichText};
+use winit::window::Window;
+
+use crate::{
+    ecs::resources::SelectedEntity,
+    render::{
+        data::RenderData,
+        dcs::DcsShared,
+        drawcall::{SortValue3d, Transparency},
+        renderer::RendererShared,
+        settings::RenderSettings,
+    },
+    resources::Resources,
+    structure::ExtendedHash,
+    technique::{Technique, TechniqueStage},
+};
+
+use super::{
+    gui::{Overlay, ViewerWindows},
+    technique_viewer::TechniqueViewer,
+};
+
+/// Shows the techniques used by the selected entity, with the shaders and textures they bind and the state they're drawn with
+pub struct MaterialInfoOverlay;
+
+impl Overlay for MaterialInfoOverlay {
+    fn draw(
+        &mut self,
+        ctx: &egui::Context,
+        _window: &Window,
+        resources: &mut Resources,
+        gui: &mut super::gui::GuiContext<'_>,
+    ) -> bool {
+        let render_settings = resources.get::<RenderSettings>().unwrap().clone();
+        if !render_settings.show_material_info {
+            return true;
+        }
+
+        let Some(selected) = resources.get::<SelectedEntity>().unwrap().0 else {
+            return true;
+        };
+
+        let mut open_technique = None;
+        {
+            let renderer = resources.get::<RendererShared>().unwrap();
+            let renderer = renderer.read();
+            let states = renderer.entity_draw_states(selected);
+            let render_data = renderer.render_data.data();
+
+            egui::Window::new("Material Info").show(ctx, |ui| {
+                if states.is_empty() {
+                    ui.label("The selected entity doesn't have any visible materials");
+                    return;
+                }
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for (material, sort) in states {
+                        egui::CollapsingHeader::new(format!("Technique {material}"))
+                            .default_open(true)
+                            .show(ui, |ui| {
+                                let Some(technique) = render_data.techniques.get(&material) else {
+                                    ui.colored_label(Color32::RED, "Technique is not loaded");
+                                    return;
+                                };
+
+                                if ui.button("Open in technique viewer").clicked() {
+                                    open_technique = Some(material);
+                                }
+
+                                state_ui(ui, material, technique, &sort, &render_settings);
+                                for (name, stage) in [
+                                    ("Vertex", &technique.stage_vertex),
+                                    ("Pixel", &technique.stage_pixel),
+                                ] {
+                                    stage_ui(ui, material, name, stage, &render_data);
+                                }
+                            });
+                    }
+                });
+            });
+        }
+
+        if let Some(tag) = open_technique {
+            if let Some(mut viewers) = resources.get_mut::<ViewerWindows>() {
+                let dcs = resources.get::<DcsShared>().unwrap();
+                let tag = ExtendedHash::Hash32(tag);
+                // TODO: Focus window if already open
+                if !viewers.0.contains_key(&tag.to_string()) {
+                    match TechniqueViewer::new(tag, dcs.clone(), gui) {
+                        Ok(o) => {
+                            viewers
+                                .0
+                                .entry(tag.to_string())
+                                .or_insert_with(|| Box::new(o));
+                        }
+                        Err(e) => {
+                            error!("Failed to load material {tag}: {e}");
+                        }
+                    }
+                }
+            }
+        }
+
+        true
+    }
+}
+
+fn state_ui(
+    ui: &mut egui::Ui,
+    material: TagHash,
+    technique: &Technique,
+    sort: &SortValue3d,
+    render_settings: &RenderSettings,
+) {
+    egui::CollapsingHeader::new("Render state")
+        .id_source((material, "state"))
+        .default_open(true)
+        .show(ui, |ui| {
+            egui::Grid::new((material, "state_grid"))
+                .num_columns(2)
+                .show(ui, |ui| {
+                    ui.label("Geometry");
+                    ui.monospace(format!("{:?}", sort.geometry_type()));
+                    ui.end_row();
+
+                    ui.label("Shading");
+                    ui.monospace(format!("{:?}", sort.shading_mode()));
+                    ui.end_row();
+
+                    ui.label("Transparency");
+                    ui.monospace(format!("{:?}", sort.transparency()));
+                    ui.end_row();
+
+                    // Mirrors the blend state selection in the forward pass of the renderer
+                    let blend = if !render_settings.alpha_blending {
+                        "Opaque (color blending disabled)"
+                    } else {
+                        match (render_settings.blend_override, sort.transparency()) {
+                            (1, _) => "Alpha blend (overridden)",
+                            (2, _) => "Additive (overridden)",
+                            (3, _) => "Discarded (overridden)",
+                            (4, _) => "Multiply (overridden)",
+                            (5, _) => "Screen (overridden)",
+                            (6, _) => "Premultiplied alpha (overridden)",
+                            (_, Transparency::Blend) => "Alpha blend",
+                            (_, Transparency::Additive) => "Additive",
+                            (_, Transparency::Cutout) => "Opaque, alpha tested",
+                            (_, Transparency::None) => "Opaque",
+                        }
+                    };
+                    ui.label("Blend state");
+                    ui.monospace(blend);
+                    ui.end_row();
+
+                    ui.label("Depth writes");
+                    ui.monospace(if sort.transparency().writes_depth() {
+                        "Enabled"
+                    } else {
+                        "Read-only"
+                    });
+                    ui.end_row();
+
+                    ui.label("Culling");
+                    ui.monospace(if technique.unkc != 0 { "None" } else { "Back" });
+                    ui.end_row();
+
+                    ui.label("Flags");
+                    ui.monospace(format!(
+                        "unk8={} unkc={} unk20={:#06x}",
+                        technique.unk8, technique.unkc, technique.unk20
+                    ));
+                    ui.end_row();
+                });
+
+            if technique.unk8 != 1 || (technique.unk20 & 0x8000) != 0 {
+                ui.colored_label(
+                    Color32::YELLOW,
+                    "The renderer skips this technique because of its flags",
+                );
+            }
+        });
+}
+
+fn stage_ui(
+    ui: &mut egui::Ui,
+    material: TagHash,
+    name: &str,
+    stage: &TechniqueStage,
+    render_data: &RenderData,
+) {
+    let shader = &stage.shader;
+    egui::CollapsingHeader::new(format!("{name} shader {}", shader.shader))
+        .id_source((material, name))
+        .show(ui, |ui| {
+            if !shader.shader.is_some() {
+                ui.label("No shader bound");
+            }
+
+            if shader.constant_buffer.is_some() {
+                ui.label(format!(
+                    "Constant buffer {} (cb{})",
+                    shader.constant_buffer, shader.constant_buffer_slot
+                ));
+            }
+
+            ui.label(RichText::new(format!("Textures ({})", shader.textures.len())).strong());
+            for assignment in shader.textures.iter() {
+                let loaded = render_data.textures.contains_key(&assignment.texture.key());
+                let text = RichText::new(format!("t{:<2} {}", assignment.slot, assignment.texture))
+                    .monospace();
+
+                if loaded {
+                    ui.label(text);
+                } else {
+                    ui.label(text.color(Color32::RED)).on_hover_text(
+                        "Texture isn't loaded, the fallback texture is bound instead",
+                    );
+                }
+            }
+
+            ui.label(RichText::new(format!("Samplers ({})", shader.samplers.len())).strong());
+            for (i, sampler) in shader.samplers.iter().enumerate() {
+                ui.label(RichText::new(format!("s{:<2} {sampler}", 1 + i)).monospace());
+            }
+        });
+}
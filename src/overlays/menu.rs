@@ -1,18 +1,45 @@
+use std::sync::Arc;
+
+use destiny_pkg::TagHash;
 use glam::Vec3;
+use hecs::Entity;
 
 use crate::{
+    benchmark::{BenchmarkOptions, BenchmarkRequest, DEFAULT_SPEED},
     camera::FpsCamera,
+    config::{AutosaveConfig, RecentMaps},
     ecs::{
-        components::{Beacon, Mutable, Ruler, Sphere},
+        components::{Beacon, Mutable, Note, ReferenceMesh, Route, RouteNode, Ruler, Sphere},
+        persistence::{self, AutosaveState},
         resources::SelectedEntity,
         tags::{EntityTag, Tags},
         transform::{Transform, TransformFlags},
     },
-    icons::{ICON_RULER_SQUARE, ICON_SIGN_POLE, ICON_SPHERE},
-    map::MapDataList,
+    hotkeys::{
+        self, SHORTCUT_GROUP, SHORTCUT_HIDE_UNSELECTED, SHORTCUT_REDO, SHORTCUT_UNDO,
+        SHORTCUT_UNGROUP, SHORTCUT_UNHIDE_ALL,
+    },
+    icons::{
+        ICON_ALERT, ICON_CHART_BAR, ICON_CONTENT_COPY, ICON_CONTENT_SAVE, ICON_CONTENT_SAVE_COG,
+        ICON_CONTENT_SAVE_EDIT, ICON_DELETE, ICON_EXPANSION_CARD, ICON_EYE, ICON_EYE_OFF,
+        ICON_FIT_TO_SCREEN, ICON_FOLDER_OPEN, ICON_GROUP, ICON_HISTORY, ICON_MAP_MARKER_PATH,
+        ICON_NOTE_TEXT, ICON_REDO, ICON_RELOAD, ICON_RULER_SQUARE, ICON_SIGN_POLE, ICON_SPHERE,
+        ICON_TIMER_OUTLINE, ICON_UNDO, ICON_UNGROUP, ICON_VECTOR_POLYGON,
+    },
+    map::{MapData, MapDataList},
+    render::{
+        asset_errors::ASSET_ERRORS, reference_mesh::ReferenceMeshData, renderer::RendererShared,
+        DeviceContextSwapchain,
+    },
+    util::{error::ErrorAlert, text::prettify_duration},
 };
 
-use super::gui::Overlay;
+use super::{
+    gpu_info::GpuInfoWindow,
+    gui::{Overlay, ViewerWindows},
+    load_errors::LoadErrorsWindow,
+    scene_stats::SceneStatsWindow,
+};
 
 pub struct MenuBar;
 
@@ -26,6 +53,131 @@ impl Overlay for MenuBar {
     ) -> bool {
         egui::TopBottomPanel::top("menu_bar").show(ctx, |ui| {
             egui::menu::bar(ui, |ui| {
+                ui.menu_button("File", |ui| {
+                    ui.menu_button(format!("{} Recent maps", ICON_HISTORY), |ui| {
+                        recent_maps_menu(ui, resources);
+                    });
+                });
+
+                ui.menu_button("Edit", |ui| {
+                    let (can_undo, can_redo) = resources
+                        .get::<MapDataList>()
+                        .unwrap()
+                        .current_map()
+                        .map_or((false, false), |(_, _, map)| {
+                            (map.history.can_undo(), map.history.can_redo())
+                        });
+
+                    if ui
+                        .add_enabled(
+                            can_undo,
+                            egui::Button::new(format!("{} Undo", ICON_UNDO))
+                                .shortcut_text(ui.ctx().format_shortcut(&SHORTCUT_UNDO)),
+                        )
+                        .clicked()
+                    {
+                        hotkeys::undo(resources);
+                        ui.close_menu();
+                    }
+
+                    if ui
+                        .add_enabled(
+                            can_redo,
+                            egui::Button::new(format!("{} Redo", ICON_REDO))
+                                .shortcut_text(ui.ctx().format_shortcut(&SHORTCUT_REDO)),
+                        )
+                        .clicked()
+                    {
+                        hotkeys::redo(resources);
+                        ui.close_menu();
+                    }
+
+                    ui.separator();
+
+                    let has_selection = resources.get::<SelectedEntity>().unwrap().0.is_some();
+                    if ui
+                        .add_enabled(
+                            has_selection,
+                            egui::Button::new(format!("{} Solo selected", ICON_EYE_OFF))
+                                .shortcut_text(ui.ctx().format_shortcut(&SHORTCUT_HIDE_UNSELECTED)),
+                        )
+                        .clicked()
+                    {
+                        hotkeys::hide_unselected(resources);
+                        ui.close_menu();
+                    }
+
+                    if ui
+                        .add(
+                            egui::Button::new(format!("{} Unhide all", ICON_EYE))
+                                .shortcut_text(ui.ctx().format_shortcut(&SHORTCUT_UNHIDE_ALL)),
+                        )
+                        .clicked()
+                    {
+                        hotkeys::unhide_all(resources);
+                        ui.close_menu();
+                    }
+
+                    ui.separator();
+
+                    if ui
+                        .add_enabled(
+                            has_selection,
+                            egui::Button::new(format!("{} Group", ICON_GROUP))
+                                .shortcut_text(ui.ctx().format_shortcut(&SHORTCUT_GROUP)),
+                        )
+                        .clicked()
+                    {
+                        hotkeys::group_selected(resources);
+                        ui.close_menu();
+                    }
+
+                    if ui
+                        .add_enabled(
+                            has_selection,
+                            egui::Button::new(format!("{} Ungroup", ICON_UNGROUP))
+                                .shortcut_text(ui.ctx().format_shortcut(&SHORTCUT_UNGROUP)),
+                        )
+                        .clicked()
+                    {
+                        hotkeys::ungroup_selected(resources);
+                        ui.close_menu();
+                    }
+
+                    let spawn_command = resources
+                        .get::<SelectedEntity>()
+                        .unwrap()
+                        .0
+                        .and_then(|selected| {
+                            let maps = resources.get::<MapDataList>().unwrap();
+                            let (_, _, map) = maps.current_map()?;
+                            persistence::utility_spawn_command(map.scene.entity(selected).ok()?)
+                        });
+                    if ui
+                        .add_enabled(
+                            spawn_command.is_some(),
+                            egui::Button::new(format!("{} Copy spawn command", ICON_CONTENT_COPY)),
+                        )
+                        .on_hover_text("Copies a command that recreates the selected ruler, sphere, beacon or note.\nPaste it into the viewport or the console to spawn it")
+                        .clicked()
+                    {
+                        if let Some(command) = spawn_command {
+                            ui.output_mut(|o| o.copied_text = command);
+                        }
+                        ui.close_menu();
+                    }
+
+                    ui.separator();
+
+                    if ui
+                        .button(format!("{} Frame entire map", ICON_FIT_TO_SCREEN))
+                        .clicked()
+                    {
+                        hotkeys::frame_map(resources);
+                        ui.close_menu();
+                    }
+                });
+
                 ui.menu_button("Utility", |ui| {
                     if ui.button(format!("{} Ruler", ICON_RULER_SQUARE)).clicked() {
                         let mut maps = resources.get_mut::<MapDataList>().unwrap();
@@ -42,9 +194,10 @@ impl Overlay for MenuBar {
                                 Tags::from_iter([EntityTag::Utility]),
                                 Mutable,
                             ));
+                            map.history.record_spawn(e);
 
                             if let Some(mut se) = resources.get_mut::<SelectedEntity>() {
-                                se.0 = Some(e);
+                                se.select(Some(e));
                             }
 
                             ui.close_menu();
@@ -68,9 +221,10 @@ impl Overlay for MenuBar {
                                 Tags::from_iter([EntityTag::Utility]),
                                 Mutable,
                             ));
+                            map.history.record_spawn(e);
 
                             if let Some(mut se) = resources.get_mut::<SelectedEntity>() {
-                                se.0 = Some(e);
+                                se.select(Some(e));
                             }
 
                             ui.close_menu();
@@ -95,14 +249,244 @@ impl Overlay for MenuBar {
                                 Tags::from_iter([EntityTag::Utility]),
                                 Mutable,
                             ));
+                            map.history.record_spawn(e);
+
+                            if let Some(mut se) = resources.get_mut::<SelectedEntity>() {
+                                se.select(Some(e));
+                            }
+
+                            ui.close_menu();
+                        }
+                    }
+                    if ui.button(format!("{} Note", ICON_NOTE_TEXT)).clicked() {
+                        let mut maps = resources.get_mut::<MapDataList>().unwrap();
+
+                        if let Some(map) = maps.current_map_mut() {
+                            let camera = resources.get::<FpsCamera>().unwrap();
+                            let e = map.scene.spawn((
+                                Transform {
+                                    translation: camera.position + camera.front * 5.0,
+                                    flags: TransformFlags::IGNORE_ROTATION
+                                        | TransformFlags::IGNORE_SCALE,
+                                    ..Default::default()
+                                },
+                                Note::default(),
+                                Tags::from_iter([EntityTag::Utility]),
+                                Mutable,
+                            ));
+                            map.history.record_spawn(e);
+
+                            if let Some(mut se) = resources.get_mut::<SelectedEntity>() {
+                                se.select(Some(e));
+                            }
+
+                            ui.close_menu();
+                        }
+                    }
+                    if ui
+                        .button(format!("{} Route", ICON_MAP_MARKER_PATH))
+                        .clicked()
+                    {
+                        let mut maps = resources.get_mut::<MapDataList>().unwrap();
+
+                        if let Some(map) = maps.current_map_mut() {
+                            let camera = resources.get::<FpsCamera>().unwrap();
+                            let e = map.scene.spawn((
+                                Route {
+                                    nodes: vec![RouteNode {
+                                        pos: camera.position,
+                                        label: None,
+                                    }],
+                                    ..Default::default()
+                                },
+                                Tags::from_iter([EntityTag::Utility]),
+                                Mutable,
+                            ));
+                            map.history.record_spawn(e);
 
                             if let Some(mut se) = resources.get_mut::<SelectedEntity>() {
-                                se.0 = Some(e);
+                                se.select(Some(e));
                             }
 
                             ui.close_menu();
                         }
                     }
+
+                    if ui
+                        .button(format!("{} Reference mesh...", ICON_VECTOR_POLYGON))
+                        .on_hover_text("Imports an OBJ file to compare placement and scale against")
+                        .clicked()
+                    {
+                        ui.close_menu();
+                        let mut maps = resources.get_mut::<MapDataList>().unwrap();
+                        if let Some(map) = maps.current_map_mut() {
+                            let camera = resources.get::<FpsCamera>().unwrap();
+                            let renderer = resources.get::<RendererShared>().unwrap();
+                            let dcs = renderer.read().dcs.clone();
+                            if let Some(e) = import_reference_mesh_dialog(
+                                map,
+                                &dcs,
+                                camera.position + camera.front * 15.0,
+                            ) {
+                                if let Some(mut se) = resources.get_mut::<SelectedEntity>() {
+                                    se.select(Some(e));
+                                }
+                            }
+                        }
+                    }
+
+                    ui.separator();
+
+                    if ui
+                        .button(format!("{} Scene statistics", ICON_CHART_BAR))
+                        .on_hover_text("Entity counts and buffer memory per kind of entity")
+                        .clicked()
+                    {
+                        ui.close_menu();
+                        if let Some(mut viewers) = resources.get_mut::<ViewerWindows>() {
+                            viewers
+                                .0
+                                .entry("scene_stats".to_string())
+                                .or_insert_with(|| Box::<SceneStatsWindow>::default());
+                        }
+                    }
+
+                    if ui
+                        .button(format!("{} GPU info", ICON_EXPANSION_CARD))
+                        .on_hover_text("The GPU Alkahest is running on, and which one to use")
+                        .clicked()
+                    {
+                        ui.close_menu();
+                        if let Some(mut viewers) = resources.get_mut::<ViewerWindows>() {
+                            viewers
+                                .0
+                                .entry("gpu_info".to_string())
+                                .or_insert_with(|| Box::<GpuInfoWindow>::default());
+                        }
+                    }
+
+                    if ui
+                        .button(format!("{} Load errors ({})", ICON_ALERT, ASSET_ERRORS.len()))
+                        .on_hover_text("Textures, models and entities that failed to load")
+                        .clicked()
+                    {
+                        ui.close_menu();
+                        if let Some(mut viewers) = resources.get_mut::<ViewerWindows>() {
+                            viewers
+                                .0
+                                .entry("load_errors".to_string())
+                                .or_insert_with(|| Box::<LoadErrorsWindow>::default());
+                        }
+                    }
+
+                    if ui
+                        .button(format!("{} Run benchmark...", ICON_TIMER_OUTLINE))
+                        .on_hover_text("Flies the camera along a route file and logs the frame timings to a CSV")
+                        .clicked()
+                    {
+                        ui.close_menu();
+                        if let Some(options) = benchmark_dialog() {
+                            resources.get_mut::<BenchmarkRequest>().unwrap().0 = Some(options);
+                        }
+                    }
+
+                    let mut maps = resources.get_mut::<MapDataList>().unwrap();
+                    if let Some(map) = maps.current_map_mut() {
+                        if ui
+                            .button(format!("{} Import route...", ICON_MAP_MARKER_PATH))
+                            .clicked()
+                        {
+                            ui.close_menu();
+                            if let Some(e) = import_route_dialog(map) {
+                                if let Some(mut se) = resources.get_mut::<SelectedEntity>() {
+                                    se.select(Some(e));
+                                }
+                            }
+                        }
+
+                        let mut autosave = resources.get_mut::<AutosaveState>().unwrap();
+                        let combined = !map.sources.is_empty();
+                        if ui
+                            .add_enabled(
+                                !combined,
+                                egui::Button::new(format!("{} Save", ICON_CONTENT_SAVE)),
+                            )
+                            .on_disabled_hover_text("Combined maps aren't saved automatically, use Save as instead")
+                            .clicked()
+                        {
+                            persistence::autosave_map(map, &mut autosave);
+                            ui.close_menu();
+                        }
+
+                        if ui
+                            .add_enabled(
+                                !combined,
+                                egui::Button::new(format!("{} Reload", ICON_RELOAD)),
+                            )
+                            .on_disabled_hover_text("Combined maps don't have a save file of their own")
+                            .clicked()
+                        {
+                            persistence::autoload_map(map, &mut autosave);
+                            ui.close_menu();
+                        }
+
+                        ui.menu_button(format!("{} Auto-save", ICON_CONTENT_SAVE_COG), |ui| {
+                            let mut config = resources.get_mut::<AutosaveConfig>().unwrap();
+                            ui.checkbox(&mut config.enabled, "Enabled");
+                            ui.add_enabled_ui(config.enabled, |ui| {
+                                ui.horizontal(|ui| {
+                                    ui.label("Every");
+                                    let mut minutes = config.interval / 60.0;
+                                    if ui
+                                        .add(
+                                            egui::DragValue::new(&mut minutes)
+                                                .speed(0.1)
+                                                .clamp_range(0.5..=60.0)
+                                                .max_decimals(1)
+                                                .suffix(" min"),
+                                        )
+                                        .changed()
+                                    {
+                                        config.interval = minutes * 60.0;
+                                    }
+                                });
+                            });
+                            ui.label(
+                                egui::RichText::new(
+                                    "Maps are also saved when switching maps and on exit. Maps without changes are skipped",
+                                )
+                                .weak(),
+                            );
+                        });
+
+                        ui.label(
+                            egui::RichText::new(match autosave.last_save {
+                                Some(t) => format!(
+                                    "Last saved {} ago",
+                                    prettify_duration(t.elapsed())
+                                ),
+                                None => "Not saved this session".to_string(),
+                            })
+                            .weak(),
+                        );
+                        drop(autosave);
+
+                        if ui
+                            .button(format!("{} Save as...", ICON_CONTENT_SAVE_EDIT))
+                            .clicked()
+                        {
+                            ui.close_menu();
+                            save_user_entities_dialog(map);
+                        }
+
+                        if ui
+                            .button(format!("{} Load from...", ICON_FOLDER_OPEN))
+                            .clicked()
+                        {
+                            ui.close_menu();
+                            load_user_entities_dialog(map);
+                        }
+                    }
                 });
             });
         });
@@ -110,3 +494,131 @@ impl Overlay for MenuBar {
         true
     }
 }
+
+fn recent_maps_menu(ui: &mut egui::Ui, resources: &crate::resources::Resources) {
+    let mut recent = resources.get_mut::<RecentMaps>().unwrap();
+    let mut maps = resources.get_mut::<MapDataList>().unwrap();
+
+    if recent.maps.is_empty() {
+        ui.label("No recently opened maps");
+    }
+
+    for entry in &recent.maps {
+        let hash = TagHash(entry.hash);
+        let loaded = maps.index_of(hash);
+        if ui
+            .add_enabled(
+                loaded != Some(maps.current_map),
+                egui::Button::new(format!("{} ({hash})", entry.name)),
+            )
+            .clicked()
+        {
+            maps.switch_or_load(
+                hash,
+                &mut resources.get_mut::<SelectedEntity>().unwrap(),
+                &mut resources.get_mut::<FpsCamera>().unwrap(),
+            );
+            ui.close_menu();
+        }
+    }
+
+    ui.separator();
+    ui.horizontal(|ui| {
+        ui.label("Max entries");
+        ui.add(egui::DragValue::new(&mut recent.max_entries).clamp_range(1..=50));
+    });
+    let max_entries = recent.max_entries;
+    recent.maps.truncate(max_entries);
+
+    if ui.button(format!("{} Clear", ICON_DELETE)).clicked() {
+        recent.maps.clear();
+        ui.close_menu();
+    }
+}
+
+fn save_user_entities_dialog(map: &MapData) {
+    let dialog_result = native_dialog::FileDialog::new()
+        .add_filter("Alkahest entities", &["ron"])
+        .set_filename(&format!("{}.ron", map.name))
+        .show_save_single_file();
+
+    if let Ok(Some(path)) = dialog_result {
+        persistence::save_user_entities(map, &path).err_alert().ok();
+    }
+}
+
+fn import_route_dialog(map: &mut MapData) -> Option<Entity> {
+    let dialog_result = native_dialog::FileDialog::new()
+        .add_filter("Waypoints", &["json", "csv"])
+        .show_open_single_file();
+
+    let Ok(Some(path)) = dialog_result else {
+        return None;
+    };
+
+    let route = persistence::import_route(&path).err_alert().ok()?;
+    let e = map
+        .scene
+        .spawn((route, Tags::from_iter([EntityTag::Utility]), Mutable));
+    map.history.record_spawn(e);
+
+    Some(e)
+}
+
+fn import_reference_mesh_dialog(
+    map: &mut MapData,
+    dcs: &DeviceContextSwapchain,
+    position: Vec3,
+) -> Option<Entity> {
+    let path = native_dialog::FileDialog::new()
+        .add_filter("Wavefront OBJ", &["obj"])
+        .show_open_single_file()
+        .ok()??;
+
+    let mesh = ReferenceMeshData::load_obj(dcs, &path).err_alert().ok()?;
+    let e = map.scene.spawn((
+        Transform {
+            translation: position,
+            ..Default::default()
+        },
+        ReferenceMesh {
+            mesh: Arc::new(mesh),
+            path,
+            color: [200, 200, 200],
+        },
+        Tags::from_iter([EntityTag::Utility]),
+        Mutable,
+    ));
+    map.history.record_spawn(e);
+
+    Some(e)
+}
+
+fn benchmark_dialog() -> Option<BenchmarkOptions> {
+    let route = native_dialog::FileDialog::new()
+        .add_filter("Waypoints", &["json", "csv"])
+        .show_open_single_file()
+        .ok()??;
+
+    let output = native_dialog::FileDialog::new()
+        .add_filter("CSV", &["csv"])
+        .set_filename("benchmark.csv")
+        .show_save_single_file()
+        .ok()??;
+
+    Some(BenchmarkOptions {
+        route,
+        output,
+        speed: DEFAULT_SPEED,
+    })
+}
+
+fn load_user_entities_dialog(map: &mut MapData) {
+    let dialog_result = native_dialog::FileDialog::new()
+        .add_filter("Alkahest entities", &["ron"])
+        .show_open_single_file();
+
+    if let Ok(Some(path)) = dialog_result {
+        persistence::load_user_entities(map, &path).err_alert().ok();
+    }
+}
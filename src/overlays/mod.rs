@@ -1,18 +1,32 @@
 use egui::Response;
 
+pub mod axis_gizmo;
+pub mod bytecode_inspector;
 pub mod camera_settings;
+pub mod clip_plane;
+pub mod command_palette;
 pub mod console;
 pub mod fps_display;
+pub mod gizmo;
+pub mod gpu_info;
 pub mod gui;
+pub mod hover_info;
 pub mod inspector;
+pub mod keybinds;
+pub mod load_errors;
 pub mod load_indicator;
+pub mod material_info;
 pub mod menu;
+pub mod notes;
 pub mod outliner;
 pub mod render_settings;
+pub mod render_stats;
 pub mod resource_nametags;
+pub mod scene_stats;
 pub mod tag_dump;
 pub mod technique_viewer;
 pub mod texture_viewer;
+pub mod toasts;
 
 pub mod chip;
 
@@ -0,0 +1,126 @@
+use egui::{Color32, Pos2, Rect};
+use winit::window::Window;
+
+use crate::{
+    camera::FpsCamera,
+    ecs::{
+        components::{Note, Visible},
+        resources::SelectedEntity,
+        transform::Transform,
+    },
+    map::MapDataList,
+    resources::Resources,
+};
+
+use super::gui::Overlay;
+
+/// Draws the text of `Note` entities at their position in the world. Right clicking a note selects it
+pub struct NoteOverlay;
+
+impl Overlay for NoteOverlay {
+    fn draw(
+        &mut self,
+        ctx: &egui::Context,
+        _window: &Window,
+        resources: &mut Resources,
+        _gui: &mut super::gui::GuiContext<'_>,
+    ) -> bool {
+        let camera = resources.get::<FpsCamera>().unwrap();
+        let maps = resources.get::<MapDataList>().unwrap();
+        let Some((_, _, map)) = maps.current_map() else {
+            return true;
+        };
+
+        let screen_size = ctx.screen_rect().size();
+        let painter = ctx.layer_painter(egui::LayerId::background());
+        let mut selection = resources.get_mut::<SelectedEntity>().unwrap();
+
+        let mut notes = map
+            .scene
+            .query::<(&Transform, &Note, Option<&Visible>)>()
+            .iter()
+            .filter(|(_, (transform, _, visible))| {
+                visible.map_or(true, |v| v.0) && camera.is_point_visible(transform.translation)
+            })
+            .map(|(e, (transform, note, _))| {
+                let projected_point = camera
+                    .projection_view_matrix
+                    .project_point3(transform.translation);
+                let screen_point = Pos2::new(
+                    ((projected_point.x + 1.0) * 0.5) * screen_size.x,
+                    ((1.0 - projected_point.y) * 0.5) * screen_size.y,
+                );
+
+                (
+                    e,
+                    camera.position.distance(transform.translation),
+                    screen_point,
+                    note.clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        // Draw far away notes first so closer ones end up on top
+        notes.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        let mut clicked = None;
+        let click = ctx.input(|i| {
+            i.pointer
+                .secondary_clicked()
+                .then(|| i.pointer.interact_pos())
+                .flatten()
+                .map(|pos| (pos, i.modifiers.ctrl))
+        });
+
+        for (e, _, screen_point, note) in notes {
+            let color = Color32::from_rgb(note.color[0], note.color[1], note.color[2]);
+            let galley = painter.layout_no_wrap(
+                note.text.clone(),
+                egui::FontId::proportional(note.font_size),
+                color,
+            );
+
+            // The note hangs above its anchor point
+            let text_pos = screen_point - egui::vec2(galley.size().x / 2.0, galley.size().y + 8.0);
+            let rect = Rect::from_min_size(text_pos, galley.size()).expand(4.0);
+
+            if selection.is_selected(e) {
+                painter.rect_stroke(
+                    rect.expand(4.0),
+                    egui::Rounding::same(4.0),
+                    egui::Stroke::new(3.0, Color32::from_rgb(255, 150, 50)),
+                );
+            }
+            painter.rect_filled(
+                rect,
+                egui::Rounding::same(2.0),
+                Color32::from_black_alpha(160),
+            );
+            painter.line_segment(
+                [rect.center_bottom(), screen_point],
+                egui::Stroke::new(1.0, color),
+            );
+            painter.circle_filled(screen_point, 3.0, color);
+            painter.galley(text_pos, galley);
+
+            if let Some((pos, _)) = click {
+                if rect.contains(pos) {
+                    clicked = Some(e);
+                }
+            }
+        }
+
+        if let (Some(e), Some((_, ctrl))) = (clicked, click) {
+            if !selection.1 && !ctx.wants_pointer_input() {
+                if ctrl {
+                    selection.toggle(e);
+                } else {
+                    selection.select(Some(e));
+                }
+                selection.1 = true;
+            }
+        }
+
+        true
+    }
+}
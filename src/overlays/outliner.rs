@@ -1,4 +1,7 @@
+use std::collections::{HashMap, HashSet};
+
 use egui::RichText;
+use hecs::Entity;
 use itertools::Itertools;
 use nohash_hasher::IntMap;
 use strum::IntoEnumIterator;
@@ -6,32 +9,58 @@ use strum::IntoEnumIterator;
 use crate::{
     camera::FpsCamera,
     ecs::{
-        components::{Mutable, Visible},
-        resolve_entity_icon, resolve_entity_name,
+        components::{Mutable, Route, Visible},
+        entity_reference_string, hierarchy,
+        history::{despawn_mutable, reparent_mutable},
+        persistence, resolve_aabb, resolve_entity_icon, resolve_entity_name,
         resources::SelectedEntity,
         tags::{EntityTag, Tags},
         transform::Transform,
     },
-    icons::{ICON_CHESS_PAWN, ICON_DELETE},
+    icons::{
+        ICON_CHESS_PAWN, ICON_CHEVRON_DOWN, ICON_CHEVRON_RIGHT, ICON_CLIPBOARD, ICON_CONTENT_COPY,
+        ICON_DELETE, ICON_EXPORT, ICON_MAGNIFY, ICON_SUBDIRECTORY_ARROW_LEFT,
+    },
     map::MapDataList,
-    util::text::{prettify_distance, text_color_for_background},
+    util::{
+        error::ErrorAlert,
+        text::{prettify_distance, text_color_for_background},
+    },
 };
 
 use super::gui::Overlay;
 
 pub struct OutlinerOverlay {
     sort_by_distance: bool,
+    focus_on_select: bool,
 
+    search: String,
     filters: IntMap<EntityTag, bool>,
+
+    /// Entities whose children are hidden in the tree
+    collapsed: HashSet<Entity>,
+    /// Entity being dragged onto another one to reparent it
+    dragging: Option<Entity>,
+
+    /// Set when the outliner was the last thing clicked, the arrow keys then move the selection through the list
+    keyboard_focus: bool,
+    /// Scroll offset and height of the entity list in the last frame
+    list_viewport: (f32, f32),
 }
 
 impl Default for OutlinerOverlay {
     fn default() -> Self {
         Self {
             sort_by_distance: false,
+            focus_on_select: false,
+            search: String::new(),
             filters: EntityTag::iter()
                 .map(|tag| (tag, false))
                 .collect::<IntMap<_, _>>(),
+            collapsed: HashSet::new(),
+            dragging: None,
+            keyboard_focus: false,
+            list_viewport: (0.0, 0.0),
         }
     }
 }
@@ -51,6 +80,7 @@ impl Overlay for OutlinerOverlay {
             let camera = resources.get::<FpsCamera>().unwrap();
 
             let enabled_filters = self.filters.iter().filter(|(_, v)| **v).count();
+            let search = self.search.to_lowercase();
             let mut entities = scene
                 .query::<(Option<&Transform>, Option<&Tags>)>()
                 .iter()
@@ -67,6 +97,14 @@ impl Overlay for OutlinerOverlay {
                             .all(|(tag, _)| tags.0.contains(tag))
                     })
                 })
+                .filter(|(e, _)| {
+                    search.is_empty()
+                        || scene.entity(*e).map_or(false, |e| {
+                            resolve_entity_name(e, true)
+                                .to_lowercase()
+                                .contains(&search)
+                        })
+                })
                 .map(|(e, (transform, _tags))| {
                     let distance = if let Some(transform) = transform {
                         (transform.translation - camera.position).length()
@@ -84,12 +122,108 @@ impl Overlay for OutlinerOverlay {
                 entities.sort_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap());
             }
 
+            drop(camera);
+
+            // Entities are nested under their parent, as long as the parent passes the filters too
+            let mut children: HashMap<Entity, Vec<(Entity, f32)>> = HashMap::new();
+            let mut roots = vec![];
+            for &(e, distance) in &entities {
+                match hierarchy::parent_of(scene, e) {
+                    Some(p) if entities.iter().any(|(pe, _)| *pe == p) => {
+                        children.entry(p).or_default().push((e, distance))
+                    }
+                    _ => roots.push((e, distance)),
+                }
+            }
+
+            let mut rows = vec![];
+            let mut stack: Vec<(Entity, f32, usize)> =
+                roots.into_iter().rev().map(|(e, d)| (e, d, 0)).collect();
+            while let Some((e, distance, depth)) = stack.pop() {
+                rows.push((e, distance, depth));
+                if self.collapsed.contains(&e) {
+                    continue;
+                }
+
+                if let Some(children) = children.get(&e) {
+                    stack.extend(children.iter().rev().map(|&(c, d)| (c, d, depth + 1)));
+                }
+            }
+
             let mut selected_entity = resources.get_mut::<SelectedEntity>().unwrap();
             let mut delete_entity = None;
+            let mut focus_entity = None;
+            // Where the dragged entity is dropped, `Some(None)` moves it to the root
+            let mut drop_target: Option<Option<Entity>> = None;
+            let mut reparent = None;
+            let mut scroll_to_row = None;
+
+            if self.keyboard_focus && !ctx.wants_keyboard_input() && !rows.is_empty() {
+                let (up, down, left, right, enter, delete) = ctx.input_mut(|i| {
+                    (
+                        i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+                        i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+                        i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowLeft),
+                        i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowRight),
+                        i.consume_key(egui::Modifiers::NONE, egui::Key::Enter),
+                        i.consume_key(egui::Modifiers::NONE, egui::Key::Delete),
+                    )
+                });
+
+                let current = selected_entity
+                    .0
+                    .and_then(|s| rows.iter().position(|(e, _, _)| *e == s));
+
+                if up || down {
+                    let next = match current {
+                        Some(i) if up => i.saturating_sub(1),
+                        Some(i) => (i + 1).min(rows.len() - 1),
+                        None if up => rows.len() - 1,
+                        None => 0,
+                    };
+
+                    let ent = rows[next].0;
+                    selected_entity.select(Some(ent));
+                    scroll_to_row = Some(next);
+                    if self.focus_on_select {
+                        focus_entity = Some(ent);
+                    }
+                }
+
+                if let Some(ent) = current.map(|i| rows[i].0) {
+                    if left && children.contains_key(&ent) {
+                        self.collapsed.insert(ent);
+                    }
+                    if right {
+                        self.collapsed.remove(&ent);
+                    }
+                }
+
+                if enter {
+                    focus_entity = selected_entity.0;
+                }
+
+                if delete {
+                    if let Some(ent) = selected_entity
+                        .0
+                        .filter(|&e| scene.entity(e).map_or(false, |e| e.has::<Mutable>()))
+                    {
+                        selected_entity.select(None);
+                        delete_entity = Some(ent);
+                    }
+                }
+            }
+
+            let window_response = egui::Window::new("Outliner").show(ctx, |ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.search)
+                        .hint_text(format!("{ICON_MAGNIFY} Search"))
+                        .desired_width(f32::INFINITY),
+                );
 
-            egui::Window::new("Outliner").show(ctx, |ui| {
                 ui.horizontal(|ui| {
                     ui.checkbox(&mut self.sort_by_distance, "Sort by distance");
+                    ui.checkbox(&mut self.focus_on_select, "Focus on select");
 
                     let filter_count = if enabled_filters > 0 {
                         format!(" ({})", enabled_filters)
@@ -111,71 +245,239 @@ impl Overlay for OutlinerOverlay {
                     });
                 });
 
-                egui::ScrollArea::vertical()
-                    .auto_shrink([false, false])
-                    .show_rows(
-                        ui,
-                        ui.spacing().interact_size.y,
-                        entities.len(),
-                        |ui, range| {
-                            for &(ent, distance) in &entities[range] {
-                                let e = scene.entity(ent).unwrap();
-                                ui.horizontal(|ui| {
-                                    let postfix = if self.sort_by_distance {
-                                        format!(" ({})", prettify_distance(distance))
-                                    } else {
-                                        "".to_string()
-                                    };
-
-                                    let visible = e.get::<&Visible>().map_or(true, |v| v.0);
-
-                                    let response = ui.selectable_label(
-                                        Some(ent) == selected_entity.0,
-                                        RichText::new(format!(
-                                            "{} {}{postfix}",
-                                            resolve_entity_icon(e).unwrap_or(ICON_CHESS_PAWN),
-                                            resolve_entity_name(e, true)
-                                        ))
-                                        .color(
-                                            if visible {
-                                                egui::Color32::WHITE
+                if self.dragging.is_some() {
+                    let response = ui.add_sized(
+                        [ui.available_width(), ui.spacing().interact_size.y],
+                        egui::Label::new(
+                            RichText::new(format!(
+                                "{} Drop here to move to the root",
+                                ICON_SUBDIRECTORY_ARROW_LEFT
+                            ))
+                            .weak(),
+                        ),
+                    );
+                    if ui.rect_contains_pointer(response.rect) {
+                        drop_target = Some(None);
+                        ui.painter()
+                            .rect_stroke(response.rect, 2.0, ui.visuals().selection.stroke);
+                    }
+                }
+
+                let mut scroll_area = egui::ScrollArea::vertical().auto_shrink([false, false]);
+                if let Some(row) = scroll_to_row {
+                    // Only the visible rows are laid out, so the offset has to be calculated by hand
+                    let row_height = ui.spacing().interact_size.y + ui.spacing().item_spacing.y;
+                    let top = row as f32 * row_height;
+                    let (offset, height) = self.list_viewport;
+                    if top < offset {
+                        scroll_area = scroll_area.vertical_scroll_offset(top);
+                    } else if top + row_height > offset + height {
+                        scroll_area = scroll_area.vertical_scroll_offset(top + row_height - height);
+                    }
+                }
+
+                let output = scroll_area.show_rows(
+                    ui,
+                    ui.spacing().interact_size.y,
+                    rows.len(),
+                    |ui, range| {
+                        for &(ent, distance, depth) in &rows[range] {
+                            let e = scene.entity(ent).unwrap();
+                            ui.horizontal(|ui| {
+                                ui.add_space(depth as f32 * ui.spacing().indent);
+                                if children.contains_key(&ent) {
+                                    let collapsed = self.collapsed.contains(&ent);
+                                    if ui
+                                        .add(
+                                            egui::Button::new(if collapsed {
+                                                ICON_CHEVRON_RIGHT.to_string()
                                             } else {
-                                                egui::Color32::GRAY
-                                            },
-                                        ),
-                                    );
-
-                                    let response = response.context_menu(|ui| {
-                                        ui.add_enabled_ui(e.has::<Mutable>(), |ui| {
-                                            // Delete button
-                                            if ui
-                                                .button(format!("{} Delete", ICON_DELETE))
-                                                .clicked()
-                                            {
-                                                selected_entity.0 = None;
-                                                delete_entity = Some(ent);
-                                            }
+                                                ICON_CHEVRON_DOWN.to_string()
+                                            })
+                                            .frame(false),
+                                        )
+                                        .clicked()
+                                    {
+                                        if collapsed {
+                                            self.collapsed.remove(&ent);
+                                        } else {
+                                            self.collapsed.insert(ent);
+                                        }
+                                    }
+                                }
+
+                                let postfix = if self.sort_by_distance {
+                                    format!(" ({})", prettify_distance(distance))
+                                } else {
+                                    "".to_string()
+                                };
+
+                                let visible = e.get::<&Visible>().map_or(true, |v| v.0);
+
+                                let response = ui.selectable_label(
+                                    selected_entity.is_selected(ent),
+                                    RichText::new(format!(
+                                        "{} {}{postfix}",
+                                        resolve_entity_icon(e).unwrap_or(ICON_CHESS_PAWN),
+                                        resolve_entity_name(e, true)
+                                    ))
+                                    .color(if visible {
+                                        egui::Color32::WHITE
+                                    } else {
+                                        egui::Color32::GRAY
+                                    }),
+                                );
+
+                                // Only user entities can be dragged onto each other
+                                let response = if e.has::<Mutable>() {
+                                    response.interact(egui::Sense::click_and_drag())
+                                } else {
+                                    response
+                                };
+
+                                if response.drag_started() {
+                                    self.dragging = Some(ent);
+                                }
+
+                                if let Some(dragged) = self.dragging {
+                                    if dragged != ent && ui.rect_contains_pointer(response.rect) {
+                                        drop_target = Some(Some(ent));
+                                        if hierarchy::can_reparent(scene, dragged, Some(ent)) {
+                                            ui.painter().rect_stroke(
+                                                response.rect,
+                                                2.0,
+                                                ui.visuals().selection.stroke,
+                                            );
+                                        }
+                                    }
+                                }
+
+                                let response = response.context_menu(|ui| {
+                                    if ui
+                                        .button(format!("{} Copy tag and position", ICON_CLIPBOARD))
+                                        .clicked()
+                                    {
+                                        ui.output_mut(|o| {
+                                            o.copied_text = entity_reference_string(e)
                                         });
-                                    });
+                                        ui.close_menu();
+                                    }
 
-                                    if response.clicked() {
-                                        selected_entity.0 = Some(ent);
+                                    if let Some(command) = persistence::utility_spawn_command(e) {
+                                        if ui
+                                            .button(format!(
+                                                "{} Copy spawn command",
+                                                ICON_CONTENT_COPY
+                                            ))
+                                            .clicked()
+                                        {
+                                            ui.output_mut(|o| o.copied_text = command);
+                                            ui.close_menu();
+                                        }
                                     }
 
-                                    if let Some(tags) = e.get::<&Tags>() {
-                                        tags.ui_chips(ui);
+                                    if let Some(route) = e.get::<&Route>() {
+                                        if ui
+                                            .button(format!("{} Export route...", ICON_EXPORT))
+                                            .clicked()
+                                        {
+                                            export_route_dialog(&route);
+                                            ui.close_menu();
+                                        }
                                     }
+
+                                    if hierarchy::parent_of(scene, ent).is_some()
+                                        && ui
+                                            .button(format!(
+                                                "{} Move to root",
+                                                ICON_SUBDIRECTORY_ARROW_LEFT
+                                            ))
+                                            .clicked()
+                                    {
+                                        reparent = Some((ent, None));
+                                        ui.close_menu();
+                                    }
+
+                                    ui.add_enabled_ui(e.has::<Mutable>(), |ui| {
+                                        // Delete button
+                                        if ui.button(format!("{} Delete", ICON_DELETE)).clicked() {
+                                            selected_entity.select(None);
+                                            delete_entity = Some(ent);
+                                        }
+                                    });
                                 });
-                            }
-                        },
-                    );
+
+                                if response.clicked() && ui.input(|i| i.modifiers.ctrl) {
+                                    selected_entity.toggle(ent);
+                                } else if response.clicked() {
+                                    selected_entity.select(Some(ent));
+                                    if self.focus_on_select {
+                                        focus_entity = Some(ent);
+                                    }
+                                }
+
+                                if let Some(tags) = e.get::<&Tags>() {
+                                    tags.ui_chips(ui);
+                                }
+                            });
+                        }
+                    },
+                );
+                self.list_viewport = (output.state.offset.y, output.inner_rect.height());
             });
 
-            if let Some(delete) = delete_entity {
-                scene.despawn(delete).ok();
+            if ctx.input(|i| i.pointer.any_pressed()) {
+                let pointer = ctx.input(|i| i.pointer.interact_pos());
+                self.keyboard_focus = window_response.zip(pointer).map_or(false, |(r, pos)| {
+                    ctx.layer_id_at(pos) == Some(r.response.layer_id)
+                });
+            }
+
+            if let Some(dragged) = self.dragging {
+                if let Ok(e) = scene.entity(dragged) {
+                    egui::show_tooltip_at_pointer(ctx, egui::Id::new("outliner_drag"), |ui| {
+                        ui.label(resolve_entity_name(e, true));
+                    });
+                }
+
+                if ctx.input(|i| i.pointer.any_released()) {
+                    self.dragging = None;
+                    if let Some(parent) = drop_target {
+                        reparent = Some((dragged, parent));
+                    }
+                }
+            }
+
+            if let Some((ent, parent)) = reparent {
+                reparent_mutable(scene, &mut map.history, ent, parent);
+            }
+
+            if let Some(e) = delete_entity.and_then(|ent| scene.entity(ent).ok()) {
+                despawn_mutable(e, &mut map.command_buffer, &mut map.history);
+            }
+
+            if let Some(e) = focus_entity.and_then(|ent| scene.entity(ent).ok()) {
+                let mut camera = resources.get_mut::<FpsCamera>().unwrap();
+                if let Some(target) = resolve_aabb(e) {
+                    camera.focus_aabb(&target);
+                } else if let Some(transform) = e.get::<&Transform>() {
+                    camera.focus(transform.translation, 10.0);
+                }
             }
         }
 
         true
     }
 }
+
+fn export_route_dialog(route: &Route) {
+    let dialog_result = native_dialog::FileDialog::new()
+        .add_filter("JSON waypoints", &["json"])
+        .add_filter("CSV waypoints", &["csv"])
+        .set_filename("route.json")
+        .show_save_single_file();
+
+    if let Ok(Some(path)) = dialog_result {
+        persistence::export_route(route, &path).err_alert().ok();
+    }
+}
@@ -1,26 +1,37 @@
 use const_format::concatcp;
+use destiny_pkg::TagHash;
 use glam::{Mat4, Vec2, Vec3, Vec4};
 use hecs::Entity;
 use itertools::Itertools;
 use nohash_hasher::{IntMap, IntSet};
-use std::{
-    fmt::Display,
-    fmt::Formatter,
-    mem::{swap, take, transmute},
-    time::Instant,
-};
+use std::{f32::consts::PI, mem::transmute, time::Instant};
+use strum::IntoEnumIterator;
 use winit::window::Window;
 
 use crate::{
-    discord,
-    ecs::components::{ActivityGroup, Global},
+    camera::{letterbox_rect, FpsCamera},
+    config::{self, MapUnloadConfig, PrefetchConfig},
+    ecs::components::ActivityGroup,
+    ecs::persistence::{self, AutosaveState},
+    ecs::resolve_aabb,
     ecs::resources::SelectedEntity,
-    map::MapDataList,
+    hotkeys,
+    icons::{
+        ICON_CHEVRON_LEFT, ICON_CHEVRON_RIGHT, ICON_CLIPBOARD, ICON_PAUSE, ICON_PLAY,
+        ICON_SET_MERGE, ICON_STEP_BACKWARD, ICON_STEP_FORWARD,
+    },
+    map::{self, MapDataList, MapSortMode},
     render::{
         overrides::{EnabledShaderOverrides, ScopeOverrides},
         renderer::ShadowMapsResource,
+        settings::{
+            builtin_presets, AntiAliasing, Background, RenderPreset, RenderSettings,
+            StageIsolation, TextureFiltering, Tonemap, COMPOSITOR_MODES,
+        },
+        time::TimeControl,
     },
     resources::Resources,
+    screenshot::ScreenshotFormat,
 };
 
 use super::gui::Overlay;
@@ -36,8 +47,190 @@ pub struct RenderSettingsOverlay {
 
     pub shadow_res_index: usize,
     pub animate_light: bool,
+    pub light_animation: LightAnimation,
     pub light_dir_degrees: Vec3,
+    pub sun_path: SunPath,
+    /// Hours since midnight, drives the light direction when following the sun path
+    pub time_of_day: f32,
     pub last_frame: Instant,
+
+    /// Name of the last applied preset, also used as the name to save under
+    pub preset_name: String,
+
+    /// Contents of the map hash text box, and the error from the last load attempt
+    pub map_hash_input: String,
+    pub map_hash_error: Option<String>,
+
+    /// Contents of the camera location text box, and the error from the last teleport attempt
+    pub camera_location_input: String,
+    pub camera_location_error: Option<String>,
+
+    /// Maps ticked to be merged into a combined map
+    pub merge_selection: Vec<TagHash>,
+}
+
+impl RenderSettingsOverlay {
+    fn apply_preset(&mut self, render_settings: &mut RenderSettings, preset: RenderPreset) {
+        *render_settings = preset.settings;
+        self.preset_name = preset.name;
+
+        // The overlay drives the light direction, so it has to be updated to match
+        let light_dir = render_settings.light_dir;
+        self.light_dir_degrees = Vec3::new(
+            light_dir.z,
+            0.0,
+            light_dir
+                .x
+                .atan2(light_dir.y)
+                .to_degrees()
+                .rem_euclid(360.0),
+        );
+    }
+
+    fn draw_split_divider(ctx: &egui::Context, render_settings: &mut RenderSettings) {
+        const HANDLE_WIDTH: f32 = 12.0;
+
+        let screen = ctx.screen_rect();
+        let x = screen.left() + screen.width() * render_settings.split_position;
+
+        let response = egui::Area::new("split_compare_divider")
+            .fixed_pos(egui::pos2(x - HANDLE_WIDTH / 2.0, screen.top()))
+            .order(egui::Order::Background)
+            .show(ctx, |ui| {
+                ui.allocate_exact_size(
+                    egui::vec2(HANDLE_WIDTH, screen.height()),
+                    egui::Sense::drag(),
+                )
+                .1
+            })
+            .inner;
+
+        if response.dragged() {
+            render_settings.split_position = (render_settings.split_position
+                + response.drag_delta().x / screen.width())
+            .clamp(0.0, 1.0);
+        }
+
+        let stroke_width = if response.hovered() || response.dragged() {
+            3.0
+        } else {
+            1.5
+        };
+
+        let painter = ctx.layer_painter(egui::LayerId::background());
+        painter.line_segment(
+            [egui::pos2(x, screen.top()), egui::pos2(x, screen.bottom())],
+            egui::Stroke::new(stroke_width, egui::Color32::WHITE),
+        );
+
+        let label_y = screen.bottom() - 24.0;
+        for (mode, offset, align) in [
+            (
+                render_settings.compositor_mode,
+                -8.0,
+                egui::Align2::RIGHT_CENTER,
+            ),
+            (
+                render_settings.compositor_mode_b,
+                8.0,
+                egui::Align2::LEFT_CENTER,
+            ),
+        ] {
+            painter.text(
+                egui::pos2(x + offset, label_y),
+                align,
+                COMPOSITOR_MODES[mode].to_string(),
+                egui::FontId::proportional(14.0),
+                egui::Color32::WHITE,
+            );
+        }
+    }
+
+    /// Covers the parts of the screen outside of the letterboxed area
+    fn draw_letterbox(ctx: &egui::Context, aspect: f32, color: Vec4) {
+        let screen = ctx.screen_rect();
+        let (min, size) = letterbox_rect(Vec2::new(screen.width(), screen.height()), aspect);
+        let inner = egui::Rect::from_min_size(
+            screen.min + egui::vec2(min.x, min.y),
+            egui::vec2(size.x, size.y),
+        );
+
+        let color = egui::Rgba::from_rgb(color.x, color.y, color.z).into();
+        let painter = ctx.layer_painter(egui::LayerId::background());
+        for bar in [
+            egui::Rect::from_min_max(screen.min, egui::pos2(screen.max.x, inner.min.y)),
+            egui::Rect::from_min_max(egui::pos2(screen.min.x, inner.max.y), screen.max),
+            egui::Rect::from_min_max(
+                egui::pos2(screen.min.x, inner.min.y),
+                egui::pos2(inner.min.x, inner.max.y),
+            ),
+            egui::Rect::from_min_max(
+                egui::pos2(inner.max.x, inner.min.y),
+                egui::pos2(screen.max.x, inner.max.y),
+            ),
+        ] {
+            if bar.is_positive() {
+                painter.rect_filled(bar, 0.0, color);
+            }
+        }
+    }
+
+    fn draw_presets(&mut self, ui: &mut egui::Ui, render_settings: &mut RenderSettings) {
+        let builtin = builtin_presets();
+        let user_presets = config::with(|c| c.render_presets.clone());
+
+        let mut apply = None;
+        egui::ComboBox::from_label("Preset")
+            .selected_text(&self.preset_name)
+            .show_ui(ui, |ui| {
+                for preset in builtin.iter().chain(user_presets.iter()) {
+                    if ui
+                        .selectable_label(preset.name == self.preset_name, &preset.name)
+                        .clicked()
+                    {
+                        apply = Some(preset.clone());
+                    }
+                }
+            });
+
+        if let Some(preset) = apply {
+            self.apply_preset(render_settings, preset);
+        }
+
+        let is_builtin = builtin.iter().any(|p| p.name == self.preset_name);
+        let is_saved = user_presets.iter().any(|p| p.name == self.preset_name);
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.preset_name).desired_width(128.0));
+
+            let can_save = !self.preset_name.trim().is_empty() && !is_builtin;
+            if ui
+                .add_enabled(can_save, egui::Button::new("Save"))
+                .on_disabled_hover_text("Built-in presets can't be overwritten")
+                .clicked()
+            {
+                let preset = RenderPreset {
+                    name: self.preset_name.trim().to_string(),
+                    settings: render_settings.clone(),
+                };
+                config::with_mut(|c| {
+                    if let Some(existing) =
+                        c.render_presets.iter_mut().find(|p| p.name == preset.name)
+                    {
+                        *existing = preset;
+                    } else {
+                        c.render_presets.push(preset);
+                    }
+                });
+            }
+
+            if ui
+                .add_enabled(is_saved, egui::Button::new("Delete"))
+                .clicked()
+            {
+                config::with_mut(|c| c.render_presets.retain(|p| p.name != self.preset_name));
+            }
+        });
+    }
 }
 
 impl Overlay for RenderSettingsOverlay {
@@ -53,6 +246,9 @@ impl Overlay for RenderSettingsOverlay {
 
         let mut render_settings = resources.get_mut::<RenderSettings>().unwrap();
         egui::Window::new("Options").show(ctx, |ui| {
+            self.draw_presets(ui, &mut render_settings);
+            ui.separator();
+
             ui.checkbox(&mut render_settings.draw_lights, "Render lights");
             ui.indent("render settings specular option indent", |ui| {
                 ui.add_enabled_ui(render_settings.draw_lights, |ui| {
@@ -70,28 +266,429 @@ impl Overlay for RenderSettingsOverlay {
                             &mut render_settings.render_shadows,
                             "Render shadows",
                         ));
+                        ui.add_enabled_ui(render_settings.render_shadows, |ui| {
+                            ui.add(
+                                egui::Slider::new(&mut render_settings.shadow_cascades, 1..=4)
+                                    .text("Shadow cascades"),
+                            );
+                            ui.checkbox(
+                                &mut render_settings.shadow_cascade_debug,
+                                "Visualize cascades",
+                            );
+                        });
+                    });
+                });
+            });
+
+            egui::ComboBox::from_label("MSAA")
+                .selected_text(match render_settings.msaa_samples {
+                    1 => "Off".to_string(),
+                    n => format!("{n}x"),
+                })
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut render_settings.msaa_samples, 1, "Off");
+                    for n in [2, 4, 8] {
+                        ui.selectable_value(&mut render_settings.msaa_samples, n, format!("{n}x"));
+                    }
+                });
+
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_label("Anti-aliasing")
+                    .selected_text(render_settings.anti_aliasing.to_string())
+                    .show_ui(ui, |ui| {
+                        for aa in AntiAliasing::iter() {
+                            ui.selectable_value(
+                                &mut render_settings.anti_aliasing,
+                                aa,
+                                aa.to_string(),
+                            );
+                        }
+                    });
+                egui::ComboBox::from_label("Tonemap")
+                    .selected_text(render_settings.tonemap.to_string())
+                    .show_ui(ui, |ui| {
+                        for tonemap in Tonemap::iter() {
+                            ui.selectable_value(
+                                &mut render_settings.tonemap,
+                                tonemap,
+                                tonemap.to_string(),
+                            );
+                        }
+                    });
+            });
+            egui::ComboBox::from_label("Screenshot format")
+                .selected_text(render_settings.screenshot_format.to_string())
+                .show_ui(ui, |ui| {
+                    for format in ScreenshotFormat::iter() {
+                        ui.selectable_value(
+                            &mut render_settings.screenshot_format,
+                            format,
+                            format.to_string(),
+                        );
+                    }
+                })
+                .response
+                .on_hover_text("EXR screenshots contain the unclamped linear color before exposure, tonemapping and FXAA, without the UI");
+            if render_settings.anti_aliasing == AntiAliasing::Taa {
+                ui.label(
+                    egui::RichText::new(
+                        "TAA isn't implemented yet, high quality FXAA is used instead",
+                    )
+                    .weak(),
+                );
+            }
+
+            ui.checkbox(&mut render_settings.bloom_enabled, "Bloom");
+            if render_settings.bloom_enabled {
+                ui.indent("render settings bloom indent", |ui| {
+                    ui.add(
+                        egui::Slider::new(&mut render_settings.bloom_threshold, 0.0..=16.0)
+                            .logarithmic(true)
+                            .text("Threshold"),
+                    );
+                    ui.add(
+                        egui::Slider::new(&mut render_settings.bloom_intensity, 0.0..=4.0)
+                            .text("Intensity"),
+                    );
+                });
+            }
+
+            egui::ComboBox::from_label("Background")
+                .selected_text(render_settings.background.to_string())
+                .show_ui(ui, |ui| {
+                    for background in Background::iter() {
+                        ui.selectable_value(
+                            &mut render_settings.background,
+                            background,
+                            background.to_string(),
+                        );
+                    }
+                });
+            if render_settings.background != Background::Default {
+                ui.indent("render settings background indent", |ui| {
+                    let gradient = render_settings.background == Background::Gradient;
+                    let mut c = render_settings.background_color.to_array();
+                    ui.horizontal(|ui| {
+                        ui.color_edit_button_rgb(unsafe { transmute(&mut c) });
+                        ui.label(if gradient { "Bottom" } else { "Color" });
+                    });
+                    c[3] = 1.0;
+                    render_settings.background_color = Vec4::from_array(c);
+
+                    if gradient {
+                        let mut c = render_settings.background_color_top.to_array();
+                        ui.horizontal(|ui| {
+                            ui.color_edit_button_rgb(unsafe { transmute(&mut c) });
+                            ui.label("Top");
+                        });
+                        c[3] = 1.0;
+                        render_settings.background_color_top = Vec4::from_array(c);
+                    }
+                });
+            }
+
+            ui.checkbox(&mut render_settings.fog_enabled, "Fog");
+            if render_settings.fog_enabled {
+                ui.indent("render settings fog indent", |ui| {
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::DragValue::new(&mut render_settings.fog_start)
+                                .clamp_range(0f32..=10000f32)
+                                .speed(1.0)
+                                .suffix("m"),
+                        );
+                        ui.label("Start distance");
+                    });
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::DragValue::new(&mut render_settings.fog_density)
+                                .clamp_range(0f32..=1f32)
+                                .speed(0.0005)
+                                .max_decimals(4),
+                        );
+                        ui.label("Density");
+                    });
+
+                    let mut use_background = render_settings.fog_color.is_none();
+                    ui.checkbox(&mut use_background, "Use background color");
+                    if use_background {
+                        render_settings.fog_color = None;
+                    } else {
+                        let mut c = render_settings
+                            .fog_color
+                            .unwrap_or(render_settings.background_color)
+                            .to_array();
+                        ui.horizontal(|ui| {
+                            ui.color_edit_button_rgb(unsafe { transmute(&mut c) });
+                            ui.label("Color");
+                        });
+                        c[3] = 1.0;
+                        render_settings.fog_color = Some(Vec4::from_array(c));
+                    }
+                });
+            }
+
+            egui::ComboBox::from_label("Aspect ratio")
+                .selected_text(
+                    LETTERBOX_ASPECTS
+                        .iter()
+                        .find(|(_, a)| *a == render_settings.letterbox_aspect)
+                        .map_or("Custom", |(name, _)| name),
+                )
+                .show_ui(ui, |ui| {
+                    for (name, aspect) in LETTERBOX_ASPECTS {
+                        ui.selectable_value(&mut render_settings.letterbox_aspect, *aspect, *name);
+                    }
+                });
+            if render_settings.letterbox_aspect.is_some() {
+                ui.indent("render settings letterbox indent", |ui| {
+                    let mut c = render_settings.letterbox_color.to_array();
+                    ui.horizontal(|ui| {
+                        ui.color_edit_button_rgb(unsafe { transmute(&mut c) });
+                        ui.label("Bar color");
+                    });
+                    c[3] = 1.0;
+                    render_settings.letterbox_color = Vec4::from_array(c);
+                });
+            }
+
+            ui.checkbox(&mut render_settings.dof_enabled, "Depth of field");
+            if render_settings.dof_enabled {
+                ui.indent("render settings dof indent", |ui| {
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::DragValue::new(&mut render_settings.dof_focus_distance)
+                                .clamp_range(0.01f32..=10000f32)
+                                .speed(0.1)
+                                .suffix("m"),
+                        );
+                        ui.label("Focus distance");
+
+                        let selected = resources.get::<SelectedEntity>().unwrap().0;
+                        if ui
+                            .add_enabled(selected.is_some(), egui::Button::new("Focus on selected"))
+                            .clicked()
+                        {
+                            let maps = resources.get::<MapDataList>().unwrap();
+                            let target = maps
+                                .current_map()
+                                .and_then(|(_, _, m)| m.scene.entity(selected?).ok())
+                                .and_then(resolve_aabb);
+
+                            if let Some(target) = target {
+                                let camera = resources.get::<FpsCamera>().unwrap();
+                                render_settings.dof_focus_distance =
+                                    camera.position.distance(target.center());
+                            }
+                        }
+                    });
+                    ui.add(
+                        egui::Slider::new(&mut render_settings.dof_strength, 0.0..=1.0)
+                            .text("Strength"),
+                    );
+                });
+            }
+
+            ui.collapsing("Level of detail", |ui| {
+                for (i, distance) in render_settings.lod_distances.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::DragValue::new(distance)
+                                .clamp_range(0f32..=10000f32)
+                                .speed(1.0)
+                                .suffix("m"),
+                        );
+                        ui.label(format!("LOD {} distance", i + 1));
+                    });
+                }
+
+                egui::ComboBox::from_label("Force LOD")
+                    .selected_text(match render_settings.force_lod {
+                        Some(lod) => format!("LOD {lod}"),
+                        None => "Off".to_string(),
+                    })
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut render_settings.force_lod, None, "Off");
+                        for lod in 0..=3 {
+                            ui.selectable_value(
+                                &mut render_settings.force_lod,
+                                Some(lod),
+                                format!("LOD {lod}"),
+                            );
+                        }
+                    });
+            });
+
+            ui.collapsing("Grid", |ui| {
+                ui.checkbox(&mut render_settings.grid_enabled, "Enabled");
+                ui.add_enabled_ui(render_settings.grid_enabled, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::DragValue::new(&mut render_settings.grid_spacing)
+                                .clamp_range(0.01f32..=1000f32)
+                                .speed(0.1)
+                                .suffix("m"),
+                        );
+                        ui.label("Spacing");
+                    });
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::DragValue::new(&mut render_settings.grid_major_every)
+                                .clamp_range(1..=100),
+                        );
+                        ui.label("Cells per major line");
+                    });
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::DragValue::new(&mut render_settings.grid_height)
+                                .speed(0.1)
+                                .suffix("m"),
+                        );
+                        ui.label("Height");
+
+                        if ui.button("Move to camera").clicked() {
+                            let camera = resources.get::<FpsCamera>().unwrap();
+                            render_settings.grid_height = camera.position.z;
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::DragValue::new(&mut render_settings.grid_fade_distance)
+                                .clamp_range(1f32..=10000f32)
+                                .speed(1.0)
+                                .suffix("m"),
+                        );
+                        ui.label("Fade distance");
+                    });
+                    ui.checkbox(&mut render_settings.grid_follow_camera, "Follow camera");
+                });
+            });
+
+            ui.collapsing("Clipping plane", |ui| {
+                ui.checkbox(&mut render_settings.clip_plane_enabled, "Enabled");
+                ui.add_enabled_ui(render_settings.clip_plane_enabled, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Point");
+                        let point = &mut render_settings.clip_plane_point;
+                        ui.add(egui::DragValue::new(&mut point.x).speed(0.1).prefix("x: "));
+                        ui.add(egui::DragValue::new(&mut point.y).speed(0.1).prefix("y: "));
+                        ui.add(egui::DragValue::new(&mut point.z).speed(0.1).prefix("z: "));
+                    });
+
+                    let normal = render_settings.clip_plane_normal.normalize_or_zero();
+                    let mut yaw = normal.y.atan2(normal.x).to_degrees();
+                    let mut pitch = normal.z.clamp(-1.0, 1.0).asin().to_degrees();
+                    ui.horizontal(|ui| {
+                        ui.label("Normal");
+                        let yaw_changed = ui
+                            .add(egui::DragValue::new(&mut yaw).speed(1.0).suffix("° yaw"))
+                            .changed();
+                        let pitch_changed = ui
+                            .add(
+                                egui::DragValue::new(&mut pitch)
+                                    .speed(1.0)
+                                    .clamp_range(-90.0..=90.0)
+                                    .suffix("° pitch"),
+                            )
+                            .changed();
+
+                        if yaw_changed || pitch_changed {
+                            let (yaw, pitch) = (yaw.to_radians(), pitch.to_radians());
+                            render_settings.clip_plane_normal = Vec3::new(
+                                yaw.cos() * pitch.cos(),
+                                yaw.sin() * pitch.cos(),
+                                pitch.sin(),
+                            );
+                        }
                     });
+
+                    ui.checkbox(&mut render_settings.clip_plane_flip, "Flip side");
+
+                    if ui.button("Place in front of camera").clicked() {
+                        let camera = resources.get::<FpsCamera>().unwrap();
+                        render_settings.clip_plane_point = camera.position + camera.front * 5.0;
+                        render_settings.clip_plane_normal = camera.front;
+                        render_settings.clip_plane_flip = false;
+                    }
                 });
             });
 
-            ui.checkbox(&mut render_settings.fxaa, "Anti-aliasing");
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::DragValue::new(&mut render_settings.max_texture_uploads)
+                        .clamp_range(0..=1024),
+                );
+                ui.label("Texture uploads per frame").on_hover_text(
+                    "Limits hitches while textures are streaming in. 0 means no limit",
+                );
+            });
+
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_label("Texture filtering")
+                    .selected_text(render_settings.texture_filtering.to_string())
+                    .show_ui(ui, |ui| {
+                        for filtering in TextureFiltering::iter() {
+                            ui.selectable_value(
+                                &mut render_settings.texture_filtering,
+                                filtering,
+                                filtering.to_string(),
+                            );
+                        }
+                    });
+
+                if render_settings.texture_filtering == TextureFiltering::Anisotropic {
+                    egui::ComboBox::from_label("Anisotropy")
+                        .selected_text(format!("{}x", render_settings.anisotropy))
+                        .show_ui(ui, |ui| {
+                            for n in [1, 2, 4, 8, 16] {
+                                ui.selectable_value(
+                                    &mut render_settings.anisotropy,
+                                    n,
+                                    format!("{n}x"),
+                                );
+                            }
+                        });
+                }
+            });
 
             ui.checkbox(
                 &mut render_settings.evaluate_bytecode,
                 "Evaluate TFX bytecode (WIP)",
             );
+            ui.checkbox(
+                &mut render_settings.show_material_info,
+                "Show material info of selected entity",
+            );
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::DragValue::new(&mut render_settings.instance_limit)
+                        .speed(1.0)
+                        .custom_formatter(|v, _| {
+                            if v == 0.0 {
+                                "Unlimited".to_string()
+                            } else {
+                                format!("{v}")
+                            }
+                        }),
+                );
+                ui.label("Static instance limit")
+                    .on_hover_text("Only draws the first N instances of each static instance group, 0 draws all of them");
+            });
             ui.checkbox(&mut render_settings.alpha_blending, "Enable color blending");
             if render_settings.alpha_blending {
                 egui::ComboBox::from_label("Blend Override").show_index(
                     ui,
                     &mut render_settings.blend_override,
-                    4,
+                    7,
                     |i| {
                         [
                             "Default",
                             concatcp!(crate::icons::ICON_VECTOR_DIFFERENCE, " Blend"),
                             concatcp!(crate::icons::ICON_PLUS, " Additive"),
                             concatcp!(crate::icons::ICON_CLOSE, " Discard"),
+                            concatcp!(crate::icons::ICON_MULTIPLICATION, " Multiply"),
+                            concatcp!(crate::icons::ICON_BRIGHTNESS_6, " Screen"),
+                            concatcp!(crate::icons::ICON_ALPHA, " Premultiplied alpha"),
                         ][i]
                     },
                 );
@@ -112,33 +709,70 @@ impl Overlay for RenderSettingsOverlay {
 
             {
                 const SHADOW_RESOLUTIONS: &[usize] = &[2048, 4096, 8192, 16384];
+                let mut csb = resources.get_mut::<ShadowMapsResource>().unwrap();
+                // Follow the actual resolution, which might have fallen back to a smaller size
+                if !csb.is_resize_pending() {
+                    if let Some(i) = SHADOW_RESOLUTIONS.iter().position(|r| *r == csb.resolution) {
+                        self.shadow_res_index = i;
+                    }
+                }
+
                 let response = egui::ComboBox::from_label("Shadow Resolution").show_index(
                     ui,
                     &mut self.shadow_res_index,
                     SHADOW_RESOLUTIONS.len(),
-                    |i| {
-                        if SHADOW_RESOLUTIONS[i] > 8192 {
-                            format!("{} (may crash)", SHADOW_RESOLUTIONS[i])
-                        } else {
-                            SHADOW_RESOLUTIONS[i].to_string()
-                        }
-                    },
+                    |i| SHADOW_RESOLUTIONS[i].to_string(),
                 );
 
                 if response.changed() {
-                    let mut csb = resources.get_mut::<ShadowMapsResource>().unwrap();
                     csb.resize(SHADOW_RESOLUTIONS[self.shadow_res_index]);
                 }
             }
 
             ui.horizontal(|ui| {
                 ui.strong("Directional Light");
+                ui.checkbox(&mut render_settings.use_map_lighting, "Use map lighting")
+                    .on_hover_text("Uses the direction and color of the map's sun (its shadowing light) instead of the manual light below");
                 ui.checkbox(&mut self.animate_light, "Animate");
             });
 
+            if render_settings.use_map_lighting {
+                let has_sun = resources
+                    .get::<MapDataList>()
+                    .unwrap()
+                    .current_map()
+                    .map_or(false, |(_, _, map)| map.sun_light().is_some());
+                ui.label(
+                    egui::RichText::new(if has_sun {
+                        "Using the sun of the current map, the manual light is used when it's disabled"
+                    } else {
+                        "The current map has no sun, using the manual light"
+                    })
+                    .weak(),
+                );
+            }
+
             if self.animate_light {
-                self.light_dir_degrees.z += delta_time * 15.0;
-                self.light_dir_degrees.z %= 360.0;
+                ui.horizontal(|ui| {
+                    for animation in LightAnimation::iter() {
+                        ui.selectable_value(
+                            &mut self.light_animation,
+                            animation,
+                            animation.to_string(),
+                        );
+                    }
+                });
+
+                match self.light_animation {
+                    LightAnimation::Spin => {
+                        self.light_dir_degrees.z += delta_time * 15.0;
+                        self.light_dir_degrees.z %= 360.0;
+                    }
+                    LightAnimation::SunPath => {
+                        self.time_of_day =
+                            (self.time_of_day + delta_time * self.sun_path.speed).rem_euclid(24.0);
+                    }
+                }
             }
 
             let mut c = render_settings.light_color.to_array();
@@ -149,27 +783,115 @@ impl Overlay for RenderSettingsOverlay {
             c[3] = 1.0;
             render_settings.light_color = Vec4::from_array(c);
 
-            ui.add(
-                egui::Slider::new(&mut self.light_dir_degrees.x, 0.0..=2.0)
-                    .text("Angle")
-                    .fixed_decimals(1),
-            );
-            ui.add_enabled_ui(!self.animate_light, |ui| {
+            if self.animate_light && self.light_animation == LightAnimation::SunPath {
+                ui.add(
+                    egui::Slider::new(&mut self.time_of_day, 0.0..=24.0)
+                        .text("Time of day")
+                        .custom_formatter(|v, _| {
+                            format!(
+                                "{:02}:{:02}",
+                                v.floor() as u32 % 24,
+                                (v.fract() * 60.0) as u32
+                            )
+                        }),
+                );
                 ui.add(
-                    egui::Slider::new(&mut self.light_dir_degrees.z, 0.0..=360.0)
-                        .text("Rotation")
+                    egui::Slider::new(&mut self.sun_path.speed, 0.0..=4.0)
+                        .text("Speed (hours/s)")
+                        .fixed_decimals(2),
+                );
+                ui.add(
+                    egui::Slider::new(&mut self.sun_path.max_elevation, 5.0..=90.0)
+                        .text("Noon elevation")
+                        .suffix("°")
+                        .fixed_decimals(0),
+                );
+                ui.add(
+                    egui::Slider::new(&mut self.sun_path.sunrise_azimuth, 0.0..=360.0)
+                        .text("Sunrise direction")
+                        .suffix("°")
+                        .fixed_decimals(0),
+                );
+
+                render_settings.light_dir = self.sun_path.direction(self.time_of_day);
+            } else {
+                ui.add(
+                    egui::Slider::new(&mut self.light_dir_degrees.x, 0.0..=2.0)
+                        .text("Angle")
                         .fixed_decimals(1),
                 );
-            });
+                ui.add_enabled_ui(!self.animate_light, |ui| {
+                    ui.add(
+                        egui::Slider::new(&mut self.light_dir_degrees.z, 0.0..=360.0)
+                            .text("Rotation")
+                            .fixed_decimals(1),
+                    );
+                });
 
-            render_settings.light_dir = Vec3::new(
-                self.light_dir_degrees.z.to_radians().sin(),
-                self.light_dir_degrees.z.to_radians().cos(),
-                self.light_dir_degrees.x,
-            );
+                render_settings.light_dir = Vec3::new(
+                    self.light_dir_degrees.z.to_radians().sin(),
+                    self.light_dir_degrees.z.to_radians().cos(),
+                    self.light_dir_degrees.x,
+                );
+            }
 
             ui.separator();
 
+            ui.collapsing("Time", |ui| {
+                let mut time = resources.get_mut::<TimeControl>().unwrap();
+                ui.horizontal(|ui| {
+                    if ui
+                        .button(if time.paused { ICON_PLAY } else { ICON_PAUSE }.to_string())
+                        .on_hover_text(if time.paused { "Play" } else { "Pause" })
+                        .clicked()
+                    {
+                        time.paused = !time.paused;
+                    }
+
+                    ui.add_enabled_ui(time.paused, |ui| {
+                        if ui
+                            .button(ICON_STEP_BACKWARD.to_string())
+                            .on_hover_text("Previous frame")
+                            .clicked()
+                        {
+                            time.step(-1);
+                        }
+                        if ui
+                            .button(ICON_STEP_FORWARD.to_string())
+                            .on_hover_text("Next frame")
+                            .clicked()
+                        {
+                            time.step(1);
+                        }
+                    });
+
+                    ui.add(
+                        egui::DragValue::new(&mut time.time)
+                            .clamp_range(0f32..=f32::MAX)
+                            .speed(0.01)
+                            .suffix("s"),
+                    );
+                });
+
+                // The slider covers the current minute. Scrubbing pauses the time, otherwise it would run away from the slider
+                let scrub_start = (time.time / 60.0).floor() * 60.0;
+                if ui
+                    .add(
+                        egui::Slider::new(&mut time.time, scrub_start..=scrub_start + 60.0)
+                            .show_value(false),
+                    )
+                    .dragged()
+                {
+                    time.paused = true;
+                }
+
+                ui.add(
+                    egui::Slider::new(&mut time.speed, 0.0..=4.0)
+                        .text("Speed")
+                        .fixed_decimals(2),
+                );
+            });
+
             ui.collapsing("Render Layers", |ui| {
                 ui.checkbox(&mut self.renderlayer_statics, "Statics");
                 ui.checkbox(
@@ -195,6 +917,51 @@ impl Overlay for RenderSettingsOverlay {
                 });
             });
 
+            ui.collapsing("Render Stages", |ui| {
+                let current = render_settings.stage_isolation();
+                ui.horizontal(|ui| {
+                    for isolation in StageIsolation::iter() {
+                        if ui
+                            .selectable_label(current == Some(isolation), isolation.to_string())
+                            .clicked()
+                        {
+                            render_settings.isolate_stages(isolation);
+                        }
+                    }
+                });
+
+                ui.checkbox(
+                    &mut render_settings.stage_generate_gbuffer,
+                    "GenerateGbuffer",
+                );
+                ui.add_enabled_ui(render_settings.stage_generate_gbuffer, |ui| {
+                    ui.indent("stage_gbuffer_color", |ui| {
+                        ui.checkbox(&mut render_settings.stage_gbuffer_color, "Color")
+                            .on_hover_text("Off keeps only the depth and normals of the geometry, so decals are shown on their own");
+                    });
+                });
+                ui.checkbox(&mut render_settings.stage_decals, "Decals");
+                ui.add_enabled_ui(render_settings.stage_decals, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::Slider::new(&mut render_settings.decal_depth_bias, 0..=256)
+                                .text("Depth bias"),
+                        );
+                        ui.add(
+                            egui::Slider::new(
+                                &mut render_settings.decal_slope_scaled_depth_bias,
+                                0.0..=8.0,
+                            )
+                            .text("Slope"),
+                        );
+                    })
+                    .response
+                    .on_hover_text("Pulls decals towards the camera to stop them from z-fighting with the surface beneath them");
+                });
+                ui.checkbox(&mut render_settings.stage_decals_additive, "DecalsAdditive");
+                ui.checkbox(&mut render_settings.stage_transparents, "Transparents");
+            });
+
             if let Some(mut enabled_overrides) = resources.get_mut::<EnabledShaderOverrides>() {
                 ui.collapsing("Shader Overrides", |ui| {
                     ui.checkbox(&mut enabled_overrides.entity_vs, "Entity (VS)");
@@ -332,55 +1099,136 @@ impl Overlay for RenderSettingsOverlay {
                     |i| COMPOSITOR_MODES[i].to_string(),
                 );
 
+            ui.checkbox(&mut render_settings.split_compare, "Split compare");
+            if render_settings.split_compare {
+                egui::ComboBox::from_label("Render Pass (right)")
+                    .width(192.0)
+                    .show_index(
+                        ui,
+                        &mut render_settings.compositor_mode_b,
+                        COMPOSITOR_MODES.len(),
+                        |i| COMPOSITOR_MODES[i].to_string(),
+                    );
+            }
+
             let mut maps = resources.get_mut::<MapDataList>().unwrap();
             if !maps.maps.is_empty() {
                 let mut current_map = maps.current_map;
-                let old_map_index = current_map;
                 let map_changed = egui::ComboBox::from_label("Map")
                     .width(192.0)
                     .show_index(ui, &mut current_map, maps.maps.len(), |i| {
                         &maps.maps[i].2.name
                     })
                     .changed();
+
+                let mut sort_mode = maps.sort_mode;
+                egui::ComboBox::from_label("Sort maps by")
+                    .width(192.0)
+                    .selected_text(sort_mode.to_string())
+                    .show_ui(ui, |ui| {
+                        for mode in MapSortMode::iter() {
+                            ui.selectable_value(&mut sort_mode, mode, mode.to_string());
+                        }
+                    });
+                if sort_mode != maps.sort_mode {
+                    maps.sort(sort_mode);
+                }
+
+                if maps.catalog.len() > 1 {
+                    let position = maps.catalog_position();
+                    let mut step = 0;
+                    ui.horizontal(|ui| {
+                        if ui
+                            .add_enabled(
+                                position.map_or(false, |p| p > 0),
+                                egui::Button::new(ICON_CHEVRON_LEFT.to_string()),
+                            )
+                            .on_hover_text("Previous map in the map list")
+                            .clicked()
+                        {
+                            step = -1;
+                        }
+                        ui.label(match position {
+                            Some(p) => format!("Map {}/{}", p + 1, maps.catalog.len()),
+                            None => format!("-/{}", maps.catalog.len()),
+                        });
+                        if ui
+                            .add_enabled(
+                                position.map_or(false, |p| p + 1 < maps.catalog.len()),
+                                egui::Button::new(ICON_CHEVRON_RIGHT.to_string()),
+                            )
+                            .on_hover_text("Next map in the map list")
+                            .clicked()
+                        {
+                            step = 1;
+                        }
+                    });
+
+                    if step != 0 {
+                        maps.step_catalog(
+                            step,
+                            &mut resources.get_mut::<SelectedEntity>().unwrap(),
+                            &mut resources.get_mut::<FpsCamera>().unwrap(),
+                        );
+                    }
+
+                    let mut prefetch = resources.get_mut::<PrefetchConfig>().unwrap();
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut prefetch.enabled, "Prefetch adjacent maps")
+                            .on_hover_text("Loads the maps next to the current one in the background, and unloads prefetched maps that haven't been visited once they're out of range");
+                        ui.add_enabled(
+                            prefetch.enabled,
+                            egui::DragValue::new(&mut prefetch.window)
+                                .clamp_range(1..=8)
+                                .prefix("±"),
+                        );
+                    });
+                }
+
                 ui.label(format!("Map hash: {}", maps.maps[maps.current_map].0));
                 ui.label(format!(
                     "Map hash64: {}",
                     maps.maps[maps.current_map].1.unwrap_or_default()
                 ));
 
-                maps.current_map = current_map;
-
-                // Move the Globals from the old scene to the new scene
                 if map_changed {
-                    // We have learned the power to Take worlds
-                    let mut old_scene = take(&mut maps.map_mut(old_map_index).unwrap().scene);
-
-                    let mut ent_list = vec![];
+                    maps.set_current_map(
+                        current_map,
+                        &mut resources.get_mut::<SelectedEntity>().unwrap(),
+                        &mut resources.get_mut::<FpsCamera>().unwrap(),
+                    );
+                }
 
-                    for (entity, global) in old_scene.query::<&Global>().iter() {
-                        if global.0 {
-                            ent_list.push(entity);
-                        }
-                    }
+                let mut unload = false;
+                let mut unload_config = resources.get_mut::<MapUnloadConfig>().unwrap();
+                ui.horizontal(|ui| {
+                    unload = ui
+                        .add_enabled(maps.maps.len() > 1, egui::Button::new("Unload map"))
+                        .on_hover_text("Frees the memory used by the current map and switches to the map used before it. The map can be loaded again from the map hash box or recent maps")
+                        .clicked();
 
-                    let mut selected = resources.get_mut::<SelectedEntity>().unwrap();
-                    if let Some(map) = maps.current_map_mut() {
-                        for entity in ent_list {
-                            let new_ent = map.scene.spawn(old_scene.take(entity).ok().unwrap());
-                            if selected.0 == Some(entity) {
-                                selected.0.replace(new_ent);
-                            }
-                        }
-                    }
-                    swap(
-                        &mut old_scene,
-                        &mut maps.map_mut(old_map_index).unwrap().scene,
+                    ui.checkbox(&mut unload_config.auto_unload, "Keep at most")
+                        .on_hover_text("Unloads the least recently used maps when more maps are loaded");
+                    ui.add_enabled(
+                        unload_config.auto_unload,
+                        egui::DragValue::new(&mut unload_config.max_loaded_maps)
+                            .clamp_range(1..=64)
+                            .suffix(" maps"),
                     );
-                }
+                });
 
-                #[cfg(feature = "discord_rpc")]
-                if map_changed {
-                    discord::set_status_from_mapdata(&maps.maps[maps.current_map].2);
+                if unload {
+                    let index = maps.current_map;
+                    let unloaded = maps.unload_map(
+                        index,
+                        &mut resources.get_mut::<SelectedEntity>().unwrap(),
+                        &mut resources.get_mut::<FpsCamera>().unwrap(),
+                    );
+                    hotkeys::release_unloaded_maps(
+                        resources,
+                        &maps,
+                        unloaded.into_iter().collect(),
+                    );
                 }
 
                 let groups_in_current_scene: IntSet<u32> = maps
@@ -408,93 +1256,207 @@ impl Overlay for RenderSettingsOverlay {
                             groups.filters.entry(*g).or_insert(true);
                         }
 
-                        for (id, enabled) in groups.filters.iter_mut() {
-                            ui.checkbox(enabled, format!("{id:08X}"));
+                        ui.horizontal(|ui| {
+                            if ui.button("All").clicked() {
+                                groups.set_all(true);
+                            }
+                            if ui.button("None").clicked() {
+                                groups.set_all(false);
+                            }
+                        });
+
+                        let mut solo = None;
+                        for id in groups.filters.keys().copied().sorted().collect_vec() {
+                            ui.horizontal(|ui| {
+                                ui.checkbox(
+                                    groups.filters.get_mut(&id).unwrap(),
+                                    format!("{id:08X}"),
+                                );
+                                if ui.small_button("Solo").clicked() {
+                                    solo = Some(id);
+                                }
+                            });
+                        }
+
+                        if let Some(id) = solo {
+                            groups.solo(id);
+                        }
+                    });
+                }
+
+                let sources = maps
+                    .current_map()
+                    .unwrap()
+                    .2
+                    .sources
+                    .iter()
+                    .map(|s| (s.hash, s.name.clone(), s.visible))
+                    .collect_vec();
+                if !sources.is_empty() {
+                    ui.collapsing("Source Maps", |ui| {
+                        let map = maps.current_map_mut().unwrap();
+                        for (hash, name, mut visible) in sources {
+                            if ui.checkbox(&mut visible, name).changed() {
+                                map.set_source_visible(hash, visible);
+                            }
+                        }
+                    });
+                }
+
+                if maps.maps.len() > 1 {
+                    ui.collapsing("Merge Maps", |ui| {
+                        ui.label("Combines the entities of the ticked maps into a single map, for viewing adjacent maps together. This can't be undone");
+
+                        for (hash, _, map) in &maps.maps {
+                            let mut ticked = self.merge_selection.contains(hash);
+                            if ui.checkbox(&mut ticked, &map.name).changed() {
+                                if ticked {
+                                    self.merge_selection.push(*hash);
+                                } else {
+                                    self.merge_selection.retain(|h| h != hash);
+                                }
+                            }
+                        }
+
+                        if ui
+                            .add_enabled(
+                                self.merge_selection.len() >= 2,
+                                egui::Button::new(format!("{} Merge", ICON_SET_MERGE)),
+                            )
+                            .clicked()
+                        {
+                            // Combined maps aren't autosaved, so save the source maps while they still have their own sidecar
+                            let mut autosave = resources.get_mut::<AutosaveState>().unwrap();
+                            for (hash, _, map) in &maps.maps {
+                                if self.merge_selection.contains(hash) {
+                                    persistence::autosave_map(map, &mut autosave);
+                                }
+                            }
+                            drop(autosave);
+
+                            if let Err(e) = maps.merge_maps(
+                                &self.merge_selection,
+                                &mut resources.get_mut::<SelectedEntity>().unwrap(),
+                            ) {
+                                error!("Failed to merge maps: {e}");
+                            }
+                            self.merge_selection.clear();
                         }
                     });
                 }
             }
+
+            ui.collapsing("Camera", |ui| {
+                let mut camera = resources.get_mut::<FpsCamera>().unwrap();
+                let (position, orientation) = (camera.position, camera.orientation);
+                ui.label(format!(
+                    "Position: {:.3}, {:.3}, {:.3}",
+                    position.x, position.y, position.z
+                ));
+                ui.label(format!(
+                    "Rotation: {:.2}° pitch, {:.2}° yaw",
+                    orientation.x, orientation.y
+                ));
+
+                if ui
+                    .button(format!("{} Copy location", ICON_CLIPBOARD))
+                    .on_hover_text(
+                        "Copies a goto command that can be pasted below or in the console",
+                    )
+                    .clicked()
+                {
+                    ui.output_mut(|o| {
+                        o.copied_text = format!(
+                            "goto {} {} {} {} {}",
+                            position.x, position.y, position.z, orientation.x, orientation.y
+                        )
+                    });
+                }
+
+                ui.horizontal(|ui| {
+                    let response = ui.add(
+                        egui::TextEdit::singleline(&mut self.camera_location_input)
+                            .hint_text("x y z [pitch yaw]")
+                            .desired_width(192.0),
+                    );
+                    let submitted =
+                        response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+
+                    if ui.button("Teleport").clicked() || submitted {
+                        match parse_camera_location(&self.camera_location_input) {
+                            Ok((position, orientation)) => {
+                                self.camera_location_error = None;
+                                camera.teleport(position, orientation);
+                            }
+                            Err(e) => self.camera_location_error = Some(e.to_string()),
+                        }
+                    }
+                });
+
+                if let Some(error) = &self.camera_location_error {
+                    ui.colored_label(egui::Color32::RED, error);
+                }
+            });
+
+            ui.horizontal(|ui| {
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut self.map_hash_input)
+                        .hint_text("Map hash")
+                        .desired_width(128.0),
+                );
+                let submitted =
+                    response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+
+                if ui.button("Load map").clicked() || submitted {
+                    match map::parse_map_hash(&self.map_hash_input) {
+                        Ok(hash) => {
+                            self.map_hash_error = None;
+                            maps.switch_or_load(
+                                hash,
+                                &mut resources.get_mut::<SelectedEntity>().unwrap(),
+                                &mut resources.get_mut::<FpsCamera>().unwrap(),
+                            );
+                        }
+                        Err(e) => self.map_hash_error = Some(e.to_string()),
+                    }
+                }
+            });
+
+            if let Some(error) = &self.map_hash_error {
+                ui.colored_label(egui::Color32::RED, error);
+            }
         });
 
+        if render_settings.split_compare {
+            Self::draw_split_divider(ctx, &mut render_settings);
+        }
+
+        if let Some(aspect) = render_settings.letterbox_aspect {
+            Self::draw_letterbox(ctx, aspect, render_settings.letterbox_color);
+        }
+
         true
     }
 }
 
-#[repr(u32)]
-#[derive(Clone, Copy, Debug)]
-pub enum CompositorMode {
-    /// Rendered output
-    Combined,
-
-    /// RT0
-    Albedo,
-    /// RT1
-    Normal,
-    /// RT2
-    PbrStack,
-    RT3,
-
-    SmoothnessFuzz,
-    Metalicness,
-    TextureAO,
-    Emission,
-    Transmission,
-    VertexAO,
-    Iridescence,
-    Cubemap,
-    Matcap,
-    Depth,
-    Specular,
-
-    LightRT0,
-    LightRT1,
-}
+/// Parses a camera location in the form `x y z [pitch yaw]`, as copied from the camera section or used by the goto command.
+/// Values can be separated by spaces and/or commas
+fn parse_camera_location(s: &str) -> anyhow::Result<(Vec3, Option<Vec2>)> {
+    let s = s.trim();
+    let s = s.strip_prefix("goto").unwrap_or(s);
+    let values = s
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|v| !v.is_empty())
+        .map(|v| {
+            v.parse::<f32>()
+                .map_err(|_| anyhow::anyhow!("'{v}' is not a number"))
+        })
+        .collect::<anyhow::Result<Vec<f32>>>()?;
 
-pub const COMPOSITOR_MODES: &[CompositorMode] = &[
-    CompositorMode::Combined,       // 0
-    CompositorMode::Albedo,         // 1
-    CompositorMode::Normal,         // 2
-    CompositorMode::PbrStack,       // 3
-    CompositorMode::RT3,            // 4
-    CompositorMode::SmoothnessFuzz, // 5
-    CompositorMode::Metalicness,    // 6
-    CompositorMode::TextureAO,      // 7
-    CompositorMode::Emission,       // 8
-    CompositorMode::Transmission,   // 9
-    CompositorMode::VertexAO,       // 10
-    CompositorMode::Iridescence,    // 11
-    CompositorMode::Cubemap,        // 12
-    CompositorMode::Matcap,         // 13
-    CompositorMode::Depth,          // 14
-    CompositorMode::Specular,       // 15
-    CompositorMode::LightRT0,       // 16
-    CompositorMode::LightRT1,       // 17
-];
-
-impl Display for CompositorMode {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let name = match self {
-            CompositorMode::Combined => "Combined",
-            CompositorMode::Albedo => "Albedo (RT0)",
-            CompositorMode::Normal => "Normal (RT1)",
-            CompositorMode::PbrStack => "PBR Stack (RT2)",
-            CompositorMode::RT3 => "RT3",
-            CompositorMode::SmoothnessFuzz => "Smoothness/Fuzz",
-            CompositorMode::Metalicness => "Metalicness",
-            CompositorMode::TextureAO => "Texture AO",
-            CompositorMode::Emission => "Emission",
-            CompositorMode::Transmission => "Transmission",
-            CompositorMode::VertexAO => "Vertex AO",
-            CompositorMode::Iridescence => "Iridescence",
-            CompositorMode::Cubemap => "Cubemap",
-            CompositorMode::Matcap => "Matcap",
-            CompositorMode::Depth => "Depth",
-            CompositorMode::Specular => "Specular",
-            CompositorMode::LightRT0 => "LightRT0",
-            CompositorMode::LightRT1 => "LightRT1",
-        };
-
-        f.write_str(name)
+    match values[..] {
+        [x, y, z] => Ok((Vec3::new(x, y, z), None)),
+        [x, y, z, pitch, yaw] => Ok((Vec3::new(x, y, z), Some(Vec2::new(pitch, yaw)))),
+        _ => anyhow::bail!("Expected 3 or 5 values, got {}", values.len()),
     }
 }
 
@@ -514,27 +1476,88 @@ pub struct CompositorOptions {
     pub time: f32,
     pub mode: u32,
     pub draw_lights: u32,
-    pub fxaa_enabled: u32,
+    /// 0 disables FXAA, see [`AntiAliasing::fxaa_quality`]
+    pub fxaa_quality: u32,
+    pub tonemap: u32,
+    pub exposure: f32,
+    /// Mode shown right of the split divider
+    pub mode_b: u32,
+    /// Horizontal position of the split divider (0.0-1.0)
+    pub split_position: f32,
+    pub background: u32,
+    pub background_color: Vec4,
+    pub background_color_top: Vec4,
+    /// Depth of pixels without any geometry
+    pub clear_depth: f32,
+    pub fog_enabled: u32,
+    pub fog_start: f32,
+    pub fog_density: f32,
+    pub fog_color: Vec4,
+    /// See [`crate::render::settings::CompositorMode::clear_color`]
+    pub clear_color: Vec4,
+    /// Clear color of the mode shown right of the split divider
+    pub clear_color_b: Vec4,
+    /// Set when blending entities with an opacity override over the previous shading pass
+    pub opacity_pass: u32,
+    pub opacity: f32,
+}
+
+/// How the directional light moves when it's animated
+#[derive(strum::Display, strum::EnumIter, Clone, Copy, PartialEq, Eq)]
+pub enum LightAnimation {
+    /// Rotates around the vertical axis at a fixed angle
+    Spin,
+    /// Rises, peaks at noon and sets like the sun
+    #[strum(serialize = "Sun path")]
+    SunPath,
 }
 
-pub struct RenderSettings {
-    pub use_global_deferred_shading: bool,
-    pub draw_lights: bool,
-    pub draw_errors: bool,
-    pub error_scale: f32,
-    pub render_shadows: bool,
-    pub alpha_blending: bool,
-    pub compositor_mode: usize,
-    pub blend_override: usize,
-    pub evaluate_bytecode: bool,
-    pub ambient_light: Vec4,
-    pub light_dir: Vec3,
-    pub light_color: Vec4,
-    pub use_specular_map: bool,
-    pub fxaa: bool,
-    pub light_mul: f32,
+/// Simple day-night arc for the directional light. The sun rises at 06:00, peaks at 12:00 and sets at 18:00
+pub struct SunPath {
+    /// Elevation at noon, in degrees
+    pub max_elevation: f32,
+    /// Direction the sun rises from, in degrees. It sets on the opposite side
+    pub sunrise_azimuth: f32,
+    /// Hours of in-game time per second when animating
+    pub speed: f32,
 }
 
+impl Default for SunPath {
+    fn default() -> Self {
+        Self {
+            max_elevation: 60.0,
+            sunrise_azimuth: 90.0,
+            speed: 0.5,
+        }
+    }
+}
+
+impl SunPath {
+    /// Direction towards the sun at the given time of day (in hours).
+    /// At night the sun stays on the horizon, slowly travelling back to the sunrise direction
+    pub fn direction(&self, time_of_day: f32) -> Vec3 {
+        // 0 at sunrise, 1 at sunset, and beyond that until the next sunrise
+        let day_progress = (time_of_day - 6.0).rem_euclid(24.0) / 12.0;
+        let elevation = (self.max_elevation.to_radians() * (day_progress * PI).sin()).max(0.0);
+        let azimuth = self.sunrise_azimuth.to_radians() + day_progress * PI;
+
+        Vec3::new(
+            azimuth.sin() * elevation.cos(),
+            azimuth.cos() * elevation.cos(),
+            elevation.sin(),
+        )
+    }
+}
+
+/// Aspect ratios the viewport can be letterboxed to, `None` uses the whole window
+const LETTERBOX_ASPECTS: &[(&str, Option<f32>)] = &[
+    ("Window", None),
+    ("16:9", Some(16.0 / 9.0)),
+    ("21:9", Some(21.0 / 9.0)),
+    ("4:3", Some(4.0 / 3.0)),
+    ("1:1", Some(1.0)),
+];
+
 #[repr(C)]
 #[derive(Default)]
 pub struct PickbufferScope {
@@ -551,29 +1574,27 @@ impl PickbufferScope {
     }
 }
 
-impl Default for RenderSettings {
-    fn default() -> Self {
-        Self {
-            use_global_deferred_shading: false,
-            compositor_mode: CompositorMode::Combined as usize,
-            alpha_blending: true,
-            draw_lights: false,
-            draw_errors: true,
-            error_scale: 1.0,
-            render_shadows: true,
-            blend_override: 0,
-            evaluate_bytecode: false,
-            ambient_light: Vec4::splat(0.04),
-            light_dir: Vec3::NEG_Z,
-            light_color: Vec4::ONE,
-            use_specular_map: true,
-            fxaa: true,
-            light_mul: 1.0,
-        }
-    }
-}
-
 #[derive(Default)]
 pub struct ActivityGroupFilter {
     pub filters: IntMap<u32, bool>,
 }
+
+impl ActivityGroupFilter {
+    /// Entities without an activity group, or in a group that isn't in the filter list, are always visible
+    pub fn is_visible(&self, group: Option<&ActivityGroup>) -> bool {
+        group.map_or(true, |g| *self.filters.get(&g.0).unwrap_or(&true))
+    }
+
+    /// Enables the given group, and disables all others
+    pub fn solo(&mut self, id: u32) {
+        for (group, enabled) in self.filters.iter_mut() {
+            *enabled = *group == id;
+        }
+    }
+
+    pub fn set_all(&mut self, enabled: bool) {
+        for v in self.filters.values_mut() {
+            *v = enabled;
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use std::time::{Duration, Instant};
+
+use egui::{Align2, Color32, RichText};
+use winit::window::Window;
+
+use crate::{render::renderer::RendererShared, resources::Resources, util::text::prettify_bytes};
+
+use super::gui::Overlay;
+
+/// Walking every loaded asset isn't free, so memory usage is only refreshed periodically
+const MEMORY_UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct RenderStatsOverlay {
+    pub visible: bool,
+
+    /// Texture and buffer memory, in bytes
+    memory_usage: (usize, usize),
+    last_memory_update: Option<Instant>,
+}
+
+impl Default for RenderStatsOverlay {
+    fn default() -> Self {
+        Self {
+            visible: false,
+            memory_usage: (0, 0),
+            last_memory_update: None,
+        }
+    }
+}
+
+impl Overlay for RenderStatsOverlay {
+    fn draw(
+        &mut self,
+        ctx: &egui::Context,
+        _window: &Window,
+        resources: &mut Resources,
+        _gui: &mut super::gui::GuiContext<'_>,
+    ) -> bool {
+        if !self.visible {
+            self.last_memory_update = None;
+            return true;
+        }
+
+        let renderer = resources.get::<RendererShared>().unwrap();
+        let renderer = renderer.read();
+
+        if self
+            .last_memory_update
+            .map_or(true, |t| t.elapsed() > MEMORY_UPDATE_INTERVAL)
+        {
+            self.memory_usage = renderer.render_data.data().memory_usage();
+            self.last_memory_update = Some(Instant::now());
+        }
+
+        let stats = renderer.stats();
+        let delta_time = *renderer.delta_time.read();
+
+        egui::Window::new("Render Statistics")
+            .title_bar(false)
+            .resizable(false)
+            .anchor(Align2::RIGHT_TOP, [-8.0, 32.0])
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "Frame time: {:.2} ms ({:.0} fps)",
+                    delta_time * 1000.0,
+                    1.0 / delta_time
+                ));
+                ui.label(format!("Visible entities: {}", stats.visible_entities));
+                ui.label(format!(
+                    "Frustum culling: {} visible, {} culled",
+                    stats.instances_visible, stats.instances_culled
+                ));
+
+                ui.separator();
+                ui.label(format!("Draw calls: {}", stats.total_draw_calls()));
+                ui.label(format!("Triangles: {}", stats.triangles));
+                for (stage, count) in stats.active_stages() {
+                    ui.label(
+                        RichText::new(format!("  {stage:?}: {count}")).color(Color32::LIGHT_GRAY),
+                    );
+                }
+
+                ui.separator();
+                let (textures, buffers) = self.memory_usage;
+                ui.label(format!(
+                    "VRAM (approx.): {}",
+                    prettify_bytes(textures + buffers)
+                ));
+                ui.label(
+                    RichText::new(format!("  Textures: {}", prettify_bytes(textures)))
+                        .color(Color32::LIGHT_GRAY),
+                );
+                ui.label(
+                    RichText::new(format!("  Buffers: {}", prettify_bytes(buffers)))
+                        .color(Color32::LIGHT_GRAY),
+                );
+            });
+
+        true
+    }
+}
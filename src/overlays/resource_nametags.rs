@@ -1,8 +1,9 @@
 use crate::{
     camera::FpsCamera,
     ecs::{
-        components::{Label, ResourceOriginType, ResourcePoint, Visible},
+        components::{ActivityGroup, Label, ResourceOriginType, ResourcePoint, Visible},
         resources::SelectedEntity,
+        tags::{TagPalette, Tags},
         transform::Transform,
     },
     map::MapDataList,
@@ -17,7 +18,9 @@ use glam::Vec2;
 use std::{cell::RefCell, rc::Rc};
 use winit::window::Window;
 
-use super::{camera_settings::CameraPositionOverlay, gui::Overlay};
+use super::{
+    camera_settings::CameraPositionOverlay, gui::Overlay, render_settings::ActivityGroupFilter,
+};
 
 pub struct ResourceTypeOverlay {
     pub debug_overlay: Rc<RefCell<CameraPositionOverlay>>,
@@ -65,8 +68,8 @@ impl Overlay for ResourceTypeOverlay {
         }
 
         if self.debug_overlay.borrow().show_map_resources {
-            let SelectedEntity(selected_entity, block_entity_selection) =
-                *resources.get::<SelectedEntity>().unwrap();
+            let selection = resources.get::<SelectedEntity>().unwrap().clone();
+            let block_entity_selection = selection.1;
 
             let maps = resources.get::<MapDataList>().unwrap();
             if let Some((_, _, m)) = maps.current_map() {
@@ -75,17 +78,28 @@ impl Overlay for ResourceTypeOverlay {
                     has_havok_data: bool,
                     origin: ResourceOriginType,
                     label: Option<String>,
+                    tint: Option<Color32>,
                 }
 
+                let palette = resources.get::<TagPalette>().unwrap();
+                let group_filter = resources.get::<ActivityGroupFilter>().unwrap();
+
                 let mut rp_list = vec![];
 
-                for (e, (transform, res, label, visible)) in m
+                for (e, (transform, res, label, visible, tags, group)) in m
                     .scene
-                    .query::<(&Transform, &ResourcePoint, Option<&Label>, Option<&Visible>)>()
+                    .query::<(
+                        &Transform,
+                        &ResourcePoint,
+                        Option<&Label>,
+                        Option<&Visible>,
+                        Option<&Tags>,
+                        Option<&ActivityGroup>,
+                    )>()
                     .iter()
                 {
-                    let distance = if selected_entity != Some(e) {
-                        if !visible.map_or(true, |v| v.0) {
+                    let distance = if !selection.is_selected(e) {
+                        if !visible.map_or(true, |v| v.0) || !group_filter.is_visible(group) {
                             continue;
                         }
 
@@ -127,7 +141,7 @@ impl Overlay for ResourceTypeOverlay {
                         0.0
                     };
 
-                    if visible.map_or(true, |v| v.0) || selected_entity == Some(e) {
+                    if visible.map_or(true, |v| v.0) || selection.is_selected(e) {
                         // Draw the debug shape before we cull the points to prevent shapes from popping in/out when the point goes off/onscreen
                         let mut debug_shapes = resources.get_mut::<DebugShapes>().unwrap();
                         res.resource.draw_debug_shape(transform, &mut debug_shapes);
@@ -146,6 +160,7 @@ impl Overlay for ResourceTypeOverlay {
                             has_havok_data: res.has_havok_data,
                             origin: res.origin,
                             label: label.map(|v| v.0.clone()),
+                            tint: tags.and_then(|t| palette.color_for(t)),
                         },
                     ))
                 }
@@ -171,10 +186,11 @@ impl Overlay for ResourceTypeOverlay {
 
                         if pressed
                             && button == egui::PointerButton::Secondary
-                            && modifiers.is_none()
+                            && (modifiers.is_none() || modifiers.ctrl)
                         {
                             for (e, _, transform, res) in &rp_list {
-                                if selected_entity == Some(*e) {
+                                // Ctrl+click on a selected entity removes it from the selection
+                                if selection.is_selected(*e) && !modifiers.ctrl {
                                     continue;
                                 }
 
@@ -225,8 +241,14 @@ impl Overlay for ResourceTypeOverlay {
                                     };
 
                                 if select_rect.contains(pos) {
-                                    *resources.get_mut::<SelectedEntity>().unwrap() =
-                                        SelectedEntity(Some(*e), true);
+                                    let mut selected =
+                                        resources.get_mut::<SelectedEntity>().unwrap();
+                                    if modifiers.ctrl {
+                                        selected.toggle(*e);
+                                    } else {
+                                        selected.select(Some(*e));
+                                    }
+                                    selected.1 = true;
                                     break;
                                 }
                             }
@@ -247,9 +269,11 @@ impl Overlay for ResourceTypeOverlay {
                     );
 
                     let c = res.resource.debug_color();
-                    let color = egui::Color32::from_rgb(c[0], c[1], c[2]);
+                    let color = res
+                        .tint
+                        .unwrap_or_else(|| egui::Color32::from_rgb(c[0], c[1], c[2]));
                     if self.debug_overlay.borrow().show_map_resource_label
-                        || selected_entity == Some(e)
+                        || selection.is_selected(e)
                     {
                         let debug_string = res.resource.debug_string();
                         let debug_string = if let Some(l) = res.label {
@@ -273,7 +297,7 @@ impl Overlay for ResourceTypeOverlay {
                         );
                         debug_string_rect.extend_with_x(debug_string_pos.x - 11.0 - 14.0);
 
-                        if selected_entity == Some(e) {
+                        if selection.is_selected(e) {
                             painter.rect(
                                 debug_string_rect.expand(8.0),
                                 egui::Rounding::same(4.0),
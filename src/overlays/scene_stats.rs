@@ -0,0 +1,192 @@
+use std::time::{Duration, Instant};
+
+use destiny_pkg::TagHash;
+use egui::{Color32, RichText};
+use nohash_hasher::IntSet;
+use winit::window::Window;
+
+use crate::{
+    ecs::{
+        components::{
+            Beacon, CubemapVolume, EntityModel, Note, PointLight, ResourcePoint, Route, Ruler,
+            Sphere, StaticInstances, Terrain, Water,
+        },
+        Scene,
+    },
+    map::MapDataList,
+    render::{renderer::RendererShared, EntityRenderers, RenderData},
+    resources::Resources,
+    util::text::prettify_bytes,
+};
+
+use super::gui::Overlay;
+
+/// Counting buffers means walking the whole scene, so the stats are only refreshed periodically
+const UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+
+struct CategoryStats {
+    name: &'static str,
+    entities: usize,
+    /// Unique vertex/index buffers used by the entities in this category
+    buffers: IntSet<TagHash>,
+    bytes: usize,
+}
+
+impl CategoryStats {
+    fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            entities: 0,
+            buffers: Default::default(),
+            bytes: 0,
+        }
+    }
+
+    fn add(&mut self, buffers: impl IntoIterator<Item = TagHash>) {
+        self.entities += 1;
+        self.buffers.extend(buffers);
+    }
+}
+
+/// Lists how many entities of each kind the current map has, and how much buffer memory they use
+#[derive(Default)]
+pub struct SceneStatsWindow {
+    categories: Vec<CategoryStats>,
+    /// Buffer memory of all categories, buffers shared between categories are only counted once
+    total_bytes: usize,
+    last_update: Option<Instant>,
+}
+
+impl SceneStatsWindow {
+    fn update(&mut self, scene: &Scene, entity_renderers: &EntityRenderers, data: &RenderData) {
+        let mut statics = CategoryStats::new("Static instances");
+        for (_, si) in scene.query::<&StaticInstances>().iter() {
+            statics.add(si.0.buffers());
+        }
+
+        let mut entities = CategoryStats::new("Map entities");
+        for (_, rp) in scene.query::<&ResourcePoint>().iter() {
+            entities.add(
+                entity_renderers
+                    .0
+                    .get(&rp.entity_key())
+                    .map(|er| er.buffers())
+                    .unwrap_or_default(),
+            );
+        }
+
+        let mut spawned = CategoryStats::new("Spawned entity models");
+        for (_, em) in scene.query::<&EntityModel>().iter() {
+            spawned.add(em.0.buffers());
+        }
+
+        let mut terrain = CategoryStats::new("Terrain");
+        for (_, t) in scene.query::<&Terrain>().iter() {
+            terrain.add(t.0.buffers());
+        }
+
+        let mut lights = CategoryStats::new("Point lights");
+        lights.entities = scene.query::<&PointLight>().iter().count();
+
+        let mut cubemaps = CategoryStats::new("Cubemap volumes");
+        cubemaps.entities = scene.query::<&CubemapVolume>().iter().count();
+
+        let mut water = CategoryStats::new("Water");
+        water.entities = scene.query::<&Water>().iter().count();
+
+        let mut utilities = CategoryStats::new("Utilities");
+        utilities.entities = scene
+            .iter()
+            .filter(|e| {
+                e.has::<Ruler>()
+                    || e.has::<Sphere>()
+                    || e.has::<Beacon>()
+                    || e.has::<Route>()
+                    || e.has::<Note>()
+            })
+            .count();
+
+        self.categories = vec![
+            statics, entities, spawned, terrain, lights, cubemaps, water, utilities,
+        ];
+
+        let mut all_buffers = IntSet::default();
+        for c in &mut self.categories {
+            c.bytes = c.buffers.iter().map(|&b| data.buffer_size(b)).sum();
+            all_buffers.extend(c.buffers.iter().copied());
+        }
+        self.total_bytes = all_buffers.iter().map(|&b| data.buffer_size(b)).sum();
+
+        self.categories.retain(|c| c.entities > 0);
+        self.categories
+            .sort_by(|a, b| b.bytes.cmp(&a.bytes).then(b.entities.cmp(&a.entities)));
+    }
+}
+
+impl Overlay for SceneStatsWindow {
+    fn draw(
+        &mut self,
+        ctx: &egui::Context,
+        _window: &Window,
+        resources: &mut Resources,
+        _gui: &mut super::gui::GuiContext<'_>,
+    ) -> bool {
+        if self
+            .last_update
+            .map_or(true, |t| t.elapsed() > UPDATE_INTERVAL)
+        {
+            let maps = resources.get::<MapDataList>().unwrap();
+            if let Some((_, _, map)) = maps.current_map() {
+                let renderer = resources.get::<RendererShared>().unwrap();
+                let renderer = renderer.read();
+                self.update(
+                    &map.scene,
+                    &resources.get::<EntityRenderers>().unwrap(),
+                    &renderer.render_data.data(),
+                );
+            }
+            self.last_update = Some(Instant::now());
+        }
+
+        let mut open = true;
+        egui::Window::new("Scene Statistics")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("scene_stats_grid")
+                    .striped(true)
+                    .num_columns(3)
+                    .show(ui, |ui| {
+                        ui.strong("Category");
+                        ui.strong("Entities");
+                        ui.strong("Buffer memory");
+                        ui.end_row();
+
+                        for c in &self.categories {
+                            ui.label(c.name);
+                            ui.label(c.entities.to_string());
+                            if c.buffers.is_empty() {
+                                ui.label(RichText::new("-").color(Color32::GRAY));
+                            } else {
+                                ui.label(format!(
+                                    "{} ({} buffers)",
+                                    prettify_bytes(c.bytes),
+                                    c.buffers.len()
+                                ));
+                            }
+                            ui.end_row();
+                        }
+                    });
+
+                ui.separator();
+                ui.label(format!("Total: {}", prettify_bytes(self.total_bytes)));
+                ui.label(
+                    RichText::new("Vertex and index buffers only. Buffers shared by multiple categories count towards each of them")
+                        .weak()
+                        .italics(),
+                );
+            });
+
+        open
+    }
+}
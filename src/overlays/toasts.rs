@@ -0,0 +1,64 @@
+use std::time::{Duration, Instant};
+
+use egui::{Color32, RichText};
+use winit::window::Window;
+
+use crate::{icons::ICON_ALERT, resources::Resources};
+
+use super::gui::Overlay;
+
+const TOAST_DURATION: Duration = Duration::from_secs(6);
+
+/// Short-lived warnings shown in the bottom right corner of the screen
+#[derive(Default)]
+pub struct Toasts {
+    toasts: Vec<(String, Instant)>,
+}
+
+impl Toasts {
+    pub fn warning(&mut self, message: impl Into<String>) {
+        let message = message.into();
+        warn!("{message}");
+        self.toasts.push((message, Instant::now()));
+    }
+}
+
+pub struct ToastOverlay;
+
+impl Overlay for ToastOverlay {
+    fn draw(
+        &mut self,
+        ctx: &egui::Context,
+        _window: &Window,
+        resources: &mut Resources,
+        _gui: &mut super::gui::GuiContext<'_>,
+    ) -> bool {
+        let mut toasts = resources.get_mut::<Toasts>().unwrap();
+        toasts
+            .toasts
+            .retain(|(_, time)| time.elapsed() < TOAST_DURATION);
+
+        if toasts.toasts.is_empty() {
+            return true;
+        }
+
+        egui::Area::new("toasts")
+            .anchor(egui::Align2::RIGHT_BOTTOM, [-12.0, -12.0])
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui| {
+                for (message, _) in &toasts.toasts {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.label(
+                            RichText::new(format!("{ICON_ALERT} {message}"))
+                                .color(Color32::from_rgb(255, 200, 60)),
+                        );
+                    });
+                }
+            });
+
+        // Keep repainting so toasts disappear on time
+        ctx.request_repaint();
+
+        true
+    }
+}
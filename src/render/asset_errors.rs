@@ -0,0 +1,65 @@
+use std::time::Instant;
+
+use crate::{structure::ExtendedHash, util::RwLock};
+
+/// Assets that failed to load or upload, collected for the load errors window
+pub static ASSET_ERRORS: AssetErrorLog = AssetErrorLog::new();
+
+#[derive(Clone, Copy, PartialEq, Eq, strum::Display)]
+pub enum AssetKind {
+    Texture,
+    #[strum(serialize = "Vertex buffer")]
+    VertexBuffer,
+    #[strum(serialize = "Index buffer")]
+    IndexBuffer,
+    #[strum(serialize = "Static model")]
+    StaticModel,
+    Entity,
+    Terrain,
+}
+
+#[derive(Clone)]
+pub struct AssetError {
+    pub kind: AssetKind,
+    pub hash: ExtendedHash,
+    pub reason: String,
+    pub time: Instant,
+}
+
+pub struct AssetErrorLog(RwLock<Vec<AssetError>>);
+
+impl AssetErrorLog {
+    pub const fn new() -> Self {
+        Self(RwLock::new(Vec::new()))
+    }
+
+    /// Records a load error. An asset that fails again only keeps its latest reason
+    pub fn push(&self, kind: AssetKind, hash: ExtendedHash, reason: impl ToString) {
+        let error = AssetError {
+            kind,
+            hash,
+            reason: reason.to_string(),
+            time: Instant::now(),
+        };
+
+        let mut errors = self.0.write();
+        errors.retain(|e| !(e.kind == kind && e.hash == hash));
+        errors.push(error);
+    }
+
+    pub fn snapshot(&self) -> Vec<AssetError> {
+        self.0.read().clone()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.read().is_empty()
+    }
+
+    pub fn clear(&self) {
+        self.0.write().clear();
+    }
+}
@@ -0,0 +1,103 @@
+use std::sync::Arc;
+
+use windows::Win32::Graphics::{
+    Direct3D::D3D_PRIMITIVE_TOPOLOGY_TRIANGLESTRIP,
+    Direct3D11::{ID3D11PixelShader, ID3D11VertexShader},
+};
+
+use super::{bytecode::externs::TfxShaderStage, shader, ConstantBuffer, DeviceContextSwapchain};
+
+#[repr(C)]
+pub struct BloomOptions {
+    pub threshold: f32,
+    pub intensity: f32,
+    _pad: [f32; 2],
+}
+
+impl BloomOptions {
+    pub fn new(threshold: f32, intensity: f32) -> Self {
+        Self {
+            threshold,
+            intensity,
+            _pad: [0.0; 2],
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum BloomPass {
+    /// Downsample the scene and keep only the bright parts
+    Extract,
+    BlurHorizontal,
+    BlurVertical,
+    /// Scale the blurred result by the intensity, meant to be drawn with additive blending
+    Composite,
+}
+
+pub struct BloomEffect {
+    bloom_vs: ID3D11VertexShader,
+    extract_ps: ID3D11PixelShader,
+    blur_h_ps: ID3D11PixelShader,
+    blur_v_ps: ID3D11PixelShader,
+    composite_ps: ID3D11PixelShader,
+    scope: ConstantBuffer<BloomOptions>,
+}
+
+impl BloomEffect {
+    pub fn create(dcs: Arc<DeviceContextSwapchain>) -> anyhow::Result<Self> {
+        let vshader_blob = shader::compile_hlsl(
+            include_str!("../../assets/shaders/bloom.hlsl"),
+            "VShader",
+            "vs_5_0",
+            "bloom.hlsl",
+        )
+        .unwrap();
+        let (bloom_vs, _) = shader::load_vshader(&dcs, &vshader_blob)?;
+
+        let load_ps = |entrypoint: &str| -> anyhow::Result<ID3D11PixelShader> {
+            let blob = shader::compile_hlsl(
+                include_str!("../../assets/shaders/bloom.hlsl"),
+                entrypoint,
+                "ps_5_0",
+                "bloom.hlsl",
+            )
+            .unwrap();
+
+            Ok(shader::load_pshader(&dcs, &blob)?.0)
+        };
+
+        Ok(Self {
+            bloom_vs,
+            extract_ps: load_ps("PSExtract")?,
+            blur_h_ps: load_ps("PSBlurHorizontal")?,
+            blur_v_ps: load_ps("PSBlurVertical")?,
+            composite_ps: load_ps("PSComposite")?,
+            scope: ConstantBuffer::create(dcs, None)?,
+        })
+    }
+
+    pub fn write_options(&self, options: &BloomOptions) -> anyhow::Result<()> {
+        self.scope.write(options)
+    }
+
+    /// Expects the source texture to be bound to t0, and the destination and viewport to be set up
+    pub fn draw(&self, dcs: &DeviceContextSwapchain, pass: BloomPass) {
+        let ps = match pass {
+            BloomPass::Extract => &self.extract_ps,
+            BloomPass::BlurHorizontal => &self.blur_h_ps,
+            BloomPass::BlurVertical => &self.blur_v_ps,
+            BloomPass::Composite => &self.composite_ps,
+        };
+
+        self.scope.bind(0, TfxShaderStage::Pixel);
+        unsafe {
+            dcs.context().VSSetShader(&self.bloom_vs, None);
+            dcs.context().PSSetShader(ps, None);
+            dcs.context()
+                .IASetPrimitiveTopology(D3D_PRIMITIVE_TOPOLOGY_TRIANGLESTRIP);
+            dcs.context().Draw(4, 0);
+
+            dcs.context().PSSetShaderResources(0, Some(&[None]));
+        }
+    }
+}
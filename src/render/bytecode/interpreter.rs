@@ -1,6 +1,7 @@
 use std::{mem::transmute, ops::Neg};
 
 use glam::{Mat4, Vec3, Vec4, Vec4Swizzles};
+use itertools::Itertools;
 use tinyvec::ArrayVec;
 
 use crate::render::{cbuffer::ConstantBufferCached, renderer::Renderer, RenderData};
@@ -395,11 +396,11 @@ impl TfxBytecodeInterpreter {
     ) -> anyhow::Result<f32> {
         match extern_ {
             TfxExtern::Frame => match offset {
-                0 => Ok(renderer.start_time.elapsed().as_secs_f32()),
+                0 => Ok(*renderer.game_time.read()),
 
                 // TODO(cohae): wrooong
-                1 => Ok(renderer.start_time.elapsed().as_secs_f32()),
-                4 => Ok(renderer.start_time.elapsed().as_secs_f32()),
+                1 => Ok(*renderer.game_time.read()),
+                4 => Ok(*renderer.game_time.read()),
 
                 // Light mul
                 7 => Ok(*renderer.light_mul.read()),
@@ -634,6 +635,26 @@ impl TfxBytecodeInterpreter {
         }
     }
 
+    pub fn opcodes(&self) -> &[TfxBytecodeOp] {
+        &self.opcodes
+    }
+
+    /// Constant buffer elements written by the bytecode
+    pub fn output_elements(&self) -> Vec<usize> {
+        self.opcodes
+            .iter()
+            .flat_map(|op| match op {
+                TfxBytecodeOp::PopOutput { element } => *element as usize..*element as usize + 1,
+                TfxBytecodeOp::PopOutputMat4 { element } => {
+                    *element as usize..*element as usize + 4
+                }
+                _ => 0..0,
+            })
+            .sorted()
+            .dedup()
+            .collect()
+    }
+
     pub fn dump(&self, constants: &[Vec4], buffer: &ConstantBufferCached<Vec4>) {
         debug!("Dumping TFX interpreter");
         debug!("- cb0 size: {} elements", buffer.data_array().len());
@@ -117,23 +117,24 @@ impl<T> ConstantBuffer<T> {
         Ok(())
     }
 
-    // pub fn write_array(&self, data: &[T]) -> anyhow::Result<()> {
-    //     unsafe {
-    //         let memory = self
-    //             .dcs
-    //             .context()
-    //             .Map(&self.buffer, 0, D3D11_MAP_WRITE_DISCARD, 0)
-    //             .context("Failed to map ConstantBuffer for writing (array)")?;
+    /// Writes to the start of the buffer. `data` must not be larger than the buffer
+    pub fn write_array(&self, data: &[T]) -> anyhow::Result<()> {
+        unsafe {
+            let memory = self
+                .dcs
+                .context()
+                .Map(&self.buffer, 0, D3D11_MAP_WRITE_DISCARD, 0)
+                .context("Failed to map ConstantBuffer for writing (array)")?;
 
-    //         memory
-    //             .pData
-    //             .copy_from_nonoverlapping(data.as_ptr() as _, std::mem::size_of_val(data));
+            memory
+                .pData
+                .copy_from_nonoverlapping(data.as_ptr() as _, std::mem::size_of_val(data));
 
-    //         self.dcs.context().Unmap(&self.buffer, 0);
-    //     }
+            self.dcs.context().Unmap(&self.buffer, 0);
+        }
 
-    //     Ok(())
-    // }
+        Ok(())
+    }
 
     pub fn map(&self, mode: D3D11_MAP) -> anyhow::Result<BufferMapGuard<T>> {
         let ptr = unsafe {
@@ -235,6 +236,11 @@ impl<T: Sized + Clone> ConstantBufferCached<T> {
         self.cbuffer.buffer()
     }
 
+    /// Read-only view of the buffer data, doesn't mark the buffer as updated
+    pub fn data_slice(&self) -> &[T] {
+        &self.data
+    }
+
     // Deny clippy from the realms of dark magic (the good kind)
     #[allow(clippy::mut_from_ref)]
     pub fn data_array(&self) -> &mut [T] {
@@ -1,8 +1,10 @@
 use std::sync::Arc;
 
+use anyhow::Context;
+
 use crate::util::image::Png;
 use crate::util::RwLock;
-use crossbeam::channel::Sender;
+use crossbeam::channel::{Receiver, Sender};
 use destiny_pkg::TagHash;
 use nohash_hasher::IntMap;
 use parking_lot::{RwLockReadGuard, RwLockWriteGuard};
@@ -10,6 +12,7 @@ use windows::Win32::Graphics::Direct3D11::*;
 
 use crate::dxgi::DxgiFormat;
 use crate::packages::package_manager;
+use crate::render::settings::TextureFiltering;
 use crate::render::vertex_layout::InputElement;
 use crate::structure::ExtendedHash;
 use crate::technique::Technique;
@@ -17,6 +20,7 @@ use crate::texture::Texture;
 
 use super::drawcall::ShadingMode;
 use super::renderer::Renderer;
+use super::resource_mt::DecodedTexture;
 use super::shader::{load_pshader, load_vshader};
 use super::vertex_layout::OutputElement;
 use super::{resource_mt, DeviceContextSwapchain};
@@ -27,6 +31,8 @@ pub struct RenderData {
     pub pshaders: IntMap<TagHash, (ID3D11PixelShader, Vec<OutputElement>)>,
     pub textures: IntMap<u64, Texture>,
     pub samplers: IntMap<u64, ID3D11SamplerState>,
+    /// Sampler descriptions as stored in the game files, used to recreate the samplers with different filtering
+    pub sampler_descs: IntMap<u64, D3D11_SAMPLER_DESC>,
 
     pub vertex_buffers: IntMap<TagHash, (ID3D11Buffer, u32, Option<ID3D11ShaderResourceView>)>,
     pub index_buffers: IntMap<TagHash, (ID3D11Buffer, DxgiFormat)>,
@@ -174,6 +180,7 @@ impl RenderData {
             pshaders: Default::default(),
             textures: Default::default(),
             samplers: Default::default(),
+            sampler_descs: Default::default(),
             vertex_buffers: Default::default(),
             index_buffers: Default::default(),
             input_layouts: Default::default(),
@@ -204,10 +211,45 @@ impl RenderData {
             Some(ShadingMode::Deferred)
         }
     }
+
+    /// Approximate video memory used by loaded textures and vertex/index buffers, in bytes
+    pub fn memory_usage(&self) -> (usize, usize) {
+        let textures = self.textures.values().map(Texture::memory_size).sum();
+
+        let buffers = self
+            .vertex_buffers
+            .values()
+            .map(|(b, _, _)| d3d_buffer_size(b))
+            .chain(self.index_buffers.values().map(|(b, _)| d3d_buffer_size(b)))
+            .sum();
+
+        (textures, buffers)
+    }
+
+    /// Size of a loaded vertex or index buffer in bytes, 0 if it isn't loaded
+    pub fn buffer_size(&self, hash: TagHash) -> usize {
+        if let Some((b, _, _)) = self.vertex_buffers.get(&hash) {
+            d3d_buffer_size(b)
+        } else if let Some((b, _)) = self.index_buffers.get(&hash) {
+            d3d_buffer_size(b)
+        } else {
+            0
+        }
+    }
+}
+
+fn d3d_buffer_size(buffer: &ID3D11Buffer) -> usize {
+    unsafe {
+        let mut desc = D3D11_BUFFER_DESC::default();
+        buffer.GetDesc(&mut desc);
+        desc.ByteWidth as usize
+    }
 }
 
 pub struct RenderDataManager {
+    dcs: Arc<DeviceContextSwapchain>,
     tx_textures: Sender<ExtendedHash>,
+    rx_decoded_textures: Receiver<DecodedTexture>,
     tx_buffers: Sender<(TagHash, bool)>,
     // tx_shaders: Sender<TagHash>,
     render_data: Arc<RwLock<RenderData>>,
@@ -219,11 +261,13 @@ use crate::util::lock::LockTracker;
 impl RenderDataManager {
     pub fn new(dcs: Arc<DeviceContextSwapchain>) -> Self {
         let render_data = Arc::new(RwLock::new(RenderData::new(&dcs).unwrap()));
-        let tx_textures = resource_mt::thread_textures(dcs.clone(), render_data.clone());
+        let (tx_textures, rx_decoded_textures) = resource_mt::thread_textures(render_data.clone());
         let tx_buffers = resource_mt::thread_buffers(dcs.clone(), render_data.clone());
 
         Self {
+            dcs,
             tx_textures,
+            rx_decoded_textures,
             tx_buffers,
             render_data,
         }
@@ -256,6 +300,16 @@ impl RenderDataManager {
             .expect("Failed to send load texture request");
     }
 
+    /// Uploads textures that finished loading, at most `max_uploads` per call to avoid hitches
+    pub fn upload_textures(&self, max_uploads: Option<usize>) {
+        resource_mt::upload_textures(
+            &self.dcs,
+            &self.render_data,
+            &self.rx_decoded_textures,
+            max_uploads,
+        );
+    }
+
     /// Load a vertex or index buffer from a hash
     pub fn load_buffer(&self, buffer: TagHash, create_rgba_srv: bool) {
         self.tx_buffers
@@ -263,22 +317,44 @@ impl RenderDataManager {
             .expect("Failed to send load buffer request");
     }
 
-    // pub fn load_sampler(&self, dcs: &DeviceContextSwapchain, hash: TagHash) {
-    //     if !hash.is_some() {
-    //         return;
-    //     }
-
-    //     let sampler_header_ref = package_manager().get_entry(hash).unwrap().reference;
-    //     let sampler_data = package_manager().read_tag(sampler_header_ref).unwrap();
-
-    //     let sampler = unsafe {
-    //         dcs.device
-    //             .CreateSamplerState(sampler_data.as_ptr() as _)
-    //             .expect("Failed to create sampler state")
-    //     };
+    /// Creates a sampler from a sampler tag, with the filtering it has in the game files
+    pub fn load_sampler(&self, hash: ExtendedHash) -> anyhow::Result<()> {
+        let tag = hash.hash32().context("Sampler hash not found")?;
+        let sampler_header_ref = package_manager()
+            .get_entry(tag)
+            .context("Sampler entry not found")?
+            .reference;
+        let sampler_data = package_manager().read_tag(sampler_header_ref)?;
+        anyhow::ensure!(
+            sampler_data.len() >= std::mem::size_of::<D3D11_SAMPLER_DESC>(),
+            "Sampler data is too small"
+        );
+
+        let desc: D3D11_SAMPLER_DESC =
+            unsafe { std::ptr::read_unaligned(sampler_data.as_ptr() as _) };
+        let sampler = unsafe { self.dcs.device.CreateSamplerState(&desc)? };
+
+        let mut data = self.data_mut();
+        data.samplers.insert(hash.key(), sampler);
+        data.sampler_descs.insert(hash.key(), desc);
+
+        Ok(())
+    }
 
-    //     self.data_mut().samplers.insert(hash, sampler);
-    // }
+    /// Recreates all samplers from their original description with the given filtering applied
+    pub fn apply_texture_filtering(&self, filtering: TextureFiltering, anisotropy: u32) {
+        let mut data = self.data_mut();
+        let descs = data.sampler_descs.clone();
+        for (key, mut desc) in descs {
+            apply_texture_filtering(&mut desc, filtering, anisotropy);
+            match unsafe { self.dcs.device.CreateSamplerState(&desc) } {
+                Ok(sampler) => {
+                    data.samplers.insert(key, sampler);
+                }
+                Err(e) => error!("Failed to recreate sampler {key:x}: {e}"),
+            }
+        }
+    }
 
     pub fn load_vshader(
         &self,
@@ -328,3 +404,34 @@ impl RenderDataManager {
             });
     }
 }
+
+fn apply_texture_filtering(
+    desc: &mut D3D11_SAMPLER_DESC,
+    filtering: TextureFiltering,
+    anisotropy: u32,
+) {
+    // Comparison, minimum and maximum filters are used for things like shadows, which should be left alone
+    if desc.Filter.0 >= 0x80 {
+        return;
+    }
+
+    // Point filtered samplers are usually used for lookup textures, where filtering would produce garbage
+    const MIN_MAG_MASK: i32 = 0x3c;
+    if desc.Filter.0 & MIN_MAG_MASK == 0 {
+        return;
+    }
+
+    match filtering {
+        TextureFiltering::GameDefault => {}
+        TextureFiltering::Bilinear => {
+            desc.Filter = D3D11_FILTER_MIN_MAG_LINEAR_MIP_POINT;
+        }
+        TextureFiltering::Trilinear => {
+            desc.Filter = D3D11_FILTER_MIN_MAG_MIP_LINEAR;
+        }
+        TextureFiltering::Anisotropic => {
+            desc.Filter = D3D11_FILTER_ANISOTROPIC;
+            desc.MaxAnisotropy = anisotropy.clamp(1, 16);
+        }
+    }
+}
@@ -11,9 +11,72 @@ use windows::Win32::Graphics::Dxgi::*;
 use winit::window::Window;
 
 pub type DcsShared = Arc<DeviceContextSwapchain>;
+
+/// A GPU as reported by DXGI
+#[derive(Clone)]
+pub struct AdapterInfo {
+    pub name: String,
+    pub vendor_id: u32,
+    pub device_id: u32,
+    pub dedicated_video_memory: usize,
+    pub shared_system_memory: usize,
+    /// Software rasterizer, like WARP
+    pub software: bool,
+}
+
+impl AdapterInfo {
+    fn from_desc(desc: &DXGI_ADAPTER_DESC1) -> Self {
+        let name_len = desc
+            .Description
+            .iter()
+            .position(|&c| c == 0)
+            .unwrap_or(desc.Description.len());
+
+        Self {
+            name: String::from_utf16_lossy(&desc.Description[..name_len]),
+            vendor_id: desc.VendorId,
+            device_id: desc.DeviceId,
+            dedicated_video_memory: desc.DedicatedVideoMemory,
+            shared_system_memory: desc.SharedSystemMemory,
+            software: (desc.Flags & DXGI_ADAPTER_FLAG_SOFTWARE.0 as u32) != 0,
+        }
+    }
+
+    pub fn vendor_name(&self) -> &'static str {
+        match self.vendor_id {
+            0x10de => "NVIDIA",
+            0x1002 | 0x1022 => "AMD",
+            0x8086 => "Intel",
+            0x1414 => "Microsoft",
+            0x5143 => "Qualcomm",
+            _ => "Unknown",
+        }
+    }
+}
+
+/// All adapters in the order DXGI enumerates them, the first one being the system default
+fn enumerate_dxgi_adapters() -> anyhow::Result<Vec<(IDXGIAdapter1, AdapterInfo)>> {
+    let factory: IDXGIFactory1 = unsafe { CreateDXGIFactory1()? };
+
+    let mut adapters = vec![];
+    // EnumAdapters1 fails with DXGI_ERROR_NOT_FOUND past the last adapter
+    while let Ok(adapter) = unsafe { factory.EnumAdapters1(adapters.len() as u32) } {
+        let desc = unsafe { adapter.GetDesc1()? };
+        adapters.push((adapter, AdapterInfo::from_desc(&desc)));
+    }
+
+    Ok(adapters)
+}
+
 pub struct DeviceContextSwapchain {
     main_thread_id: ThreadId,
 
+    /// The adapter the device was created on
+    pub adapter: AdapterInfo,
+    /// All adapters that were available when the device was created
+    pub adapters: Vec<AdapterInfo>,
+    pub feature_level: D3D_FEATURE_LEVEL,
+
     pub device: ID3D11Device,
     context: ID3D11DeviceContext,
     pub swap_chain: IDXGISwapChain,
@@ -21,8 +84,24 @@ pub struct DeviceContextSwapchain {
 }
 
 impl DeviceContextSwapchain {
-    #[allow(const_item_mutation)]
-    pub fn create(window: &Window) -> anyhow::Result<Self> {
+    /// Creates the device on the adapter named `preferred_adapter`, or on the system default adapter if it's `None` or not found
+    pub fn create(window: &Window, preferred_adapter: Option<&str>) -> anyhow::Result<Self> {
+        let adapters = enumerate_dxgi_adapters()?;
+        let (adapter, adapter_info) = match preferred_adapter {
+            Some(name) => adapters
+                .iter()
+                .find(|(_, info)| info.name == name)
+                .or_else(|| {
+                    warn!(
+                        "Preferred GPU adapter '{name}' was not found, using the default adapter"
+                    );
+                    adapters.first()
+                }),
+            None => adapters.first(),
+        }
+        .cloned()
+        .ok_or_else(|| anyhow::anyhow!("No GPU adapters found"))?;
+
         let mut device: Option<ID3D11Device> = None;
         let mut swap_chain: Option<IDXGISwapChain> = None;
         let mut device_context: Option<ID3D11DeviceContext> = None;
@@ -63,10 +142,12 @@ impl DeviceContextSwapchain {
             }
         };
 
+        let mut feature_level = D3D_FEATURE_LEVEL_11_1;
         unsafe {
             D3D11CreateDeviceAndSwapChain(
-                None,
-                D3D_DRIVER_TYPE_HARDWARE,
+                &adapter,
+                // The driver type has to be unknown when passing an adapter
+                D3D_DRIVER_TYPE_UNKNOWN,
                 HINSTANCE::default(),
                 Default::default(), // | D3D11_CREATE_DEVICE_DEBUG,
                 Some(&[D3D_FEATURE_LEVEL_11_1]),
@@ -74,7 +155,7 @@ impl DeviceContextSwapchain {
                 Some(&swap_chain_description),
                 Some(&mut swap_chain),
                 Some(&mut device),
-                Some(&mut D3D_FEATURE_LEVEL_11_1),
+                Some(&mut feature_level),
                 Some(&mut device_context),
             )?;
         }
@@ -88,8 +169,18 @@ impl DeviceContextSwapchain {
             Some(device.CreateRenderTargetView(&buffer, None)?)
         };
 
+        info!(
+            "Using GPU adapter '{}' ({} MiB VRAM, feature level {})",
+            adapter_info.name,
+            adapter_info.dedicated_video_memory / (1024 * 1024),
+            feature_level_name(feature_level)
+        );
+
         Ok(Self {
             main_thread_id: std::thread::current().id(),
+            adapter: adapter_info,
+            adapters: adapters.into_iter().map(|(_, info)| info).collect(),
+            feature_level,
             device,
             context: device_context,
             swap_chain,
@@ -106,5 +197,10 @@ impl DeviceContextSwapchain {
     }
 }
 
+/// Formats a feature level the way D3D names them, eg. `11_1`
+pub fn feature_level_name(level: D3D_FEATURE_LEVEL) -> String {
+    format!("{}_{}", (level.0 >> 12) & 0xf, (level.0 >> 8) & 0xf)
+}
+
 unsafe impl Send for DeviceContextSwapchain {}
 unsafe impl Sync for DeviceContextSwapchain {}
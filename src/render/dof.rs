@@ -0,0 +1,87 @@
+use std::sync::Arc;
+
+use glam::Mat4;
+use windows::Win32::Graphics::{
+    Direct3D::D3D_PRIMITIVE_TOPOLOGY_TRIANGLESTRIP,
+    Direct3D11::{ID3D11PixelShader, ID3D11VertexShader},
+};
+
+use super::{bytecode::externs::TfxShaderStage, shader, ConstantBuffer, DeviceContextSwapchain};
+
+#[repr(C)]
+pub struct DofOptions {
+    pub focus_distance: f32,
+    pub strength: f32,
+    /// Maximum blur radius, in pixels
+    pub max_radius: f32,
+    /// Depth mapping terms of the projection matrix, `depth = -a - b / z`
+    pub depth_a: f32,
+    pub depth_b: f32,
+    _pad: [f32; 3],
+}
+
+impl DofOptions {
+    pub fn new(focus_distance: f32, strength: f32, max_radius: f32, projection: &Mat4) -> Self {
+        Self {
+            focus_distance,
+            strength,
+            max_radius,
+            depth_a: projection.z_axis.z,
+            depth_b: projection.w_axis.z,
+            _pad: [0.0; 3],
+        }
+    }
+}
+
+pub struct DepthOfFieldEffect {
+    dof_vs: ID3D11VertexShader,
+    dof_ps: ID3D11PixelShader,
+    scope: ConstantBuffer<DofOptions>,
+}
+
+impl DepthOfFieldEffect {
+    pub fn create(dcs: Arc<DeviceContextSwapchain>) -> anyhow::Result<Self> {
+        let vshader_blob = shader::compile_hlsl(
+            include_str!("../../assets/shaders/dof.hlsl"),
+            "VShader",
+            "vs_5_0",
+            "dof.hlsl",
+        )
+        .unwrap();
+        let pshader_blob = shader::compile_hlsl(
+            include_str!("../../assets/shaders/dof.hlsl"),
+            "PShader",
+            "ps_5_0",
+            "dof.hlsl",
+        )
+        .unwrap();
+
+        let (dof_vs, _) = shader::load_vshader(&dcs, &vshader_blob)?;
+        let (dof_ps, _) = shader::load_pshader(&dcs, &pshader_blob)?;
+
+        Ok(Self {
+            dof_vs,
+            dof_ps,
+            scope: ConstantBuffer::create(dcs, None)?,
+        })
+    }
+
+    /// Expects the color and depth inputs to be bound to t0 and t1 respectively
+    pub fn draw(&self, dcs: &DeviceContextSwapchain, options: &DofOptions) {
+        if let Err(e) = self.scope.write(options) {
+            error!("Failed to write DOF options: {e}");
+            return;
+        }
+
+        self.scope.bind(0, TfxShaderStage::Pixel);
+        unsafe {
+            dcs.context().VSSetShader(&self.dof_vs, None);
+            dcs.context().PSSetShader(&self.dof_ps, None);
+            dcs.context()
+                .IASetPrimitiveTopology(D3D_PRIMITIVE_TOPOLOGY_TRIANGLESTRIP);
+            dcs.context().Draw(4, 0);
+
+            dcs.context().PSSetShaderResources(0, Some(&[None, None]));
+        }
+    }
+}
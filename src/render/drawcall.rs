@@ -48,6 +48,11 @@ impl SortValue3d {
         Self(self.0 | (t.into_bits()) << 61)
     }
 
+    /// Forces alpha blending regardless of the material. Only valid for forward drawcalls, deferred shaders write gbuffer data instead of color
+    pub fn with_forced_blend(self) -> Self {
+        Self(self.0 & !(0b11 << 61)).with_transparency(Transparency::Blend)
+    }
+
     pub fn material(&self) -> u32 {
         let this = self.0 & 0xffffffff;
         this as _
@@ -75,13 +80,15 @@ impl SortValue3d {
 }
 
 #[repr(u64)]
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum GeometryType {
     Static = 0,
     StaticDecal = 1,
     Terrain = 2,
     Entity = 3,
     // Decal = 4,
+    /// Water surfaces. Forward water materials are alpha blended, deferred ones are drawn in the geometry pass
+    Water = 5,
 }
 
 impl GeometryType {
@@ -96,6 +103,7 @@ impl GeometryType {
             2 => Self::Terrain,
             3 => Self::Entity,
             // 4 => Self::Decal,
+            5 => Self::Water,
             _ => Self::Static,
         }
     }
@@ -131,7 +139,7 @@ impl Transparency {
 }
 
 #[repr(u64)]
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ShadingMode {
     Deferred = 0,
     Forward = 1,
@@ -198,4 +206,7 @@ pub struct DrawCall {
     pub instance_count: Option<u32>,
     pub primitive_type: D3D_PRIMITIVE_TOPOLOGY,
     pub entity: Entity,
+    /// Constant opacity the drawcall is blended with, set by `OpacityOverride`.
+    /// Forward drawcalls are blended directly, deferred ones are shaded first and blended with the shaded scene afterwards
+    pub opacity: Option<f32>,
 }
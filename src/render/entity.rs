@@ -2,6 +2,8 @@ use anyhow::Context;
 use destiny_pkg::TagHash;
 
 use glam::Vec4;
+use itertools::Itertools;
+use nohash_hasher::IntMap;
 
 use hecs::Entity;
 use windows::Win32::Graphics::Direct3D11::*;
@@ -11,6 +13,7 @@ use crate::entity::Unk8080737e;
 
 use crate::entity::SEntityModel;
 use crate::render::vertex_buffers::load_vertex_buffers;
+use crate::types::AABB;
 
 use super::drawcall::ConstantBufferBinding;
 use super::drawcall::DrawCall;
@@ -30,6 +33,10 @@ pub struct EntityModelBuffer {
     input_layout: u64,
 }
 
+/// Renderers for the entities of all loaded maps, keyed by `ResourcePoint::entity_key`
+#[derive(Default)]
+pub struct EntityRenderers(pub IntMap<u64, EntityRenderer>);
+
 #[derive(Clone)]
 pub struct EntityRenderer {
     meshes: Vec<(EntityModelBuffer, Vec<Unk8080737e>)>,
@@ -70,6 +77,17 @@ impl EntityRenderer {
         .into()
     }
 
+    /// Model-space bounds. Vertex positions are normalized, so these are derived from the mesh offset and scale
+    pub fn bounds(&self) -> AABB {
+        let offset = self.mesh_offset().truncate();
+        let scale = self.mesh_scale().truncate().abs();
+
+        AABB {
+            min: offset - scale,
+            max: offset + scale,
+        }
+    }
+
     pub fn load(
         model: SEntityModel,
         material_map: Vec<Unk808072c5>,
@@ -115,6 +133,33 @@ impl EntityRenderer {
         })
     }
 
+    /// All materials used by this model, including variant materials
+    pub fn materials(&self) -> Vec<TagHash> {
+        self.meshes
+            .iter()
+            .flat_map(|(_, parts)| parts.iter().map(|p| p.material))
+            .chain(self.materials.iter().cloned())
+            .filter(|m| m.is_some())
+            .unique()
+            .collect()
+    }
+
+    /// Vertex, color and index buffers used by all meshes
+    pub fn buffers(&self) -> Vec<TagHash> {
+        self.meshes
+            .iter()
+            .flat_map(|(b, _)| {
+                [
+                    b.vertex_buffer1,
+                    b.vertex_buffer2,
+                    b.color_buffer,
+                    b.index_buffer,
+                ]
+            })
+            .unique()
+            .collect()
+    }
+
     fn get_variant_material(&self, index: u16, variant: usize) -> Option<TagHash> {
         if index == u16::MAX {
             None
@@ -131,11 +176,21 @@ impl EntityRenderer {
         &self,
         renderer: &Renderer,
         cb11: ID3D11Buffer,
+        lod: u8,
+        geometry_type: GeometryType,
         entity: Entity,
     ) -> anyhow::Result<()> {
         for (buffers, parts) in self.meshes.iter() {
+            // Not every mesh has every LOD, fall back to the closest more detailed one
+            let mesh_lod = parts
+                .iter()
+                .map(|p| p.lod_category.lod_level())
+                .filter(|&l| l <= lod)
+                .max()
+                .or_else(|| parts.iter().map(|p| p.lod_category.lod_level()).min());
+
             for p in parts {
-                if !p.lod_category.is_highest_detail() {
+                if Some(p.lod_category.lod_level()) != mesh_lod {
                     continue;
                 }
 
@@ -146,19 +201,21 @@ impl EntityRenderer {
                     .data()
                     .material_shading_technique(variant_material.unwrap_or(p.material))
                     .unwrap_or(ShadingMode::Forward);
+                // Water keeps the shading mode of its material, deferred water shaders write gbuffer data and can't be blended
+                let transparency = match (geometry_type, shading_technique) {
+                    (GeometryType::Water, ShadingMode::Forward) => Transparency::Blend,
+                    (_, ShadingMode::Deferred) => Transparency::None,
+                    (_, ShadingMode::Forward) => Transparency::Additive,
+                };
 
                 renderer.push_drawcall(
                     SortValue3d::empty()
                         // TODO(cohae): calculate depth (need to draw instances separately)
                         .with_depth(u32::MAX)
                         .with_material(p.material.0)
-                        .with_transparency(if shading_technique == ShadingMode::Deferred {
-                            Transparency::None
-                        } else {
-                            Transparency::Additive
-                        })
+                        .with_transparency(transparency)
                         .with_shading_mode(shading_technique)
-                        .with_geometry_type(GeometryType::Entity),
+                        .with_geometry_type(geometry_type),
                     DrawCall {
                         vertex_buffers: vec![buffers.vertex_buffer1, buffers.vertex_buffer2],
                         index_buffer: buffers.index_buffer,
@@ -173,6 +230,7 @@ impl EntityRenderer {
                         instance_count: None,
                         primitive_type: p.primitive_type.to_dx(),
                         entity,
+                        opacity: None,
                     },
                 );
             }
@@ -28,11 +28,25 @@ pub struct GBuffer {
     pub staging: RenderTarget,
     pub staging_clone: RenderTarget,
     pub depth: DepthState,
+
+    /// Half resolution ping-pong targets for bloom
+    pub bloom_a: RenderTarget,
+    pub bloom_b: RenderTarget,
+
+    /// Multisampled geometry targets, only present when the sample count is higher than 1
+    pub msaa: Option<MsaaTargets>,
+    pub sample_count: u32,
+    /// Size of the full resolution targets, in pixels
+    pub size: (u32, u32),
     dcs: Arc<DeviceContextSwapchain>,
 }
 
 impl GBuffer {
-    pub fn create(size: (u32, u32), dcs: Arc<DeviceContextSwapchain>) -> anyhow::Result<Self> {
+    pub fn create(
+        size: (u32, u32),
+        sample_count: u32,
+        dcs: Arc<DeviceContextSwapchain>,
+    ) -> anyhow::Result<Self> {
         Ok(Self {
             rt0: RenderTarget::create(size, DxgiFormat::B8G8R8A8_UNORM_SRGB, dcs.clone(), "RT0")
                 .context("RT0")?,
@@ -81,30 +95,54 @@ impl GBuffer {
             )
             .context("Light_Specular")?,
 
+            // Kept in HDR so bloom and the final pass work on unclamped color
             staging: RenderTarget::create(
                 size,
-                DxgiFormat::B8G8R8A8_UNORM_SRGB,
+                DxgiFormat::R16G16B16A16_FLOAT,
                 dcs.clone(),
                 "Staging",
             )
             .context("Staging")?,
             staging_clone: RenderTarget::create(
                 size,
-                DxgiFormat::B8G8R8A8_UNORM_SRGB,
+                DxgiFormat::R16G16B16A16_FLOAT,
                 dcs.clone(),
                 "Staging_Clone",
             )
             .context("Staging_Clone")?,
             depth: DepthState::create(size, &dcs.device).context("Depth")?,
+            bloom_a: RenderTarget::create(
+                Self::bloom_size(size),
+                DxgiFormat::R16G16B16A16_FLOAT,
+                dcs.clone(),
+                "Bloom_A",
+            )
+            .context("Bloom_A")?,
+            bloom_b: RenderTarget::create(
+                Self::bloom_size(size),
+                DxgiFormat::R16G16B16A16_FLOAT,
+                dcs.clone(),
+                "Bloom_B",
+            )
+            .context("Bloom_B")?,
+            msaa: MsaaTargets::create_if_needed(size, sample_count, dcs.clone())?,
+            sample_count,
+            size,
             dcs,
         })
     }
 
-    pub fn resize(&mut self, new_size: (u32, u32)) -> anyhow::Result<()> {
+    pub fn resize(&mut self, new_size: (u32, u32), sample_count: u32) -> anyhow::Result<()> {
         if new_size.0 == 0 || new_size.1 == 0 {
             return Ok(());
         }
 
+        // Drop the old targets before allocating new ones
+        self.msaa = None;
+        self.msaa = MsaaTargets::create_if_needed(new_size, sample_count, self.dcs.clone())?;
+        self.sample_count = sample_count;
+        self.size = new_size;
+
         self.rt0.resize(new_size).context("RT0")?;
         self.rt1.resize(new_size).context("RT1")?;
         self.rt1_clone.resize(new_size).context("RT1_Clone")?;
@@ -137,8 +175,97 @@ impl GBuffer {
             .resize(new_size, &self.dcs.device)
             .context("Depth")?;
 
+        self.bloom_a
+            .resize(Self::bloom_size(new_size))
+            .context("Bloom_A")?;
+        self.bloom_b
+            .resize(Self::bloom_size(new_size))
+            .context("Bloom_B")?;
+
         Ok(())
     }
+
+    pub fn bloom_size(size: (u32, u32)) -> (u32, u32) {
+        ((size.0 / 2).max(1), (size.1 / 2).max(1))
+    }
+
+    /// Checks whether the device supports multisampling all of the geometry target formats with the given sample count
+    pub fn supports_sample_count(device: &ID3D11Device, sample_count: u32) -> bool {
+        if sample_count <= 1 {
+            return true;
+        }
+
+        [
+            DXGI_FORMAT(DxgiFormat::B8G8R8A8_UNORM_SRGB as u32),
+            DXGI_FORMAT(DxgiFormat::R10G10B10A2_UNORM as u32),
+            DXGI_FORMAT(DxgiFormat::B8G8R8A8_UNORM as u32),
+            DXGI_FORMAT_D32_FLOAT,
+        ]
+        .into_iter()
+        .all(|format| unsafe {
+            device
+                .CheckMultisampleQualityLevels(format, sample_count)
+                .map_or(false, |levels| levels > 0)
+        })
+    }
+}
+
+/// Multisampled counterparts of the geometry pass targets, resolved into the regular GBuffer targets before deferred shading
+pub struct MsaaTargets {
+    pub rt0: RenderTarget,
+    pub rt1: RenderTarget,
+    pub rt2: RenderTarget,
+    pub rt3: RenderTarget,
+    pub depth: DepthState,
+}
+
+impl MsaaTargets {
+    fn create_if_needed(
+        size: (u32, u32),
+        sample_count: u32,
+        dcs: Arc<DeviceContextSwapchain>,
+    ) -> anyhow::Result<Option<Self>> {
+        if sample_count <= 1 {
+            return Ok(None);
+        }
+
+        Ok(Some(Self {
+            rt0: RenderTarget::create_multisampled(
+                size,
+                DxgiFormat::B8G8R8A8_UNORM_SRGB,
+                sample_count,
+                dcs.clone(),
+                "RT0_MS",
+            )
+            .context("RT0_MS")?,
+            rt1: RenderTarget::create_multisampled(
+                size,
+                DxgiFormat::R10G10B10A2_UNORM,
+                sample_count,
+                dcs.clone(),
+                "RT1_MS",
+            )
+            .context("RT1_MS")?,
+            rt2: RenderTarget::create_multisampled(
+                size,
+                DxgiFormat::B8G8R8A8_UNORM,
+                sample_count,
+                dcs.clone(),
+                "RT2_MS",
+            )
+            .context("RT2_MS")?,
+            rt3: RenderTarget::create_multisampled(
+                size,
+                DxgiFormat::B8G8R8A8_UNORM,
+                sample_count,
+                dcs.clone(),
+                "RT3_MS",
+            )
+            .context("RT3_MS")?,
+            depth: DepthState::create_multisampled(size, sample_count, &dcs.device)
+                .context("Depth_MS")?,
+        }))
+    }
 }
 
 pub struct RenderTarget {
@@ -147,6 +274,7 @@ pub struct RenderTarget {
     pub view: ID3D11ShaderResourceView,
     pub format: DxgiFormat,
     pub name: String,
+    pub sample_count: u32,
 
     dcs: Arc<DeviceContextSwapchain>,
 }
@@ -157,6 +285,16 @@ impl RenderTarget {
         format: DxgiFormat,
         dcs: Arc<DeviceContextSwapchain>,
         name: &str,
+    ) -> anyhow::Result<Self> {
+        Self::create_multisampled(size, format, 1, dcs, name)
+    }
+
+    pub fn create_multisampled(
+        size: (u32, u32),
+        format: DxgiFormat,
+        sample_count: u32,
+        dcs: Arc<DeviceContextSwapchain>,
+        name: &str,
     ) -> anyhow::Result<Self> {
         unsafe {
             let texture = dcs
@@ -169,7 +307,7 @@ impl RenderTarget {
                         ArraySize: 1,
                         Format: DXGI_FORMAT(format as u32),
                         SampleDesc: DXGI_SAMPLE_DESC {
-                            Count: 1,
+                            Count: sample_count,
                             Quality: 0,
                         },
                         Usage: D3D11_USAGE_DEFAULT,
@@ -189,15 +327,27 @@ impl RenderTarget {
                 .device
                 .CreateShaderResourceView(
                     &texture,
-                    Some(&D3D11_SHADER_RESOURCE_VIEW_DESC {
-                        Format: DXGI_FORMAT(format as u32),
-                        ViewDimension: D3D11_SRV_DIMENSION_TEXTURE2D,
-                        Anonymous: D3D11_SHADER_RESOURCE_VIEW_DESC_0 {
-                            Texture2D: D3D11_TEX2D_SRV {
-                                MostDetailedMip: 0,
-                                MipLevels: 1,
+                    Some(&if sample_count > 1 {
+                        D3D11_SHADER_RESOURCE_VIEW_DESC {
+                            Format: DXGI_FORMAT(format as u32),
+                            ViewDimension: D3D11_SRV_DIMENSION_TEXTURE2DMS,
+                            Anonymous: D3D11_SHADER_RESOURCE_VIEW_DESC_0 {
+                                Texture2DMS: D3D11_TEX2DMS_SRV {
+                                    UnusedField_NothingToDefine: 0,
+                                },
                             },
-                        },
+                        }
+                    } else {
+                        D3D11_SHADER_RESOURCE_VIEW_DESC {
+                            Format: DXGI_FORMAT(format as u32),
+                            ViewDimension: D3D11_SRV_DIMENSION_TEXTURE2D,
+                            Anonymous: D3D11_SHADER_RESOURCE_VIEW_DESC_0 {
+                                Texture2D: D3D11_TEX2D_SRV {
+                                    MostDetailedMip: 0,
+                                    MipLevels: 1,
+                                },
+                            },
+                        }
                     }),
                 )
                 .context("Failed to create SRV")?;
@@ -217,6 +367,7 @@ impl RenderTarget {
                 view,
                 format,
                 name: name.to_string(),
+                sample_count,
                 dcs,
             })
         }
@@ -238,8 +389,27 @@ impl RenderTarget {
         }
     }
 
+    /// Resolves this multisampled target into a single-sampled one
+    pub fn resolve_to(&self, dest: &RenderTarget) {
+        unsafe {
+            self.dcs.context().ResolveSubresource(
+                &dest.texture,
+                0,
+                &self.texture,
+                0,
+                DXGI_FORMAT(self.format as u32),
+            )
+        }
+    }
+
     pub fn resize(&mut self, new_size: (u32, u32)) -> anyhow::Result<()> {
-        *self = Self::create(new_size, self.format, self.dcs.clone(), &self.name)?;
+        *self = Self::create_multisampled(
+            new_size,
+            self.format,
+            self.sample_count,
+            self.dcs.clone(),
+            &self.name,
+        )?;
         Ok(())
     }
 }
@@ -325,17 +495,31 @@ impl CpuStagingBuffer {
 pub struct DepthState {
     pub texture: ID3D11Texture2D,
     // TODO(cohae): Should this be here?
-    pub state: ID3D11DepthStencilState,
-    pub state_readonly: ID3D11DepthStencilState,
+    /// Reverse-Z depth states
+    state: ID3D11DepthStencilState,
+    state_readonly: ID3D11DepthStencilState,
+    /// Standard depth states, see [`FpsCamera::reverse_z`](crate::camera::FpsCamera::reverse_z)
+    state_standard: ID3D11DepthStencilState,
+    state_readonly_standard: ID3D11DepthStencilState,
     pub view: ID3D11DepthStencilView,
     pub texture_view: ID3D11ShaderResourceView,
 
     pub texture_copy: ID3D11Texture2D,
     pub texture_copy_view: ID3D11ShaderResourceView,
+
+    pub sample_count: u32,
 }
 
 impl DepthState {
     pub fn create(size: (u32, u32), device: &ID3D11Device) -> anyhow::Result<Self> {
+        Self::create_multisampled(size, 1, device)
+    }
+
+    pub fn create_multisampled(
+        size: (u32, u32),
+        sample_count: u32,
+        device: &ID3D11Device,
+    ) -> anyhow::Result<Self> {
         let texture = unsafe {
             device
                 .CreateTexture2D(
@@ -346,7 +530,7 @@ impl DepthState {
                         ArraySize: 1,
                         Format: DXGI_FORMAT_R32_TYPELESS,
                         SampleDesc: DXGI_SAMPLE_DESC {
-                            Count: 1,
+                            Count: sample_count,
                             Quality: 0,
                         },
                         Usage: D3D11_USAGE_DEFAULT,
@@ -359,86 +543,43 @@ impl DepthState {
                 .context("Failed to create depth texture")?
         };
 
-        let state = unsafe {
-            device
-                .CreateDepthStencilState(&D3D11_DEPTH_STENCIL_DESC {
-                    DepthEnable: true.into(),
-                    DepthWriteMask: D3D11_DEPTH_WRITE_MASK_ALL,
-                    DepthFunc: D3D11_COMPARISON_GREATER_EQUAL,
-                    StencilEnable: false.into(),
-                    StencilReadMask: 0xff,
-                    StencilWriteMask: 0xff,
-                    FrontFace: D3D11_DEPTH_STENCILOP_DESC {
-                        StencilFailOp: D3D11_STENCIL_OP_KEEP,
-                        StencilDepthFailOp: D3D11_STENCIL_OP_INCR,
-                        StencilPassOp: D3D11_STENCIL_OP_KEEP,
-                        StencilFunc: D3D11_COMPARISON_ALWAYS,
-                    },
-                    BackFace: D3D11_DEPTH_STENCILOP_DESC {
-                        StencilFailOp: D3D11_STENCIL_OP_KEEP,
-                        StencilDepthFailOp: D3D11_STENCIL_OP_DECR,
-                        StencilPassOp: D3D11_STENCIL_OP_KEEP,
-                        StencilFunc: D3D11_COMPARISON_ALWAYS,
-                    },
-                })
-                .context("Failed to create depth stencil state")?
-        };
-
-        let state_readonly = unsafe {
-            device
-                .CreateDepthStencilState(&D3D11_DEPTH_STENCIL_DESC {
-                    DepthEnable: true.into(),
-                    DepthWriteMask: D3D11_DEPTH_WRITE_MASK_ZERO,
-                    DepthFunc: D3D11_COMPARISON_GREATER_EQUAL,
-                    StencilEnable: false.into(),
-                    StencilReadMask: 0xff,
-                    StencilWriteMask: 0xff,
-                    FrontFace: D3D11_DEPTH_STENCILOP_DESC {
-                        StencilFailOp: D3D11_STENCIL_OP_KEEP,
-                        StencilDepthFailOp: D3D11_STENCIL_OP_INCR,
-                        StencilPassOp: D3D11_STENCIL_OP_KEEP,
-                        StencilFunc: D3D11_COMPARISON_ALWAYS,
-                    },
-                    BackFace: D3D11_DEPTH_STENCILOP_DESC {
-                        StencilFailOp: D3D11_STENCIL_OP_KEEP,
-                        StencilDepthFailOp: D3D11_STENCIL_OP_DECR,
-                        StencilPassOp: D3D11_STENCIL_OP_KEEP,
-                        StencilFunc: D3D11_COMPARISON_ALWAYS,
-                    },
-                })
-                .context("Failed to create read-only depth stencil state")?
-        };
+        let state = Self::create_state(device, true, D3D11_COMPARISON_GREATER_EQUAL)?;
+        let state_readonly = Self::create_state(device, false, D3D11_COMPARISON_GREATER_EQUAL)?;
+        let state_standard = Self::create_state(device, true, D3D11_COMPARISON_LESS_EQUAL)?;
+        let state_readonly_standard =
+            Self::create_state(device, false, D3D11_COMPARISON_LESS_EQUAL)?;
 
         let view = unsafe {
             device
                 .CreateDepthStencilView(
                     &texture,
-                    Some(&D3D11_DEPTH_STENCIL_VIEW_DESC {
-                        Format: DXGI_FORMAT_D32_FLOAT,
-                        ViewDimension: D3D11_DSV_DIMENSION_TEXTURE2D,
-                        Flags: 0,
-                        Anonymous: D3D11_DEPTH_STENCIL_VIEW_DESC_0 {
-                            Texture2D: { D3D11_TEX2D_DSV { MipSlice: 0 } },
-                        },
+                    Some(&if sample_count > 1 {
+                        D3D11_DEPTH_STENCIL_VIEW_DESC {
+                            Format: DXGI_FORMAT_D32_FLOAT,
+                            ViewDimension: D3D11_DSV_DIMENSION_TEXTURE2DMS,
+                            Flags: 0,
+                            Anonymous: D3D11_DEPTH_STENCIL_VIEW_DESC_0 {
+                                Texture2DMS: D3D11_TEX2DMS_DSV {
+                                    UnusedField_NothingToDefine: 0,
+                                },
+                            },
+                        }
+                    } else {
+                        D3D11_DEPTH_STENCIL_VIEW_DESC {
+                            Format: DXGI_FORMAT_D32_FLOAT,
+                            ViewDimension: D3D11_DSV_DIMENSION_TEXTURE2D,
+                            Flags: 0,
+                            Anonymous: D3D11_DEPTH_STENCIL_VIEW_DESC_0 {
+                                Texture2D: { D3D11_TEX2D_DSV { MipSlice: 0 } },
+                            },
+                        }
                     }),
                 )
                 .context("Failed to create depth stencil view")?
         };
 
         let texture_view = unsafe {
-            device.CreateShaderResourceView(
-                &texture,
-                Some(&D3D11_SHADER_RESOURCE_VIEW_DESC {
-                    Format: DXGI_FORMAT_R32_FLOAT,
-                    ViewDimension: D3D11_SRV_DIMENSION_TEXTURE2D,
-                    Anonymous: D3D11_SHADER_RESOURCE_VIEW_DESC_0 {
-                        Texture2D: D3D11_TEX2D_SRV {
-                            MostDetailedMip: 0,
-                            MipLevels: 1,
-                        },
-                    },
-                }),
-            )?
+            device.CreateShaderResourceView(&texture, Some(&Self::srv_desc(sample_count)))?
         };
 
         let texture_copy = unsafe {
@@ -451,7 +592,7 @@ impl DepthState {
                         ArraySize: 1,
                         Format: DXGI_FORMAT_R32_TYPELESS,
                         SampleDesc: DXGI_SAMPLE_DESC {
-                            Count: 1,
+                            Count: sample_count,
                             Quality: 0,
                         },
                         Usage: D3D11_USAGE_DEFAULT,
@@ -465,32 +606,100 @@ impl DepthState {
         };
 
         let texture_copy_view = unsafe {
-            device.CreateShaderResourceView(
-                &texture_copy,
-                Some(&D3D11_SHADER_RESOURCE_VIEW_DESC {
-                    Format: DXGI_FORMAT_R32_FLOAT,
-                    ViewDimension: D3D11_SRV_DIMENSION_TEXTURE2D,
-                    Anonymous: D3D11_SHADER_RESOURCE_VIEW_DESC_0 {
-                        Texture2D: D3D11_TEX2D_SRV {
-                            MostDetailedMip: 0,
-                            MipLevels: 1,
-                        },
-                    },
-                }),
-            )?
+            device.CreateShaderResourceView(&texture_copy, Some(&Self::srv_desc(sample_count)))?
         };
 
         Ok(Self {
             texture,
             state,
             state_readonly,
+            state_standard,
+            state_readonly_standard,
             view,
             texture_view,
             texture_copy,
             texture_copy_view,
+            sample_count,
         })
     }
 
+    /// Depth state matching the depth convention of the camera
+    pub fn state(&self, reverse_z: bool) -> &ID3D11DepthStencilState {
+        if reverse_z {
+            &self.state
+        } else {
+            &self.state_standard
+        }
+    }
+
+    pub fn state_readonly(&self, reverse_z: bool) -> &ID3D11DepthStencilState {
+        if reverse_z {
+            &self.state_readonly
+        } else {
+            &self.state_readonly_standard
+        }
+    }
+
+    fn create_state(
+        device: &ID3D11Device,
+        write: bool,
+        func: D3D11_COMPARISON_FUNC,
+    ) -> anyhow::Result<ID3D11DepthStencilState> {
+        unsafe {
+            device
+                .CreateDepthStencilState(&D3D11_DEPTH_STENCIL_DESC {
+                    DepthEnable: true.into(),
+                    DepthWriteMask: if write {
+                        D3D11_DEPTH_WRITE_MASK_ALL
+                    } else {
+                        D3D11_DEPTH_WRITE_MASK_ZERO
+                    },
+                    DepthFunc: func,
+                    StencilEnable: false.into(),
+                    StencilReadMask: 0xff,
+                    StencilWriteMask: 0xff,
+                    FrontFace: D3D11_DEPTH_STENCILOP_DESC {
+                        StencilFailOp: D3D11_STENCIL_OP_KEEP,
+                        StencilDepthFailOp: D3D11_STENCIL_OP_INCR,
+                        StencilPassOp: D3D11_STENCIL_OP_KEEP,
+                        StencilFunc: D3D11_COMPARISON_ALWAYS,
+                    },
+                    BackFace: D3D11_DEPTH_STENCILOP_DESC {
+                        StencilFailOp: D3D11_STENCIL_OP_KEEP,
+                        StencilDepthFailOp: D3D11_STENCIL_OP_DECR,
+                        StencilPassOp: D3D11_STENCIL_OP_KEEP,
+                        StencilFunc: D3D11_COMPARISON_ALWAYS,
+                    },
+                })
+                .context("Failed to create depth stencil state")
+        }
+    }
+
+    fn srv_desc(sample_count: u32) -> D3D11_SHADER_RESOURCE_VIEW_DESC {
+        if sample_count > 1 {
+            D3D11_SHADER_RESOURCE_VIEW_DESC {
+                Format: DXGI_FORMAT_R32_FLOAT,
+                ViewDimension: D3D11_SRV_DIMENSION_TEXTURE2DMS,
+                Anonymous: D3D11_SHADER_RESOURCE_VIEW_DESC_0 {
+                    Texture2DMS: D3D11_TEX2DMS_SRV {
+                        UnusedField_NothingToDefine: 0,
+                    },
+                },
+            }
+        } else {
+            D3D11_SHADER_RESOURCE_VIEW_DESC {
+                Format: DXGI_FORMAT_R32_FLOAT,
+                ViewDimension: D3D11_SRV_DIMENSION_TEXTURE2D,
+                Anonymous: D3D11_SHADER_RESOURCE_VIEW_DESC_0 {
+                    Texture2D: D3D11_TEX2D_SRV {
+                        MostDetailedMip: 0,
+                        MipLevels: 1,
+                    },
+                },
+            }
+        }
+    }
+
     /// Copies the depth texture to texture_copy
     pub fn copy_depth(&self, context: &ID3D11DeviceContext) {
         unsafe {
@@ -499,7 +708,7 @@ impl DepthState {
     }
 
     pub fn resize(&mut self, new_size: (u32, u32), device: &ID3D11Device) -> anyhow::Result<()> {
-        *self = Self::create(new_size, device)?;
+        *self = Self::create_multisampled(new_size, self.sample_count, device)?;
         Ok(())
     }
 }
@@ -617,9 +826,4 @@ impl ShadowDepthMap {
             layers,
         })
     }
-
-    pub fn resize(&mut self, new_size: (u32, u32), device: &ID3D11Device) -> anyhow::Result<()> {
-        *self = Self::create(new_size, self.layers, device)?;
-        Ok(())
-    }
 }
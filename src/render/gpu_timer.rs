@@ -0,0 +1,165 @@
+use std::{collections::VecDeque, ffi::c_void, sync::Arc};
+
+use anyhow::Context;
+use windows::Win32::Graphics::Direct3D11::{
+    ID3D11Query, D3D11_QUERY_DATA_TIMESTAMP_DISJOINT, D3D11_QUERY_DESC, D3D11_QUERY_TIMESTAMP,
+    D3D11_QUERY_TIMESTAMP_DISJOINT,
+};
+
+use super::DeviceContextSwapchain;
+
+/// Amount of frames that can be in flight before `begin` has to wait for the GPU
+const QUERY_SETS: usize = 4;
+
+struct TimestampQueries {
+    disjoint: ID3D11Query,
+    start: ID3D11Query,
+    end: ID3D11Query,
+}
+
+/// Measures the GPU time of frames with timestamp queries.
+/// Results are read back a few frames later so the CPU doesn't have to wait on the GPU
+pub struct GpuTimer {
+    dcs: Arc<DeviceContextSwapchain>,
+    queries: Vec<TimestampQueries>,
+    /// Index of the next frame to be timed
+    frame: usize,
+    /// Frames that have been submitted but not read back yet, oldest first
+    pending: VecDeque<usize>,
+    /// Frame index and GPU time in milliseconds. Disjoint frames are reported as NaN
+    results: Vec<(usize, f32)>,
+}
+
+impl GpuTimer {
+    pub fn create(dcs: Arc<DeviceContextSwapchain>) -> anyhow::Result<Self> {
+        let query = |kind| unsafe {
+            dcs.device
+                .CreateQuery(&D3D11_QUERY_DESC {
+                    Query: kind,
+                    MiscFlags: 0,
+                })
+                .context("Failed to create timestamp query")
+        };
+
+        let queries = (0..QUERY_SETS)
+            .map(|_| {
+                Ok(TimestampQueries {
+                    disjoint: query(D3D11_QUERY_TIMESTAMP_DISJOINT)?,
+                    start: query(D3D11_QUERY_TIMESTAMP)?,
+                    end: query(D3D11_QUERY_TIMESTAMP)?,
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(Self {
+            dcs,
+            queries,
+            frame: 0,
+            pending: VecDeque::new(),
+            results: vec![],
+        })
+    }
+
+    /// Starts timing a frame, returns the index of the frame
+    pub fn begin(&mut self) -> usize {
+        if self.pending.len() >= QUERY_SETS {
+            self.read_pending(true);
+        }
+
+        let q = &self.queries[self.frame % QUERY_SETS];
+        unsafe {
+            self.dcs.context().Begin(&q.disjoint);
+            self.dcs.context().End(&q.start);
+        }
+
+        self.frame
+    }
+
+    pub fn end(&mut self) {
+        let q = &self.queries[self.frame % QUERY_SETS];
+        unsafe {
+            self.dcs.context().End(&q.end);
+            self.dcs.context().End(&q.disjoint);
+        }
+
+        self.pending.push_back(self.frame);
+        self.frame += 1;
+        self.read_pending(false);
+    }
+
+    /// Waits for the GPU to finish all frames that are still being timed
+    pub fn flush(&mut self) {
+        while !self.pending.is_empty() {
+            self.read_pending(true);
+        }
+    }
+
+    /// Returns the frames that have been read back since the last call
+    pub fn take_results(&mut self) -> Vec<(usize, f32)> {
+        std::mem::take(&mut self.results)
+    }
+
+    /// Reads back finished frames in order. When `wait` is set, blocks until at least the oldest frame is available
+    fn read_pending(&mut self, mut wait: bool) {
+        while let Some(&frame) = self.pending.front() {
+            let elapsed = loop {
+                match self.read_queries(&self.queries[frame % QUERY_SETS]) {
+                    Some(elapsed) => break Some(elapsed),
+                    None if wait => std::thread::yield_now(),
+                    None => break None,
+                }
+            };
+
+            let Some(elapsed) = elapsed else {
+                return;
+            };
+
+            self.results.push((frame, elapsed));
+            self.pending.pop_front();
+            wait = false;
+        }
+    }
+
+    /// Returns `None` if the GPU hasn't finished the frame yet
+    fn read_queries(&self, q: &TimestampQueries) -> Option<f32> {
+        let ctx = self.dcs.context();
+
+        // GetData returns S_FALSE while the data isn't available, which isn't an error. The frequency is never 0 once it is
+        let mut disjoint = D3D11_QUERY_DATA_TIMESTAMP_DISJOINT::default();
+        let mut start = 0u64;
+        let mut end = 0u64;
+        unsafe {
+            ctx.GetData(
+                &q.disjoint,
+                Some(&mut disjoint as *mut _ as *mut c_void),
+                std::mem::size_of::<D3D11_QUERY_DATA_TIMESTAMP_DISJOINT>() as u32,
+                0,
+            )
+            .ok()?;
+            if disjoint.Frequency == 0 {
+                return None;
+            }
+
+            ctx.GetData(
+                &q.start,
+                Some(&mut start as *mut _ as *mut c_void),
+                std::mem::size_of::<u64>() as u32,
+                0,
+            )
+            .ok()?;
+            ctx.GetData(
+                &q.end,
+                Some(&mut end as *mut _ as *mut c_void),
+                std::mem::size_of::<u64>() as u32,
+                0,
+            )
+            .ok()?;
+        }
+
+        if disjoint.Disjoint.as_bool() {
+            return Some(f32::NAN);
+        }
+
+        Some((end.saturating_sub(start) as f64 / disjoint.Frequency as f64 * 1000.0) as f32)
+    }
+}
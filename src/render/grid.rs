@@ -0,0 +1,107 @@
+use std::sync::Arc;
+
+use glam::{Vec3, Vec4};
+use windows::Win32::Graphics::{
+    Direct3D::D3D_PRIMITIVE_TOPOLOGY_TRIANGLESTRIP,
+    Direct3D11::{ID3D11PixelShader, ID3D11VertexShader},
+};
+
+use super::{bytecode::externs::TfxShaderStage, shader, ConstantBuffer, DeviceContextSwapchain};
+
+#[repr(C)]
+pub struct GridOptions {
+    /// Center of the grid quad, w is the half-size of the quad
+    pub origin: Vec4,
+    /// Distance between minor lines
+    pub spacing: f32,
+    /// Number of minor cells per major cell
+    pub major_every: f32,
+    /// Distance from the camera at which the grid has fully faded out
+    pub fade_distance: f32,
+    _pad: f32,
+}
+
+impl GridOptions {
+    pub fn new(
+        center: Vec3,
+        spacing: f32,
+        major_every: u32,
+        fade_distance: f32,
+        follow_camera: Option<Vec3>,
+    ) -> Self {
+        let spacing = spacing.max(0.001);
+        let major_every = major_every.max(1) as f32;
+
+        // Snap to major cells so the lines stay put in the world while the quad moves along
+        let origin = match follow_camera {
+            Some(camera) => {
+                let major_spacing = spacing * major_every;
+                let snapped = (camera.truncate() / major_spacing).round() * major_spacing;
+                snapped.extend(center.z)
+            }
+            None => center,
+        };
+
+        Self {
+            origin: origin.extend(fade_distance),
+            spacing,
+            major_every,
+            fade_distance,
+            _pad: 0.0,
+        }
+    }
+}
+
+/// Draws a world-space reference grid on the XY plane
+pub struct GridRenderer {
+    grid_vs: ID3D11VertexShader,
+    grid_ps: ID3D11PixelShader,
+    scope: ConstantBuffer<GridOptions>,
+}
+
+impl GridRenderer {
+    pub fn create(dcs: Arc<DeviceContextSwapchain>) -> anyhow::Result<Self> {
+        let vshader_blob = shader::compile_hlsl(
+            include_str!("../../assets/shaders/grid.hlsl"),
+            "VShader",
+            "vs_5_0",
+            "grid.hlsl",
+        )
+        .unwrap();
+        let pshader_blob = shader::compile_hlsl(
+            include_str!("../../assets/shaders/grid.hlsl"),
+            "PShader",
+            "ps_5_0",
+            "grid.hlsl",
+        )
+        .unwrap();
+
+        let (grid_vs, _) = shader::load_vshader(&dcs, &vshader_blob)?;
+        let (grid_ps, _) = shader::load_pshader(&dcs, &pshader_blob)?;
+
+        Ok(Self {
+            grid_vs,
+            grid_ps,
+            scope: ConstantBuffer::create(dcs, None)?,
+        })
+    }
+
+    /// Expects the compositor options to be bound to b0, and a read-only depth state
+    pub fn draw(&self, dcs: &DeviceContextSwapchain, options: &GridOptions) {
+        if let Err(e) = self.scope.write(options) {
+            error!("Failed to write grid options: {e}");
+            return;
+        }
+
+        self.scope.bind(1, TfxShaderStage::Vertex);
+        self.scope.bind(1, TfxShaderStage::Pixel);
+        unsafe {
+            dcs.context().VSSetShader(&self.grid_vs, None);
+            dcs.context().GSSetShader(None, None);
+            dcs.context().PSSetShader(&self.grid_ps, None);
+            dcs.context()
+                .IASetPrimitiveTopology(D3D_PRIMITIVE_TOPOLOGY_TRIANGLESTRIP);
+            dcs.context().Draw(4, 0);
+        }
+    }
+}
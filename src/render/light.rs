@@ -1,5 +1,6 @@
 use std::sync::Arc;
 
+use crate::ecs::components::LightColor;
 use crate::map::{SLight, SShadowingLight};
 
 use super::drawcall::ShaderStages;
@@ -11,9 +12,12 @@ use genmesh::generators::SharedVertex;
 use genmesh::Triangulate;
 use windows::Win32::Graphics::Direct3D::D3D11_PRIMITIVE_TOPOLOGY_TRIANGLELIST;
 use windows::Win32::Graphics::Direct3D11::{
-    ID3D11DepthStencilState, D3D11_COMPARISON_ALWAYS, D3D11_DEPTH_STENCILOP_DESC,
-    D3D11_DEPTH_STENCIL_DESC, D3D11_DEPTH_WRITE_MASK_ZERO, D3D11_STENCIL_OP_DECR,
-    D3D11_STENCIL_OP_INCR, D3D11_STENCIL_OP_KEEP,
+    ID3D11BlendState, ID3D11DepthStencilState, D3D11_BLEND_BLEND_FACTOR, D3D11_BLEND_DESC,
+    D3D11_BLEND_ONE, D3D11_BLEND_OP_ADD, D3D11_BLEND_ZERO, D3D11_COLOR_WRITE_ENABLE_BLUE,
+    D3D11_COLOR_WRITE_ENABLE_GREEN, D3D11_COLOR_WRITE_ENABLE_RED, D3D11_COMPARISON_ALWAYS,
+    D3D11_DEPTH_STENCILOP_DESC, D3D11_DEPTH_STENCIL_DESC, D3D11_DEPTH_WRITE_MASK_ZERO,
+    D3D11_RENDER_TARGET_BLEND_DESC, D3D11_STENCIL_OP_DECR, D3D11_STENCIL_OP_INCR,
+    D3D11_STENCIL_OP_KEEP,
 };
 use windows::Win32::Graphics::Dxgi::Common::DXGI_FORMAT_R16_UINT;
 use windows::Win32::Graphics::{
@@ -29,6 +33,8 @@ pub struct LightRenderer {
     dcs: Arc<DeviceContextSwapchain>,
 
     depth_state: ID3D11DepthStencilState,
+    /// Additive, with the source scaled by the blend factor so lights can be tinted
+    blend_state: ID3D11BlendState,
 
     input_layout: ID3D11InputLayout,
     vb_cube: ID3D11Buffer,
@@ -134,9 +140,31 @@ impl LightRenderer {
                 .context("Failed to create light renderer depth state")?
         };
 
+        let blend_state = unsafe {
+            dcs.device
+                .CreateBlendState(&D3D11_BLEND_DESC {
+                    RenderTarget: [D3D11_RENDER_TARGET_BLEND_DESC {
+                        BlendEnable: true.into(),
+                        SrcBlend: D3D11_BLEND_BLEND_FACTOR,
+                        DestBlend: D3D11_BLEND_ONE,
+                        BlendOp: D3D11_BLEND_OP_ADD,
+                        SrcBlendAlpha: D3D11_BLEND_ONE,
+                        DestBlendAlpha: D3D11_BLEND_ZERO,
+                        BlendOpAlpha: D3D11_BLEND_OP_ADD,
+                        RenderTargetWriteMask: (D3D11_COLOR_WRITE_ENABLE_RED.0
+                            | D3D11_COLOR_WRITE_ENABLE_BLUE.0
+                            | D3D11_COLOR_WRITE_ENABLE_GREEN.0)
+                            as u8,
+                    }; 8],
+                    ..Default::default()
+                })
+                .context("Failed to create light renderer blend state")?
+        };
+
         Ok(Self {
             dcs,
             depth_state,
+            blend_state,
             input_layout,
             vb_cube,
             ib_cube,
@@ -144,7 +172,7 @@ impl LightRenderer {
         })
     }
 
-    pub fn draw_normal(&self, renderer: &Renderer, light: &SLight) {
+    pub fn draw_normal(&self, renderer: &Renderer, light: &SLight, color: LightColor) {
         let render_data = renderer.render_data.data();
 
         if let Some(mat) = render_data.techniques.get(&light.technique_shading) {
@@ -159,10 +187,10 @@ impl LightRenderer {
             return;
         }
 
-        self.draw(renderer)
+        self.draw(color)
     }
 
-    pub fn draw_shadowing(&self, renderer: &Renderer, light: &SShadowingLight) {
+    pub fn draw_shadowing(&self, renderer: &Renderer, light: &SShadowingLight, color: LightColor) {
         let render_data = renderer.render_data.data();
 
         if let Some(mat) = render_data.techniques.get(&light.technique_shading) {
@@ -177,18 +205,18 @@ impl LightRenderer {
             return;
         }
 
-        self.draw(renderer)
+        self.draw(color)
     }
 
-    fn draw(&self, renderer: &Renderer) {
+    fn draw(&self, color: LightColor) {
         unsafe {
             self.dcs
                 .context()
                 .OMSetDepthStencilState(Some(&self.depth_state), 0);
 
             self.dcs.context().OMSetBlendState(
-                &renderer.blend_state_additive,
-                Some(&[1f32, 1., 1., 1.] as _),
+                &self.blend_state,
+                Some(&color.blend_factor() as _),
                 0xffffffff,
             );
 
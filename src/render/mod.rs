@@ -1,24 +1,34 @@
+pub mod asset_errors;
+pub mod bloom;
 pub mod bytecode;
 pub mod cbuffer;
 pub mod color;
 pub mod data;
 pub mod dcs;
 pub mod debug;
+pub mod dof;
 pub mod drawcall;
 pub mod entity;
 pub mod error;
 pub mod gbuffer;
+pub mod gpu_timer;
+pub mod grid;
 pub mod light;
+pub mod msaa;
 pub mod outline;
 pub mod overrides;
+pub mod reference_mesh;
 pub mod renderer;
 pub mod resource_mt;
 pub mod scopes;
+pub mod settings;
 pub mod shader;
 pub mod static_instanced;
 pub mod static_render;
+pub mod stats;
 pub mod terrain;
 pub mod tfx;
+pub mod time;
 pub mod tween;
 mod vertex_buffers;
 pub mod vertex_layout;
@@ -26,7 +36,7 @@ pub mod vertex_layout;
 pub use cbuffer::ConstantBuffer;
 pub use data::RenderData;
 pub use dcs::DeviceContextSwapchain;
-pub use entity::EntityRenderer;
+pub use entity::{EntityRenderer, EntityRenderers};
 pub use gbuffer::GBuffer;
 pub use static_instanced::InstancedRenderer;
 pub use static_render::StaticModel;
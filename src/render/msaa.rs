@@ -0,0 +1,133 @@
+use anyhow::Context;
+use windows::Win32::Graphics::{
+    Direct3D::D3D_PRIMITIVE_TOPOLOGY_TRIANGLESTRIP,
+    Direct3D11::{
+        ID3D11DepthStencilState, ID3D11PixelShader, ID3D11VertexShader, D3D11_COMPARISON_ALWAYS,
+        D3D11_DEPTH_STENCILOP_DESC, D3D11_DEPTH_STENCIL_DESC, D3D11_DEPTH_WRITE_MASK_ALL,
+        D3D11_STENCIL_OP_KEEP,
+    },
+};
+
+use super::{
+    gbuffer::{DepthState, MsaaTargets},
+    shader, DeviceContextSwapchain, GBuffer,
+};
+
+/// Resolves the multisampled geometry targets into the regular GBuffer
+pub struct MsaaResolver {
+    depth_vs: ID3D11VertexShader,
+    depth_ps: ID3D11PixelShader,
+    depth_ps_standard: ID3D11PixelShader,
+    depth_state_always: ID3D11DepthStencilState,
+}
+
+impl MsaaResolver {
+    pub fn create(dcs: &DeviceContextSwapchain) -> anyhow::Result<Self> {
+        let vshader_blob = shader::compile_hlsl(
+            include_str!("../../assets/shaders/msaa_resolve_depth.hlsl"),
+            "VShader",
+            "vs_5_0",
+            "msaa_resolve_depth.hlsl",
+        )
+        .unwrap();
+        let pshader_blob = shader::compile_hlsl(
+            include_str!("../../assets/shaders/msaa_resolve_depth.hlsl"),
+            "PShader",
+            "ps_5_0",
+            "msaa_resolve_depth.hlsl",
+        )
+        .unwrap();
+        let pshader_standard_blob = shader::compile_hlsl(
+            include_str!("../../assets/shaders/msaa_resolve_depth.hlsl"),
+            "PShaderStandard",
+            "ps_5_0",
+            "msaa_resolve_depth.hlsl",
+        )
+        .unwrap();
+
+        let (depth_vs, _) = shader::load_vshader(dcs, &vshader_blob)?;
+        let (depth_ps, _) = shader::load_pshader(dcs, &pshader_blob)?;
+        let (depth_ps_standard, _) = shader::load_pshader(dcs, &pshader_standard_blob)?;
+
+        let stencil_op = D3D11_DEPTH_STENCILOP_DESC {
+            StencilFailOp: D3D11_STENCIL_OP_KEEP,
+            StencilDepthFailOp: D3D11_STENCIL_OP_KEEP,
+            StencilPassOp: D3D11_STENCIL_OP_KEEP,
+            StencilFunc: D3D11_COMPARISON_ALWAYS,
+        };
+        let depth_state_always = unsafe {
+            dcs.device
+                .CreateDepthStencilState(&D3D11_DEPTH_STENCIL_DESC {
+                    DepthEnable: true.into(),
+                    DepthWriteMask: D3D11_DEPTH_WRITE_MASK_ALL,
+                    DepthFunc: D3D11_COMPARISON_ALWAYS,
+                    StencilEnable: false.into(),
+                    StencilReadMask: 0xff,
+                    StencilWriteMask: 0xff,
+                    FrontFace: stencil_op,
+                    BackFace: stencil_op,
+                })
+                .context("Failed to create depth resolve state")?
+        };
+
+        Ok(Self {
+            depth_vs,
+            depth_ps,
+            depth_ps_standard,
+            depth_state_always,
+        })
+    }
+
+    /// Resolves the color targets and depth buffer. Leaves the render targets unbound
+    pub fn resolve(
+        &self,
+        dcs: &DeviceContextSwapchain,
+        msaa: &MsaaTargets,
+        gbuffer: &GBuffer,
+        reverse_z: bool,
+    ) {
+        unsafe {
+            dcs.context().OMSetRenderTargets(None, None);
+        }
+
+        msaa.rt0.resolve_to(&gbuffer.rt0);
+        msaa.rt1.resolve_to(&gbuffer.rt1);
+        msaa.rt2.resolve_to(&gbuffer.rt2);
+        msaa.rt3.resolve_to(&gbuffer.rt3);
+
+        self.resolve_depth(dcs, &msaa.depth, &gbuffer.depth, reverse_z);
+    }
+
+    // Depth buffers can't be resolved with ResolveSubresource, so we have to do it ourselves
+    fn resolve_depth(
+        &self,
+        dcs: &DeviceContextSwapchain,
+        source: &DepthState,
+        dest: &DepthState,
+        reverse_z: bool,
+    ) {
+        unsafe {
+            dcs.context().OMSetRenderTargets(None, &dest.view);
+            dcs.context()
+                .OMSetDepthStencilState(&self.depth_state_always, 0);
+            dcs.context()
+                .PSSetShaderResources(0, Some(&[Some(source.texture_view.clone())]));
+
+            dcs.context().VSSetShader(&self.depth_vs, None);
+            dcs.context().PSSetShader(
+                if reverse_z {
+                    &self.depth_ps
+                } else {
+                    &self.depth_ps_standard
+                },
+                None,
+            );
+            dcs.context()
+                .IASetPrimitiveTopology(D3D_PRIMITIVE_TOPOLOGY_TRIANGLESTRIP);
+            dcs.context().Draw(4, 0);
+
+            dcs.context().PSSetShaderResources(0, Some(&[None]));
+            dcs.context().OMSetRenderTargets(None, None);
+        }
+    }
+}
@@ -1,17 +1,23 @@
+use std::sync::Arc;
+
+use glam::Vec4;
 use windows::Win32::Graphics::{
     Direct3D::D3D_PRIMITIVE_TOPOLOGY_TRIANGLESTRIP,
     Direct3D11::{ID3D11PixelShader, ID3D11VertexShader},
 };
 
-use super::{shader, DeviceContextSwapchain};
+use super::{bytecode::externs::TfxShaderStage, shader, ConstantBuffer, DeviceContextSwapchain};
+
+pub const OUTLINE_COLOR_DEFAULT: Vec4 = Vec4::new(1.0, 0.6, 0.2, 1.0);
 
 pub struct OutlineScreenEffect {
     outline_vs: ID3D11VertexShader,
     outline_ps: ID3D11PixelShader,
+    scope: ConstantBuffer<Vec4>,
 }
 
 impl OutlineScreenEffect {
-    pub fn create(dcs: &DeviceContextSwapchain) -> anyhow::Result<Self> {
+    pub fn create(dcs: Arc<DeviceContextSwapchain>) -> anyhow::Result<Self> {
         let vshader_blob = shader::compile_hlsl(
             include_str!("../../assets/shaders/outline.hlsl"),
             "VShader",
@@ -27,16 +33,23 @@ impl OutlineScreenEffect {
         )
         .unwrap();
 
-        let (outline_vs, _) = shader::load_vshader(dcs, &vshader_blob)?;
-        let (outline_ps, _) = shader::load_pshader(dcs, &pshader_blob)?;
+        let (outline_vs, _) = shader::load_vshader(&dcs, &vshader_blob)?;
+        let (outline_ps, _) = shader::load_pshader(&dcs, &pshader_blob)?;
 
         Ok(Self {
             outline_vs,
             outline_ps,
+            scope: ConstantBuffer::create(dcs, None)?,
         })
     }
 
-    pub fn draw(&self, dcs: &DeviceContextSwapchain) {
+    pub fn draw(&self, dcs: &DeviceContextSwapchain, color: Vec4) {
+        if let Err(e) = self.scope.write(&color) {
+            error!("Failed to write outline color: {e}");
+            return;
+        }
+
+        self.scope.bind(0, TfxShaderStage::Pixel);
         unsafe {
             dcs.context().VSSetShader(&self.outline_vs, None);
             dcs.context().PSSetShader(&self.outline_ps, None);
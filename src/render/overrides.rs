@@ -1,3 +1,5 @@
+use std::{borrow::Cow, path::Path};
+
 use windows::Win32::Graphics::Direct3D11::{
     ID3D11PixelShader, ID3D11SamplerState, ID3D11VertexShader,
 };
@@ -37,42 +39,54 @@ pub struct ShaderOverrides {
 impl ShaderOverrides {
     pub fn load(dcs: &DeviceContextSwapchain) -> anyhow::Result<Self> {
         let vshader_blob = shader::compile_hlsl(
-            include_str!("../../assets/shaders/overrides/entity.vsh"),
+            &override_source(
+                "entity.vsh",
+                include_str!("../../assets/shaders/overrides/entity.vsh"),
+            ),
             "main",
             "vs_5_0",
             "entity.vsh",
         )
-        .unwrap();
+        .map_err(|e| anyhow::anyhow!(e))?;
 
         let (entity_vs, _) = shader::load_vshader(dcs, &vshader_blob)?;
 
         let pshader_blob = shader::compile_hlsl(
-            include_str!("../../assets/shaders/overrides/entity_deferred.psh"),
+            &override_source(
+                "entity_deferred.psh",
+                include_str!("../../assets/shaders/overrides/entity_deferred.psh"),
+            ),
             "main",
             "ps_5_0",
             "entity_deferred.psh",
         )
-        .unwrap();
+        .map_err(|e| anyhow::anyhow!(e))?;
 
         let (entity_ps_deferred, _) = shader::load_pshader(dcs, &pshader_blob)?;
 
         let pshader_blob = shader::compile_hlsl(
-            include_str!("../../assets/shaders/overrides/entity_forward.psh"),
+            &override_source(
+                "entity_forward.psh",
+                include_str!("../../assets/shaders/overrides/entity_forward.psh"),
+            ),
             "main",
             "ps_5_0",
             "entity_forward.psh",
         )
-        .unwrap();
+        .map_err(|e| anyhow::anyhow!(e))?;
 
         let (entity_ps_forward, _) = shader::load_pshader(dcs, &pshader_blob)?;
 
         let pshader_blob = shader::compile_hlsl(
-            include_str!("../../assets/shaders/overrides/terrain.psh"),
+            &override_source(
+                "terrain.psh",
+                include_str!("../../assets/shaders/overrides/terrain.psh"),
+            ),
             "main",
             "ps_5_0",
             "terrain.psh",
         )
-        .unwrap();
+        .map_err(|e| anyhow::anyhow!(e))?;
 
         let (terrain_ps, _) = shader::load_pshader(dcs, &pshader_blob)?;
 
@@ -104,6 +118,19 @@ impl ShaderOverrides {
     }
 }
 
+/// Reads an override shader from the source tree when it's available, so edits are picked up by a shader reload.
+/// Falls back to the copy embedded at compile time
+fn override_source(filename: &str, embedded: &'static str) -> Cow<'static, str> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("assets/shaders/overrides")
+        .join(filename);
+
+    match std::fs::read_to_string(path) {
+        Ok(source) => Cow::Owned(source),
+        Err(_) => Cow::Borrowed(embedded),
+    }
+}
+
 #[derive(Default)]
 pub struct ScopeOverrides {
     pub view: ScopeView,
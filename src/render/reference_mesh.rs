@@ -0,0 +1,225 @@
+// Renders external meshes imported as a reference for placement and scale comparisons
+
+use std::{io::BufReader, path::Path, sync::Arc};
+
+use anyhow::Context;
+use glam::{Mat4, Vec3, Vec4};
+use windows::Win32::Graphics::{
+    Direct3D::D3D11_PRIMITIVE_TOPOLOGY_TRIANGLELIST, Direct3D11::*, Dxgi::Common::*,
+};
+
+use crate::{render::shader, types::AABB};
+
+use super::{bytecode::externs::TfxShaderStage, ConstantBuffer, DeviceContextSwapchain};
+
+/// Vertex buffer of an imported mesh, with the mesh triangulated and a position + normal per vertex
+pub struct ReferenceMeshData {
+    vertex_buffer: ID3D11Buffer,
+    pub vertex_count: usize,
+    /// Bounds in model space
+    pub bounds: AABB,
+}
+
+impl ReferenceMeshData {
+    pub fn load_obj(dcs: &DeviceContextSwapchain, path: &Path) -> anyhow::Result<Self> {
+        let obj = obj::ObjData::load_buf(BufReader::new(fs_err::File::open(path)?))
+            .context("Failed to parse OBJ file")?;
+
+        let mut vertices: Vec<[f32; 6]> = vec![];
+        for poly in obj
+            .objects
+            .iter()
+            .flat_map(|o| &o.groups)
+            .flat_map(|g| &g.polys)
+        {
+            let positions = poly
+                .0
+                .iter()
+                .map(|p| obj.position.get(p.0).copied().map(Vec3::from))
+                .collect::<Option<Vec<_>>>()
+                .context("OBJ face references a missing vertex")?;
+            if positions.len() < 3 {
+                continue;
+            }
+
+            // Faces without normals get a flat one
+            let face_normal = (positions[1] - positions[0])
+                .cross(positions[2] - positions[0])
+                .normalize_or_zero();
+
+            // Triangulate as a fan, which is fine for the convex faces most exporters write
+            for i in 1..positions.len() - 1 {
+                for vi in [0, i, i + 1] {
+                    let normal = poly.0[vi]
+                        .2
+                        .and_then(|ni| obj.normal.get(ni))
+                        .map_or(face_normal, |&n| Vec3::from(n));
+
+                    let p = positions[vi];
+                    vertices.push([p.x, p.y, p.z, normal.x, normal.y, normal.z]);
+                }
+            }
+        }
+
+        anyhow::ensure!(!vertices.is_empty(), "OBJ file doesn't contain any faces");
+
+        let bounds = AABB::from_points(
+            vertices
+                .iter()
+                .map(|v| Vec3::new(v[0], v[1], v[2]))
+                .collect::<Vec<_>>(),
+        );
+
+        let vertex_buffer = unsafe {
+            dcs.device
+                .CreateBuffer(
+                    &D3D11_BUFFER_DESC {
+                        ByteWidth: (std::mem::size_of::<[f32; 6]>() * vertices.len()) as _,
+                        Usage: D3D11_USAGE_IMMUTABLE,
+                        BindFlags: D3D11_BIND_VERTEX_BUFFER,
+                        ..Default::default()
+                    },
+                    Some(&D3D11_SUBRESOURCE_DATA {
+                        pSysMem: vertices.as_ptr() as _,
+                        ..Default::default()
+                    }),
+                )
+                .context("Failed to create reference mesh vertex buffer")?
+        };
+
+        Ok(Self {
+            vertex_buffer,
+            vertex_count: vertices.len(),
+            bounds,
+        })
+    }
+}
+
+pub struct ReferenceMeshRenderer {
+    vertex_layout: ID3D11InputLayout,
+    vshader: ID3D11VertexShader,
+    pshader: ID3D11PixelShader,
+
+    scope: ConstantBuffer<AlkScopeReferenceMesh>,
+}
+
+impl ReferenceMeshRenderer {
+    pub fn create(dcs: Arc<DeviceContextSwapchain>) -> anyhow::Result<Self> {
+        let vshader_blob = shader::compile_hlsl(
+            include_str!("../../assets/shaders/reference_mesh.hlsl"),
+            "VShader",
+            "vs_5_0",
+            "reference_mesh.hlsl",
+        )
+        .unwrap();
+        let pshader_blob = shader::compile_hlsl(
+            include_str!("../../assets/shaders/reference_mesh.hlsl"),
+            "PShader",
+            "ps_5_0",
+            "reference_mesh.hlsl",
+        )
+        .unwrap();
+
+        let (vshader, _) = shader::load_vshader(&dcs, &vshader_blob)?;
+        let (pshader, _) = shader::load_pshader(&dcs, &pshader_blob)?;
+
+        let vertex_layout = unsafe {
+            dcs.device
+                .CreateInputLayout(
+                    &[
+                        D3D11_INPUT_ELEMENT_DESC {
+                            SemanticName: s!("POSITION"),
+                            SemanticIndex: 0,
+                            Format: DXGI_FORMAT_R32G32B32_FLOAT,
+                            InputSlot: 0,
+                            AlignedByteOffset: 0,
+                            InputSlotClass: D3D11_INPUT_PER_VERTEX_DATA,
+                            InstanceDataStepRate: 0,
+                        },
+                        D3D11_INPUT_ELEMENT_DESC {
+                            SemanticName: s!("NORMAL"),
+                            SemanticIndex: 0,
+                            Format: DXGI_FORMAT_R32G32B32_FLOAT,
+                            InputSlot: 0,
+                            AlignedByteOffset: 12,
+                            InputSlotClass: D3D11_INPUT_PER_VERTEX_DATA,
+                            InstanceDataStepRate: 0,
+                        },
+                    ],
+                    &vshader_blob,
+                )
+                .context("Failed to create reference mesh vertex layout")?
+        };
+
+        Ok(Self {
+            vertex_layout,
+            vshader,
+            pshader,
+            scope: ConstantBuffer::create(dcs, None)?,
+        })
+    }
+
+    pub fn draw(
+        &self,
+        dcs: &DeviceContextSwapchain,
+        mesh: &ReferenceMeshData,
+        transform: Mat4,
+        color: Vec4,
+        proj_view: Mat4,
+        view: Mat4,
+    ) {
+        unsafe {
+            dcs.context().PSSetShader(&self.pshader, None);
+        }
+
+        self.draw_nopshader(dcs, mesh, transform, color, proj_view, view)
+    }
+
+    pub fn draw_nopshader(
+        &self,
+        dcs: &DeviceContextSwapchain,
+        mesh: &ReferenceMeshData,
+        transform: Mat4,
+        color: Vec4,
+        proj_view: Mat4,
+        view: Mat4,
+    ) {
+        self.scope
+            .write(&AlkScopeReferenceMesh {
+                proj_view,
+                view,
+                model: transform,
+                color,
+            })
+            .unwrap();
+
+        unsafe {
+            dcs.context().IASetVertexBuffers(
+                0,
+                1,
+                Some([Some(mesh.vertex_buffer.clone())].as_ptr()),
+                Some([6 * 4].as_ptr()),
+                Some(&0),
+            );
+
+            dcs.context()
+                .IASetPrimitiveTopology(D3D11_PRIMITIVE_TOPOLOGY_TRIANGLELIST);
+
+            self.scope.bind(7, TfxShaderStage::Vertex);
+            self.scope.bind(7, TfxShaderStage::Pixel);
+
+            dcs.context().IASetInputLayout(&self.vertex_layout);
+            dcs.context().VSSetShader(&self.vshader, None);
+
+            dcs.context().Draw(mesh.vertex_count as u32, 0);
+        }
+    }
+}
+
+#[repr(C)]
+struct AlkScopeReferenceMesh {
+    pub proj_view: Mat4,
+    pub view: Mat4,
+    pub model: Mat4,
+    pub color: Vec4,
+}
@@ -1,34 +1,51 @@
-use std::{sync::Arc, time::Instant};
+use std::{collections::HashMap, sync::Arc, time::Instant};
 
+use crate::dxgi::DxgiFormat;
+use crate::ecs::components::{ActivityGroup, LightColor, OpacityOverride, ReferenceMesh, Visible};
 use crate::ecs::resources::SelectedEntity;
+use crate::ecs::tags::{TagPalette, Tags};
 use crate::ecs::transform::Transform;
 use crate::map::{MapDataList, SLight, SShadowingLight};
 use crate::overlays::camera_settings::CurrentCubemap;
 use crate::types::AABB;
 use crate::util::RwLock;
+use destiny_pkg::TagHash;
 use glam::{Mat4, Quat, UVec2, Vec3, Vec4};
 use hecs::Entity;
+use itertools::Itertools;
 use windows::Win32::Graphics::Direct3D::D3D_PRIMITIVE_TOPOLOGY_TRIANGLESTRIP;
 use windows::Win32::Graphics::Direct3D11::*;
 use windows::Win32::Graphics::Dxgi::Common::DXGI_FORMAT;
 use winit::window::Window;
 
-use crate::overlays::render_settings::{CompositorOptions, PickbufferScope, RenderSettings};
+use crate::overlays::render_settings::{ActivityGroupFilter, CompositorOptions, PickbufferScope};
 use crate::render::drawcall::ShaderStages;
 use crate::render::scopes::ScopeUnk3;
+use crate::render::settings::{RenderSettings, TextureFiltering, COMPOSITOR_MODES};
 use crate::render::shader;
-use crate::{camera::FpsCamera, resources::Resources};
+use crate::{
+    camera::{self, FpsCamera},
+    resources::Resources,
+};
 
+use super::bloom::{BloomEffect, BloomOptions, BloomPass};
 use super::bytecode::externs::TfxShaderStage;
 use super::data::RenderDataManager;
 use super::debug::{DebugShapeRenderer, DebugShapes};
+use super::dof::{DepthOfFieldEffect, DofOptions};
 use super::drawcall::{GeometryType, Transparency};
 use super::error::ErrorRenderer;
-use super::gbuffer::ShadowDepthMap;
+use super::gbuffer::{CpuStagingBuffer, ShadowDepthMap};
+use super::grid::{GridOptions, GridRenderer};
 use super::light::LightRenderer;
-use super::outline::OutlineScreenEffect;
+use super::msaa::MsaaResolver;
+use super::outline::{OutlineScreenEffect, OUTLINE_COLOR_DEFAULT};
 use super::overrides::{EnabledShaderOverrides, ScopeOverrides, ShaderOverrides};
+use super::reference_mesh::{ReferenceMeshData, ReferenceMeshRenderer};
 use super::scopes::{ScopeUnk2, ScopeUnk8};
+use super::stats::RenderStats;
+use super::tfx::TfxRenderStage;
+use super::time::TimeControl;
 use super::{
     drawcall::{DrawCall, ShadingMode, SortValue3d},
     scopes::{ScopeFrame, ScopeView},
@@ -62,9 +79,11 @@ pub struct Renderer {
     scope_unk3: ConstantBuffer<ScopeUnk3>,
     scope_unk8: ConstantBuffer<ScopeUnk8>,
     scope_alk_composite: ConstantBuffer<CompositorOptions>,
-    scope_alk_cascade_transforms: ConstantBuffer<[Mat4; Self::CAMERA_CASCADE_LEVEL_COUNT]>,
+    scope_alk_cascades: ConstantBuffer<CascadeOptions>,
 
     pub start_time: Instant,
+    /// Game time of the current frame, from [`TimeControl`]
+    pub game_time: RwLock<f32>,
     pub last_frame: RwLock<Instant>,
     pub delta_time: RwLock<f32>,
 
@@ -73,10 +92,17 @@ pub struct Renderer {
     blend_state_none: ID3D11BlendState,
     blend_state_blend: ID3D11BlendState,
     pub blend_state_additive: ID3D11BlendState,
+    blend_state_multiply: ID3D11BlendState,
+    blend_state_screen: ID3D11BlendState,
+    blend_state_premultiplied: ID3D11BlendState,
     blend_state_decals: ID3D11BlendState,
+    /// Blends with a constant factor, for entities with an opacity override
+    blend_state_opacity: ID3D11BlendState,
 
     pub rasterizer_state: ID3D11RasterizerState,
     pub rasterizer_state_nocull: ID3D11RasterizerState,
+    /// Recreated when the decal depth bias or depth direction changes
+    decal_rasterizer_states: RwLock<Option<DecalRasterizerStates>>,
 
     composite_vs: ID3D11VertexShader,
     composite_ps: ID3D11PixelShader,
@@ -91,17 +117,29 @@ pub struct Renderer {
 
     debug_shape_renderer: DebugShapeRenderer,
     error_renderer: ErrorRenderer,
+    reference_mesh_renderer: ReferenceMeshRenderer,
     outline_renderer: OutlineScreenEffect,
+    dof_renderer: DepthOfFieldEffect,
+    grid_renderer: GridRenderer,
+    bloom_renderer: BloomEffect,
+    msaa_resolver: MsaaResolver,
 
     shader_overrides: ShaderOverrides,
 
     light_cascade_transforms: RwLock<[Mat4; Self::CAMERA_CASCADE_LEVEL_COUNT]>,
+    /// Number of cascades rendered this frame
+    cascade_count: RwLock<usize>,
+    /// Side planes of the cascades that are rendered this frame, anything inside of them can cast a visible shadow
+    shadow_caster_planes: RwLock<Vec<[Vec4; 4]>>,
     shadow_rs: ID3D11RasterizerState,
 
     last_material: RwLock<u32>,
+    /// Texture filtering, anisotropy and sampler count the samplers were last recreated with
+    applied_texture_filtering: RwLock<Option<(TextureFiltering, u32, usize)>>,
 
     // Objects that failed to render this frame
     fiddlesticks: RwLock<Vec<(Transform, Option<Entity>)>>,
+    stats: RwLock<RenderStats>,
 
     // TODO(cohae): find a better way to get the light transform into the bytecode interpreter
     pub light_transform: RwLock<Transform>,
@@ -172,6 +210,82 @@ impl Renderer {
             })?
         };
 
+        let blend_state_multiply = unsafe {
+            dcs.device.CreateBlendState(&D3D11_BLEND_DESC {
+                RenderTarget: [D3D11_RENDER_TARGET_BLEND_DESC {
+                    BlendEnable: true.into(),
+                    SrcBlend: D3D11_BLEND_DEST_COLOR,
+                    DestBlend: D3D11_BLEND_ZERO,
+                    BlendOp: D3D11_BLEND_OP_ADD,
+                    SrcBlendAlpha: D3D11_BLEND_ONE,
+                    DestBlendAlpha: D3D11_BLEND_ZERO,
+                    BlendOpAlpha: D3D11_BLEND_OP_ADD,
+                    RenderTargetWriteMask: (D3D11_COLOR_WRITE_ENABLE_RED.0
+                        | D3D11_COLOR_WRITE_ENABLE_BLUE.0
+                        | D3D11_COLOR_WRITE_ENABLE_GREEN.0)
+                        as u8,
+                }; 8],
+                ..Default::default()
+            })?
+        };
+
+        let blend_state_screen = unsafe {
+            dcs.device.CreateBlendState(&D3D11_BLEND_DESC {
+                RenderTarget: [D3D11_RENDER_TARGET_BLEND_DESC {
+                    BlendEnable: true.into(),
+                    SrcBlend: D3D11_BLEND_ONE,
+                    DestBlend: D3D11_BLEND_INV_SRC_COLOR,
+                    BlendOp: D3D11_BLEND_OP_ADD,
+                    SrcBlendAlpha: D3D11_BLEND_ONE,
+                    DestBlendAlpha: D3D11_BLEND_ZERO,
+                    BlendOpAlpha: D3D11_BLEND_OP_ADD,
+                    RenderTargetWriteMask: (D3D11_COLOR_WRITE_ENABLE_RED.0
+                        | D3D11_COLOR_WRITE_ENABLE_BLUE.0
+                        | D3D11_COLOR_WRITE_ENABLE_GREEN.0)
+                        as u8,
+                }; 8],
+                ..Default::default()
+            })?
+        };
+
+        let blend_state_premultiplied = unsafe {
+            dcs.device.CreateBlendState(&D3D11_BLEND_DESC {
+                RenderTarget: [D3D11_RENDER_TARGET_BLEND_DESC {
+                    BlendEnable: true.into(),
+                    SrcBlend: D3D11_BLEND_ONE,
+                    DestBlend: D3D11_BLEND_INV_SRC_ALPHA,
+                    BlendOp: D3D11_BLEND_OP_ADD,
+                    SrcBlendAlpha: D3D11_BLEND_ONE,
+                    DestBlendAlpha: D3D11_BLEND_ZERO,
+                    BlendOpAlpha: D3D11_BLEND_OP_ADD,
+                    RenderTargetWriteMask: (D3D11_COLOR_WRITE_ENABLE_RED.0
+                        | D3D11_COLOR_WRITE_ENABLE_BLUE.0
+                        | D3D11_COLOR_WRITE_ENABLE_GREEN.0)
+                        as u8,
+                }; 8],
+                ..Default::default()
+            })?
+        };
+
+        let blend_state_opacity = unsafe {
+            dcs.device.CreateBlendState(&D3D11_BLEND_DESC {
+                RenderTarget: [D3D11_RENDER_TARGET_BLEND_DESC {
+                    BlendEnable: true.into(),
+                    SrcBlend: D3D11_BLEND_BLEND_FACTOR,
+                    DestBlend: D3D11_BLEND_INV_BLEND_FACTOR,
+                    BlendOp: D3D11_BLEND_OP_ADD,
+                    SrcBlendAlpha: D3D11_BLEND_ONE,
+                    DestBlendAlpha: D3D11_BLEND_ZERO,
+                    BlendOpAlpha: D3D11_BLEND_OP_ADD,
+                    RenderTargetWriteMask: (D3D11_COLOR_WRITE_ENABLE_RED.0
+                        | D3D11_COLOR_WRITE_ENABLE_BLUE.0
+                        | D3D11_COLOR_WRITE_ENABLE_GREEN.0)
+                        as u8,
+                }; 8],
+                ..Default::default()
+            })?
+        };
+
         let blend_state_decals = unsafe {
             dcs.device.CreateBlendState(&D3D11_BLEND_DESC {
                 RenderTarget: [D3D11_RENDER_TARGET_BLEND_DESC {
@@ -313,14 +427,22 @@ impl Renderer {
             light_cascade_transforms: RwLock::new(
                 [Mat4::IDENTITY; Self::CAMERA_CASCADE_LEVEL_COUNT],
             ),
+            cascade_count: RwLock::new(Self::CAMERA_CASCADE_LEVEL_COUNT),
+            shadow_caster_planes: RwLock::new(vec![]),
             shader_overrides: ShaderOverrides::load(&dcs)?,
             debug_shape_renderer: DebugShapeRenderer::new(dcs.clone())?,
             error_renderer: ErrorRenderer::load(dcs.clone()),
-            outline_renderer: OutlineScreenEffect::create(&dcs)?,
+            reference_mesh_renderer: ReferenceMeshRenderer::create(dcs.clone())?,
+            outline_renderer: OutlineScreenEffect::create(dcs.clone())?,
+            dof_renderer: DepthOfFieldEffect::create(dcs.clone())?,
+            grid_renderer: GridRenderer::create(dcs.clone())?,
+            bloom_renderer: BloomEffect::create(dcs.clone())?,
+            msaa_resolver: MsaaResolver::create(&dcs)?,
             draw_queue: RwLock::new(Vec::with_capacity(8192)),
             state: RwLock::new(RendererState::Awaiting),
             gbuffer: GBuffer::create(
                 (window.inner_size().width, window.inner_size().height),
+                1,
                 dcs.clone(),
             )?,
             window_size: (window.inner_size().width, window.inner_size().height),
@@ -333,19 +455,25 @@ impl Renderer {
             scope_unk3: ConstantBuffer::create(dcs.clone(), None)?,
             scope_unk8: ConstantBuffer::create(dcs.clone(), None)?,
             scope_alk_composite: ConstantBuffer::create(dcs.clone(), None)?,
-            scope_alk_cascade_transforms: ConstantBuffer::create(dcs.clone(), None)?,
+            scope_alk_cascades: ConstantBuffer::create(dcs.clone(), None)?,
             render_data: RenderDataManager::new(dcs.clone()),
             light_renderer: LightRenderer::new(dcs.clone())?,
             dcs,
             start_time: Instant::now(),
+            game_time: RwLock::new(0.0),
             last_frame: RwLock::new(Instant::now()),
             delta_time: RwLock::new(0.016),
             blend_state_none,
             blend_state_blend,
             blend_state_additive,
+            blend_state_multiply,
+            blend_state_screen,
+            blend_state_premultiplied,
             blend_state_decals,
+            blend_state_opacity,
             rasterizer_state,
             rasterizer_state_nocull,
+            decal_rasterizer_states: RwLock::new(None),
             shadow_rs,
             composite_vs: vshader_composite,
             composite_ps: pshader_composite,
@@ -356,7 +484,9 @@ impl Renderer {
             clear_pickbuffer_vs,
             clear_pickbuffer_ps,
             last_material: RwLock::new(u32::MAX),
+            applied_texture_filtering: RwLock::new(None),
             fiddlesticks: RwLock::new(vec![]),
+            stats: RwLock::new(RenderStats::default()),
             light_mat: RwLock::new(Mat4::IDENTITY),
             light_transform: RwLock::new(Transform::default()),
             camera_viewproj: RwLock::new(Mat4::IDENTITY),
@@ -375,6 +505,7 @@ impl Renderer {
 
         self.draw_queue.write().clear();
         self.fiddlesticks.write().clear();
+        *self.stats.write() = RenderStats::default();
         *self.state.write() = RendererState::Recording;
     }
 
@@ -383,24 +514,188 @@ impl Renderer {
         self.draw_queue.write().push((ordering, drawcall))
     }
 
+    /// Materials used by the drawcalls of an entity in the last submitted frame
+    pub fn entity_materials(&self, entity: Entity) -> Vec<TagHash> {
+        self.draw_queue
+            .read()
+            .iter()
+            .filter(|(_, d)| d.entity == entity)
+            .flat_map(|(s, d)| [Some(s.material().into()), d.variant_material])
+            .flatten()
+            .unique()
+            .collect()
+    }
+
+    /// Materials used by the drawcalls of an entity in the last submitted frame, with the sort key they were drawn with.
+    /// The sort key decides the geometry type, shading mode and blend state the drawcall is rendered with
+    pub fn entity_draw_states(&self, entity: Entity) -> Vec<(TagHash, SortValue3d)> {
+        self.draw_queue
+            .read()
+            .iter()
+            .filter(|(_, d)| d.entity == entity)
+            .flat_map(|(s, d)| {
+                [Some(s.material().into()), d.variant_material]
+                    .into_iter()
+                    .flatten()
+                    .map(|m| (m, s.clone()))
+            })
+            .unique_by(|(m, _)| *m)
+            .collect()
+    }
+
+    /// Reads the entity ID at the given pixel from the pick buffer of the last submitted frame.
+    /// Mapping the buffer waits for the GPU to finish that frame, so this shouldn't be called every frame
+    pub fn pick_entity_id(&self, x: usize, y: usize) -> Option<u32> {
+        // Rounding can push the cursor just past the edge of the pick buffer
+        if x >= self.window_size.0 as usize || y >= self.window_size.1 as usize {
+            return None;
+        }
+
+        let m = self.gbuffer.pick_buffer_staging.map(D3D11_MAP_READ).ok()?;
+        let id = unsafe { *(m.ptr.add(y * m.row_pitch as usize + x * 4) as *const u32) };
+
+        (id != u32::MAX).then_some(id)
+    }
+
+    /// Statistics of the last submitted frame
+    pub fn stats(&self) -> RenderStats {
+        self.stats.read().clone()
+    }
+
+    pub fn count_culling(&self, visible: usize, culled: usize) {
+        self.stats.write().count_culling(visible, culled);
+    }
+
     pub fn push_fiddlesticks(&self, transform: Transform, entity: Option<Entity>) {
         self.fiddlesticks.write().push((transform, entity))
     }
 
+    /// Marks the drawcalls of entities with an `OpacityOverride` below 1. Forward drawcalls are switched to alpha blending,
+    /// deferred ones are left to [`Self::run_deferred_opacity_overrides`]
+    fn apply_opacity_overrides(&self, resources: &Resources) {
+        let Some(maps) = resources.get::<MapDataList>() else {
+            return;
+        };
+        let Some((_, _, map)) = maps.current_map() else {
+            return;
+        };
+
+        let overrides: HashMap<Entity, f32> = map
+            .scene
+            .query::<&OpacityOverride>()
+            .iter()
+            .filter(|(_, o)| o.0 < 1.0)
+            .map(|(e, o)| (e, o.0.max(0.0)))
+            .collect();
+        if overrides.is_empty() {
+            return;
+        }
+
+        for (s, d) in self.draw_queue.write().iter_mut() {
+            if let Some(&opacity) = overrides.get(&d.entity) {
+                if s.shading_mode() == ShadingMode::Forward {
+                    *s = s.clone().with_forced_blend();
+                }
+                d.opacity = Some(opacity);
+            }
+        }
+    }
+
+    /// Recreates the samplers when the filtering settings change or new samplers have been loaded
+    fn update_texture_filtering(&self, render_settings: &RenderSettings) {
+        let sampler_count = self.render_data.data().sampler_descs.len();
+        let settings = (
+            render_settings.texture_filtering,
+            render_settings.anisotropy,
+            sampler_count,
+        );
+
+        let mut applied = self.applied_texture_filtering.write();
+        if *applied != Some(settings) {
+            self.render_data
+                .apply_texture_filtering(settings.0, settings.1);
+            *applied = Some(settings);
+        }
+    }
+
+    /// Recreates the decal rasterizer states when the depth bias settings change
+    fn update_decal_rasterizer_states(&self, render_settings: &RenderSettings, reverse_z: bool) {
+        let bias = (
+            render_settings.decal_depth_bias,
+            render_settings.decal_slope_scaled_depth_bias,
+            reverse_z,
+        );
+
+        let mut states = self.decal_rasterizer_states.write();
+        if states.as_ref().map(|s| s.bias) == Some(bias) {
+            return;
+        }
+
+        match DecalRasterizerStates::create(&self.dcs, bias) {
+            Ok(s) => *states = Some(s),
+            Err(e) => {
+                error!("Failed to create decal rasterizer states: {e}");
+                *states = None;
+            }
+        }
+    }
+
+    /// Visible reference meshes in the current map, with their model matrix and color
+    fn reference_meshes(
+        &self,
+        resources: &Resources,
+    ) -> Vec<(Entity, Mat4, Vec4, Arc<ReferenceMeshData>)> {
+        let Some(maps) = resources.get::<MapDataList>() else {
+            return vec![];
+        };
+        let Some((_, _, map)) = maps.current_map() else {
+            return vec![];
+        };
+
+        map.scene
+            .query::<(&Transform, &ReferenceMesh, Option<&Visible>)>()
+            .iter()
+            .filter(|(_, (_, _, visible))| visible.map_or(true, |v| v.0))
+            .map(|(e, (transform, rm, _))| {
+                let [r, g, b] = rm.color.map(|c| c as f32 / 255.0);
+                (
+                    e,
+                    transform.to_mat4(),
+                    Vec4::new(r, g, b, 1.0),
+                    rm.mesh.clone(),
+                )
+            })
+            .collect()
+    }
+
     /// Submits recorded drawcalls
     pub fn submit_frame(&self, resources: &Resources) {
         if *self.state.read() != RendererState::Recording {
             panic!("Called submit(), but the renderer is not recording! Did you call begin()?")
         }
 
+        self.apply_opacity_overrides(resources);
         self.draw_queue
             .write()
             .sort_unstable_by(|(o1, _), (o2, _)| o1.cmp(o2));
 
+        self.stats.write().visible_entities = self
+            .draw_queue
+            .read()
+            .iter()
+            .map(|(_, d)| d.entity)
+            .filter(|e| *e != Entity::DANGLING)
+            .unique()
+            .count();
+
         self.update_buffers(resources)
             .expect("Renderer::update_buffers");
 
         let render_settings = resources.get::<RenderSettings>().unwrap();
+        let reverse_z = resources.get::<FpsCamera>().unwrap().reverse_z;
+
+        self.update_texture_filtering(&render_settings);
+        self.update_decal_rasterizer_states(&render_settings, reverse_z);
 
         self.scope_unk2.bind(2, TfxShaderStage::Vertex);
         self.scope_unk2.bind(2, TfxShaderStage::Pixel);
@@ -420,18 +715,22 @@ impl Renderer {
 
         unsafe {
             self.dcs.context().RSSetState(&self.shadow_rs);
-            self.dcs.context().OMSetRenderTargets(
-                Some(&[
-                    Some(self.gbuffer.rt0.render_target.clone()),
-                    Some(self.gbuffer.rt1.render_target.clone()),
-                    Some(self.gbuffer.rt2.render_target.clone()),
-                    Some(self.gbuffer.rt3.render_target.clone()),
-                ]),
-                &self.gbuffer.depth.view,
-            );
+            if let Some(msaa) = &self.gbuffer.msaa {
+                self.dcs.context().OMSetRenderTargets(
+                    Some(&[
+                        Some(msaa.rt0.render_target.clone()),
+                        Some(msaa.rt1.render_target.clone()),
+                        Some(msaa.rt2.render_target.clone()),
+                        Some(msaa.rt3.render_target.clone()),
+                    ]),
+                    &msaa.depth.view,
+                );
+            } else {
+                self.bind_gbuffer_targets();
+            }
             self.dcs
                 .context()
-                .OMSetDepthStencilState(&self.gbuffer.depth.state, 0);
+                .OMSetDepthStencilState(self.gbuffer.depth.state(reverse_z), 0);
             self.dcs.context().OMSetBlendState(
                 &self.blend_state_none,
                 Some(&[1f32, 1., 1., 1.] as _),
@@ -450,11 +749,17 @@ impl Renderer {
 
         let shader_overrides = resources.get::<EnabledShaderOverrides>().unwrap();
 
+        // Destiny's deferred shading can't be blended per entity, so overridden entities stay opaque there
+        let blend_deferred_overrides =
+            render_settings.alpha_blending && !render_settings.use_global_deferred_shading;
+
         // region: Deferred
         let draw_queue = self.draw_queue.read();
         for i in 0..draw_queue.len() {
-            if draw_queue[i].0.shading_mode() != ShadingMode::Deferred
+            if !render_settings.stage_generate_gbuffer
+                || draw_queue[i].0.shading_mode() != ShadingMode::Deferred
                 || draw_queue[i].0.geometry_type() == GeometryType::StaticDecal
+                || (blend_deferred_overrides && draw_queue[i].1.opacity.is_some())
             {
                 continue;
             }
@@ -470,11 +775,32 @@ impl Renderer {
         }
         // endregion
 
+        // region: MSAA resolve
+        // Everything after the geometry pass (decals, shading, picking) works on the resolved single-sampled targets
+        if let Some(msaa) = &self.gbuffer.msaa {
+            self.msaa_resolver
+                .resolve(&self.dcs, msaa, &self.gbuffer, reverse_z);
+
+            unsafe {
+                self.bind_gbuffer_targets();
+                self.dcs
+                    .context()
+                    .OMSetDepthStencilState(self.gbuffer.depth.state(reverse_z), 0);
+            }
+        }
+        // endregion
+
+        // Decals only need the depth and normals of the geometry, the rest is dropped so the compositor only sees what they write
+        if render_settings.stage_generate_gbuffer && !render_settings.stage_gbuffer_color {
+            self.clear_gbuffer_color();
+        }
+
         // region: Deferred (decals)
         self.gbuffer.rt1.copy_to(&self.gbuffer.rt1_clone);
         let draw_queue = self.draw_queue.read();
         for i in 0..draw_queue.len() {
-            if draw_queue[i].0.shading_mode() != ShadingMode::Deferred
+            if !render_settings.stage_decals
+                || draw_queue[i].0.shading_mode() != ShadingMode::Deferred
                 || draw_queue[i].0.geometry_type() != GeometryType::StaticDecal
             {
                 continue;
@@ -505,12 +831,25 @@ impl Renderer {
             render_settings.draw_lights,
             render_settings.use_global_deferred_shading,
             render_settings.compositor_mode,
+            None,
         );
 
+        if blend_deferred_overrides
+            && render_settings.stage_generate_gbuffer
+            && render_settings.stage_gbuffer_color
+        {
+            self.run_deferred_opacity_overrides(
+                resources,
+                &render_settings,
+                &shader_overrides,
+                reverse_z,
+            );
+        }
+
         unsafe {
             self.dcs
                 .context()
-                .OMSetDepthStencilState(&self.gbuffer.depth.state, 0);
+                .OMSetDepthStencilState(self.gbuffer.depth.state(reverse_z), 0);
 
             self.dcs.context().OMSetRenderTargets(
                 Some(&[Some(
@@ -522,6 +861,27 @@ impl Renderer {
 
         self.gbuffer.staging.copy_to(&self.gbuffer.staging_clone);
 
+        let reference_meshes = self.reference_meshes(resources);
+
+        // region: Reference meshes
+        if !reference_meshes.is_empty() {
+            let camera = resources.get::<FpsCamera>().unwrap();
+            unsafe {
+                self.dcs.context().RSSetState(&self.rasterizer_state_nocull);
+            }
+            for (_, transform, color, mesh) in &reference_meshes {
+                self.reference_mesh_renderer.draw(
+                    &self.dcs,
+                    mesh,
+                    *transform,
+                    *color,
+                    camera.projection_view_matrix,
+                    camera.view_matrix,
+                );
+            }
+        }
+        // endregion
+
         // region: Errors
         if render_settings.draw_errors {
             let camera = resources.get::<FpsCamera>().unwrap();
@@ -547,6 +907,16 @@ impl Renderer {
                 continue;
             }
 
+            // Forward decals are the additive ones
+            let stage_enabled = if draw_queue[i].0.geometry_type() == GeometryType::StaticDecal {
+                render_settings.stage_decals_additive
+            } else {
+                render_settings.stage_transparents
+            };
+            if !stage_enabled {
+                continue;
+            }
+
             self.render_data
                 .data()
                 .blend_texture
@@ -632,9 +1002,10 @@ impl Renderer {
                         && s.transparency() != Transparency::Cutout
                     {
                         unsafe {
-                            self.dcs
-                                .context()
-                                .OMSetDepthStencilState(&self.gbuffer.depth.state_readonly, 0);
+                            self.dcs.context().OMSetDepthStencilState(
+                                self.gbuffer.depth.state_readonly(reverse_z),
+                                0,
+                            );
                         }
                     }
 
@@ -651,6 +1022,21 @@ impl Renderer {
                                 0xffffffff,
                             ),
                             3 => continue,
+                            4 => self.dcs.context().OMSetBlendState(
+                                &self.blend_state_multiply,
+                                Some(&[1f32, 1., 1., 1.] as _),
+                                0xffffffff,
+                            ),
+                            5 => self.dcs.context().OMSetBlendState(
+                                &self.blend_state_screen,
+                                Some(&[1f32, 1., 1., 1.] as _),
+                                0xffffffff,
+                            ),
+                            6 => self.dcs.context().OMSetBlendState(
+                                &self.blend_state_premultiplied,
+                                Some(&[1f32, 1., 1., 1.] as _),
+                                0xffffffff,
+                            ),
                             _ => match s.transparency() {
                                 Transparency::Blend => self.dcs.context().OMSetBlendState(
                                     &self.blend_state_blend,
@@ -672,6 +1058,19 @@ impl Renderer {
                 transparency_mode = s.transparency();
             }
 
+            if let Some(opacity) = d.opacity.filter(|_| render_settings.alpha_blending) {
+                unsafe {
+                    self.dcs.context().OMSetBlendState(
+                        &self.blend_state_opacity,
+                        Some(&[opacity; 4] as _),
+                        0xffffffff,
+                    );
+                }
+
+                // Make sure the next drawcall sets its own blend state again
+                transparency_mode = Transparency::None;
+            }
+
             self.draw(
                 s,
                 &d,
@@ -682,6 +1081,14 @@ impl Renderer {
         }
         // endregion
 
+        if render_settings.dof_enabled {
+            self.run_dof(&render_settings, &resources.get::<FpsCamera>().unwrap());
+        }
+
+        if render_settings.bloom_enabled {
+            self.run_bloom(&render_settings);
+        }
+
         self.run_final();
 
         // Render debug elements after final to prevent color space weirdness
@@ -700,7 +1107,7 @@ impl Renderer {
 
                 self.dcs
                     .context()
-                    .OMSetDepthStencilState(&self.gbuffer.depth.state_readonly, 0);
+                    .OMSetDepthStencilState(self.gbuffer.depth.state_readonly(reverse_z), 0);
 
                 self.dcs.context().OMSetBlendState(
                     &self.blend_state_blend,
@@ -709,12 +1116,36 @@ impl Renderer {
                 );
                 self.dcs.context().RSSetState(&self.rasterizer_state);
             }
+
+            if render_settings.grid_enabled {
+                let camera = resources.get::<FpsCamera>().unwrap();
+                unsafe {
+                    self.dcs.context().RSSetState(&self.rasterizer_state_nocull);
+                }
+                self.grid_renderer.draw(
+                    &self.dcs,
+                    &GridOptions::new(
+                        Vec3::new(0.0, 0.0, render_settings.grid_height),
+                        render_settings.grid_spacing,
+                        render_settings.grid_major_every,
+                        render_settings.grid_fade_distance,
+                        render_settings
+                            .grid_follow_camera
+                            .then_some(camera.position),
+                    ),
+                );
+                unsafe {
+                    self.dcs.context().RSSetState(&self.rasterizer_state);
+                }
+            }
+
             self.debug_shape_renderer
                 .draw_all(&mut shapes, DrawMode::Normal);
         }
 
         // region: Outline rendering
-        if let SelectedEntity(Some(selected_entity), _) = &(*resources.get().unwrap()) {
+        let selection = resources.get::<SelectedEntity>().unwrap().clone();
+        if let Some(selected_entity) = selection.0 {
             unsafe {
                 self.dcs.context().OMSetBlendState(
                     &self.blend_state_none,
@@ -723,7 +1154,7 @@ impl Renderer {
                 );
                 self.dcs
                     .context()
-                    .OMSetDepthStencilState(&self.gbuffer.outline_depth.state, 0);
+                    .OMSetDepthStencilState(self.gbuffer.outline_depth.state(reverse_z), 0);
 
                 self.dcs.context().OMSetRenderTargets(
                     Some(&[None, None, None]),
@@ -733,14 +1164,14 @@ impl Renderer {
                 self.dcs.context().ClearDepthStencilView(
                     &self.gbuffer.outline_depth.view,
                     D3D11_CLEAR_DEPTH.0 as _,
-                    0.0,
+                    if reverse_z { 0.0 } else { 1.0 },
                     0,
                 );
             }
 
-            // Render the selected object to the depth buffer
+            // Render the selected objects to the depth buffer
             for i in 0..draw_queue.len() {
-                if draw_queue[i].1.entity != *selected_entity {
+                if !selection.is_selected(draw_queue[i].1.entity) {
                     continue;
                 }
                 let (s, d) = draw_queue[i].clone();
@@ -755,8 +1186,27 @@ impl Renderer {
                 self.draw(s, &d, &shader_overrides, draw_mode, false);
             }
 
+            for (e, transform, color, mesh) in &reference_meshes {
+                if !selection.is_selected(*e) {
+                    continue;
+                }
+                let camera = resources.get::<FpsCamera>().unwrap();
+
+                unsafe {
+                    self.dcs.context().PSSetShader(&self.null_ps, None);
+                }
+                self.reference_mesh_renderer.draw_nopshader(
+                    &self.dcs,
+                    mesh,
+                    *transform,
+                    *color,
+                    camera.projection_view_matrix,
+                    camera.view_matrix,
+                );
+            }
+
             for (t, e) in self.fiddlesticks.read().iter() {
-                if Some(*selected_entity) != *e {
+                if !e.map_or(false, |e| selection.is_selected(e)) {
                     continue;
                 }
                 let camera = resources.get::<FpsCamera>().unwrap();
@@ -799,18 +1249,29 @@ impl Renderer {
                 );
             }
 
-            self.outline_renderer.draw(&self.dcs);
+            let outline_color = resources
+                .get::<TagPalette>()
+                .and_then(|palette| {
+                    let maps = resources.get::<MapDataList>()?;
+                    let (_, _, map) = maps.current_map()?;
+                    let tags = map.scene.get::<&Tags>(selected_entity).ok()?;
+                    palette.color_for(&tags)
+                })
+                .map_or(OUTLINE_COLOR_DEFAULT, |c| {
+                    Vec4::from(c.to_array().map(|v| v as f32 / 255.0))
+                });
+
+            self.outline_renderer.draw(&self.dcs, outline_color);
         }
 
         // endregion
 
         // region: Pickbuffer
 
-        // Skip the entity that's already selected
-        let skip_entity = resources
-            .get::<SelectedEntity>()
-            .unwrap()
+        // Skip the entity that's already selected, unless there are multiple so Ctrl+click can deselect it
+        let skip_entity = selection
             .0
+            .filter(|_| !selection.is_multiple())
             .unwrap_or(Entity::DANGLING);
 
         // Render the outline to the screen in conjunction with the scene depth buffer to test occlusion
@@ -843,7 +1304,7 @@ impl Renderer {
             );
             self.dcs
                 .context()
-                .OMSetDepthStencilState(&self.gbuffer.depth.state_readonly, 0);
+                .OMSetDepthStencilState(self.gbuffer.depth.state_readonly(reverse_z), 0);
         }
 
         let b = ConstantBuffer::<PickbufferScope>::create(self.dcs.clone(), None).unwrap();
@@ -869,7 +1330,7 @@ impl Renderer {
 
                 self.dcs
                     .context()
-                    .OMSetDepthStencilState(&self.gbuffer.depth.state_readonly, 0);
+                    .OMSetDepthStencilState(self.gbuffer.depth.state_readonly(reverse_z), 0);
 
                 self.dcs.context().OMSetBlendState(
                     &self.blend_state_none,
@@ -910,6 +1371,28 @@ impl Renderer {
             }
         }
 
+        for (e, transform, color, mesh) in &reference_meshes {
+            if *e == skip_entity {
+                continue;
+            }
+            let camera = resources.get::<FpsCamera>().unwrap();
+
+            b.write(&PickbufferScope::from_entity(*e)).ok();
+
+            unsafe {
+                self.dcs.context().RSSetState(&self.rasterizer_state_nocull);
+                self.dcs.context().PSSetShader(&self.pickbuffer_ps, None);
+            }
+            self.reference_mesh_renderer.draw_nopshader(
+                &self.dcs,
+                mesh,
+                *transform,
+                *color,
+                camera.projection_view_matrix,
+                camera.view_matrix,
+            );
+        }
+
         self.gbuffer
             .pick_buffer
             .copy_to_staging(&self.gbuffer.pick_buffer_staging);
@@ -979,10 +1462,18 @@ impl Renderer {
                         | DrawMode::DepthOnlyIgnoreTransparent
                         | DrawMode::PickBuffer
                 ) {
+                    let decal_states = self.decal_rasterizer_states.read();
+                    let (rs_cull, rs_nocull) = match decal_states.as_ref() {
+                        Some(s) if sort.geometry_type() == GeometryType::StaticDecal => {
+                            (&s.cull, &s.nocull)
+                        }
+                        _ => (&self.rasterizer_state, &self.rasterizer_state_nocull),
+                    };
+
                     if mat.unkc != 0 {
-                        self.dcs.context().RSSetState(&self.rasterizer_state_nocull);
+                        self.dcs.context().RSSetState(rs_nocull);
                     } else {
-                        self.dcs.context().RSSetState(&self.rasterizer_state);
+                        self.dcs.context().RSSetState(rs_cull);
                     }
                 }
             }
@@ -1048,6 +1539,8 @@ impl Renderer {
                     );
                 }
             },
+            // Water is drawn with its own materials, the shader overrides don't apply to it
+            GeometryType::Water => {}
         }
 
         if matches!(
@@ -1165,6 +1658,30 @@ impl Renderer {
             }
         }
 
+        let stage = match mode {
+            DrawMode::DepthOnly => Some(TfxRenderStage::ShadowGenerate),
+            // Editor-only passes (outlines, picking)
+            DrawMode::DepthOnlyIgnoreTransparent | DrawMode::PickBuffer => None,
+            DrawMode::Normal => Some(match (sort.geometry_type(), sort.shading_mode()) {
+                (GeometryType::StaticDecal, _) => TfxRenderStage::Decals,
+                (_, ShadingMode::Deferred) => TfxRenderStage::GenerateGbuffer,
+                (_, ShadingMode::Forward) => TfxRenderStage::Transparents,
+            }),
+        };
+        if let Some(stage) = stage {
+            let indices = drawcall.index_count as usize;
+            let triangles = if drawcall.primitive_type == D3D_PRIMITIVE_TOPOLOGY_TRIANGLESTRIP {
+                indices.saturating_sub(2)
+            } else {
+                indices / 3
+            };
+
+            self.stats.write().count_draw(
+                stage,
+                triangles * drawcall.instance_count.unwrap_or(1) as usize,
+            );
+        }
+
         if let Some(mat) = render_data.techniques.get(&sort.material().into()) {
             mat.unbind_textures(&self.dcs)
         }
@@ -1177,12 +1694,83 @@ impl Renderer {
     }
 
     /// Swaps to primary swapchain render target, binds gbuffers and runs the shading passes
+    /// Draws the deferred drawcalls of entities with an opacity override on top of the shaded scene, one pass per opacity.
+    /// Each pass shades the gbuffer again, and only the pixels whose depth was changed by the overridden entities are blended
+    /// over the output of the previous pass. Decals aren't projected onto these entities
+    fn run_deferred_opacity_overrides(
+        &self,
+        resources: &Resources,
+        render_settings: &RenderSettings,
+        shader_overrides: &EnabledShaderOverrides,
+        reverse_z: bool,
+    ) {
+        let draw_queue = self.draw_queue.read();
+        let is_overridden_deferred = |s: &SortValue3d| {
+            s.shading_mode() == ShadingMode::Deferred
+                && s.geometry_type() != GeometryType::StaticDecal
+        };
+
+        let mut opacities = draw_queue
+            .iter()
+            .filter(|(s, _)| is_overridden_deferred(s))
+            .filter_map(|(_, d)| d.opacity)
+            .collect_vec();
+        opacities.sort_by(f32::total_cmp);
+        opacities.dedup();
+
+        for opacity in opacities {
+            // The shading pass compares against this copy to find the pixels drawn by this group
+            self.gbuffer.depth.copy_depth(self.dcs.context());
+
+            unsafe {
+                self.dcs.context().RSSetState(&self.shadow_rs);
+                self.bind_gbuffer_targets();
+                self.dcs
+                    .context()
+                    .OMSetDepthStencilState(self.gbuffer.depth.state(reverse_z), 0);
+                self.dcs.context().OMSetBlendState(
+                    &self.blend_state_none,
+                    Some(&[1f32, 1., 1., 1.] as _),
+                    0xffffffff,
+                );
+            }
+
+            for (s, d) in draw_queue.iter() {
+                if !is_overridden_deferred(s) || d.opacity != Some(opacity) {
+                    continue;
+                }
+
+                self.draw(
+                    s.clone(),
+                    d,
+                    shader_overrides,
+                    DrawMode::Normal,
+                    render_settings.evaluate_bytecode,
+                );
+            }
+
+            self.run_deferred_shading(
+                resources,
+                render_settings.draw_lights,
+                false,
+                render_settings.compositor_mode,
+                Some(opacity),
+            );
+        }
+
+        // Passes after this one expect the copy to match the final depth
+        self.gbuffer.depth.copy_depth(self.dcs.context());
+    }
+
+    /// Shades the gbuffer into the staging target. With `blend_opacity` set, the pixels whose depth differs from the depth copy
+    /// are blended over the current contents of the staging target instead, see [`Self::run_deferred_opacity_overrides`]
     fn run_deferred_shading(
         &self,
         resources: &Resources,
         draw_lights: bool,
         use_global_deferred_shading: bool,
         compositor_mode: usize,
+        blend_opacity: Option<f32>,
     ) {
         let maps = resources.get::<MapDataList>().unwrap();
 
@@ -1211,11 +1799,22 @@ impl Renderer {
             }
 
             if let Some((_, _, map)) = maps.current_map() {
-                for (_, (transform, light, bounds)) in map
+                let group_filter = resources.get::<ActivityGroupFilter>().unwrap();
+                for (_, (transform, light, bounds, color, group)) in map
                     .scene
-                    .query::<(&Transform, &SLight, Option<&AABB>)>()
+                    .query::<(
+                        &Transform,
+                        &SLight,
+                        Option<&AABB>,
+                        Option<&LightColor>,
+                        Option<&ActivityGroup>,
+                    )>()
                     .iter()
                 {
+                    if !group_filter.is_visible(group) {
+                        continue;
+                    }
+
                     if let Some(bb) = bounds {
                         *self.light_mat.write() = Mat4::from_scale(-(bb.extents() * 4.0));
                     } else {
@@ -1223,23 +1822,46 @@ impl Renderer {
                     }
 
                     *self.light_transform.write() = *transform;
-                    self.light_renderer.draw_normal(self, light);
+                    self.light_renderer.draw_normal(
+                        self,
+                        light,
+                        color.copied().unwrap_or_default(),
+                    );
                 }
 
-                for (_, (transform, light)) in
-                    map.scene.query::<(&Transform, &SShadowingLight)>().iter()
+                for (_, (transform, light, color, group)) in map
+                    .scene
+                    .query::<(
+                        &Transform,
+                        &SShadowingLight,
+                        Option<&LightColor>,
+                        Option<&ActivityGroup>,
+                    )>()
+                    .iter()
                 {
+                    if !group_filter.is_visible(group) {
+                        continue;
+                    }
+
                     // *self.light_mat.write() = light.unk64.into();
                     *self.light_mat.write() = Mat4::from_scale(Vec3::splat(-(3000.0 * 2.0)));
                     *self.light_transform.write() = *transform;
-                    self.light_renderer.draw_shadowing(self, light);
+                    self.light_renderer.draw_shadowing(
+                        self,
+                        light,
+                        color.copied().unwrap_or_default(),
+                    );
                 }
             }
         }
 
         unsafe {
             self.dcs.context().OMSetBlendState(
-                &self.blend_state_none,
+                if blend_opacity.is_some() {
+                    &self.blend_state_blend
+                } else {
+                    &self.blend_state_none
+                },
                 Some(&[1f32, 1., 1., 1.] as _),
                 0xffffffff,
             );
@@ -1248,10 +1870,12 @@ impl Renderer {
                 Some(&[Some(self.gbuffer.staging.render_target.clone())]),
                 None,
             );
-            self.dcs.context().ClearRenderTargetView(
-                &self.gbuffer.staging.render_target,
-                [0.0, 0.0, 0.0, 0.0].as_ptr() as _,
-            );
+            if blend_opacity.is_none() {
+                self.dcs.context().ClearRenderTargetView(
+                    &self.gbuffer.staging.render_target,
+                    [0.0, 0.0, 0.0, 0.0].as_ptr() as _,
+                );
+            }
 
             self.dcs.context().RSSetViewports(Some(&[D3D11_VIEWPORT {
                 TopLeftX: 0.0,
@@ -1267,6 +1891,14 @@ impl Renderer {
             let camera = resources.get::<FpsCamera>().unwrap();
             let render_settings = resources.get::<RenderSettings>().unwrap();
             *self.light_mul.write() = render_settings.light_mul;
+            let (sun_dir, sun_color) =
+                render_settings.sun_light(&resources.get::<MapDataList>().unwrap());
+
+            let mode_b = if render_settings.split_compare {
+                render_settings.compositor_mode_b
+            } else {
+                compositor_mode
+            };
 
             let compositor_options = CompositorOptions {
                 viewport_proj_view_matrix_inv: *self.camera_svp_inv.read(),
@@ -1277,17 +1909,39 @@ impl Renderer {
                 viewport_size: UVec2::from(self.window_size).as_vec2(),
                 camera_pos: camera.position.extend(1.0),
                 camera_dir: camera.front.extend(1.0),
-                time: self.start_time.elapsed().as_secs_f32(),
+                time: *self.game_time.read(),
                 mode: compositor_mode as u32,
+                mode_b: mode_b as u32,
+                clear_color: COMPOSITOR_MODES[compositor_mode].clear_color(),
+                clear_color_b: COMPOSITOR_MODES[mode_b].clear_color(),
+                split_position: render_settings.split_position,
+                background: render_settings.background as u32,
+                background_color: render_settings.background_color,
+                background_color_top: render_settings.background_color_top,
+                clear_depth: camera.depth_clear_value(),
+                fog_enabled: render_settings.fog_enabled.into(),
+                fog_start: render_settings.fog_start,
+                fog_density: render_settings.fog_density,
+                fog_color: render_settings
+                    .fog_color
+                    .unwrap_or(render_settings.background_color),
                 draw_lights: draw_lights.into(),
-                global_light_dir: render_settings.light_dir.extend(1.0),
-                global_light_color: render_settings.light_color,
+                global_light_dir: sun_dir.extend(1.0),
+                global_light_color: sun_color,
                 specular_scale: if render_settings.use_specular_map {
                     1.0
                 } else {
                     0.0
                 },
-                fxaa_enabled: if render_settings.fxaa { 1 } else { 0 },
+                fxaa_quality: render_settings.anti_aliasing.fxaa_quality(),
+                tonemap: render_settings.tonemap as u32,
+                exposure: resources
+                    .get::<ScopeOverrides>()
+                    .unwrap()
+                    .frame
+                    .exposure_illum_relative,
+                opacity_pass: blend_opacity.is_some().into(),
+                opacity: blend_opacity.unwrap_or(1.0),
             };
             self.scope_alk_composite.write(&compositor_options).unwrap();
         }
@@ -1353,11 +2007,14 @@ impl Renderer {
                 self.dcs
                     .context()
                     .PSSetShaderResources(9, Some(&[cubemap_texture]));
+                self.dcs.context().PSSetShaderResources(
+                    14,
+                    Some(&[blend_opacity.map(|_| self.gbuffer.depth.texture_copy_view.clone())]),
+                );
 
                 self.scope_alk_composite.bind(0, TfxShaderStage::Vertex);
                 self.scope_alk_composite.bind(0, TfxShaderStage::Pixel);
-                self.scope_alk_cascade_transforms
-                    .bind(3, TfxShaderStage::Pixel);
+                self.scope_alk_cascades.bind(3, TfxShaderStage::Pixel);
 
                 self.dcs.context().VSSetShader(&self.composite_vs, None);
                 self.dcs.context().PSSetShader(&self.composite_ps, None);
@@ -1373,6 +2030,128 @@ impl Renderer {
         }
     }
 
+    /// Blurs the staging target based on the distance to the focus plane
+    fn run_dof(&self, render_settings: &RenderSettings, camera: &FpsCamera) {
+        self.gbuffer.staging.copy_to(&self.gbuffer.staging_clone);
+
+        unsafe {
+            self.dcs.context().OMSetBlendState(
+                &self.blend_state_none,
+                Some(&[1f32, 1., 1., 1.] as _),
+                0xffffffff,
+            );
+
+            self.dcs.context().OMSetRenderTargets(
+                Some(&[Some(self.gbuffer.staging.render_target.clone())]),
+                None,
+            );
+            self.dcs.context().PSSetShaderResources(
+                0,
+                Some(&[
+                    Some(self.gbuffer.staging_clone.view.clone()),
+                    Some(self.gbuffer.depth.texture_copy_view.clone()),
+                ]),
+            );
+        }
+
+        self.dof_renderer.draw(
+            &self.dcs,
+            &DofOptions::new(
+                render_settings.dof_focus_distance,
+                render_settings.dof_strength,
+                Self::DOF_MAX_RADIUS,
+                &camera.projection_matrix,
+            ),
+        );
+    }
+
+    /// Maximum depth of field blur radius, in pixels
+    const DOF_MAX_RADIUS: f32 = 16.0;
+
+    /// Extracts the bright parts of the staging target, blurs them at half resolution and adds them back
+    fn run_bloom(&self, render_settings: &RenderSettings) {
+        if let Err(e) = self.bloom_renderer.write_options(&BloomOptions::new(
+            render_settings.bloom_threshold,
+            render_settings.bloom_intensity,
+        )) {
+            error!("Failed to write bloom options: {e}");
+            return;
+        }
+
+        let bloom_size = GBuffer::bloom_size(self.window_size);
+        let passes = [
+            (
+                BloomPass::Extract,
+                &self.gbuffer.staging,
+                &self.gbuffer.bloom_a,
+            ),
+            (
+                BloomPass::BlurHorizontal,
+                &self.gbuffer.bloom_a,
+                &self.gbuffer.bloom_b,
+            ),
+            (
+                BloomPass::BlurVertical,
+                &self.gbuffer.bloom_b,
+                &self.gbuffer.bloom_a,
+            ),
+        ];
+
+        unsafe {
+            self.dcs.context().OMSetBlendState(
+                &self.blend_state_none,
+                Some(&[1f32, 1., 1., 1.] as _),
+                0xffffffff,
+            );
+            self.dcs.context().RSSetViewports(Some(&[D3D11_VIEWPORT {
+                TopLeftX: 0.0,
+                TopLeftY: 0.0,
+                Width: bloom_size.0 as f32,
+                Height: bloom_size.1 as f32,
+                MinDepth: 0.0,
+                MaxDepth: 1.0,
+            }]));
+        }
+
+        for (pass, source, target) in passes {
+            unsafe {
+                self.dcs
+                    .context()
+                    .OMSetRenderTargets(Some(&[Some(target.render_target.clone())]), None);
+                self.dcs
+                    .context()
+                    .PSSetShaderResources(0, Some(&[Some(source.view.clone())]));
+            }
+
+            self.bloom_renderer.draw(&self.dcs, pass);
+        }
+
+        unsafe {
+            self.dcs.context().OMSetBlendState(
+                &self.blend_state_additive,
+                Some(&[1f32, 1., 1., 1.] as _),
+                0xffffffff,
+            );
+            self.dcs.context().RSSetViewports(Some(&[D3D11_VIEWPORT {
+                TopLeftX: 0.0,
+                TopLeftY: 0.0,
+                Width: self.window_size.0 as f32,
+                Height: self.window_size.1 as f32,
+                MinDepth: 0.0,
+                MaxDepth: 1.0,
+            }]));
+            self.dcs.context().OMSetRenderTargets(
+                Some(&[Some(self.gbuffer.staging.render_target.clone())]),
+                None,
+            );
+            self.dcs
+                .context()
+                .PSSetShaderResources(0, Some(&[Some(self.gbuffer.bloom_a.view.clone())]));
+        }
+
+        self.bloom_renderer.draw(&self.dcs, BloomPass::Composite);
+    }
+
     fn run_final(&self) {
         unsafe {
             self.scope_alk_composite.bind(0, TfxShaderStage::Vertex);
@@ -1420,62 +2199,105 @@ impl Renderer {
         }
     }
 
+    /// Smallest distance the first cascade starts at, very small near planes make for a degenerate first cascade
     const CAMERA_CASCADE_CLIP_NEAR: f32 = 0.1;
+    /// Maximum shadow distance, also used when the camera has an infinite far plane
     const CAMERA_CASCADE_CLIP_FAR: f32 = 4000.0;
-    const CAMERA_CASCADE_LEVELS: &'static [f32] = &[
-        Self::CAMERA_CASCADE_CLIP_FAR / 50.0,
-        Self::CAMERA_CASCADE_CLIP_FAR / 25.0,
-        Self::CAMERA_CASCADE_CLIP_FAR / 10.0,
-        Self::CAMERA_CASCADE_CLIP_FAR / 1.0,
-    ];
-    const CAMERA_CASCADE_LEVEL_COUNT: usize = Self::CAMERA_CASCADE_LEVELS.len();
-
-    fn update_directional_cascades(&self, resources: &Resources) {
-        let camera = resources.get::<FpsCamera>().unwrap();
+    /// Blend between logarithmic (1.0) and uniform (0.0) cascade splits
+    const CAMERA_CASCADE_SPLIT_LAMBDA: f32 = 0.9;
+    /// Maximum number of cascades, the shadow map array always has this many layers
+    pub const CAMERA_CASCADE_LEVEL_COUNT: usize = 4;
+
+    /// Far distance of each cascade, using the practical split scheme
+    fn cascade_splits(
+        near: f32,
+        far: f32,
+        count: usize,
+    ) -> [f32; Self::CAMERA_CASCADE_LEVEL_COUNT] {
+        let mut splits = [far; Self::CAMERA_CASCADE_LEVEL_COUNT];
+        for (i, split) in splits.iter_mut().enumerate().take(count) {
+            let p = (i + 1) as f32 / count as f32;
+            let log = near * (far / near).powf(p);
+            let uniform = near + (far - near) * p;
+            *split = Self::CAMERA_CASCADE_SPLIT_LAMBDA * log
+                + (1.0 - Self::CAMERA_CASCADE_SPLIT_LAMBDA) * uniform;
+        }
 
-        let light_dir = resources.get::<RenderSettings>().unwrap().light_dir;
+        splits
+    }
 
-        let mut cascade_matrices = [Mat4::IDENTITY; Self::CAMERA_CASCADE_LEVEL_COUNT];
+    /// Updates the shadow cascades for the current camera. Called before recording the frame, so geometry
+    /// can be culled against the cascades with [`Renderer::is_shadow_caster_visible`]
+    pub fn update_directional_cascades(&self, resources: &Resources) {
+        let camera = resources.get::<FpsCamera>().unwrap();
+        let render_settings = resources.get::<RenderSettings>().unwrap();
+        let (sun_dir, _) = render_settings.sun_light(&resources.get::<MapDataList>().unwrap());
+
+        let cascade_count = render_settings
+            .shadow_cascades
+            .clamp(1, Self::CAMERA_CASCADE_LEVEL_COUNT);
+        let near = camera.z_near.max(Self::CAMERA_CASCADE_CLIP_NEAR);
+        let far = camera
+            .z_far
+            .map_or(Self::CAMERA_CASCADE_CLIP_FAR, |f| {
+                f.min(Self::CAMERA_CASCADE_CLIP_FAR)
+            })
+            .max(near * 2.0);
+        let splits = Self::cascade_splits(near, far, cascade_count);
 
+        let mut cascade_matrices = [Mat4::IDENTITY; Self::CAMERA_CASCADE_LEVEL_COUNT];
         // clippy: Annoying lint, code is harder to read with the lint's suggested method
         #[allow(clippy::needless_range_loop)]
-        for i in 0..Self::CAMERA_CASCADE_LEVEL_COUNT {
-            let (z_start, z_end) = if i == 0 {
-                (
-                    Self::CAMERA_CASCADE_CLIP_NEAR,
-                    Self::CAMERA_CASCADE_LEVELS[i],
-                )
-            } else if i < Self::CAMERA_CASCADE_LEVEL_COUNT {
-                (
-                    Self::CAMERA_CASCADE_LEVELS[i - 1],
-                    Self::CAMERA_CASCADE_LEVELS[i],
-                )
-            } else {
-                (
-                    Self::CAMERA_CASCADE_LEVELS[i - 1],
-                    Self::CAMERA_CASCADE_CLIP_FAR,
-                )
-            };
+        for i in 0..cascade_count {
+            let z_start = if i == 0 { near } else { splits[i - 1] };
 
-            let light_matrix = camera.build_cascade(
-                light_dir,
+            cascade_matrices[i] = camera.build_cascade(
+                sun_dir,
                 camera.view_matrix,
                 z_start,
-                z_end,
+                splits[i],
                 self.window_size.0 as f32 / self.window_size.1 as f32,
             );
-
-            cascade_matrices[i] = light_matrix;
         }
-        self.scope_alk_cascade_transforms
-            .write(&cascade_matrices)
+
+        self.scope_alk_cascades
+            .write(&CascadeOptions {
+                matrices: cascade_matrices,
+                splits: Vec4::from_array(splits),
+                count: cascade_count as u32,
+                debug: render_settings.shadow_cascade_debug as u32,
+                _pad: [0; 2],
+            })
             .unwrap();
         *self.light_cascade_transforms.write() = cascade_matrices;
+        *self.cascade_count.write() = cascade_count;
+
+        let renders_shadows = render_settings.draw_lights
+            && render_settings.render_shadows
+            && !render_settings.use_global_deferred_shading;
+        *self.shadow_caster_planes.write() = if renders_shadows {
+            cascade_matrices[..cascade_count]
+                .iter()
+                .map(|m| {
+                    // The near and far planes are left out, casters between the light and the cascade still cast shadows into it
+                    let (r0, r1, r3) = (m.row(0), m.row(1), m.row(3));
+                    [r3 + r0, r3 - r0, r3 + r1, r3 - r1].map(|p| p / p.truncate().length())
+                })
+                .collect()
+        } else {
+            vec![]
+        };
     }
 
-    fn render_cascade_depthmaps(&self, resources: &Resources) {
-        self.update_directional_cascades(resources);
+    /// Returns true if the box is inside of any of the shadow cascades rendered this frame
+    pub fn is_shadow_caster_visible(&self, bb: &AABB) -> bool {
+        self.shadow_caster_planes
+            .read()
+            .iter()
+            .any(|planes| camera::is_aabb_inside_planes(planes, bb))
+    }
 
+    fn render_cascade_depthmaps(&self, resources: &Resources) {
         let shader_overrides = resources.get::<EnabledShaderOverrides>().unwrap();
         let render_settings = resources.get::<RenderSettings>().unwrap();
 
@@ -1513,7 +2335,7 @@ impl Renderer {
         }
 
         let scope_view_base = self.scope_view_backup.read();
-        for cascade_level in 0..Self::CAMERA_CASCADE_LEVEL_COUNT {
+        for cascade_level in 0..*self.cascade_count.read() {
             unsafe {
                 self.dcs
                     .context()
@@ -1556,11 +2378,13 @@ impl Renderer {
     fn update_buffers(&self, resources: &Resources) -> anyhow::Result<()> {
         let camera = resources.get::<FpsCamera>().unwrap();
         let overrides = resources.get::<ScopeOverrides>().unwrap();
+        let time = resources.get::<TimeControl>().unwrap();
+        *self.game_time.write() = time.time;
 
         self.scope_frame.write(&ScopeFrame {
-            game_time: self.start_time.elapsed().as_secs_f32(),
-            render_time: self.start_time.elapsed().as_secs_f32(),
-            delta_game_time: *self.delta_time.read(),
+            game_time: time.time,
+            render_time: time.time,
+            delta_game_time: time.delta,
             // exposure_time: 0.0,
 
             // exposure_scale: 1.0,
@@ -1645,11 +2469,141 @@ impl Renderer {
 
     pub fn resize(&mut self, new_size: (u32, u32)) -> anyhow::Result<()> {
         self.window_size = new_size;
-        self.gbuffer.resize(new_size)
+        self.gbuffer.resize(new_size, self.gbuffer.sample_count)
+    }
+
+    /// Recompiles the shader overrides, the current ones are only replaced if all of them compile
+    pub fn reload_shader_overrides(&mut self) -> anyhow::Result<()> {
+        self.shader_overrides = ShaderOverrides::load(&self.dcs)?;
+        Ok(())
+    }
+
+    /// Reads back the current contents of the swapchain as RGBA8
+    pub fn capture_swapchain(&self) -> anyhow::Result<(Vec<u8>, (u32, u32))> {
+        let size = self.window_size;
+        let staging = CpuStagingBuffer::create(
+            size,
+            DxgiFormat::B8G8R8A8_UNORM_SRGB,
+            self.dcs.clone(),
+            "Swapchain_Capture",
+        )?;
+
+        unsafe {
+            let backbuffer: ID3D11Texture2D = self.dcs.swap_chain.GetBuffer(0)?;
+            self.dcs
+                .context()
+                .CopyResource(&staging.texture, &backbuffer);
+        }
+
+        let m = staging.map(D3D11_MAP_READ)?;
+        let mut data = Vec::with_capacity(size.0 as usize * size.1 as usize * 4);
+        for y in 0..size.1 as usize {
+            let row = unsafe {
+                std::slice::from_raw_parts(m.ptr.add(y * m.row_pitch as usize), size.0 as usize * 4)
+            };
+
+            // BGRA -> RGBA
+            for bgra in row.chunks_exact(4) {
+                data.extend_from_slice(&[bgra[2], bgra[1], bgra[0], 255]);
+            }
+        }
+
+        Ok((data, size))
+    }
+
+    /// Reads back the staging target as RGBA half floats. The composite pass leaves the lighting uncompressed, so this is the linear
+    /// color of the last frame including bloom and depth of field. Exposure, tonemapping and FXAA are only applied by the final pass
+    pub fn capture_staging_hdr(&self) -> anyhow::Result<(Vec<u16>, (u32, u32))> {
+        let size = self.window_size;
+        let staging = CpuStagingBuffer::create(
+            size,
+            DxgiFormat::R16G16B16A16_FLOAT,
+            self.dcs.clone(),
+            "Staging_Capture",
+        )?;
+
+        self.gbuffer.staging.copy_to_staging(&staging);
+
+        let m = staging.map(D3D11_MAP_READ)?;
+        let mut data = Vec::with_capacity(size.0 as usize * size.1 as usize * 4);
+        for y in 0..size.1 as usize {
+            let row = unsafe {
+                std::slice::from_raw_parts(
+                    m.ptr.add(y * m.row_pitch as usize) as *const u16,
+                    size.0 as usize * 4,
+                )
+            };
+
+            data.extend_from_slice(row);
+        }
+
+        Ok((data, size))
+    }
+
+    /// Changes the MSAA sample count of the geometry pass, 1 disables MSAA
+    pub fn set_sample_count(&mut self, sample_count: u32) -> anyhow::Result<()> {
+        if !GBuffer::supports_sample_count(&self.dcs.device, sample_count) {
+            anyhow::bail!("{sample_count}x MSAA is not supported by this device");
+        }
+
+        self.gbuffer.resize(self.window_size, sample_count)
+    }
+
+    unsafe fn bind_gbuffer_targets(&self) {
+        self.dcs.context().OMSetRenderTargets(
+            Some(&[
+                Some(self.gbuffer.rt0.render_target.clone()),
+                Some(self.gbuffer.rt1.render_target.clone()),
+                Some(self.gbuffer.rt2.render_target.clone()),
+                Some(self.gbuffer.rt3.render_target.clone()),
+            ]),
+            &self.gbuffer.depth.view,
+        );
+    }
+
+    /// `depth` should be the far plane value of the camera, see [`FpsCamera::depth_clear_value`]
+    /// Clears RT0, RT2 and RT3 of the resolved gbuffer to their initial values, leaving depth and the normals in RT1 intact
+    fn clear_gbuffer_color(&self) {
+        unsafe {
+            self.dcs.context().ClearRenderTargetView(
+                &self.gbuffer.rt0.render_target,
+                [0.0, 0.0, 0.0, 0.0].as_ptr() as _,
+            );
+            self.dcs.context().ClearRenderTargetView(
+                &self.gbuffer.rt2.render_target,
+                [1.0, 0.5, 1.0, 0.0].as_ptr() as _,
+            );
+            self.dcs.context().ClearRenderTargetView(
+                &self.gbuffer.rt3.render_target,
+                [0.0, 0.0, 0.0, 0.0].as_ptr() as _,
+            );
+        }
     }
 
-    pub fn clear_render_targets(&self) {
+    pub fn clear_render_targets(&self, depth: f32) {
         unsafe {
+            if let Some(msaa) = &self.gbuffer.msaa {
+                self.dcs
+                    .context()
+                    .ClearRenderTargetView(&msaa.rt0.render_target, [0.0; 4].as_ptr() as _);
+                self.dcs
+                    .context()
+                    .ClearRenderTargetView(&msaa.rt1.render_target, [0.0; 4].as_ptr() as _);
+                self.dcs.context().ClearRenderTargetView(
+                    &msaa.rt2.render_target,
+                    [1.0, 0.5, 1.0, 0.0].as_ptr() as _,
+                );
+                self.dcs
+                    .context()
+                    .ClearRenderTargetView(&msaa.rt3.render_target, [0.0; 4].as_ptr() as _);
+                self.dcs.context().ClearDepthStencilView(
+                    &msaa.depth.view,
+                    D3D11_CLEAR_DEPTH.0 as _,
+                    depth,
+                    0,
+                );
+            }
+
             self.dcs.context().ClearRenderTargetView(
                 &self.gbuffer.rt0.render_target,
                 [0.0, 0.0, 0.0, 0.0].as_ptr() as _,
@@ -1662,10 +2616,14 @@ impl Renderer {
                 &self.gbuffer.rt2.render_target,
                 [1.0, 0.5, 1.0, 0.0].as_ptr() as _,
             );
+            self.dcs.context().ClearRenderTargetView(
+                &self.gbuffer.rt3.render_target,
+                [0.0, 0.0, 0.0, 0.0].as_ptr() as _,
+            );
             self.dcs.context().ClearDepthStencilView(
                 &self.gbuffer.depth.view,
                 D3D11_CLEAR_DEPTH.0 as _,
-                0.0,
+                depth,
                 0,
             );
         }
@@ -1681,14 +2639,66 @@ pub enum DrawMode {
     PickBuffer,
 }
 
+/// Rasterizer states for decals, offset towards the camera to avoid z-fighting with the surface beneath them
+struct DecalRasterizerStates {
+    /// Depth bias, slope scaled depth bias and reverse Z the states were created with
+    bias: (i32, f32, bool),
+    cull: ID3D11RasterizerState,
+    nocull: ID3D11RasterizerState,
+}
+
+impl DecalRasterizerStates {
+    fn create(dcs: &DeviceContextSwapchain, bias: (i32, f32, bool)) -> anyhow::Result<Self> {
+        let (depth_bias, slope_scaled_depth_bias, reverse_z) = bias;
+        // Closer surfaces have a higher depth with reverse Z
+        let sign = if reverse_z { 1 } else { -1 };
+
+        let create = |cull_mode| unsafe {
+            dcs.device.CreateRasterizerState(&D3D11_RASTERIZER_DESC {
+                FillMode: D3D11_FILL_SOLID,
+                CullMode: cull_mode,
+                FrontCounterClockwise: true.into(),
+                DepthBias: depth_bias * sign,
+                DepthBiasClamp: 0.0,
+                SlopeScaledDepthBias: slope_scaled_depth_bias * sign as f32,
+                DepthClipEnable: true.into(),
+                ScissorEnable: Default::default(),
+                MultisampleEnable: Default::default(),
+                AntialiasedLineEnable: Default::default(),
+            })
+        };
+
+        Ok(Self {
+            bias,
+            cull: create(D3D11_CULL_BACK)?,
+            nocull: create(D3D11_CULL_NONE)?,
+        })
+    }
+}
+
+#[repr(C)]
+pub struct CascadeOptions {
+    pub matrices: [Mat4; Renderer::CAMERA_CASCADE_LEVEL_COUNT],
+    /// Far distance of each cascade
+    pub splits: Vec4,
+    pub count: u32,
+    /// Tints the shaded output by cascade
+    pub debug: u32,
+    _pad: [u32; 2],
+}
+
 pub struct ShadowMapsResource {
     pub cascade_depth_buffers: ShadowDepthMap,
     pub resolution: usize,
+    /// Resolution requested with [`Self::resize`], applied by [`Self::apply_resize`] before the next frame
+    pending_resolution: Option<usize>,
     dcs: Arc<DeviceContextSwapchain>,
 }
 
 impl ShadowMapsResource {
     pub const DEFAULT_RESOLUTION: usize = 4096;
+    /// Smallest resolution to fall back to when allocating the shadow maps fails
+    pub const MIN_RESOLUTION: usize = 1024;
 
     pub fn create(dcs: Arc<DeviceContextSwapchain>) -> Self {
         Self {
@@ -1699,23 +2709,53 @@ impl ShadowMapsResource {
             )
             .expect("Failed to create CSM depth map"),
             resolution: Self::DEFAULT_RESOLUTION,
+            pending_resolution: None,
             dcs,
         }
     }
 
+    /// Requests a new resolution, the shadow maps are reallocated by [`Self::apply_resize`]
     pub fn resize(&mut self, new_resolution: usize) {
-        if new_resolution == self.resolution {
-            return;
-        }
+        self.pending_resolution = (new_resolution != self.resolution).then_some(new_resolution);
+    }
+
+    pub fn is_resize_pending(&self) -> bool {
+        self.pending_resolution.is_some()
+    }
 
-        self.cascade_depth_buffers
-            .resize(
-                (new_resolution as u32, new_resolution as u32),
+    /// Reallocates the shadow maps if a resize was requested, must not be called while rendering a frame.
+    /// Halves the resolution until allocation succeeds, and returns a warning if the requested resolution couldn't be used
+    pub fn apply_resize(&mut self) -> Option<String> {
+        let requested = self.pending_resolution.take()?;
+
+        let mut resolution = requested;
+        // Stop once we reach the current resolution, the existing textures can be reused
+        while resolution != self.resolution {
+            match ShadowDepthMap::create(
+                (resolution as u32, resolution as u32),
+                self.cascade_depth_buffers.layers,
                 &self.dcs.device,
-            )
-            .expect("Failed to resize shadow map depth buffer");
-        self.resolution = new_resolution;
+            ) {
+                Ok(depth_map) => {
+                    self.cascade_depth_buffers = depth_map;
+                    self.resolution = resolution;
+                    info!("Resized shadow maps to {resolution}");
+                }
+                Err(e) => {
+                    error!("Failed to allocate {resolution}x{resolution} shadow maps: {e:?}");
+                    if resolution / 2 < Self::MIN_RESOLUTION {
+                        break;
+                    }
+                    resolution /= 2;
+                }
+            }
+        }
 
-        info!("Resized shadow maps to {new_resolution}");
+        (self.resolution != requested).then(|| {
+            format!(
+                "Couldn't allocate {requested}x{requested} shadow maps, using {0}x{0} instead",
+                self.resolution
+            )
+        })
     }
 }
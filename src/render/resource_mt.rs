@@ -2,6 +2,7 @@ use crate::util::RwLock;
 use anyhow::Context;
 use crossbeam::channel::{self as mpsc, Receiver};
 use destiny_pkg::TagHash;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::Instant;
 use windows::Win32::Graphics::{
@@ -20,10 +21,13 @@ use crate::{
     entity::{IndexBufferHeader, VertexBufferHeader},
     packages::package_manager,
     structure::ExtendedHash,
-    texture::Texture,
+    texture::{STextureHeader, Texture},
 };
 
-use super::{DeviceContextSwapchain, RenderData};
+use super::{
+    asset_errors::{AssetKind, ASSET_ERRORS},
+    DeviceContextSwapchain, RenderData,
+};
 
 #[derive(PartialEq, Eq, Clone)]
 pub enum LoadingThreadState {
@@ -38,6 +42,85 @@ pub static STATUS_TEXTURES: RwLock<LoadingThreadState> = RwLock::new(LoadingThre
 pub static STATUS_BUFFERS: RwLock<LoadingThreadState> = RwLock::new(LoadingThreadState::Idle);
 // pub static STATUS_SHADERS: RwLock<LoadingThreadState> = RwLock::new(LoadingThreadState::Idle);
 
+pub static PROGRESS_TEXTURES: LoadingThreadProgress = LoadingThreadProgress::new();
+pub static PROGRESS_BUFFERS: LoadingThreadProgress = LoadingThreadProgress::new();
+
+/// Lock-free loading counters, cheap enough to be polled every frame
+pub struct LoadingThreadProgress {
+    queued: AtomicUsize,
+    in_flight: AtomicUsize,
+    completed: AtomicUsize,
+    current: AtomicU32,
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct LoadingProgress {
+    pub queued: usize,
+    pub in_flight: usize,
+    /// Number of items completed since the loader was last idle
+    pub completed: usize,
+    pub current: Option<TagHash>,
+}
+
+impl LoadingProgress {
+    pub fn total(&self) -> usize {
+        self.queued + self.in_flight + self.completed
+    }
+
+    pub fn fraction(&self) -> f32 {
+        if self.total() == 0 {
+            1.0
+        } else {
+            self.completed as f32 / self.total() as f32
+        }
+    }
+}
+
+impl LoadingThreadProgress {
+    pub const fn new() -> Self {
+        Self {
+            queued: AtomicUsize::new(0),
+            in_flight: AtomicUsize::new(0),
+            completed: AtomicUsize::new(0),
+            current: AtomicU32::new(u32::MAX),
+        }
+    }
+
+    /// Marks `hash` as in-flight until the returned guard is dropped
+    fn begin(&self, hash: TagHash, queued: usize) -> LoadingProgressGuard<'_> {
+        self.queued.store(queued, Ordering::Relaxed);
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+        self.current.store(hash.0, Ordering::Relaxed);
+
+        LoadingProgressGuard(self)
+    }
+
+    pub fn snapshot(&self) -> LoadingProgress {
+        let current = TagHash(self.current.load(Ordering::Relaxed));
+        LoadingProgress {
+            queued: self.queued.load(Ordering::Relaxed),
+            in_flight: self.in_flight.load(Ordering::Relaxed),
+            completed: self.completed.load(Ordering::Relaxed),
+            current: current.is_some().then_some(current),
+        }
+    }
+}
+
+struct LoadingProgressGuard<'a>(&'a LoadingThreadProgress);
+
+impl Drop for LoadingProgressGuard<'_> {
+    fn drop(&mut self) {
+        let p = self.0;
+        if p.in_flight.fetch_sub(1, Ordering::Relaxed) == 1 && p.queued.load(Ordering::Relaxed) == 0
+        {
+            p.completed.store(0, Ordering::Relaxed);
+            p.current.store(u32::MAX, Ordering::Relaxed);
+        } else {
+            p.completed.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
 fn update_status(state: &RwLock<LoadingThreadState>, remaining: usize) {
     let status = state.read().clone();
     if remaining == 0 {
@@ -60,26 +143,34 @@ fn update_status(state: &RwLock<LoadingThreadState>, remaining: usize) {
     }
 }
 
+/// Texture data that has been read and is waiting to be uploaded by [`upload_textures`]
+pub type DecodedTexture = (ExtendedHash, STextureHeader, Vec<u8>);
+
 fn spawn_thread_textures(
-    dcs: Arc<DeviceContextSwapchain>,
     data: Arc<RwLock<RenderData>>,
     rx: Receiver<ExtendedHash>,
+    tx_decoded: mpsc::Sender<DecodedTexture>,
     name: &'static str,
 ) {
     std::thread::Builder::new()
         .name(name.to_string())
         .spawn(move || {
             while let Ok(hash) = rx.recv() {
+                let _progress =
+                    PROGRESS_TEXTURES.begin(hash.hash32().unwrap_or(TagHash::NONE), rx.len());
                 if hash.is_some() && !data.read().textures.contains_key(&hash.key()) {
-                    match Texture::load(&dcs, hash) {
-                        Ok(t) => {
-                            data.write().textures.insert(hash.key(), t);
+                    match Texture::load_data(hash, true) {
+                        Ok((header, texture_data)) => {
+                            tx_decoded.send((hash, header, texture_data)).ok();
+                        }
+                        Err(e) => {
+                            error!("Failed to load texture {hash:?}: {e}");
+                            ASSET_ERRORS.push(AssetKind::Texture, hash, e);
                         }
-                        Err(e) => error!("Failed to load texture {hash:?}: {e}"),
                     }
                 }
 
-                update_status(&STATUS_TEXTURES, rx.len());
+                update_status(&STATUS_TEXTURES, rx.len() + tx_decoded.len());
             }
 
             info!("Thread '{}' exited", name);
@@ -87,16 +178,59 @@ fn spawn_thread_textures(
         .unwrap();
 }
 
+/// Returns the sender for texture load requests, and the receiver for textures that are ready to be uploaded
 pub fn thread_textures(
-    dcs: Arc<DeviceContextSwapchain>,
     data: Arc<RwLock<RenderData>>,
-) -> mpsc::Sender<ExtendedHash> {
+) -> (mpsc::Sender<ExtendedHash>, Receiver<DecodedTexture>) {
     let (tx, rx) = mpsc::unbounded::<ExtendedHash>();
+    let (tx_decoded, rx_decoded) = mpsc::unbounded::<DecodedTexture>();
 
-    spawn_thread_textures(dcs.clone(), data.clone(), rx.clone(), "Texture loader 1");
-    spawn_thread_textures(dcs, data, rx, "Texture loader 2");
+    spawn_thread_textures(
+        data.clone(),
+        rx.clone(),
+        tx_decoded.clone(),
+        "Texture loader 1",
+    );
+    spawn_thread_textures(data, rx, tx_decoded, "Texture loader 2");
 
-    tx
+    (tx, rx_decoded)
+}
+
+/// Uploads up to `max_uploads` decoded textures (all of them if `None`).
+/// Until a texture is uploaded, materials using it are drawn with the fallback texture
+pub fn upload_textures(
+    dcs: &DeviceContextSwapchain,
+    data: &RwLock<RenderData>,
+    rx_decoded: &Receiver<DecodedTexture>,
+    max_uploads: Option<usize>,
+) {
+    let mut uploaded = 0;
+    while max_uploads.map_or(true, |max| uploaded < max) {
+        let Ok((hash, header, texture_data)) = rx_decoded.try_recv() else {
+            break;
+        };
+
+        // The same texture might have been requested (and decoded) more than once
+        if data.read().textures.contains_key(&hash.key()) {
+            continue;
+        }
+
+        match Texture::create(dcs, hash, header, texture_data) {
+            Ok(t) => {
+                data.write().textures.insert(hash.key(), t);
+            }
+            Err(e) => {
+                error!("Failed to upload texture {hash:?}: {e}");
+                ASSET_ERRORS.push(AssetKind::Texture, hash, format!("Upload failed: {e}"));
+            }
+        }
+        uploaded += 1;
+    }
+
+    let progress = PROGRESS_TEXTURES.snapshot();
+    if uploaded > 0 && progress.queued == 0 && progress.in_flight == 0 {
+        update_status(&STATUS_TEXTURES, rx_decoded.len());
+    }
 }
 
 fn spawn_thread_buffers(
@@ -109,6 +243,7 @@ fn spawn_thread_buffers(
         .name(name.to_string())
         .spawn(move || {
             while let Ok((hash, create_rgba_srv)) = rx.recv() {
+                let _progress = PROGRESS_BUFFERS.begin(hash, rx.len());
                 if hash.is_some() {
                     if let Some(entry) = package_manager().get_entry(hash) {
                         match (entry.file_type, entry.file_subtype) {
@@ -200,7 +335,14 @@ fn spawn_thread_buffers(
                                             ),
                                         );
                                     }
-                                    Err(e) => error!("Failed to load vertex buffer {hash}: {e}"),
+                                    Err(e) => {
+                                        error!("Failed to load vertex buffer {hash}: {e}");
+                                        ASSET_ERRORS.push(
+                                            AssetKind::VertexBuffer,
+                                            ExtendedHash::Hash32(hash),
+                                            e,
+                                        );
+                                    }
                                 }
                             }
                             // Index buffer
@@ -245,7 +387,14 @@ fn spawn_thread_buffers(
                                             ),
                                         );
                                     }
-                                    Err(e) => error!("Failed to load vertex buffer {hash}: {e}"),
+                                    Err(e) => {
+                                        error!("Failed to load index buffer {hash}: {e}");
+                                        ASSET_ERRORS.push(
+                                            AssetKind::IndexBuffer,
+                                            ExtendedHash::Hash32(hash),
+                                            e,
+                                        );
+                                    }
                                 }
                             }
                             u => panic!("Unsupported mt loader buffer type {u:?}"),
@@ -103,6 +103,11 @@ pub struct ScopeInstances {
 
 impl ScopeInstances {
     pub fn write(&self) -> Vec<u8> {
+        self.write_with_transforms(&self.transforms)
+    }
+
+    /// Writes the header followed by `transforms` instead of the full instance list
+    pub fn write_with_transforms(&self, transforms: &[Mat4]) -> Vec<u8> {
         let mut buffer = vec![];
 
         buffer
@@ -118,9 +123,7 @@ impl ScopeInstances {
             ]))
             .unwrap();
 
-        buffer
-            .write_all(bytemuck::cast_slice(&self.transforms))
-            .unwrap();
+        buffer.write_all(bytemuck::cast_slice(transforms)).unwrap();
 
         buffer
     }
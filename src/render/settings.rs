@@ -0,0 +1,470 @@
+use glam::{Vec3, Vec4};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use strum::IntoEnumIterator;
+
+use crate::{map::MapDataList, screenshot::ScreenshotFormat};
+
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CompositorMode {
+    /// Rendered output
+    Combined,
+
+    /// RT0
+    Albedo,
+    /// RT1
+    Normal,
+    /// RT2
+    PbrStack,
+    RT3,
+
+    SmoothnessFuzz,
+    Metalicness,
+    TextureAO,
+    Emission,
+    Transmission,
+    VertexAO,
+    Iridescence,
+    Cubemap,
+    Matcap,
+    Depth,
+    Specular,
+
+    LightRT0,
+    LightRT1,
+}
+
+pub const COMPOSITOR_MODES: &[CompositorMode] = &[
+    CompositorMode::Combined,       // 0
+    CompositorMode::Albedo,         // 1
+    CompositorMode::Normal,         // 2
+    CompositorMode::PbrStack,       // 3
+    CompositorMode::RT3,            // 4
+    CompositorMode::SmoothnessFuzz, // 5
+    CompositorMode::Metalicness,    // 6
+    CompositorMode::TextureAO,      // 7
+    CompositorMode::Emission,       // 8
+    CompositorMode::Transmission,   // 9
+    CompositorMode::VertexAO,       // 10
+    CompositorMode::Iridescence,    // 11
+    CompositorMode::Cubemap,        // 12
+    CompositorMode::Matcap,         // 13
+    CompositorMode::Depth,          // 14
+    CompositorMode::Specular,       // 15
+    CompositorMode::LightRT0,       // 16
+    CompositorMode::LightRT1,       // 17
+];
+
+/// Background for pixels without geometry in debug views, where the raw clear values of the buffers would be confusing
+/// (eg. RT2 is cleared to magenta). Modes not listed show the buffer as-is
+const COMPOSITOR_CLEAR_COLORS: &[(CompositorMode, [f32; 3])] = &[
+    (CompositorMode::Normal, [0.5, 0.5, 0.5]),
+    (CompositorMode::PbrStack, [0.0, 0.0, 0.0]),
+    (CompositorMode::Metalicness, [0.0, 0.0, 0.0]),
+    (CompositorMode::TextureAO, [0.0, 0.0, 0.0]),
+    (CompositorMode::Transmission, [0.0, 0.0, 0.0]),
+    (CompositorMode::Depth, [0.0, 0.0, 0.0]),
+];
+
+impl CompositorMode {
+    /// Color passed to the compositor for pixels without geometry, the alpha is 0 when the mode doesn't override the background
+    pub fn clear_color(self) -> Vec4 {
+        COMPOSITOR_CLEAR_COLORS
+            .iter()
+            .find(|(mode, _)| *mode == self)
+            .map_or(Vec4::ZERO, |(_, c)| Vec3::from(*c).extend(1.0))
+    }
+}
+
+impl Display for CompositorMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            CompositorMode::Combined => "Combined",
+            CompositorMode::Albedo => "Albedo (RT0)",
+            CompositorMode::Normal => "Normal (RT1)",
+            CompositorMode::PbrStack => "PBR Stack (RT2)",
+            CompositorMode::RT3 => "RT3",
+            CompositorMode::SmoothnessFuzz => "Smoothness/Fuzz",
+            CompositorMode::Metalicness => "Metalicness",
+            CompositorMode::TextureAO => "Texture AO",
+            CompositorMode::Emission => "Emission",
+            CompositorMode::Transmission => "Transmission",
+            CompositorMode::VertexAO => "Vertex AO",
+            CompositorMode::Iridescence => "Iridescence",
+            CompositorMode::Cubemap => "Cubemap",
+            CompositorMode::Matcap => "Matcap",
+            CompositorMode::Depth => "Depth",
+            CompositorMode::Specular => "Specular",
+            CompositorMode::LightRT0 => "LightRT0",
+            CompositorMode::LightRT1 => "LightRT1",
+        };
+
+        f.write_str(name)
+    }
+}
+
+/// Tonemapping operator applied in the final pass, after exposure. The staging target holds linear color, so each of these sees the unclamped lighting
+#[repr(u32)]
+#[derive(strum::Display, strum::EnumIter, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Tonemap {
+    /// Clamps to [0, 1]
+    None,
+    /// `c / (1 + c)`
+    Reinhard,
+    /// Narkowicz's fit of the ACES filmic curve
+    #[strum(serialize = "ACES")]
+    Aces,
+    /// Reinhard followed by Destiny's own film curve
+    Filmic,
+}
+
+/// Anti-aliasing applied in the final pass
+#[derive(strum::Display, strum::EnumIter, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AntiAliasing {
+    None,
+    #[strum(serialize = "FXAA (low)")]
+    FxaaLow,
+    #[strum(serialize = "FXAA (medium)")]
+    FxaaMedium,
+    #[strum(serialize = "FXAA (high)")]
+    FxaaHigh,
+    /// Temporal anti-aliasing, needs jittered projections and a history buffer. Not implemented yet
+    #[strum(serialize = "TAA (experimental)")]
+    Taa,
+}
+
+impl AntiAliasing {
+    /// FXAA preset used by final.hlsl, 0 disables FXAA
+    pub fn fxaa_quality(self) -> u32 {
+        match self {
+            AntiAliasing::None => 0,
+            AntiAliasing::FxaaLow => 1,
+            AntiAliasing::FxaaMedium => 2,
+            // TODO: TAA falls back to FXAA until it's implemented
+            AntiAliasing::FxaaHigh | AntiAliasing::Taa => 3,
+        }
+    }
+}
+
+/// Texture filtering applied to the samplers of game materials
+#[derive(strum::Display, strum::EnumIter, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TextureFiltering {
+    /// Uses the filtering stored in the sampler tags
+    #[strum(serialize = "Game default")]
+    GameDefault,
+    Bilinear,
+    Trilinear,
+    Anisotropic,
+}
+
+/// What to show behind the scene, where nothing was drawn
+#[repr(u32)]
+#[derive(strum::Display, strum::EnumIter, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Background {
+    /// Whatever the shading produces for the cleared gbuffer
+    Default,
+    Solid,
+    /// Vertical gradient from `background_color` at the bottom to `background_color_top`
+    Gradient,
+}
+
+/// Presets for the render stage toggles
+#[derive(strum::Display, strum::EnumIter, Clone, Copy, PartialEq, Eq)]
+pub enum StageIsolation {
+    All,
+    #[strum(serialize = "Opaque only")]
+    Opaque,
+    #[strum(serialize = "Decals only")]
+    Decals,
+    #[strum(serialize = "Transparent only")]
+    Transparent,
+}
+
+impl StageIsolation {
+    /// GenerateGbuffer, GbufferColor, Decals, DecalsAdditive and Transparents
+    ///
+    /// Decals still need the geometry pass for the depth and normals they project onto, so "Decals only" keeps it but hands the
+    /// compositor a gbuffer with only the albedo and material data the decals write
+    fn stages(self) -> [bool; 5] {
+        match self {
+            StageIsolation::All => [true, true, true, true, true],
+            StageIsolation::Opaque => [true, true, false, false, false],
+            StageIsolation::Decals => [true, false, true, true, false],
+            StageIsolation::Transparent => [false, true, false, false, true],
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RenderSettings {
+    pub use_global_deferred_shading: bool,
+    pub draw_lights: bool,
+    pub draw_errors: bool,
+    pub error_scale: f32,
+    pub render_shadows: bool,
+    /// Number of shadow cascades (1-4)
+    pub shadow_cascades: usize,
+    /// Tints the shaded output by the shadow cascade it falls in
+    pub shadow_cascade_debug: bool,
+    pub alpha_blending: bool,
+    pub compositor_mode: usize,
+    /// Shows `compositor_mode_b` right of the divider, for comparing two modes
+    pub split_compare: bool,
+    pub compositor_mode_b: usize,
+    /// Horizontal position of the split divider (0.0-1.0)
+    pub split_position: f32,
+    pub blend_override: usize,
+    pub evaluate_bytecode: bool,
+    /// Shows the techniques, textures and render state used by the selected entity
+    pub show_material_info: bool,
+    /// Maximum amount of instances drawn per static instance group, 0 draws all of them. Only the first instances are drawn
+    pub instance_limit: usize,
+    /// Depth bias towards the camera for decals, in units of the smallest representable depth difference
+    pub decal_depth_bias: i32,
+    /// Depth bias towards the camera for decals, scaled by the slope of the surface
+    pub decal_slope_scaled_depth_bias: f32,
+    pub ambient_light: Vec4,
+    /// Use the sun of the loaded map instead of the manual directional light
+    pub use_map_lighting: bool,
+    pub light_dir: Vec3,
+    pub light_color: Vec4,
+    pub use_specular_map: bool,
+    pub anti_aliasing: AntiAliasing,
+    /// Sample count for the geometry pass, 1 disables MSAA
+    pub msaa_samples: u32,
+    pub tonemap: Tonemap,
+    pub screenshot_format: ScreenshotFormat,
+    pub light_mul: f32,
+
+    pub dof_enabled: bool,
+    pub dof_focus_distance: f32,
+    pub dof_strength: f32,
+
+    pub bloom_enabled: bool,
+    pub bloom_threshold: f32,
+    pub bloom_intensity: f32,
+
+    pub background: Background,
+    pub background_color: Vec4,
+    pub background_color_top: Vec4,
+
+    /// Exponential distance fog, applied to the combined output
+    pub fog_enabled: bool,
+    /// Distance from the camera at which the fog starts
+    pub fog_start: f32,
+    pub fog_density: f32,
+    /// `None` uses the background color
+    pub fog_color: Option<Vec4>,
+
+    /// Letterboxes or pillarboxes the viewport to this aspect ratio (width / height)
+    pub letterbox_aspect: Option<f32>,
+    pub letterbox_color: Vec4,
+
+    pub grid_enabled: bool,
+    /// Distance between minor grid lines
+    pub grid_spacing: f32,
+    /// Number of minor cells per major cell
+    pub grid_major_every: u32,
+    pub grid_height: f32,
+    /// Keeps the grid centered below the camera instead of at the world origin
+    pub grid_follow_camera: bool,
+    pub grid_fade_distance: f32,
+
+    /// Per-stage toggles, for isolating what each pass contributes to the frame
+    pub stage_generate_gbuffer: bool,
+    /// Keeps the albedo and material data (RT0, RT2, RT3) written by the geometry pass. Off leaves only its depth and normals for the decals
+    pub stage_gbuffer_color: bool,
+    pub stage_decals: bool,
+    pub stage_decals_additive: bool,
+    pub stage_transparents: bool,
+
+    /// Camera distances at which entity models switch to LOD 1, 2 and 3
+    pub lod_distances: [f32; 3],
+    /// Draws every entity model at this LOD (or the closest one it has), for debugging
+    pub force_lod: Option<u8>,
+
+    /// Maximum number of textures uploaded to the GPU per frame, 0 for no limit
+    pub max_texture_uploads: usize,
+
+    pub texture_filtering: TextureFiltering,
+    /// Maximum anisotropy (1-16), only used with anisotropic filtering
+    pub anisotropy: u32,
+
+    /// Clips away all geometry behind the clipping plane (opposite to its normal)
+    pub clip_plane_enabled: bool,
+    pub clip_plane_point: Vec3,
+    pub clip_plane_normal: Vec3,
+    /// Clips the other side of the plane instead
+    pub clip_plane_flip: bool,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            use_global_deferred_shading: false,
+            compositor_mode: CompositorMode::Combined as usize,
+            split_compare: false,
+            compositor_mode_b: CompositorMode::Albedo as usize,
+            split_position: 0.5,
+            alpha_blending: true,
+            draw_lights: false,
+            draw_errors: true,
+            error_scale: 1.0,
+            render_shadows: true,
+            shadow_cascades: 4,
+            shadow_cascade_debug: false,
+            blend_override: 0,
+            evaluate_bytecode: false,
+            show_material_info: false,
+            instance_limit: 0,
+            decal_depth_bias: 16,
+            decal_slope_scaled_depth_bias: 1.0,
+            ambient_light: Vec4::splat(0.04),
+            // Matches the initial angle and rotation of the options overlay
+            light_dir: Vec3::new(50f32.to_radians().sin(), 50f32.to_radians().cos(), 1.0),
+            light_color: Vec4::ONE,
+            use_map_lighting: true,
+            use_specular_map: true,
+            anti_aliasing: AntiAliasing::FxaaHigh,
+            msaa_samples: 1,
+            tonemap: Tonemap::Filmic,
+            screenshot_format: ScreenshotFormat::Png,
+            light_mul: 1.0,
+            dof_enabled: false,
+            dof_focus_distance: 10.0,
+            dof_strength: 0.5,
+            bloom_enabled: false,
+            bloom_threshold: 4.0,
+            bloom_intensity: 0.6,
+            background: Background::Default,
+            // Neutral studio grey
+            background_color: Vec4::new(0.18, 0.18, 0.18, 1.0),
+            background_color_top: Vec4::new(0.45, 0.45, 0.45, 1.0),
+            fog_enabled: false,
+            fog_start: 50.0,
+            fog_density: 0.005,
+            fog_color: None,
+            letterbox_aspect: None,
+            letterbox_color: Vec4::new(0.0, 0.0, 0.0, 1.0),
+            grid_enabled: false,
+            grid_spacing: 1.0,
+            grid_major_every: 10,
+            grid_height: 0.0,
+            grid_follow_camera: true,
+            grid_fade_distance: 150.0,
+            stage_generate_gbuffer: true,
+            stage_gbuffer_color: true,
+            stage_decals: true,
+            stage_decals_additive: true,
+            stage_transparents: true,
+            lod_distances: [50.0, 150.0, 400.0],
+            force_lod: None,
+            max_texture_uploads: 16,
+            texture_filtering: TextureFiltering::Anisotropic,
+            anisotropy: 8,
+            clip_plane_enabled: false,
+            clip_plane_point: Vec3::ZERO,
+            clip_plane_normal: Vec3::X,
+            clip_plane_flip: false,
+        }
+    }
+}
+
+impl RenderSettings {
+    /// Direction and color of the directional light. Uses the sun of the current map when `use_map_lighting` is enabled and the map has one,
+    /// otherwise the manual light
+    pub fn sun_light(&self, maps: &MapDataList) -> (Vec3, Vec4) {
+        self.use_map_lighting
+            .then(|| maps.current_map().and_then(|(_, _, map)| map.sun_light()))
+            .flatten()
+            .unwrap_or((self.light_dir, self.light_color))
+    }
+
+    /// Returns the isolation preset matching the current stage toggles, if any
+    pub fn stage_isolation(&self) -> Option<StageIsolation> {
+        let stages = [
+            self.stage_generate_gbuffer,
+            self.stage_gbuffer_color,
+            self.stage_decals,
+            self.stage_decals_additive,
+            self.stage_transparents,
+        ];
+        StageIsolation::iter().find(|i| i.stages() == stages)
+    }
+
+    pub fn isolate_stages(&mut self, isolation: StageIsolation) {
+        [
+            self.stage_generate_gbuffer,
+            self.stage_gbuffer_color,
+            self.stage_decals,
+            self.stage_decals_additive,
+            self.stage_transparents,
+        ] = isolation.stages();
+    }
+
+    /// Returns the clipping plane as (normal, distance), with the kept side being positive
+    pub fn clip_plane(&self) -> Option<Vec4> {
+        if !self.clip_plane_enabled {
+            return None;
+        }
+
+        let mut normal = self.clip_plane_normal.try_normalize()?;
+        if self.clip_plane_flip {
+            normal = -normal;
+        }
+
+        Some(normal.extend(-normal.dot(self.clip_plane_point)))
+    }
+
+    /// Returns the LOD level an entity model at the given distance from the camera should use
+    pub fn select_lod(&self, distance: f32) -> u8 {
+        self.force_lod.unwrap_or_else(|| {
+            self.lod_distances
+                .iter()
+                .filter(|&&threshold| distance >= threshold)
+                .count() as u8
+        })
+    }
+}
+
+/// A named snapshot of the render settings
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RenderPreset {
+    pub name: String,
+    pub settings: RenderSettings,
+}
+
+/// Presets that are always available, and can't be overwritten or deleted
+pub fn builtin_presets() -> Vec<RenderPreset> {
+    vec![
+        RenderPreset {
+            name: "Default".to_string(),
+            settings: RenderSettings::default(),
+        },
+        RenderPreset {
+            name: "Albedo debug".to_string(),
+            settings: RenderSettings {
+                compositor_mode: CompositorMode::Albedo as usize,
+                draw_lights: false,
+                anti_aliasing: AntiAliasing::None,
+                tonemap: Tonemap::None,
+                bloom_enabled: false,
+                dof_enabled: false,
+                ..Default::default()
+            },
+        },
+        RenderPreset {
+            name: "Beauty shot".to_string(),
+            settings: RenderSettings {
+                draw_lights: true,
+                render_shadows: true,
+                msaa_samples: 4,
+                bloom_enabled: true,
+                lod_distances: [150.0, 400.0, 1000.0],
+                ..Default::default()
+            },
+        },
+    ]
+}
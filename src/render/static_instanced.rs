@@ -1,3 +1,4 @@
+use crate::camera::FpsCamera;
 use crate::entity::VertexBufferHeader;
 use crate::map::SMeshInstanceOcclusionBounds;
 use crate::packages::package_manager;
@@ -6,6 +7,8 @@ use crate::render::{ConstantBuffer, DeviceContextSwapchain, StaticModel};
 
 use crate::statics::Unk808071a3;
 use crate::types::AABB;
+use crate::util::RwLock;
+use destiny_pkg::TagHash;
 
 use glam::{Mat4, Quat, Vec3};
 use hecs::Entity;
@@ -19,9 +22,21 @@ pub struct InstancedRenderer {
     pub instance_count: usize,
     pub occlusion_bounds: Vec<AABB>,
     instance_buffer: ConstantBuffer<u8>,
+
+    instance_data: ScopeInstances,
+    /// Indices of the instances currently in `instance_buffer`
+    buffered_instances: RwLock<Vec<usize>>,
 }
 
 impl InstancedRenderer {
+    pub fn buffers(&self) -> Vec<TagHash> {
+        self.renderer.buffers()
+    }
+
+    pub fn materials(&self) -> Vec<TagHash> {
+        self.renderer.materials()
+    }
+
     pub fn load(
         model: Arc<StaticModel>,
         instances: &[Unk808071a3],
@@ -85,21 +100,72 @@ impl InstancedRenderer {
             instance_count: instances.len(),
             occlusion_bounds: occlusion_bounds.iter().map(|v| v.bb).collect(),
             instance_buffer,
+            buffered_instances: RwLock::new((0..instances.len()).collect()),
+            instance_data,
         })
     }
 
+    /// World matrix of the given instance, without the model's mesh offset and scale
+    pub fn instance_transform(&self, index: usize) -> Option<Mat4> {
+        self.instance_data.transforms.get(index).copied()
+    }
+
+    /// Finds the instance whose bounds are hit first by the given ray
+    pub fn pick_instance(&self, origin: Vec3, direction: Vec3) -> Option<usize> {
+        self.occlusion_bounds
+            .iter()
+            .enumerate()
+            .filter_map(|(i, bb)| Some((i, bb.ray_intersection(origin, direction)?)))
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(i, _)| i)
+    }
+
+    /// Culls instances outside of the camera frustum and the shadow cascades, compacting the instance buffer if needed.
+    /// Only the first `instance_limit` instances are considered, 0 considers all of them
     pub fn draw(
         &self,
         renderer: &Renderer,
+        camera: &FpsCamera,
         draw_opaque: bool,
         draw_transparent: bool,
         draw_decals: bool,
+        instance_limit: usize,
         entity: Entity,
     ) -> anyhow::Result<()> {
+        let considered = if instance_limit == 0 {
+            self.occlusion_bounds.len()
+        } else {
+            instance_limit.min(self.occlusion_bounds.len())
+        };
+
+        let visible: Vec<usize> = self.occlusion_bounds[..considered]
+            .iter()
+            .enumerate()
+            .filter(|(_, bb)| camera.is_aabb_visible(bb) || renderer.is_shadow_caster_visible(bb))
+            .map(|(i, _)| i)
+            .collect();
+
+        renderer.count_culling(visible.len(), considered - visible.len());
+        if visible.is_empty() {
+            return Ok(());
+        }
+
+        let mut buffered = self.buffered_instances.write();
+        if *buffered != visible {
+            let transforms: Vec<Mat4> = visible
+                .iter()
+                .map(|&i| self.instance_data.transforms[i])
+                .collect();
+
+            self.instance_buffer
+                .write_array(&self.instance_data.write_with_transforms(&transforms))?;
+            *buffered = visible;
+        }
+
         self.renderer.draw(
             renderer,
             self.instance_buffer.buffer().clone(),
-            self.instance_count,
+            buffered.len(),
             draw_opaque,
             draw_transparent,
             draw_decals,
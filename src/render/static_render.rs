@@ -37,6 +37,34 @@ pub struct StaticModel {
 }
 
 impl StaticModel {
+    /// All materials used by this model and its overlay models
+    pub fn materials(&self) -> Vec<TagHash> {
+        self.model
+            .materials
+            .iter()
+            .cloned()
+            .chain(self.overlay_models.iter().map(|m| m.model.material))
+            .unique()
+            .collect()
+    }
+
+    /// Vertex, color and index buffers used by this model and its overlay models
+    pub fn buffers(&self) -> Vec<TagHash> {
+        self.buffers
+            .iter()
+            .chain(self.overlay_models.iter().map(|m| &m.buffers))
+            .flat_map(|b| {
+                [
+                    b.vertex_buffer1,
+                    b.vertex_buffer2,
+                    b.color_buffer,
+                    b.index_buffer,
+                ]
+            })
+            .unique()
+            .collect()
+    }
+
     pub fn load(model: SStaticMesh, renderer: &Renderer) -> anyhow::Result<StaticModel> {
         let pm = package_manager();
         let header: SStaticMeshData = pm.read_tag_struct(model.unk8).unwrap();
@@ -186,6 +214,7 @@ impl StaticModel {
                             instance_count: Some(instance_count as _),
                             primitive_type: p.primitive_type.to_dx(),
                             entity,
+                            opacity: None,
                         },
                     );
                 }
@@ -282,6 +311,7 @@ impl StaticOverlayModel {
                 instance_count: Some(instance_count as _),
                 primitive_type: self.model.primitive_type.to_dx(),
                 entity,
+                opacity: None,
             },
         );
     }
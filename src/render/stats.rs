@@ -0,0 +1,57 @@
+use strum::{EnumCount, IntoEnumIterator};
+
+use super::tfx::TfxRenderStage;
+
+/// Counters for the frame that was last submitted
+#[derive(Clone)]
+pub struct RenderStats {
+    /// Indexed by `TfxRenderStage as usize`
+    draw_calls: [usize; TfxRenderStage::COUNT],
+    /// Entities with at least one drawcall in the queue
+    pub visible_entities: usize,
+    /// Triangles submitted across all counted stages
+    pub triangles: usize,
+    /// Static instances and entities that passed frustum culling
+    pub instances_visible: usize,
+    /// Static instances and entities that were skipped by frustum culling
+    pub instances_culled: usize,
+}
+
+impl Default for RenderStats {
+    fn default() -> Self {
+        Self {
+            draw_calls: [0; TfxRenderStage::COUNT],
+            visible_entities: 0,
+            triangles: 0,
+            instances_visible: 0,
+            instances_culled: 0,
+        }
+    }
+}
+
+impl RenderStats {
+    pub fn count_draw(&mut self, stage: TfxRenderStage, triangles: usize) {
+        self.draw_calls[stage as usize] += 1;
+        self.triangles += triangles;
+    }
+
+    pub fn count_culling(&mut self, visible: usize, culled: usize) {
+        self.instances_visible += visible;
+        self.instances_culled += culled;
+    }
+
+    pub fn draw_calls(&self, stage: TfxRenderStage) -> usize {
+        self.draw_calls[stage as usize]
+    }
+
+    pub fn total_draw_calls(&self) -> usize {
+        self.draw_calls.iter().sum()
+    }
+
+    /// Stages that had at least one drawcall
+    pub fn active_stages(&self) -> impl Iterator<Item = (TfxRenderStage, usize)> + '_ {
+        TfxRenderStage::iter()
+            .map(|s| (s, self.draw_calls(s)))
+            .filter(|(_, c)| *c > 0)
+    }
+}
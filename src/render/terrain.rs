@@ -27,6 +27,10 @@ pub struct TerrainRenderer {
 }
 
 impl TerrainRenderer {
+    pub fn buffers(&self) -> [TagHash; 3] {
+        [self.vertex_buffer1, self.vertex_buffer2, self.index_buffer]
+    }
+
     pub fn load(
         terrain: STerrain,
         dcs: Arc<DeviceContextSwapchain>,
@@ -121,6 +125,7 @@ impl TerrainRenderer {
                         instance_count: None,
                         primitive_type: D3D11_PRIMITIVE_TOPOLOGY_TRIANGLESTRIP,
                         entity,
+                        opacity: None,
                     },
                 );
             } else {
@@ -5,7 +5,7 @@ use super::drawcall::ShadingMode;
 #[binread]
 #[br(repr(u8))]
 #[repr(u8)]
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, strum::EnumIter, strum::EnumCount)]
 pub enum TfxRenderStage {
     GenerateGbuffer = 0,
     Decals = 1,
@@ -0,0 +1,41 @@
+/// Length of a single frame step, in seconds
+pub const FRAME_STEP: f32 = 1.0 / 60.0;
+
+/// Game time as seen by TFX shaders and bytecode. Can be paused and scrubbed to capture animated materials at a specific moment
+pub struct TimeControl {
+    pub paused: bool,
+    /// Playback speed multiplier
+    pub speed: f32,
+    /// Current game time, in seconds
+    pub time: f32,
+    /// Amount of time advanced during the last frame
+    pub delta: f32,
+}
+
+impl Default for TimeControl {
+    fn default() -> Self {
+        Self {
+            paused: false,
+            speed: 1.0,
+            time: 0.0,
+            delta: 0.0,
+        }
+    }
+}
+
+impl TimeControl {
+    /// Advances the game time by the real time elapsed since the last frame, unless paused
+    pub fn advance(&mut self, elapsed: f32) {
+        self.delta = if self.paused {
+            0.0
+        } else {
+            elapsed * self.speed
+        };
+        self.time += self.delta;
+    }
+
+    /// Moves the game time by the given amount of frames, negative to go back
+    pub fn step(&mut self, frames: i32) {
+        self.time = (self.time + frames as f32 * FRAME_STEP).max(0.0);
+    }
+}
@@ -1,5 +1,7 @@
 use glam::{Vec2, Vec3};
-use std::time::Instant;
+use std::rc::Rc;
+
+use crate::{commands::CommandFn, resources::Resources};
 
 #[derive(Clone)]
 pub struct Tween {
@@ -8,8 +10,11 @@ pub struct Tween {
     pub last_pos: Option<Vec3>,
     pub angle_movement: Option<(Vec2, Vec2)>,
     pub last_angle: Option<Vec2>,
-    pub start_time: Instant,
+    /// Game time passed since the tween started, in seconds
+    pub elapsed: f32,
     pub duration: f32,
+    /// Ran by the main loop once the tween reaches its end, not when it's cancelled
+    pub on_finish: Option<CommandFn>,
 }
 
 impl Tween {
@@ -25,14 +30,25 @@ impl Tween {
             last_pos: pos_movement.map(|pos| pos.0),
             angle_movement,
             last_angle: angle_movement.map(|angle| angle.0),
-            start_time: Instant::now(),
+            elapsed: 0.0,
             duration,
+            on_finish: None,
         }
     }
 
+    pub fn with_on_finish(self, on_finish: impl Fn(&mut Resources) + 'static) -> Self {
+        Self {
+            on_finish: Some(Rc::new(on_finish)),
+            ..self
+        }
+    }
+
+    pub fn advance(&mut self, delta: f32) {
+        self.elapsed += delta;
+    }
+
     pub fn update_pos(&mut self) -> Option<Vec3> {
-        let time = self.start_time.elapsed().as_secs_f32();
-        let t = (time / self.duration).clamp(0., 1.);
+        let t = (self.elapsed / self.duration).clamp(0., 1.);
         let s = (self.func)(t);
 
         let new_pos = self.pos_movement.map(|pos| pos.0.lerp(pos.1, s));
@@ -41,8 +57,7 @@ impl Tween {
     }
 
     pub fn update_angle(&mut self) -> Option<Vec2> {
-        let time = self.start_time.elapsed().as_secs_f32();
-        let t = (time / self.duration).clamp(0., 1.);
+        let t = (self.elapsed / self.duration).clamp(0., 1.);
         let s = (self.func)(t);
 
         let new_angle = self.angle_movement.map(|angle| angle.0.lerp(angle.1, s));
@@ -51,7 +66,7 @@ impl Tween {
     }
 
     pub fn is_finished(&self) -> bool {
-        self.start_time.elapsed().as_secs_f32() >= self.duration
+        self.elapsed >= self.duration
     }
 }
 
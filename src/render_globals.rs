@@ -1,10 +1,20 @@
 use std::io::SeekFrom;
 
+use anyhow::Context;
 use binrw::{binread, NullString};
 use destiny_pkg::TagHash;
+use windows::Win32::Graphics::Direct3D11::{ID3D11PixelShader, ID3D11VertexShader};
 
 use crate::{
+    packages::{get_named_tag, package_manager},
+    render::{
+        renderer::Renderer,
+        shader::{load_pshader, load_vshader},
+        vertex_layout::{InputElement, OutputElement},
+    },
     structure::{ExtendedHash, RelPointer, TablePointer, Tag},
+    technique::Technique,
+    texture::Texture,
     types::Vector4,
 };
 
@@ -98,3 +108,119 @@ pub struct SScopeStage {
 
     pub unksomething: [u32; 10],
 }
+
+/// Resources taken from the render globals, loaded in full before they replace the ones in the render data
+pub struct RenderGlobals {
+    technique_deferred_shading_no_atm: Technique,
+    vshader: Option<(TagHash, (ID3D11VertexShader, Vec<InputElement>, Vec<u8>))>,
+    pshader: Option<(TagHash, (ID3D11PixelShader, Vec<OutputElement>))>,
+    iridescence_lookup: Option<Texture>,
+}
+
+impl RenderGlobals {
+    pub fn load(renderer: &Renderer) -> anyhow::Result<Self> {
+        let tag = get_named_tag::<0x8080978C>("render_globals")
+            .context("Could not find render globals")?;
+        let globals: SRenderGlobals = package_manager()
+            .read_tag_struct(tag)
+            .context("Failed to read render globals")?;
+        let globals = &globals
+            .unk8
+            .first()
+            .context("Render globals don't contain any scopes")?
+            .unk8;
+
+        let technique_tag = globals
+            .unk20
+            .iter()
+            .find(|t| t.name.to_string() == "deferred_shading_no_atm")
+            .map(|t| t.technique)
+            .context("Could not find the deferred_shading_no_atm technique")?;
+        let technique = Technique::load(
+            renderer,
+            package_manager()
+                .read_tag_struct(technique_tag)
+                .context("Failed to read deferred_shading_no_atm")?,
+            technique_tag,
+            false,
+        );
+
+        let vshader_tag = technique.stage_vertex.shader.shader;
+        let vshader = if vshader_tag.is_some() {
+            let data = read_shader(vshader_tag)?;
+            let (shader, layout) = load_vshader(&renderer.dcs, &data)
+                .with_context(|| format!("Failed to load vertex shader {vshader_tag}"))?;
+            Some((vshader_tag, (shader, layout, data)))
+        } else {
+            None
+        };
+
+        let pshader_tag = technique.stage_pixel.shader.shader;
+        let pshader = if pshader_tag.is_some() {
+            let data = read_shader(pshader_tag)?;
+            let shader = load_pshader(&renderer.dcs, &data)
+                .with_context(|| format!("Failed to load pixel shader {pshader_tag}"))?;
+            Some((pshader_tag, shader))
+        } else {
+            None
+        };
+
+        let iridescence_lookup = Texture::load(
+            &renderer.dcs,
+            ExtendedHash::Hash32(globals.unk30.iridescence_lookup_texture),
+        )
+        .map_err(|e| warn!("Failed to load iridescence lookup texture: {e}"))
+        .ok();
+
+        Ok(Self {
+            technique_deferred_shading_no_atm: technique,
+            vshader,
+            pshader,
+            iridescence_lookup,
+        })
+    }
+
+    /// Swaps the render globals into the render data in one go
+    pub fn apply(self, renderer: &Renderer) {
+        let mut data = renderer.render_data.data_mut();
+        if let Some((tag, shader)) = self.vshader {
+            data.vshaders.insert(tag, shader);
+        }
+        if let Some((tag, shader)) = self.pshader {
+            data.pshaders.insert(tag, shader);
+        }
+        data.technique_deferred_shading_no_atm = Some(self.technique_deferred_shading_no_atm);
+        data.iridescence_lookup = self.iridescence_lookup;
+    }
+
+    /// Rebuilds the shader overrides and render globals. Whatever fails to load keeps its previous version
+    pub fn reload(renderer: &mut Renderer) {
+        let _span = info_span!("Reloading shaders").entered();
+
+        match renderer.reload_shader_overrides() {
+            Ok(()) => info!("Reloaded shader overrides"),
+            Err(e) => error!("Failed to reload shader overrides, keeping the previous ones: {e:?}"),
+        }
+
+        match Self::load(renderer) {
+            Ok(globals) => {
+                globals.apply(renderer);
+                info!("Reloaded render globals");
+            }
+            Err(e) => error!("Failed to reload render globals, keeping the previous ones: {e:?}"),
+        }
+    }
+}
+
+fn read_shader(tag: TagHash) -> anyhow::Result<Vec<u8>> {
+    let entry = package_manager()
+        .get_entry(tag)
+        .with_context(|| format!("Shader entry {tag} not found"))?;
+    package_manager()
+        .read_tag(entry.reference)
+        .with_context(|| format!("Failed to read shader {tag}"))
+}
+
+/// Reload of the shader overrides and render globals requested by a keybind or command, done by the main loop before the next frame
+#[derive(Default)]
+pub struct ReloadShadersRequest(pub bool);
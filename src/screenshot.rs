@@ -0,0 +1,299 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    path::{Path, PathBuf},
+};
+
+use glam::{Vec2, Vec3};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    camera::{letterbox_rect, FpsCamera},
+    ecs::{components::StaticInstances, resolve_aabb},
+    map::MapDataList,
+    render::{renderer::Renderer, settings::RenderSettings},
+    resources::Resources,
+    types::AABB,
+    util::image::{Exr, Png},
+};
+
+/// Amount of frames without any resources loading before a screenshot is taken
+const SETTLE_FRAMES: usize = 10;
+/// Camera angle used for thumbnails (pitch, yaw)
+const THUMBNAIL_ORIENTATION: Vec2 = Vec2::new(-35.0, 45.0);
+
+enum ScreenshotCamera {
+    Fixed {
+        position: Option<Vec3>,
+        orientation: Option<Vec2>,
+    },
+    /// Frame the entire map
+    Auto,
+}
+
+struct ScreenshotJob {
+    map_index: usize,
+    path: PathBuf,
+    camera: ScreenshotCamera,
+}
+
+pub enum CaptureState {
+    Running,
+    Finished,
+}
+
+/// Renders one or more maps to PNG files without user interaction
+pub struct HeadlessCapture {
+    jobs: VecDeque<ScreenshotJob>,
+    /// Output directory for batch thumbnails. Single screenshots don't get a manifest
+    thumbnail_dir: Option<PathBuf>,
+    pending_single: Option<ScreenshotJob>,
+    manifest: BTreeMap<String, String>,
+
+    maps_ready: bool,
+    job_started: bool,
+    settled_frames: usize,
+}
+
+impl HeadlessCapture {
+    /// Renders the first loaded map from the given camera position/rotation
+    pub fn single(path: PathBuf, position: Option<Vec3>, orientation: Option<Vec2>) -> Self {
+        Self {
+            pending_single: Some(ScreenshotJob {
+                map_index: 0,
+                path,
+                camera: ScreenshotCamera::Fixed {
+                    position,
+                    orientation,
+                },
+            }),
+            ..Self::empty()
+        }
+    }
+
+    /// Renders a thumbnail of every loaded map into `dir`
+    pub fn thumbnails(dir: PathBuf) -> Self {
+        Self {
+            thumbnail_dir: Some(dir),
+            ..Self::empty()
+        }
+    }
+
+    fn empty() -> Self {
+        Self {
+            jobs: VecDeque::new(),
+            thumbnail_dir: None,
+            pending_single: None,
+            manifest: BTreeMap::new(),
+            maps_ready: false,
+            job_started: false,
+            settled_frames: 0,
+        }
+    }
+
+    /// Queues up the jobs for the maps that have been loaded
+    pub fn maps_loaded(&mut self, maps: &MapDataList) {
+        self.maps_ready = true;
+        if let Some(job) = self.pending_single.take() {
+            self.jobs.push_back(job);
+        }
+
+        if let Some(dir) = &self.thumbnail_dir {
+            for (i, (hash, _, _)) in maps.maps.iter().enumerate() {
+                self.jobs.push_back(ScreenshotJob {
+                    map_index: i,
+                    path: dir.join(format!("{i:03}_{hash}.png")),
+                    camera: ScreenshotCamera::Auto,
+                });
+            }
+        }
+    }
+
+    /// Should be called after each frame has been submitted. `loading` indicates whether any resources are still being loaded
+    pub fn after_frame(
+        &mut self,
+        renderer: &Renderer,
+        resources: &Resources,
+        loading: bool,
+    ) -> anyhow::Result<CaptureState> {
+        if !self.maps_ready {
+            return Ok(CaptureState::Running);
+        }
+
+        let Some(job) = self.jobs.front() else {
+            self.write_manifest()?;
+            return Ok(CaptureState::Finished);
+        };
+
+        if !self.job_started {
+            Self::setup_job(job, resources);
+            self.job_started = true;
+            self.settled_frames = 0;
+            return Ok(CaptureState::Running);
+        }
+
+        if loading {
+            self.settled_frames = 0;
+            return Ok(CaptureState::Running);
+        }
+
+        self.settled_frames += 1;
+        if self.settled_frames < SETTLE_FRAMES {
+            return Ok(CaptureState::Running);
+        }
+
+        save_screenshot(
+            renderer,
+            &job.path,
+            resources.get::<RenderSettings>().unwrap().letterbox_aspect,
+        )?;
+        info!("Saved screenshot to {}", job.path.display());
+
+        let maps = resources.get::<MapDataList>().unwrap();
+        if let Some((hash, _, _)) = maps.maps.get(job.map_index) {
+            let filename = job
+                .path
+                .file_name()
+                .map(|f| f.to_string_lossy().to_string())
+                .unwrap_or_default();
+            self.manifest.insert(hash.to_string(), filename);
+        }
+
+        self.jobs.pop_front();
+        self.job_started = false;
+
+        Ok(CaptureState::Running)
+    }
+
+    fn setup_job(job: &ScreenshotJob, resources: &Resources) {
+        let mut maps = resources.get_mut::<MapDataList>().unwrap();
+        maps.current_map = job.map_index;
+
+        let mut camera = resources.get_mut::<FpsCamera>().unwrap();
+        match job.camera {
+            ScreenshotCamera::Fixed {
+                position,
+                orientation,
+            } => {
+                let position = position.unwrap_or(camera.position);
+                camera.teleport(position, orientation);
+            }
+            ScreenshotCamera::Auto => {
+                let Some((_, _, map)) = maps.current_map() else {
+                    return;
+                };
+
+                let points = map
+                    .scene
+                    .query::<()>()
+                    .with::<&StaticInstances>()
+                    .iter()
+                    .filter_map(|(e, _)| resolve_aabb(map.scene.entity(e).ok()?))
+                    .flat_map(|bb| [bb.min, bb.max])
+                    .collect_vec();
+
+                if !points.is_empty() {
+                    camera.frame_aabb(&AABB::from_points(points), THUMBNAIL_ORIENTATION);
+                }
+            }
+        }
+    }
+
+    fn write_manifest(&self) -> anyhow::Result<()> {
+        let Some(dir) = &self.thumbnail_dir else {
+            return Ok(());
+        };
+
+        let path = dir.join("manifest.json");
+        fs_err::write(&path, serde_json::to_string_pretty(&self.manifest)?)?;
+        info!("Wrote thumbnail manifest to {}", path.display());
+
+        Ok(())
+    }
+}
+
+/// Screenshot requested by a keybind or command, taken by the main loop after the next frame
+#[derive(Default)]
+pub struct ScreenshotRequest(pub bool);
+
+#[derive(
+    Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, strum::Display, strum::EnumIter,
+)]
+pub enum ScreenshotFormat {
+    /// Tonemapped 8-bit color, as shown on screen
+    #[default]
+    #[strum(serialize = "PNG")]
+    Png,
+    /// Linear HDR color of the staging target, before exposure, tonemapping and FXAA
+    #[strum(serialize = "EXR (linear HDR)")]
+    Exr,
+}
+
+impl ScreenshotFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ScreenshotFormat::Png => "png",
+            ScreenshotFormat::Exr => "exr",
+        }
+    }
+
+    /// Picks the format based on the file extension, defaulting to PNG
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension() {
+            Some(ext) if ext.eq_ignore_ascii_case("exr") => ScreenshotFormat::Exr,
+            _ => ScreenshotFormat::Png,
+        }
+    }
+}
+
+/// Saves the rendered image to the format matching the extension of `path`, cropped to the letterboxed area if `letterbox_aspect` is set.
+/// PNGs contain the swapchain, EXRs the HDR staging target without the UI
+pub fn save_screenshot(
+    renderer: &Renderer,
+    path: &Path,
+    letterbox_aspect: Option<f32>,
+) -> anyhow::Result<()> {
+    let file = match ScreenshotFormat::from_path(path) {
+        ScreenshotFormat::Png => {
+            let (data, size) = renderer.capture_swapchain()?;
+            let (data, size) = crop_letterbox(data, size, letterbox_aspect);
+            Png::from_rgba(&data, size)?
+        }
+        ScreenshotFormat::Exr => {
+            let (data, size) = renderer.capture_staging_hdr()?;
+            let (data, size) = crop_letterbox(data, size, letterbox_aspect);
+            Exr::from_rgba_f16(&data, size)?
+        }
+    };
+
+    if let Some(parent) = path.parent() {
+        fs_err::create_dir_all(parent)?;
+    }
+
+    fs_err::write(path, file)?;
+    Ok(())
+}
+
+/// Crops RGBA pixel data to the letterboxed area
+fn crop_letterbox<T: Copy>(
+    data: Vec<T>,
+    size: (u32, u32),
+    letterbox_aspect: Option<f32>,
+) -> (Vec<T>, (u32, u32)) {
+    let Some(aspect) = letterbox_aspect else {
+        return (data, size);
+    };
+
+    let (min, inner) = letterbox_rect(Vec2::new(size.0 as f32, size.1 as f32), aspect);
+    let (x, y) = (min.x.round() as usize, min.y.round() as usize);
+    let width = (inner.x.round() as usize).min(size.0 as usize - x);
+    let height = (inner.y.round() as usize).min(size.1 as usize - y);
+    let stride = size.0 as usize * 4;
+
+    let data = (y..y + height)
+        .flat_map(|row| &data[row * stride + x * 4..row * stride + (x + width) * 4])
+        .copied()
+        .collect();
+
+    (data, (width as u32, height as u32))
+}
@@ -297,6 +297,35 @@ impl TechniqueStage {
         }
     }
 
+    pub fn bytecode_constants(&self) -> &[Vec4] {
+        if self.shader.bytecode_constants.is_empty() {
+            &[]
+        } else {
+            bytemuck::cast_slice(&self.shader.bytecode_constants)
+        }
+    }
+
+    /// Disassembled TFX bytecode and the constant buffer elements it writes to, `None` if the stage has no bytecode
+    pub fn bytecode_disassembly(&self) -> Option<(Vec<String>, Vec<usize>)> {
+        let bytecode = self.bytecode.read();
+        let interpreter = bytecode.as_ref()?;
+        let constants = self.bytecode_constants();
+
+        Some((
+            interpreter
+                .opcodes()
+                .iter()
+                .map(|op| op.disassemble(Some(constants)))
+                .collect(),
+            interpreter.output_elements(),
+        ))
+    }
+
+    /// Constant buffer contents, as last written by the bytecode
+    pub fn cbuffer_data(&self) -> Option<&[Vec4]> {
+        self.cbuffer.as_ref().map(|c| c.data_slice())
+    }
+
     pub fn evaluate_bytecode(
         &self,
         renderer: &Renderer,
@@ -306,16 +335,7 @@ impl TechniqueStage {
         if let Some(ref cbuffer) = self.cbuffer {
             let _span = info_span!("Evaluating TFX bytecode (VS)").entered();
             let res = if let Some(interpreter) = self.bytecode.read().as_ref() {
-                interpreter.evaluate(
-                    renderer,
-                    render_data,
-                    cbuffer,
-                    if self.shader.bytecode_constants.is_empty() {
-                        &[]
-                    } else {
-                        bytemuck::cast_slice(&self.shader.bytecode_constants)
-                    },
-                )
+                interpreter.evaluate(renderer, render_data, cbuffer, self.bytecode_constants())
             } else {
                 Ok(())
             };
@@ -332,14 +352,11 @@ impl TechniqueStage {
                         "TFX bytecode evaluation failed for {} ({:?}): {e}",
                         parent, self.stage
                     );
-                    self.bytecode.read().as_ref().unwrap().dump(
-                        if self.shader.bytecode_constants.is_empty() {
-                            &[]
-                        } else {
-                            bytemuck::cast_slice(&self.shader.bytecode_constants)
-                        },
-                        cbuffer,
-                    );
+                    self.bytecode
+                        .read()
+                        .as_ref()
+                        .unwrap()
+                        .dump(self.bytecode_constants(), cbuffer);
                     self.bytecode.write().as_mut().unwrap().error_shown = true;
                 }
             }
@@ -87,6 +87,35 @@ pub struct Texture {
 }
 
 impl Texture {
+    /// Approximate size of the texture in video memory, including all mips
+    pub fn memory_size(&self) -> usize {
+        let (width, height, depth, mip_levels, array_size) = unsafe {
+            match &self.handle {
+                TextureHandle::Texture2D(t) | TextureHandle::TextureCube(t) => {
+                    let mut desc = D3D11_TEXTURE2D_DESC::default();
+                    t.GetDesc(&mut desc);
+                    (desc.Width, desc.Height, 1, desc.MipLevels, desc.ArraySize)
+                }
+                TextureHandle::Texture3D(t) => {
+                    let mut desc = D3D11_TEXTURE3D_DESC::default();
+                    t.GetDesc(&mut desc);
+                    (desc.Width, desc.Height, desc.Depth, desc.MipLevels, 1)
+                }
+            }
+        };
+
+        let bits: usize = (0..mip_levels)
+            .map(|mip| {
+                let w = (width >> mip).max(1) as usize;
+                let h = (height >> mip).max(1) as usize;
+                let d = (depth >> mip).max(1) as usize;
+                w * h * d * self.format.bpp()
+            })
+            .sum();
+
+        bits / 8 * array_size as usize
+    }
+
     pub fn load_data(
         hash: ExtendedHash,
         load_full_mip: bool,
@@ -127,6 +156,16 @@ impl Texture {
         let _span = debug_span!("Load texture", ?hash).entered();
         let (texture, texture_data) = Self::load_data(hash, true)?;
 
+        Self::create(dcs, hash, texture, texture_data)
+    }
+
+    /// Uploads texture data returned by [`Texture::load_data`] to the GPU
+    pub fn create(
+        dcs: &DeviceContextSwapchain,
+        hash: ExtendedHash,
+        texture: STextureHeader,
+        texture_data: Vec<u8>,
+    ) -> anyhow::Result<Texture> {
         let (tex, view) = unsafe {
             if texture.depth > 1 {
                 let (pitch, slice_pitch) = texture
@@ -272,6 +272,42 @@ impl AABB {
 
         Self { min, max }
     }
+
+    /// Returns the smallest box that contains both boxes
+    pub fn union(&self, other: &AABB) -> AABB {
+        Self {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    /// Distance along the ray to where it enters the box, or `None` if the ray misses it.
+    /// A ray starting inside the box hits it at distance 0
+    pub fn ray_intersection(&self, origin: Vec3, direction: Vec3) -> Option<f32> {
+        let inv = direction.recip();
+        let t1 = (self.min - origin) * inv;
+        let t2 = (self.max - origin) * inv;
+
+        let near = t1.min(t2).max_element().max(0.0);
+        let far = t1.max(t2).min_element();
+
+        (far >= near).then_some(near)
+    }
+
+    /// Returns the axis-aligned box that encloses this box after being transformed by `m`
+    pub fn transform(&self, m: &Mat4) -> AABB {
+        let corners: Vec<Vec3> = (0..8)
+            .map(|i| {
+                m.transform_point3(Vec3::new(
+                    if i & 1 == 0 { self.min.x } else { self.max.x },
+                    if i & 2 == 0 { self.min.y } else { self.max.y },
+                    if i & 4 == 0 { self.min.z } else { self.max.z },
+                ))
+            })
+            .collect();
+
+        Self::from_points(corners)
+    }
 }
 
 impl BinRead for AABB {
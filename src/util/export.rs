@@ -1,7 +1,15 @@
+use destiny_pkg::TagHash;
 use fs_err::File;
-use std::io::Write;
+use std::{collections::HashSet, io::Write, path::Path};
 
-use super::error::ErrorAlert;
+use crate::{
+    dxgi::DxgiFormat,
+    render::RenderData,
+    structure::ExtendedHash,
+    texture::{STextureHeader, Texture},
+};
+
+use super::{dds, error::ErrorAlert, image::Png};
 
 pub fn save_dds_dialog(data: &[u8], filename: String) {
     let data = data.to_vec();
@@ -18,3 +26,91 @@ pub fn save_dds_dialog(data: &[u8], filename: String) {
         }
     });
 }
+
+/// Writes every texture bound to the given materials to `dir` as `{texture}_{slot}.dds`, along with a `.png`
+/// copy of the first mip for the formats that can be decoded.
+/// Textures that fail to export are logged and skipped. Returns the amount of textures that were exported.
+pub fn export_material_textures(
+    render_data: &RenderData,
+    materials: &[TagHash],
+    dir: &Path,
+) -> anyhow::Result<usize> {
+    fs_err::create_dir_all(dir)?;
+
+    let mut visited: HashSet<(u64, u32)> = HashSet::default();
+    let mut exported = 0;
+    for material in materials {
+        let Some(technique) = render_data.techniques.get(material) else {
+            warn!("Material {material} is not loaded, skipping texture export");
+            continue;
+        };
+
+        for stage in technique.all_stages() {
+            for assignment in stage.shader.textures.iter() {
+                if !assignment.texture.is_some()
+                    || !visited.insert((assignment.texture.key(), assignment.slot))
+                {
+                    continue;
+                }
+
+                let name = format!("{}_{}", assignment.texture, assignment.slot);
+                match export_texture(assignment.texture, dir, &name) {
+                    Ok(()) => exported += 1,
+                    Err(e) => error!("Failed to export texture {}: {e:?}", assignment.texture),
+                }
+            }
+        }
+    }
+
+    Ok(exported)
+}
+
+fn export_texture(texture: ExtendedHash, dir: &Path, name: &str) -> anyhow::Result<()> {
+    let (header, data) = Texture::load_data(texture, true)?;
+    let mut dds_data: Vec<u8> = vec![];
+    dds::dump_to_dds(&mut dds_data, &header, &data);
+    File::create(dir.join(format!("{name}.dds")))?.write_all(&dds_data)?;
+
+    match decode_rgba8(&header, &data) {
+        Some(rgba) => {
+            let png_data = Png::from_rgba(&rgba, (header.width as u32, header.height as u32))?;
+            File::create(dir.join(format!("{name}.png")))?.write_all(&png_data)?;
+        }
+        None => warn!(
+            "Texture {texture} has format {:?}, which can't be exported to PNG",
+            header.format
+        ),
+    }
+
+    Ok(())
+}
+
+/// Decodes the first mip of the first slice to 8-bit RGBA, returns `None` for unsupported formats
+fn decode_rgba8(header: &STextureHeader, data: &[u8]) -> Option<Vec<u8>> {
+    let (width, height) = (header.width as usize, header.height as usize);
+    let pixel_count = width * height;
+
+    let block_format = match header.format {
+        DxgiFormat::R8G8B8A8_UNORM | DxgiFormat::R8G8B8A8_UNORM_SRGB => {
+            return data.get(..pixel_count * 4).map(|d| d.to_vec());
+        }
+        DxgiFormat::B8G8R8A8_UNORM | DxgiFormat::B8G8R8A8_UNORM_SRGB => {
+            let mut rgba = data.get(..pixel_count * 4)?.to_vec();
+            for pixel in rgba.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+            return Some(rgba);
+        }
+        DxgiFormat::BC1_UNORM | DxgiFormat::BC1_UNORM_SRGB => texpresso::Format::Bc1,
+        DxgiFormat::BC2_UNORM | DxgiFormat::BC2_UNORM_SRGB => texpresso::Format::Bc2,
+        DxgiFormat::BC3_UNORM | DxgiFormat::BC3_UNORM_SRGB => texpresso::Format::Bc3,
+        DxgiFormat::BC4_UNORM => texpresso::Format::Bc4,
+        DxgiFormat::BC5_UNORM => texpresso::Format::Bc5,
+        _ => return None,
+    };
+
+    let compressed = data.get(..block_format.compressed_size(width, height))?;
+    let mut rgba = vec![0u8; pixel_count * 4];
+    block_format.decompress(compressed, width, height, &mut rgba);
+    Some(rgba)
+}
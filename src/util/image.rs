@@ -104,15 +104,115 @@ impl Png {
         }
     }
 
-    // /// Converts RGBA data into PNG file data
-    // pub fn from_rgba(data: &[u8], dimensions: (u32, u32)) -> Result<Vec<u8>> {
-    //     let mut result = vec![];
-    //     let mut encoder = png::Encoder::new(&mut result, dimensions.0, dimensions.1);
-    //     encoder.set_color(ColorType::Rgba);
-    //     encoder.set_depth(png::BitDepth::Eight);
-    //     let mut writer = encoder.write_header()?;
-    //     writer.write_image_data(data)?;
-    //     writer.finish()?;
-    //     Ok(result)
-    // }
+    /// Converts RGBA data into PNG file data
+    pub fn from_rgba(data: &[u8], dimensions: (u32, u32)) -> Result<Vec<u8>> {
+        let mut result = vec![];
+        let mut encoder = png::Encoder::new(&mut result, dimensions.0, dimensions.1);
+        encoder.set_color(ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(data)?;
+        writer.finish()?;
+        Ok(result)
+    }
+}
+
+/// Writes uncompressed scanline OpenEXR files
+pub struct Exr;
+
+impl Exr {
+    const MAGIC: u32 = 0x01312f76;
+    const PIXEL_TYPE_HALF: i32 = 1;
+
+    /// Converts RGBA half float data into EXR file data. Alpha is dropped, the channels are stored as linear half floats
+    pub fn from_rgba_f16(data: &[u16], dimensions: (u32, u32)) -> Result<Vec<u8>> {
+        let (width, height) = (dimensions.0 as usize, dimensions.1 as usize);
+        anyhow::ensure!(
+            width > 0 && height > 0,
+            "Can't write an empty {width}x{height} image"
+        );
+        anyhow::ensure!(
+            data.len() == width * height * 4,
+            "Expected {} values for a {width}x{height} image, got {}",
+            width * height * 4,
+            data.len()
+        );
+
+        let mut header = vec![];
+        header.extend_from_slice(&Self::MAGIC.to_le_bytes());
+        // Version 2, single part scanline file
+        header.extend_from_slice(&2u32.to_le_bytes());
+
+        // Channels have to be sorted by name
+        let channels = ["B", "G", "R"];
+        let mut chlist = vec![];
+        for name in channels {
+            chlist.extend_from_slice(name.as_bytes());
+            chlist.push(0);
+            chlist.extend_from_slice(&Self::PIXEL_TYPE_HALF.to_le_bytes());
+            // pLinear + reserved
+            chlist.extend_from_slice(&[0; 4]);
+            // x/y sampling
+            chlist.extend_from_slice(&1i32.to_le_bytes());
+            chlist.extend_from_slice(&1i32.to_le_bytes());
+        }
+        chlist.push(0);
+
+        let window = [0, 0, width as i32 - 1, height as i32 - 1]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect_vec();
+
+        Self::write_attribute(&mut header, "channels", "chlist", &chlist);
+        Self::write_attribute(&mut header, "compression", "compression", &[0]);
+        Self::write_attribute(&mut header, "dataWindow", "box2i", &window);
+        Self::write_attribute(&mut header, "displayWindow", "box2i", &window);
+        Self::write_attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+        Self::write_attribute(
+            &mut header,
+            "pixelAspectRatio",
+            "float",
+            &1f32.to_le_bytes(),
+        );
+        Self::write_attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8]);
+        Self::write_attribute(
+            &mut header,
+            "screenWindowWidth",
+            "float",
+            &1f32.to_le_bytes(),
+        );
+        header.push(0);
+
+        let line_size = width * channels.len() * 2;
+        let chunk_size = 8 + line_size;
+        let table_end = header.len() + height * 8;
+
+        let mut result = Vec::with_capacity(table_end + height * chunk_size);
+        result.extend_from_slice(&header);
+        for y in 0..height {
+            result.extend_from_slice(&((table_end + y * chunk_size) as u64).to_le_bytes());
+        }
+
+        for (y, row) in data.chunks_exact(width * 4).enumerate() {
+            result.extend_from_slice(&(y as i32).to_le_bytes());
+            result.extend_from_slice(&(line_size as i32).to_le_bytes());
+            // Pixel data is stored per channel for each scanline
+            for channel in [2, 1, 0] {
+                for pixel in row.chunks_exact(4) {
+                    result.extend_from_slice(&pixel[channel].to_le_bytes());
+                }
+            }
+        }
+
+        Ok(result)
+    }
+
+    fn write_attribute(out: &mut Vec<u8>, name: &str, ty: &str, value: &[u8]) {
+        out.extend_from_slice(name.as_bytes());
+        out.push(0);
+        out.extend_from_slice(ty.as_bytes());
+        out.push(0);
+        out.extend_from_slice(&(value.len() as i32).to_le_bytes());
+        out.extend_from_slice(value);
+    }
 }
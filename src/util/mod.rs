@@ -4,6 +4,7 @@ pub mod error;
 pub mod export;
 pub mod image;
 pub mod lock;
+pub mod scan;
 pub mod text;
 
 use std::path::PathBuf;
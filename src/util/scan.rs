@@ -0,0 +1,39 @@
+use rayon::prelude::*;
+
+/// Buffers smaller than this are scanned on the calling thread, splitting them up isn't worth the overhead
+pub const PARALLEL_SCAN_THRESHOLD: usize = 4 * 1024 * 1024;
+
+/// Size of the (4-byte aligned) windows a large buffer is split into
+const PARALLEL_SCAN_WINDOW: usize = 512 * 1024;
+
+/// Scans every 4-byte aligned little-endian u32 in `data`, returning the byte offset and value of every word matching `predicate`.
+/// Large buffers are divided into aligned windows that are scanned in parallel, results are returned in ascending offset order.
+pub fn scan_u32<F>(data: &[u8], predicate: F) -> Vec<(u64, u32)>
+where
+    F: Fn(u32) -> bool + Sync,
+{
+    if data.len() < PARALLEL_SCAN_THRESHOLD {
+        return scan_u32_window(data, 0, &predicate);
+    }
+
+    data.par_chunks(PARALLEL_SCAN_WINDOW)
+        .enumerate()
+        .flat_map_iter(|(i, window)| {
+            scan_u32_window(window, (i * PARALLEL_SCAN_WINDOW) as u64, &predicate)
+        })
+        .collect()
+}
+
+fn scan_u32_window<F>(window: &[u8], base_offset: u64, predicate: &F) -> Vec<(u64, u32)>
+where
+    F: Fn(u32) -> bool,
+{
+    window
+        .chunks_exact(4)
+        .enumerate()
+        .filter_map(|(i, b)| {
+            let v = u32::from_le_bytes(b.try_into().unwrap());
+            predicate(v).then_some((base_offset + i as u64 * 4, v))
+        })
+        .collect()
+}
@@ -44,6 +44,35 @@ pub fn prettify_distance(meters: f32) -> String {
     }
 }
 
+/// Formats a byte count using binary units (B, KiB, MiB, GiB)
+pub fn prettify_bytes(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.2} {}", UNITS[unit])
+    }
+}
+
+/// Formats a duration as seconds, minutes or hours, rounded down to the largest unit
+pub fn prettify_duration(duration: std::time::Duration) -> String {
+    let secs = duration.as_secs();
+    if secs < 60 {
+        format!("{secs}s")
+    } else if secs < 3600 {
+        format!("{}m {}s", secs / 60, secs % 60)
+    } else {
+        format!("{}h {}m", secs / 3600, (secs % 3600) / 60)
+    }
+}
+
 pub fn split_pascal_case(s: &str) -> String {
     let mut result = String::new();
     let mut last_upper = false;